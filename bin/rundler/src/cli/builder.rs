@@ -16,9 +16,9 @@ use std::net::SocketAddr;
 use anyhow::{bail, Context};
 use clap::Args;
 use rundler_builder::{
-    self, BloxrouteSenderArgs, BuilderEvent, BuilderEventKind, BuilderTask, BuilderTaskArgs,
-    EntryPointBuilderSettings, FlashbotsSenderArgs, LocalBuilderBuilder, RawSenderArgs,
-    TransactionSenderArgs, TransactionSenderKind,
+    self, AggregatedOpsPolicy, BloxrouteSenderArgs, BuilderEvent, BuilderEventKind, BuilderTask,
+    BuilderTaskArgs, BundleTriggerMode, EntryPointBuilderSettings, FlashbotsSenderArgs,
+    LocalBuilderBuilder, RawSenderArgs, TransactionSenderArgs, TransactionSenderKind,
 };
 use rundler_pool::RemotePoolClient;
 use rundler_sim::{MempoolConfigs, PriorityFeeMode};
@@ -245,6 +245,50 @@ pub struct BuilderArgs {
     )]
     max_replacement_underpriced_blocks: u64,
 
+    /// When to attempt building a bundle while in auto bundling mode.
+    ///
+    /// Options: `block_or_interval`, `block`, and `interval`
+    #[arg(
+        long = "builder.bundle_trigger_mode",
+        name = "builder.bundle_trigger_mode",
+        env = "BUILDER_BUNDLE_TRIGGER_MODE",
+        default_value = "block_or_interval"
+    )]
+    bundle_trigger_mode: BundleTriggerMode,
+
+    /// Interval in milliseconds between bundle attempts when the trigger mode
+    /// uses an interval.
+    ///
+    /// Defaults to the chain spec's `bundle_max_send_interval_millis`.
+    #[arg(
+        long = "builder.bundle_build_interval_millis",
+        name = "builder.bundle_build_interval_millis",
+        env = "BUILDER_BUNDLE_BUILD_INTERVAL_MILLIS"
+    )]
+    bundle_build_interval_millis: Option<u64>,
+
+    /// How to handle ops using a signature aggregator whose fees don't cover
+    /// the aggregator's signature verification overhead.
+    ///
+    /// Options: `include`, `deprioritize`, and `exclude`
+    #[arg(
+        long = "builder.aggregated_ops_policy",
+        name = "builder.aggregated_ops_policy",
+        env = "BUILDER_AGGREGATED_OPS_POLICY",
+        default_value = "include"
+    )]
+    aggregated_ops_policy: AggregatedOpsPolicy,
+
+    /// Estimated gas overhead of verifying an aggregator's signature in a bundle,
+    /// used by the aggregated ops policy.
+    #[arg(
+        long = "builder.aggregator_overhead_gas",
+        name = "builder.aggregator_overhead_gas",
+        env = "BUILDER_AGGREGATOR_OVERHEAD_GAS",
+        default_value = "100000"
+    )]
+    aggregator_overhead_gas: u128,
+
     /// The index offset to apply to the builder index
     #[arg(
         long = "builder_index_offset",
@@ -271,6 +315,18 @@ impl BuilderArgs {
 
         let rpc_url = common.node_http.clone().context("must provide node_http")?;
 
+        let bundle_build_interval_millis = self
+            .bundle_build_interval_millis
+            .unwrap_or(chain_spec.bundle_max_send_interval_millis);
+        if bundle_build_interval_millis == 0 {
+            bail!("builder.bundle_build_interval_millis must be greater than zero");
+        }
+        if self.bundle_trigger_mode == BundleTriggerMode::Interval
+            && bundle_build_interval_millis == u64::MAX
+        {
+            bail!("builder.bundle_trigger_mode interval requires a bundle build interval, set builder.bundle_build_interval_millis");
+        }
+
         let mempool_configs = match &common.mempool_config_path {
             Some(path) => get_json_config::<MempoolConfigs>(path)
                 .await
@@ -350,12 +406,17 @@ impl BuilderArgs {
             bundle_base_fee_overhead_percent: common.bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
             priority_fee_mode,
+            priority_fee_percentile_blocks: common.priority_fee_percentile_blocks,
+            aggregated_ops_policy: self.aggregated_ops_policy,
+            aggregator_overhead_gas: self.aggregator_overhead_gas,
             sender_args,
             sim_settings: common.try_into()?,
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
             max_cancellation_fee_increases: self.max_cancellation_fee_increases,
             max_replacement_underpriced_blocks: self.max_replacement_underpriced_blocks,
+            bundle_trigger_mode: self.bundle_trigger_mode,
+            bundle_build_interval_millis,
             remote_address,
             da_gas_tracking_enabled,
         })
@@ -16,7 +16,7 @@ use std::{net::SocketAddr, time::Duration};
 use itertools::Itertools;
 use metrics::Gauge;
 use metrics_derive::Metrics;
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use metrics_process::Collector;
 use metrics_util::layers::{PrefixLayer, Stack};
 use rundler_task::TaskSpawner;
@@ -27,7 +27,7 @@ pub fn initialize<'a, T: TaskSpawner>(
     listen_addr: SocketAddr,
     tags: impl IntoIterator<Item = &'a String>,
     buckets: &[f64],
-) -> anyhow::Result<()> {
+) -> anyhow::Result<PrometheusHandle> {
     let mut builder = PrometheusBuilder::new().with_http_listener(listen_addr);
 
     let tags: Vec<(&str, &str)> = tags
@@ -41,6 +41,7 @@ pub fn initialize<'a, T: TaskSpawner>(
     builder = builder.set_buckets(buckets)?;
 
     let (recorder, exporter) = builder.build()?;
+    let handle = recorder.handle();
     task_spawner.spawn_critical(
         "metrics exporter",
         Box::pin(async move {
@@ -79,7 +80,7 @@ pub fn initialize<'a, T: TaskSpawner>(
         }),
     );
 
-    Ok(())
+    Ok(handle)
 }
 
 #[allow(dead_code)]
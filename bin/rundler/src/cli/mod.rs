@@ -33,16 +33,20 @@ use reth_tasks::TaskManager;
 use rpc::RpcCliArgs;
 use rundler_provider::{
     AlloyEntryPointV0_6, AlloyEntryPointV0_7, AlloyEvmProvider, DAGasOracleSync,
-    EntryPointProvider, EvmProvider, Providers,
+    EntryPointProvider, EvmProvider, Providers, RetryProvider,
 };
 use rundler_rpc::{EthApiSettings, RundlerApiSettings};
 use rundler_sim::{
-    EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
+    AbsurdFeeHandling, EmptySignatureHandling, EstimationSettings, EstimationStrategy,
+    PrecheckSettings, PriorityFeeMode, PrunedStateHandling, SimulationSettings, MIN_CALL_GAS_LIMIT,
 };
 use rundler_types::{
     chain::ChainSpec, da::DAGasOracleType, v0_6::UserOperation as UserOperationV0_6,
-    v0_7::UserOperation as UserOperationV0_7,
+    v0_7::UserOperation as UserOperationV0_7, AccountType,
 };
+use rundler_utils::retry::RetryOpts;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 /// Main entry point for the CLI
 ///
@@ -57,7 +61,7 @@ pub async fn run() -> anyhow::Result<()> {
     let task_spawner = task_manager.executor();
 
     let metrics_addr = format!("{}:{}", opt.metrics.host, opt.metrics.port).parse()?;
-    metrics::initialize(
+    let metrics_handle = metrics::initialize(
         &task_spawner,
         opt.metrics.sample_interval_millis,
         metrics_addr,
@@ -66,32 +70,65 @@ pub async fn run() -> anyhow::Result<()> {
     )
     .context("metrics server should start")?;
 
+    let shutdown = CancellationToken::new();
+    let rpc_drained = TaskTracker::new();
+
     let cs = chain_spec::resolve_chain_spec(&opt.common.network, &opt.common.chain_spec);
     tracing::info!("Chain spec: {:#?}", cs);
 
     match opt.command {
         Command::Node(args) => {
-            node::spawn_tasks(task_spawner.clone(), cs, *args, opt.common).await?
+            node::spawn_tasks(
+                task_spawner.clone(),
+                cs,
+                *args,
+                opt.common,
+                metrics_handle,
+                shutdown.clone(),
+                rpc_drained.clone(),
+            )
+            .await?
         }
         Command::Pool(args) => {
             pool::spawn_tasks(task_spawner.clone(), cs, args, opt.common).await?
         }
-        Command::Rpc(args) => rpc::spawn_tasks(task_spawner.clone(), cs, args, opt.common).await?,
+        Command::Rpc(args) => {
+            rpc::spawn_tasks(
+                task_spawner.clone(),
+                cs,
+                args,
+                opt.common,
+                metrics_handle,
+                shutdown.clone(),
+                rpc_drained.clone(),
+            )
+            .await?
+        }
         Command::Builder(args) => {
             builder::spawn_tasks(task_spawner.clone(), cs, args, opt.common).await?
         }
     }
 
-    // wait for ctrl-c or the task manager to panic
+    let mut sigterm = signal(SignalKind::terminate()).context("SIGTERM handler should install")?;
+
+    // wait for ctrl-c, SIGTERM or the task manager to panic
     tokio::select! {
         _ = tokio::signal::ctrl_c() => {
             tracing::info!("Received ctrl-c, shutting down");
         },
+        _ = sigterm.recv() => {
+            tracing::info!("Received SIGTERM, shutting down");
+        },
         e = &mut task_manager => {
             tracing::error!("Task manager panicked, shutting down: {e}");
         },
     }
 
+    // drain the RPC server before shutting down the pool and builder it depends on
+    shutdown.cancel();
+    rpc_drained.close();
+    rpc_drained.wait().await;
+
     // wait for the task manager to shutdown
     task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10));
 
@@ -159,6 +196,17 @@ pub struct CommonArgs {
     )]
     node_http: Option<String>,
 
+    /// Maximum number of attempts, including the first, for node reads that fail
+    /// with a transient error
+    #[arg(
+        long = "node_max_attempts",
+        name = "node_max_attempts",
+        default_value = "3",
+        env = "NODE_MAX_ATTEMPTS",
+        global = true
+    )]
+    node_max_attempts: u64,
+
     /// Flag for turning unsafe bundling mode on
     #[arg(long = "unsafe", env = "UNSAFE", global = true)]
     unsafe_mode: bool,
@@ -172,6 +220,15 @@ pub struct CommonArgs {
     )]
     max_verification_gas: u64,
 
+    #[arg(
+        long = "min_verification_gas_limit",
+        name = "min_verification_gas_limit",
+        default_value = "0",
+        env = "MIN_VERIFICATION_GAS_LIMIT",
+        global = true
+    )]
+    min_verification_gas_limit: u128,
+
     #[arg(
         long = "max_bundle_gas",
         name = "max_bundle_gas",
@@ -220,6 +277,70 @@ pub struct CommonArgs {
     )]
     user_operation_event_block_distance: Option<u64>,
 
+    /// Maximum number of blocks to query for user operation events in a single
+    /// eth_getLogs request. Larger ranges are scanned in windows, newest first.
+    #[arg(
+        long = "user_operation_event_max_block_range",
+        name = "user_operation_event_max_block_range",
+        env = "USER_OPERATION_EVENT_MAX_BLOCK_RANGE",
+        default_value = "10000",
+        global = true
+    )]
+    user_operation_event_max_block_range: u64,
+
+    /// Maximum number of call frames to search when tracing a transaction for a
+    /// user operation in eth_getUserOperationByHash.
+    #[arg(
+        long = "user_operation_trace_max_frames",
+        name = "user_operation_trace_max_frames",
+        env = "USER_OPERATION_TRACE_MAX_FRAMES",
+        default_value = "10000",
+        global = true
+    )]
+    user_operation_trace_max_frames: usize,
+
+    /// Maximum call depth to search when tracing a transaction for a
+    /// user operation in eth_getUserOperationByHash.
+    #[arg(
+        long = "user_operation_trace_max_depth",
+        name = "user_operation_trace_max_depth",
+        env = "USER_OPERATION_TRACE_MAX_DEPTH",
+        default_value = "64",
+        global = true
+    )]
+    user_operation_trace_max_depth: usize,
+
+    /// Maximum number of user operations accepted from a single sender within
+    /// `sender_rate_limit_window_seconds`. Unlimited if unset.
+    #[arg(
+        long = "max_ops_per_sender",
+        name = "max_ops_per_sender",
+        env = "MAX_OPS_PER_SENDER",
+        global = true
+    )]
+    max_ops_per_sender: Option<usize>,
+
+    /// Sliding window over which `max_ops_per_sender` is enforced.
+    #[arg(
+        long = "sender_rate_limit_window_seconds",
+        name = "sender_rate_limit_window_seconds",
+        env = "SENDER_RATE_LIMIT_WINDOW_SECONDS",
+        default_value = "60",
+        global = true
+    )]
+    sender_rate_limit_window_seconds: u64,
+
+    /// Maximum number of mined user operation receipts to cache for
+    /// eth_getUserOperationReceipt. Caching is disabled if zero.
+    #[arg(
+        long = "user_operation_receipt_cache_size",
+        name = "user_operation_receipt_cache_size",
+        env = "USER_OPERATION_RECEIPT_CACHE_SIZE",
+        default_value = "1000",
+        global = true
+    )]
+    user_operation_receipt_cache_size: u32,
+
     #[arg(
         long = "max_simulate_handle_ops_gas",
         name = "max_simulate_handle_ops_gas",
@@ -238,6 +359,112 @@ pub struct CommonArgs {
     )]
     verification_estimation_gas_fee: u128,
 
+    #[arg(
+        long = "verification_gas_buffer_percent",
+        name = "verification_gas_buffer_percent",
+        env = "VERIFICATION_GAS_BUFFER_PERCENT",
+        default_value = "10",
+        global = true
+    )]
+    verification_gas_buffer_percent: u32,
+
+    #[arg(
+        long = "estimation_strategy",
+        name = "estimation_strategy",
+        env = "ESTIMATION_STRATEGY",
+        default_value = "binary_search",
+        global = true
+    )]
+    estimation_strategy: EstimationStrategy,
+
+    #[arg(
+        long = "gas_rounding",
+        name = "gas_rounding",
+        env = "GAS_ROUNDING",
+        default_value = "4096",
+        global = true
+    )]
+    gas_rounding: u64,
+
+    #[arg(
+        long = "pruned_state_handling",
+        name = "pruned_state_handling",
+        env = "PRUNED_STATE_HANDLING",
+        default_value = "error",
+        global = true
+    )]
+    pruned_state_handling: PrunedStateHandling,
+
+    #[arg(
+        long = "empty_signature_handling",
+        name = "empty_signature_handling",
+        env = "EMPTY_SIGNATURE_HANDLING",
+        default_value = "warn",
+        global = true
+    )]
+    empty_signature_handling: EmptySignatureHandling,
+
+    #[arg(
+        long = "dummy_signature_account_type",
+        name = "dummy_signature_account_type",
+        env = "DUMMY_SIGNATURE_ACCOUNT_TYPE",
+        default_value = "ecdsa",
+        global = true
+    )]
+    dummy_signature_account_type: AccountType,
+
+    #[arg(
+        long = "estimation_rounds_enabled",
+        name = "estimation_rounds_enabled",
+        env = "ESTIMATION_ROUNDS_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    estimation_rounds_enabled: bool,
+
+    #[arg(
+        long = "sequential_estimation",
+        name = "sequential_estimation",
+        env = "SEQUENTIAL_ESTIMATION",
+        default_value = "false",
+        global = true
+    )]
+    sequential_estimation: bool,
+
+    #[arg(
+        long = "block_drift_repin_millis",
+        name = "block_drift_repin_millis",
+        env = "BLOCK_DRIFT_REPIN_MILLIS",
+        global = true
+    )]
+    block_drift_repin_millis: Option<u64>,
+
+    #[arg(
+        long = "max_pre_verification_gas",
+        name = "max_pre_verification_gas",
+        env = "MAX_PRE_VERIFICATION_GAS",
+        global = true
+    )]
+    max_pre_verification_gas: Option<u128>,
+
+    #[arg(
+        long = "pre_verification_gas_random_fill_samples",
+        name = "pre_verification_gas_random_fill_samples",
+        env = "PRE_VERIFICATION_GAS_RANDOM_FILL_SAMPLES",
+        default_value = "1",
+        global = true
+    )]
+    pre_verification_gas_random_fill_samples: u32,
+
+    #[arg(
+        long = "validation_reuse_max_blocks",
+        name = "validation_reuse_max_blocks",
+        env = "VALIDATION_REUSE_MAX_BLOCKS",
+        default_value = "1",
+        global = true
+    )]
+    validation_reuse_max_blocks: u64,
+
     #[arg(
         long = "bundle_base_fee_overhead_percent",
         name = "bundle_base_fee_overhead_percent",
@@ -260,7 +487,7 @@ pub struct CommonArgs {
         long = "priority_fee_mode_kind",
         name = "priority_fee_mode_kind",
         env = "PRIORITY_FEE_MODE_KIND",
-        value_parser = PossibleValuesParser::new(["base_fee_percent", "priority_fee_increase_percent"]),
+        value_parser = PossibleValuesParser::new(["base_fee_percent", "priority_fee_increase_percent", "percentile"]),
         default_value = "priority_fee_increase_percent",
         global = true
     )]
@@ -275,6 +502,17 @@ pub struct CommonArgs {
     )]
     priority_fee_mode_value: u32,
 
+    /// Number of recent blocks sampled for the network priority fee when using the
+    /// `percentile` priority fee mode.
+    #[arg(
+        long = "priority_fee_percentile_blocks",
+        name = "priority_fee_percentile_blocks",
+        env = "PRIORITY_FEE_PERCENTILE_BLOCKS",
+        default_value = "20",
+        global = true
+    )]
+    priority_fee_percentile_blocks: u64,
+
     #[arg(
         long = "base_fee_accept_percent",
         name = "base_fee_accept_percent",
@@ -293,6 +531,56 @@ pub struct CommonArgs {
     )]
     pre_verification_gas_accept_percent: u32,
 
+    #[arg(
+        long = "max_call_data_len",
+        name = "max_call_data_len",
+        env = "MAX_CALL_DATA_LEN",
+        global = true
+    )]
+    max_call_data_len: Option<usize>,
+
+    #[arg(
+        long = "max_total_op_gas",
+        name = "max_total_op_gas",
+        env = "MAX_TOTAL_OP_GAS",
+        global = true
+    )]
+    max_total_op_gas: Option<u128>,
+
+    #[arg(
+        long = "absurd_fee_multiple",
+        name = "absurd_fee_multiple",
+        env = "ABSURD_FEE_MULTIPLE",
+        global = true
+    )]
+    absurd_fee_multiple: Option<u128>,
+
+    #[arg(
+        long = "absurd_fee_handling",
+        name = "absurd_fee_handling",
+        env = "ABSURD_FEE_HANDLING",
+        default_value = "warn",
+        global = true
+    )]
+    absurd_fee_handling: AbsurdFeeHandling,
+
+    #[arg(
+        long = "warn_sender_pending_txs",
+        name = "warn_sender_pending_txs",
+        env = "WARN_SENDER_PENDING_TXS",
+        default_value = "false",
+        global = true
+    )]
+    warn_sender_pending_txs: bool,
+
+    #[arg(
+        long = "max_paymaster_verification_gas",
+        name = "max_paymaster_verification_gas",
+        env = "MAX_PAYMASTER_VERIFICATION_GAS",
+        global = true
+    )]
+    max_paymaster_verification_gas: Option<u128>,
+
     #[arg(
         long = "mempool_config_path",
         name = "mempool_config_path",
@@ -346,6 +634,16 @@ pub struct CommonArgs {
         default_value = "false"
     )]
     pub da_gas_tracking_enabled: bool,
+
+    #[cfg(feature = "revm")]
+    #[arg(
+        long = "local_evm",
+        name = "local_evm",
+        env = "LOCAL_EVM",
+        default_value = "false",
+        global = true
+    )]
+    pub local_evm: bool,
 }
 
 const SIMULATION_GAS_OVERHEAD: u64 = 100_000;
@@ -373,14 +671,36 @@ impl TryFrom<&CommonArgs> for EstimationSettings {
                 value.max_simulate_handle_ops_gas,
             );
         }
+        if !value.gas_rounding.is_power_of_two() {
+            anyhow::bail!(
+                "gas_rounding ({}) must be a non-zero power of two",
+                value.gas_rounding
+            );
+        }
+        if value.pre_verification_gas_random_fill_samples == 0 {
+            anyhow::bail!("pre_verification_gas_random_fill_samples must be non-zero");
+        }
         Ok(Self {
             max_verification_gas: value.max_verification_gas as u128,
+            min_verification_gas_limit: value.min_verification_gas_limit,
             max_call_gas,
             max_paymaster_verification_gas: value.max_verification_gas as u128,
             max_paymaster_post_op_gas: max_call_gas,
             max_total_execution_gas: value.max_bundle_gas,
             max_simulate_handle_ops_gas: value.max_simulate_handle_ops_gas,
             verification_estimation_gas_fee: value.verification_estimation_gas_fee,
+            verification_gas_buffer_percent: value.verification_gas_buffer_percent,
+            estimation_strategy: value.estimation_strategy,
+            gas_rounding: value.gas_rounding,
+            pruned_state_handling: value.pruned_state_handling,
+            empty_signature_handling: value.empty_signature_handling,
+            dummy_signature_account_type: value.dummy_signature_account_type,
+            sequential_estimation: value.sequential_estimation,
+            block_drift_repin_millis: value.block_drift_repin_millis,
+            max_pre_verification_gas: value.max_pre_verification_gas,
+            pre_verification_gas_random_fill_samples: value
+                .pre_verification_gas_random_fill_samples,
+            validation_reuse_max_blocks: value.validation_reuse_max_blocks,
         })
     }
 }
@@ -389,8 +709,13 @@ impl TryFrom<&CommonArgs> for PrecheckSettings {
     type Error = anyhow::Error;
 
     fn try_from(value: &CommonArgs) -> Result<Self, Self::Error> {
+        if value.absurd_fee_multiple == Some(0) {
+            bail!("absurd_fee_multiple must be greater than 0");
+        }
+
         Ok(Self {
             max_verification_gas: value.max_verification_gas as u128,
+            max_paymaster_verification_gas: value.max_paymaster_verification_gas,
             max_total_execution_gas: value.max_bundle_gas,
             bundle_base_fee_overhead_percent: value.bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent: value.bundle_priority_fee_overhead_percent,
@@ -398,8 +723,14 @@ impl TryFrom<&CommonArgs> for PrecheckSettings {
                 value.priority_fee_mode_kind.as_str(),
                 value.priority_fee_mode_value,
             )?,
+            priority_fee_percentile_blocks: value.priority_fee_percentile_blocks,
             base_fee_accept_percent: value.base_fee_accept_percent,
             pre_verification_gas_accept_percent: value.pre_verification_gas_accept_percent,
+            max_call_data_len: value.max_call_data_len,
+            max_total_op_gas: value.max_total_op_gas,
+            absurd_fee_multiple: value.absurd_fee_multiple,
+            absurd_fee_handling: value.absurd_fee_handling,
+            warn_sender_pending_txs: value.warn_sender_pending_txs,
         })
     }
 }
@@ -422,7 +753,15 @@ impl TryFrom<&CommonArgs> for SimulationSettings {
 
 impl From<&CommonArgs> for EthApiSettings {
     fn from(value: &CommonArgs) -> Self {
-        Self::new(value.user_operation_event_block_distance)
+        Self::new(
+            value.user_operation_event_block_distance,
+            value.user_operation_event_max_block_range,
+            value.user_operation_trace_max_frames,
+            value.user_operation_trace_max_depth,
+            value.max_ops_per_sender,
+            Duration::from_secs(value.sender_rate_limit_window_seconds),
+            value.user_operation_receipt_cache_size,
+        )
     }
 }
 
@@ -436,6 +775,7 @@ impl TryFrom<&CommonArgs> for RundlerApiSettings {
                 value.priority_fee_mode_value,
             )?,
             bundle_priority_fee_overhead_percent: value.bundle_priority_fee_overhead_percent,
+            estimation_rounds_enabled: value.estimation_rounds_enabled,
         })
     }
 }
@@ -624,8 +964,43 @@ pub fn construct_providers(
         ))
     };
 
+    if args.node_max_attempts == 0 {
+        bail!("node_max_attempts must be non-zero");
+    }
+    let provider = RetryProvider::new(
+        AlloyEvmProvider::new(provider),
+        RetryOpts {
+            max_attempts: args.node_max_attempts,
+            ..Default::default()
+        },
+    );
+
+    // run simulations for gas estimation in an embedded EVM, reading state from the node
+    #[cfg(feature = "revm")]
+    let (provider, ep_v0_6, ep_v0_7) = {
+        let provider = rundler_provider::LocalEvmProvider::new(provider, chain_spec)
+            .with_enabled(args.local_evm);
+        let ep_v0_6 = ep_v0_6.map(|ep| {
+            rundler_provider::LocalEvmEntryPoint::new(
+                ep,
+                provider.clone(),
+                chain_spec.clone(),
+                args.max_simulate_handle_ops_gas,
+            )
+        });
+        let ep_v0_7 = ep_v0_7.map(|ep| {
+            rundler_provider::LocalEvmEntryPoint::new(
+                ep,
+                provider.clone(),
+                chain_spec.clone(),
+                args.max_simulate_handle_ops_gas,
+            )
+        });
+        (provider, ep_v0_6, ep_v0_7)
+    };
+
     Ok(RundlerProviders {
-        provider: AlloyEvmProvider::new(provider),
+        provider,
         ep_v0_6,
         ep_v0_7,
         da_gas_oracle_sync,
@@ -641,7 +1016,8 @@ fn lint_da_gas_tracking(da_gas_tracking_enabled: bool, chain_spec: &ChainSpec) -
         tracing::warn!("DA tracking is disabled because DA pre-verification gas is not enabled");
         false
     } else if !(chain_spec.da_gas_oracle_type == DAGasOracleType::CachedNitro
-        || chain_spec.da_gas_oracle_type == DAGasOracleType::LocalBedrock)
+        || chain_spec.da_gas_oracle_type == DAGasOracleType::LocalBedrock
+        || chain_spec.da_gas_oracle_type == DAGasOracleType::LocalBlob)
     {
         tracing::warn!("DA tracking is disabled because DA gas oracle contract type {:?} does not support caching", chain_spec.da_gas_oracle_type);
         false
@@ -12,6 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use clap::Args;
+use metrics_exporter_prometheus::PrometheusHandle;
 use rundler_builder::{BuilderEvent, BuilderTask, LocalBuilderBuilder};
 use rundler_pool::{LocalPoolBuilder, PoolEvent, PoolTask};
 use rundler_rpc::RpcTask;
@@ -19,6 +20,7 @@ use rundler_task::TaskSpawnerExt;
 use rundler_types::chain::ChainSpec;
 use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use self::events::Event;
 use crate::cli::{
@@ -49,6 +51,9 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     chain_spec: ChainSpec,
     bundler_args: NodeCliArgs,
     common_args: CommonArgs,
+    metrics_handle: PrometheusHandle,
+    shutdown: CancellationToken,
+    rpc_drained: TaskTracker,
 ) -> anyhow::Result<()> {
     let NodeCliArgs {
         pool: pool_args,
@@ -69,6 +74,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         (&common_args).into(),
         (&common_args).try_into()?,
         (&common_args).try_into()?,
+        Some(metrics_handle),
     )?;
 
     let (event_sender, event_rx) =
@@ -131,7 +137,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     .await?;
 
     RpcTask::new(rpc_task_args, pool_handle, builder_handle, providers)
-        .spawn(task_spawner)
+        .spawn(task_spawner, shutdown, rpc_drained)
         .await?;
 
     Ok(())
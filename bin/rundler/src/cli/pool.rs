@@ -89,6 +89,13 @@ pub struct PoolArgs {
     )]
     pub allowlist_path: Option<String>,
 
+    #[arg(
+        long = "pool.call_target_allowlist_path",
+        name = "pool.call_target_allowlist_path",
+        env = "POOL_CALL_TARGET_ALLOWLIST_PATH"
+    )]
+    pub call_target_allowlist_path: Option<String>,
+
     /// Interval at which the pool polls an Eth node for new blocks
     #[arg(
         long = "pool.chain_poll_interval_millis",
@@ -140,6 +147,14 @@ pub struct PoolArgs {
     )]
     pub throttled_entity_live_blocks: u64,
 
+    #[arg(
+        long = "pool.throttled_entity_bundle_count",
+        name = "pool.throttled_entity_bundle_count",
+        env = "POOL_THROTTLED_ENTITY_BUNDLE_COUNT",
+        default_value = "1"
+    )]
+    pub throttled_entity_bundle_count: u64,
+
     #[arg(
         long = "pool.paymaster_tracking_enabled",
         name = "pool.paymaster_tracking_enabled",
@@ -179,6 +194,52 @@ pub struct PoolArgs {
         default_value = "0.0"
     )]
     pub gas_limit_efficiency_reject_threshold: f32,
+
+    #[arg(
+        long = "pool.sim_gas_penalty_percent",
+        name = "pool.sim_gas_penalty_percent",
+        env = "POOL_SIM_GAS_PENALTY_PERCENT",
+        default_value = "0"
+    )]
+    pub sim_gas_penalty_percent: u32,
+
+    #[arg(
+        long = "pool.revalidation_enabled",
+        name = "pool.revalidation_enabled",
+        env = "POOL_REVALIDATION_ENABLED",
+        default_value = "false"
+    )]
+    pub revalidation_enabled: bool,
+
+    #[arg(
+        long = "pool.revalidation_max_concurrency",
+        name = "pool.revalidation_max_concurrency",
+        env = "POOL_REVALIDATION_MAX_CONCURRENCY",
+        default_value = "16"
+    )]
+    pub revalidation_max_concurrency: usize,
+
+    #[arg(
+        long = "pool.congestion_base_fee_threshold",
+        name = "pool.congestion_base_fee_threshold",
+        env = "POOL_CONGESTION_BASE_FEE_THRESHOLD"
+    )]
+    pub congestion_base_fee_threshold: Option<u128>,
+
+    #[arg(
+        long = "pool.congestion_pool_fill_percent",
+        name = "pool.congestion_pool_fill_percent",
+        env = "POOL_CONGESTION_POOL_FILL_PERCENT"
+    )]
+    pub congestion_pool_fill_percent: Option<u32>,
+
+    #[arg(
+        long = "pool.congestion_fee_increase_percent",
+        name = "pool.congestion_fee_increase_percent",
+        env = "POOL_CONGESTION_FEE_INCREASE_PERCENT",
+        default_value = "0"
+    )]
+    pub congestion_fee_increase_percent: u32,
 }
 
 impl PoolArgs {
@@ -198,8 +259,13 @@ impl PoolArgs {
             Some(allowlist) => Some(get_json_config(allowlist).await?),
             None => None,
         };
+        let call_target_allowlist = match &self.call_target_allowlist_path {
+            Some(call_target_allowlist) => Some(get_json_config(call_target_allowlist).await?),
+            None => None,
+        };
         tracing::info!("blocklist: {:?}", blocklist);
         tracing::info!("allowlist: {:?}", allowlist);
+        tracing::info!("call target allowlist: {:?}", call_target_allowlist);
 
         let mempool_channel_configs = match &common.mempool_config_path {
             Some(path) => get_json_config::<MempoolConfigs>(path)
@@ -225,16 +291,24 @@ impl PoolArgs {
             max_size_of_pool_bytes: self.max_size_in_bytes,
             blocklist: blocklist.clone(),
             allowlist: allowlist.clone(),
+            call_target_allowlist: call_target_allowlist.clone(),
             precheck_settings: common.try_into()?,
             sim_settings: common.try_into()?,
             throttled_entity_mempool_count: self.throttled_entity_mempool_count,
             throttled_entity_live_blocks: self.throttled_entity_live_blocks,
+            throttled_entity_bundle_count: self.throttled_entity_bundle_count,
             paymaster_tracking_enabled: self.paymaster_tracking_enabled,
             paymaster_cache_length: self.paymaster_cache_length,
             reputation_tracking_enabled: self.reputation_tracking_enabled,
             drop_min_num_blocks: self.drop_min_num_blocks,
             da_gas_tracking_enabled,
             gas_limit_efficiency_reject_threshold: self.gas_limit_efficiency_reject_threshold,
+            sim_gas_penalty_percent: self.sim_gas_penalty_percent,
+            revalidation_enabled: self.revalidation_enabled,
+            revalidation_max_concurrency: self.revalidation_max_concurrency,
+            congestion_base_fee_threshold: self.congestion_base_fee_threshold,
+            congestion_pool_fill_percent: self.congestion_pool_fill_percent,
+            congestion_fee_increase_percent: self.congestion_fee_increase_percent,
         };
 
         let mut pool_configs = vec![];
@@ -11,16 +11,18 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use anyhow::Context;
 use clap::Args;
+use metrics_exporter_prometheus::PrometheusHandle;
 use rundler_builder::RemoteBuilderClient;
 use rundler_pool::RemotePoolClient;
 use rundler_rpc::{EthApiSettings, RpcTask, RpcTaskArgs, RundlerApiSettings};
 use rundler_sim::{EstimationSettings, PrecheckSettings};
 use rundler_task::{server::connect_with_retries_shutdown, TaskSpawnerExt};
 use rundler_types::chain::ChainSpec;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use super::CommonArgs;
 
@@ -66,6 +68,15 @@ pub struct RpcArgs {
     )]
     timeout_seconds: String,
 
+    /// Timeouts for specific methods overriding `rpc.timeout_seconds`, as `method=seconds` pairs
+    #[arg(
+        long = "rpc.method_timeouts",
+        name = "rpc.method_timeouts",
+        env = "RPC_METHOD_TIMEOUTS",
+        value_delimiter = ','
+    )]
+    method_timeouts: Vec<String>,
+
     /// Maximum number of concurrent connections
     #[arg(
         long = "rpc.max_connections",
@@ -74,6 +85,73 @@ pub struct RpcArgs {
         default_value = "100"
     )]
     max_connections: u32,
+
+    /// Maximum number of method calls executing at once, additional calls are queued
+    #[arg(
+        long = "rpc.max_concurrent_requests",
+        name = "rpc.max_concurrent_requests",
+        env = "RPC_MAX_CONCURRENT_REQUESTS",
+        default_value = "100"
+    )]
+    max_concurrent_requests: usize,
+
+    /// Maximum number of active subscriptions per connection
+    #[arg(
+        long = "rpc.max_subscriptions_per_connection",
+        name = "rpc.max_subscriptions_per_connection",
+        env = "RPC_MAX_SUBSCRIPTIONS_PER_CONNECTION",
+        default_value = "1024"
+    )]
+    max_subscriptions_per_connection: u32,
+
+    /// Whether to serve WebSocket connections in addition to HTTP
+    #[arg(
+        long = "rpc.ws_enabled",
+        name = "rpc.ws_enabled",
+        env = "RPC_WS_ENABLED",
+        default_value = "false"
+    )]
+    ws_enabled: bool,
+
+    /// Port to listen on for WebSocket connections, defaults to the JSON-RPC port
+    #[arg(long = "rpc.ws_port", name = "rpc.ws_port", env = "RPC_WS_PORT")]
+    ws_port: Option<u16>,
+
+    /// Whether to route requests that don't specify an entry point to the only enabled entry point version
+    #[arg(
+        long = "rpc.entry_point_fallback",
+        name = "rpc.entry_point_fallback",
+        env = "RPC_ENTRY_POINT_FALLBACK",
+        default_value = "false"
+    )]
+    entry_point_fallback: bool,
+
+    /// Max time to wait for in-flight requests to complete on shutdown
+    #[arg(
+        long = "rpc.shutdown_grace_period_seconds",
+        name = "rpc.shutdown_grace_period_seconds",
+        env = "RPC_SHUTDOWN_GRACE_PERIOD_SECONDS",
+        default_value = "5"
+    )]
+    shutdown_grace_period_seconds: u64,
+
+    /// Max number of blocks the pool may lag behind the node before the health check fails
+    #[arg(
+        long = "rpc.max_provider_block_lag",
+        name = "rpc.max_provider_block_lag",
+        env = "RPC_MAX_PROVIDER_BLOCK_LAG",
+        default_value = "10"
+    )]
+    max_provider_block_lag: u64,
+
+    /// Whether to serve Prometheus metrics on GET /metrics from the JSON-RPC port
+    #[arg(
+        long = "rpc.metrics_enabled",
+        name = "rpc.metrics_enabled",
+        env = "RPC_METRICS_ENABLED",
+        default_value = "false"
+    )]
+    metrics_enabled: bool,
 }
 
 impl RpcArgs {
@@ -87,12 +165,29 @@ impl RpcArgs {
         eth_api_settings: EthApiSettings,
         rundler_api_settings: RundlerApiSettings,
         estimation_settings: EstimationSettings,
+        metrics_handle: Option<PrometheusHandle>,
     ) -> anyhow::Result<RpcTaskArgs> {
         let apis = self
             .api
             .iter()
             .map(|api| api.parse())
             .collect::<Result<Vec<_>, _>>()?;
+        if self.max_concurrent_requests == 0 {
+            anyhow::bail!("rpc.max_concurrent_requests must be greater than 0");
+        }
+        let method_timeouts = self
+            .method_timeouts
+            .iter()
+            .map(|entry| {
+                let (method, seconds) = entry.split_once('=').with_context(|| {
+                    format!("invalid rpc.method_timeouts entry {entry}, expected method=seconds")
+                })?;
+                let seconds = seconds.parse().with_context(|| {
+                    format!("invalid timeout in rpc.method_timeouts entry {entry}")
+                })?;
+                Ok((method.to_string(), Duration::from_secs(seconds)))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
 
         Ok(RpcTaskArgs {
             chain_spec,
@@ -106,9 +201,18 @@ impl RpcArgs {
             rundler_api_settings,
             estimation_settings,
             rpc_timeout: Duration::from_secs(self.timeout_seconds.parse()?),
+            method_timeouts,
             max_connections: self.max_connections,
+            max_concurrent_requests: self.max_concurrent_requests,
+            max_subscriptions_per_connection: self.max_subscriptions_per_connection,
             entry_point_v0_6_enabled: !common.disable_entry_point_v0_6,
             entry_point_v0_7_enabled: !common.disable_entry_point_v0_7,
+            metrics_handle: metrics_handle.filter(|_| self.metrics_enabled),
+            ws_enabled: self.ws_enabled,
+            ws_port: self.ws_port,
+            entry_point_fallback: self.entry_point_fallback,
+            shutdown_grace_period: Duration::from_secs(self.shutdown_grace_period_seconds),
+            max_provider_block_lag: self.max_provider_block_lag,
         })
     }
 }
@@ -143,6 +247,9 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
     chain_spec: ChainSpec,
     rpc_args: RpcCliArgs,
     common_args: CommonArgs,
+    metrics_handle: PrometheusHandle,
+    shutdown: CancellationToken,
+    rpc_drained: TaskTracker,
 ) -> anyhow::Result<()> {
     let RpcCliArgs {
         rpc: rpc_args,
@@ -157,6 +264,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         (&common_args).into(),
         (&common_args).try_into()?,
         (&common_args).try_into()?,
+        Some(metrics_handle),
     )?;
 
     let pool = connect_with_retries_shutdown(
@@ -181,7 +289,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         builder,
         super::construct_providers(&common_args, &chain_spec)?,
     )
-    .spawn(task_spawner)
+    .spawn(task_spawner, shutdown, rpc_drained)
     .await?;
 
     Ok(())
@@ -16,7 +16,7 @@ use std::{
     future::Future,
     mem,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use alloy_primitives::{Address, Bytes, B256, U256};
@@ -35,9 +35,10 @@ use rundler_provider::{
 };
 use rundler_sim::{
     ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError, SimulationResult, Simulator,
-    ViolationError,
+    StorageWrites, ViolationError,
 };
 use rundler_types::{
+    builder::BundleBuildReport,
     chain::ChainSpec,
     da::DAGasBlockData,
     pool::{Pool, PoolOperation, SimulationViolation},
@@ -49,7 +50,9 @@ use rundler_utils::{emit::WithEntryPoint, guard_timer::CustomTimerGuard, math};
 use tokio::{sync::broadcast, try_join};
 use tracing::{debug, error, info, warn};
 
-use crate::emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason};
+use crate::emit::{
+    BuilderEvent, BuilderEventKind, ConditionNotMetReason, OpRejectionReason, SkipReason,
+};
 
 /// Extra buffer percent to add on the bundle transaction gas estimate to be sure it will be enough
 const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u32 = 5;
@@ -62,6 +65,8 @@ pub(crate) struct Bundle<UO: UserOperation> {
     pub(crate) expected_storage: ExpectedStorage,
     pub(crate) rejected_ops: Vec<UO>,
     pub(crate) entity_updates: Vec<EntityUpdate>,
+    /// Expected fee payment to the beneficiary for each op, in the order of `iter_ops`
+    pub(crate) op_revenues: Vec<U256>,
 }
 
 impl<UO: UserOperation> Default for Bundle<UO> {
@@ -73,6 +78,7 @@ impl<UO: UserOperation> Default for Bundle<UO> {
             expected_storage: ExpectedStorage::default(),
             rejected_ops: Vec::new(),
             entity_updates: Vec::new(),
+            op_revenues: Vec::new(),
         }
     }
 }
@@ -92,6 +98,27 @@ impl<UO: UserOperation> Bundle<UO> {
     pub(crate) fn iter_ops(&self) -> impl Iterator<Item = &UO> + '_ {
         self.ops_per_aggregator.iter().flat_map(|ops| &ops.user_ops)
     }
+
+    /// Total expected fee payment to the beneficiary across all ops in the bundle
+    pub(crate) fn expected_revenue(&self) -> U256 {
+        self.op_revenues.iter().sum()
+    }
+
+    /// Calldata bytes each op contributes to the bundle transaction, including its offset word,
+    /// as `(index in iter_ops, size)` pairs sorted largest first
+    pub(crate) fn ops_by_calldata_size(&self) -> Vec<(usize, usize)> {
+        let mut sizes: Vec<_> = self
+            .iter_ops()
+            .map(|op| {
+                op.abi_encoded_size()
+                    .saturating_add(USER_OP_OFFSET_WORD_SIZE)
+            })
+            .enumerate()
+            .collect();
+        // stable sort keeps bundle order among ops of equal size
+        sizes.sort_by(|a, b| b.1.cmp(&a.1));
+        sizes
+    }
 }
 
 #[async_trait]
@@ -144,6 +171,26 @@ pub(crate) struct BundleProposerImpl<EP, BP> {
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     condition_not_met_notified: bool,
     metric: BuilderProposerMetric,
+    build_report: Mutex<BundleBuildReport>,
+    last_build_report: LastBundleBuildReport,
+}
+
+/// The report of the most recent bundle build attempt of each bundle proposer, keyed by
+/// builder index and entry point
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LastBundleBuildReport(Arc<Mutex<BTreeMap<(u64, Address), BundleBuildReport>>>);
+
+impl LastBundleBuildReport {
+    pub(crate) fn get(&self) -> Vec<BundleBuildReport> {
+        self.0.lock().unwrap().values().cloned().collect()
+    }
+
+    fn set(&self, report: BundleBuildReport) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert((report.builder_index, report.entry_point), report);
+    }
 }
 
 #[derive(Debug)]
@@ -156,6 +203,23 @@ pub(crate) struct Settings {
     pub(crate) bundle_priority_fee_overhead_percent: u32,
     pub(crate) priority_fee_mode: PriorityFeeMode,
     pub(crate) da_gas_tracking_enabled: bool,
+    pub(crate) aggregated_ops_policy: AggregatedOpsPolicy,
+    pub(crate) aggregator_overhead_gas: u128,
+}
+
+/// Determines how the builder handles ops using a signature aggregator whose fees
+/// don't cover the aggregator's signature verification overhead
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum AggregatedOpsPolicy {
+    /// Include aggregated ops in pool order regardless of the aggregation overhead
+    #[default]
+    Include,
+    /// Order an aggregator's ops after all other ops if their fees don't cover the
+    /// aggregation overhead, so that they only fill remaining bundle space
+    Deprioritize,
+    /// Skip an aggregator's ops if their fees don't cover the aggregation overhead
+    Exclude,
 }
 
 #[async_trait]
@@ -186,6 +250,66 @@ where
         required_fees: Option<GasFees>,
         is_replacement: bool,
     ) -> BundleProposerResult<Bundle<Self::UO>> {
+        *self.build_report.get_mut().unwrap() = BundleBuildReport {
+            builder_index: self.builder_index,
+            entry_point: *self.ep_providers.entry_point().address(),
+            ..Default::default()
+        };
+
+        let result = self.build_bundle(required_fees, is_replacement).await;
+
+        let mut report = mem::take(self.build_report.get_mut().unwrap());
+        match &result {
+            Ok(bundle) => report.ops_included = bundle.len() as u64,
+            Err(error) => report.error = Some(error.to_string()),
+        }
+        self.last_build_report.set(report);
+
+        result
+    }
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "builder_proposer")]
+struct BuilderProposerMetric {
+    #[metric(describe = "the distribution of end to end bundle build time.")]
+    bundle_build_ms: Histogram,
+    #[metric(describe = "the distribution of op simulation time of a bundle.")]
+    op_simulation_ms: Histogram,
+}
+
+impl<EP, BP> BundleProposerImpl<EP, BP>
+where
+    EP: ProvidersWithEntryPointT,
+    BP: BundleProposerProvidersT,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        builder_index: u64,
+        ep_providers: EP,
+        bundle_providers: BP,
+        settings: Settings,
+        event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+        last_build_report: LastBundleBuildReport,
+    ) -> Self {
+        Self {
+            builder_index,
+            ep_providers,
+            bundle_providers,
+            settings,
+            event_sender,
+            condition_not_met_notified: false,
+            metric: BuilderProposerMetric::default(),
+            build_report: Mutex::new(BundleBuildReport::default()),
+            last_build_report,
+        }
+    }
+
+    async fn build_bundle(
+        &mut self,
+        required_fees: Option<GasFees>,
+        is_replacement: bool,
+    ) -> BundleProposerResult<Bundle<<Self as BundleProposer>::UO>> {
         let _bundler_build_timer = CustomTimerGuard::new(self.metric.bundle_build_ms.clone());
         let (ops, (block_hash, _), (bundle_fees, base_fee)) = try_join!(
             self.get_ops_from_pool(),
@@ -195,6 +319,7 @@ where
                 .map_err(BundleProposerError::from),
             self.estimate_gas_fees(required_fees)
         )?;
+        self.build_report.get_mut().unwrap().ops_considered = ops.len() as u64;
         if ops.is_empty() {
             return Err(BundleProposerError::NoOperationsInitially);
         }
@@ -279,6 +404,8 @@ where
             .into_iter()
             .flatten()
             .collect::<Vec<_>>();
+        let ops_with_simulations =
+            self.order_aggregated_ops(ops_with_simulations, bundle_fees, base_fee);
         let mut context = self
             .assemble_context(ops_with_simulations, balances_by_paymaster)
             .await;
@@ -310,6 +437,7 @@ where
                     gas_estimate,
                     gas_fees: bundle_fees,
                     expected_storage,
+                    op_revenues: context.op_revenues(&self.settings.chain_spec, base_fee),
                     rejected_ops: context.rejected_ops.iter().map(|po| po.0.clone()).collect(),
                     entity_updates: context.entity_updates.into_values().collect(),
                 });
@@ -324,40 +452,6 @@ where
             ..Default::default()
         })
     }
-}
-
-#[derive(Metrics)]
-#[metrics(scope = "builder_proposer")]
-struct BuilderProposerMetric {
-    #[metric(describe = "the distribution of end to end bundle build time.")]
-    bundle_build_ms: Histogram,
-    #[metric(describe = "the distribution of op simulation time of a bundle.")]
-    op_simulation_ms: Histogram,
-}
-
-impl<EP, BP> BundleProposerImpl<EP, BP>
-where
-    EP: ProvidersWithEntryPointT,
-    BP: BundleProposerProvidersT,
-{
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn new(
-        builder_index: u64,
-        ep_providers: EP,
-        bundle_providers: BP,
-        settings: Settings,
-        event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
-    ) -> Self {
-        Self {
-            builder_index,
-            ep_providers,
-            bundle_providers,
-            settings,
-            event_sender,
-            condition_not_met_notified: false,
-            metric: BuilderProposerMetric::default(),
-        }
-    }
 
     // Check fees for a single user op. Returns None if the op should be skipped.
     //
@@ -512,6 +606,78 @@ where
         Some(result)
     }
 
+    // Apply the aggregated ops policy to the simulated ops. Returns the ops in the order
+    // they should be considered for the bundle.
+    //
+    // An aggregator's ops are considered worth including if the fees they pay above the
+    // bundle's gas price cover the cost of the aggregator's signature verification overhead.
+    fn order_aggregated_ops(
+        &self,
+        ops_with_simulations: Vec<(PoolOperation, Result<SimulationResult, SimulationError>)>,
+        bundle_fees: GasFees,
+        base_fee: u128,
+    ) -> Vec<(PoolOperation, Result<SimulationResult, SimulationError>)> {
+        if self.settings.aggregated_ops_policy == AggregatedOpsPolicy::Include {
+            return ops_with_simulations;
+        }
+
+        let bundle_gas_price = bundle_fees
+            .max_fee_per_gas
+            .min(base_fee.saturating_add(bundle_fees.max_priority_fee_per_gas));
+        let overhead_cost = self
+            .settings
+            .aggregator_overhead_gas
+            .saturating_mul(bundle_gas_price);
+
+        let mut surplus_by_aggregator = HashMap::<Address, u128>::new();
+        for (po, simulation) in &ops_with_simulations {
+            let Some(aggregator) = simulation
+                .as_ref()
+                .ok()
+                .and_then(|s| s.aggregator_address())
+            else {
+                continue;
+            };
+            let surplus = po
+                .uo
+                .gas_price(base_fee)
+                .saturating_sub(bundle_gas_price)
+                .saturating_mul(po.uo.execution_gas_limit(&self.settings.chain_spec, None));
+            let total = surplus_by_aggregator.entry(aggregator).or_default();
+            *total = total.saturating_add(surplus);
+        }
+
+        let (mut ordered, deprioritized): (Vec<_>, Vec<_>) = ops_with_simulations
+            .into_iter()
+            .partition(|(_, simulation)| {
+                simulation
+                    .as_ref()
+                    .ok()
+                    .and_then(|s| s.aggregator_address())
+                    .map_or(true, |aggregator| {
+                        surplus_by_aggregator[&aggregator] >= overhead_cost
+                    })
+            });
+
+        if self.settings.aggregated_ops_policy == AggregatedOpsPolicy::Deprioritize {
+            ordered.extend(deprioritized);
+        } else {
+            for (po, simulation) in deprioritized {
+                let aggregator = simulation
+                    .ok()
+                    .and_then(|s| s.aggregator_address())
+                    .unwrap_or_default();
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&po.uo),
+                    SkipReason::AggregationOverhead { aggregator },
+                ));
+            }
+        }
+
+        ordered
+    }
+
     async fn assemble_context(
         &self,
         ops_with_simulations: Vec<(PoolOperation, Result<SimulationResult, SimulationError>)>,
@@ -524,6 +690,9 @@ where
         let mut context = ProposalContext::<<Self as BundleProposer>::UO>::new();
         let mut paymasters_to_reject = Vec::<EntityInfo>::new();
 
+        let mut storage_access =
+            BundleStorageAccess::new(*self.ep_providers.entry_point().address());
+
         let mut gas_spent = rundler_types::bundle_shared_gas(&self.settings.chain_spec);
         let mut constructed_bundle_size = BUNDLE_BYTE_OVERHEAD;
         for (po, simulation) in ops_with_simulations {
@@ -575,12 +744,14 @@ where
             if op_size_with_offset_word.saturating_add(constructed_bundle_size)
                 >= self.settings.chain_spec.max_transaction_size_bytes
             {
+                self.record_skipped_op("transaction_size");
                 continue;
             }
 
             // Skip this op if the bundle does not have enough remaining gas to execute it.
             let required_gas = gas_spent + op.execution_gas_limit(&self.settings.chain_spec, None);
             if required_gas > self.settings.max_bundle_gas {
+                self.record_skipped_op("bundle_gas_limit");
                 continue;
             }
 
@@ -599,6 +770,17 @@ where
                 ));
                 continue;
             }
+            if let Some((address, slot)) = storage_access.find_conflict(&simulation) {
+                // Exclude ops whose validation writes a slot accessed by another op
+                // in the bundle (or vice versa), as they could invalidate each other.
+                info!("Excluding op from {:?} because its validation storage access conflicts with another op in the bundle.", op.sender());
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op),
+                    SkipReason::ConflictingStorageAccess { address, slot },
+                ));
+                continue;
+            }
             if let Some(paymaster) = op.paymaster() {
                 let Some(balance) = balances_by_paymaster.get_mut(&paymaster) else {
                     error!("Op had paymaster with unknown balance, but balances should have been loaded for all paymasters in bundle.");
@@ -608,6 +790,7 @@ where
                 if *balance < max_cost {
                     info!("Rejected paymaster {paymaster:?} because its balance {balance:?} was too low.");
                     paymasters_to_reject.push(po.entity_infos.paymaster.unwrap());
+                    self.record_skipped_op("paymaster_balance");
                     continue;
                 } else {
                     *balance -= max_cost;
@@ -620,6 +803,8 @@ where
             constructed_bundle_size =
                 constructed_bundle_size.saturating_add(op_size_with_offset_word);
 
+            storage_access.add(&simulation);
+
             context
                 .groups_by_aggregator
                 .entry(simulation.aggregator_address())
@@ -1085,12 +1270,27 @@ where
     }
 
     fn emit(&self, event: BuilderEvent) {
+        match &event.kind {
+            BuilderEventKind::SkippedOp { reason, .. } => self.record_skipped_op(reason.into()),
+            BuilderEventKind::RejectedOp { reason, .. } => self.record_skipped_op(reason.into()),
+            _ => {}
+        }
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: *self.ep_providers.entry_point().address(),
             event,
         });
     }
 
+    fn record_skipped_op(&self, reason: &'static str) {
+        *self
+            .build_report
+            .lock()
+            .unwrap()
+            .ops_skipped
+            .entry(reason.to_string())
+            .or_default() += 1;
+    }
+
     fn op_hash<T>(&self, op: &T) -> B256
     where
         T: UserOperation,
@@ -1175,6 +1375,107 @@ impl<UO: UserOperation> OpWithSimulation<UO> {
     }
 }
 
+/// The storage slots accessed during validation by the ops included in a bundle
+/// so far, used to detect ops whose validation storage access conflicts.
+///
+/// Storage of the entry point is ignored, as its deposit and nonce accounting is
+/// handled by the entry point itself during bundle execution. Storage of an op's
+/// staked entities is also ignored, as staked entities may share their storage
+/// across ops and are throttled by reputation instead.
+#[derive(Debug)]
+struct BundleStorageAccess {
+    entry_point: Address,
+    accessed: HashMap<Address, HashSet<B256>>,
+    written: StorageWrites,
+}
+
+impl BundleStorageAccess {
+    fn new(entry_point: Address) -> Self {
+        Self {
+            entry_point,
+            accessed: HashMap::new(),
+            written: StorageWrites::default(),
+        }
+    }
+
+    /// Returns a slot that the op writes and an included op accessed, or that the
+    /// op accesses and an included op wrote, if any.
+    fn find_conflict(&self, simulation: &SimulationResult) -> Option<(Address, B256)> {
+        let checked = self.checked_addresses(simulation);
+
+        let writes = simulation
+            .storage_writes
+            .0
+            .iter()
+            .filter(|(address, _)| checked(address))
+            .flat_map(|(address, slots)| slots.iter().map(move |slot| (*address, *slot)));
+        for (address, slot) in writes {
+            if self
+                .accessed
+                .get(&address)
+                .map_or(false, |slots| slots.contains(&slot))
+            {
+                return Some((address, slot));
+            }
+        }
+
+        let reads = simulation
+            .expected_storage
+            .0
+            .iter()
+            .filter(|(address, _)| checked(address))
+            .flat_map(|(address, slots)| slots.keys().map(move |slot| (*address, *slot)));
+        for (address, slot) in reads {
+            if self.written.contains(address, slot) {
+                return Some((address, slot));
+            }
+        }
+
+        None
+    }
+
+    /// Adds the storage access of an op included in the bundle.
+    fn add(&mut self, simulation: &SimulationResult) {
+        let checked = self.checked_addresses(simulation);
+
+        for (address, slots) in &simulation.expected_storage.0 {
+            if !checked(address) {
+                continue;
+            }
+            self.accessed
+                .entry(*address)
+                .or_default()
+                .extend(slots.keys().copied());
+        }
+        for (address, slots) in &simulation.storage_writes.0 {
+            if !checked(address) {
+                continue;
+            }
+            self.accessed
+                .entry(*address)
+                .or_default()
+                .extend(slots.iter().copied());
+            self.written
+                .0
+                .entry(*address)
+                .or_default()
+                .extend(slots.iter().copied());
+        }
+    }
+
+    /// Returns a filter for the addresses whose storage is checked for conflicts
+    fn checked_addresses(&self, simulation: &SimulationResult) -> impl Fn(&Address) -> bool {
+        let entry_point = self.entry_point;
+        let staked: HashSet<Address> = simulation
+            .entity_infos
+            .entities()
+            .filter(|(_, info)| info.is_staked())
+            .map(|(_, info)| info.address())
+            .collect();
+        move |address| *address != entry_point && !staked.contains(address)
+    }
+}
+
 /// A struct used internally to represent the current state of a proposed bundle
 /// as it goes through iterations. Contains similar data to the
 /// `Vec<UserOpsPerAggregator>` that will eventually be passed to the entry
@@ -1381,6 +1682,18 @@ impl<UO: UserOperation> ProposalContext<UO> {
         self.iter_ops_with_simulations().map(|op| &op.op)
     }
 
+    /// Expected fee payment to the beneficiary for each op, assuming each op is charged
+    /// its full gas limit at its effective gas price for the given base fee.
+    fn op_revenues(&self, chain_spec: &ChainSpec, base_fee: u128) -> Vec<U256> {
+        let bundle_size = self.iter_ops().count();
+        self.iter_ops()
+            .map(|op| {
+                U256::from(op.gas_limit(chain_spec, Some(bundle_size)))
+                    * U256::from(op.gas_price(base_fee))
+            })
+            .collect()
+    }
+
     // Go through the simulation violations for a given op and add all entity updates to pass to the mempool in entity_updates
     fn process_simulation_violations(
         &mut self,
@@ -1639,6 +1952,126 @@ mod tests {
         assert_eq!(bundle.gas_estimate, expected_gas);
     }
 
+    #[tokio::test]
+    async fn test_op_revenues_sum_to_bundle_revenue() {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        let op1 = UserOperation {
+            sender: address(1),
+            max_fee_per_gas: 2000,
+            max_priority_fee_per_gas: 200,
+            call_gas_limit: 50_000,
+            verification_gas_limit: 10_000,
+            pre_verification_gas: DEFAULT_PVG,
+            ..Default::default()
+        };
+        // max fee caps the effective gas price below base fee + priority fee
+        let op2 = UserOperation {
+            sender: address(2),
+            max_fee_per_gas: 1100,
+            max_priority_fee_per_gas: 500,
+            call_gas_limit: 100_000,
+            verification_gas_limit: 20_000,
+            pre_verification_gas: DEFAULT_PVG,
+            ..Default::default()
+        };
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+            false,
+            ExpectedStorage::default(),
+            false,
+        )
+        .await;
+
+        let cs = ChainSpec::default();
+        let expected_revenues: Vec<_> = [(&op1, 1200), (&op2, 1100)]
+            .into_iter()
+            .map(|(op, gas_price)| U256::from(op.gas_limit(&cs, Some(2)) * gas_price))
+            .collect();
+        assert_eq!(bundle.len(), 2);
+        assert_eq!(bundle.op_revenues, expected_revenues);
+        assert_eq!(
+            bundle.expected_revenue(),
+            expected_revenues.iter().sum::<U256>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_bundle_has_no_revenue() {
+        let bundle = simple_make_bundle(vec![MockOp {
+            op: default_op(),
+            simulation_result: Box::new(|| {
+                Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![]),
+                    entity_infos: None,
+                })
+            }),
+        }])
+        .await;
+        assert!(bundle.is_empty());
+        assert!(bundle.op_revenues.is_empty());
+        assert_eq!(bundle.expected_revenue(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_ops_by_calldata_size() {
+        let op_with_data = |call_data_len: usize, signature_len: usize| UserOperation {
+            call_data: vec![1; call_data_len].into(),
+            signature: vec![2; signature_len].into(),
+            ..default_op()
+        };
+        let ops = vec![
+            op_with_data(10, 65),
+            op_with_data(300, 65),
+            op_with_data(10, 65),
+            op_with_data(100, 200),
+        ];
+        let bundle = Bundle {
+            ops_per_aggregator: vec![
+                UserOpsPerAggregator {
+                    user_ops: ops[..2].to_vec(),
+                    ..Default::default()
+                },
+                UserOpsPerAggregator {
+                    user_ops: ops[2..].to_vec(),
+                    aggregator: address(1),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let sizes = bundle.ops_by_calldata_size();
+        assert_eq!(
+            sizes.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![1, 3, 0, 2]
+        );
+        for (i, size) in &sizes {
+            assert_eq!(*size, ops[*i].abi_encoded_size() + USER_OP_OFFSET_WORD_SIZE);
+        }
+        assert!(sizes.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn test_ops_by_calldata_size_empty_bundle() {
+        let bundle = Bundle::<UserOperation>::default();
+        assert!(bundle.ops_by_calldata_size().is_empty());
+    }
+
     #[tokio::test]
     async fn test_rejects_on_violation() {
         let op = default_op();
@@ -1750,6 +2183,138 @@ mod tests {
         assert!(bundle.rejected_ops.is_empty())
     }
 
+    #[tokio::test]
+    async fn test_skips_but_not_rejects_op_with_conflicting_storage_access() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(|| {
+                    let mut storage_writes = StorageWrites::default();
+                    storage_writes.insert(address(3), U256::from(1));
+                    Ok(SimulationResult {
+                        storage_writes,
+                        ..Default::default()
+                    })
+                }),
+            },
+            MockOp {
+                op: op2,
+                simulation_result: Box::new(|| {
+                    let mut expected_storage = ExpectedStorage::default();
+                    expected_storage.insert(address(3), U256::from(1), U256::ZERO);
+                    Ok(SimulationResult {
+                        expected_storage,
+                        ..Default::default()
+                    })
+                }),
+            },
+        ])
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty())
+    }
+
+    #[tokio::test]
+    async fn test_includes_ops_with_non_conflicting_storage_access() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(|| {
+                    let mut expected_storage = ExpectedStorage::default();
+                    expected_storage.insert(address(3), U256::from(1), U256::ZERO);
+                    let mut storage_writes = StorageWrites::default();
+                    storage_writes.insert(address(3), U256::from(2));
+                    Ok(SimulationResult {
+                        expected_storage,
+                        storage_writes,
+                        ..Default::default()
+                    })
+                }),
+            },
+            MockOp {
+                op: op2.clone(),
+                simulation_result: Box::new(|| {
+                    let mut expected_storage = ExpectedStorage::default();
+                    expected_storage.insert(address(3), U256::from(1), U256::ZERO);
+                    let mut storage_writes = StorageWrites::default();
+                    storage_writes.insert(address(3), U256::from(3));
+                    Ok(SimulationResult {
+                        expected_storage,
+                        storage_writes,
+                        ..Default::default()
+                    })
+                }),
+            },
+        ])
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1, op2],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty())
+    }
+
+    #[tokio::test]
+    async fn test_includes_ops_sharing_staked_entity_storage() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let staked_paymaster = |entity_infos: &mut EntityInfos| {
+            entity_infos.set_paymaster(address(3), true);
+        };
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(move || {
+                    let mut storage_writes = StorageWrites::default();
+                    storage_writes.insert(address(3), U256::from(1));
+                    let mut entity_infos = EntityInfos::default();
+                    staked_paymaster(&mut entity_infos);
+                    Ok(SimulationResult {
+                        storage_writes,
+                        entity_infos,
+                        ..Default::default()
+                    })
+                }),
+            },
+            MockOp {
+                op: op2.clone(),
+                simulation_result: Box::new(move || {
+                    let mut expected_storage = ExpectedStorage::default();
+                    expected_storage.insert(address(3), U256::from(1), U256::ZERO);
+                    let mut entity_infos = EntityInfos::default();
+                    staked_paymaster(&mut entity_infos);
+                    Ok(SimulationResult {
+                        expected_storage,
+                        entity_infos,
+                        ..Default::default()
+                    })
+                }),
+            },
+        ])
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1, op2],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty())
+    }
+
     #[tokio::test]
     async fn test_skips_but_not_rejects_op_with_too_low_max_priority_fee() {
         // With 10% required overhead on priority fee, op1 should be excluded
@@ -1789,6 +2354,82 @@ mod tests {
         assert!(bundle.rejected_ops.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_build_report_all_ops_underpriced() {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        let op1 = op_with_sender_and_fees(address(1), 2049, 49);
+        let op2 = op_with_sender_and_fees(address(2), 1049, 50);
+        let last_build_report = LastBundleBuildReport::default();
+        let result = mock_try_make_bundle(
+            vec![
+                MockOp {
+                    op: op1,
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2,
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+            false,
+            ExpectedStorage::default(),
+            false,
+            AggregatedOpsPolicy::Include,
+            last_build_report.clone(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BundleProposerError::NoOperationsAfterFeeFilter)
+        ));
+
+        let reports = last_build_report.get();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.entry_point, address(123));
+        assert_eq!(report.ops_considered, 2);
+        assert_eq!(report.ops_included, 0);
+        assert_eq!(
+            report.ops_skipped,
+            BTreeMap::from([("insufficient_fees".to_string(), 2)])
+        );
+        assert_eq!(
+            report.error,
+            Some(BundleProposerError::NoOperationsAfterFeeFilter.to_string())
+        );
+    }
+
+    #[test]
+    fn test_last_build_report_per_builder_and_entry_point() {
+        let last_build_report = LastBundleBuildReport::default();
+        let report = |builder_index, entry_point, ops_included| BundleBuildReport {
+            builder_index,
+            entry_point,
+            ops_included,
+            ..Default::default()
+        };
+        last_build_report.set(report(0, address(1), 1));
+        last_build_report.set(report(1, address(1), 2));
+        last_build_report.set(report(0, address(2), 3));
+        // replaces the first report of builder 0 on entry point 1
+        last_build_report.set(report(0, address(1), 4));
+
+        assert_eq!(
+            last_build_report.get(),
+            vec![
+                report(0, address(1), 4),
+                report(0, address(2), 3),
+                report(1, address(1), 2),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_skips_but_not_rejects_op_with_too_low_max_fee_per_gas() {
         let base_fee = 1000;
@@ -1998,6 +2639,87 @@ mod tests {
         );
     }
 
+    // One op using an aggregator that pays exactly the bundle fees, and so can't
+    // cover the aggregation overhead, and one op without an aggregator.
+    async fn make_bundle_with_single_aggregated_op(
+        policy: AggregatedOpsPolicy,
+    ) -> (Bundle<UserOperation>, UserOperation, UserOperation) {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        let aggregated_op = op_with_sender_and_fees(address(1), 1050, 50);
+        let unaggregated_op = op_with_sender_and_fees(address(2), 1050, 50);
+        let aggregator_address = address(10);
+        let bundle = mock_make_bundle_with_policy(
+            vec![
+                MockOp {
+                    op: aggregated_op.clone(),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            aggregator: Some(AggregatorSimOut {
+                                address: aggregator_address,
+                                signature: bytes(11),
+                            }),
+                            ..Default::default()
+                        })
+                    }),
+                },
+                MockOp {
+                    op: unaggregated_op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![MockAggregator {
+                address: aggregator_address,
+                signature: Box::new(move || Ok(Some(bytes(101)))),
+            }],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+            false,
+            ExpectedStorage::default(),
+            false,
+            policy,
+        )
+        .await;
+        let aggregated_op = UserOperation {
+            signature: Bytes::new(),
+            ..aggregated_op
+        };
+        (bundle, aggregated_op, unaggregated_op)
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_ops_policy_include() {
+        let (bundle, aggregated_op, unaggregated_op) =
+            make_bundle_with_single_aggregated_op(AggregatedOpsPolicy::Include).await;
+        let ops: Vec<_> = bundle.iter_ops().cloned().collect();
+        assert_eq!(ops, vec![aggregated_op, unaggregated_op]);
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_ops_policy_deprioritize_single_op() {
+        let (bundle, aggregated_op, unaggregated_op) =
+            make_bundle_with_single_aggregated_op(AggregatedOpsPolicy::Deprioritize).await;
+        let ops: Vec<_> = bundle.iter_ops().cloned().collect();
+        assert_eq!(ops, vec![unaggregated_op, aggregated_op]);
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_ops_policy_exclude_single_op() {
+        let (bundle, _, unaggregated_op) =
+            make_bundle_with_single_aggregated_op(AggregatedOpsPolicy::Exclude).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![unaggregated_op],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
     #[tokio::test]
     async fn test_reject_entities() {
         let op1 = op_with_sender_paymaster(address(1), address(1));
@@ -2672,6 +3394,65 @@ mod tests {
         actual_storage: ExpectedStorage,
         da_gas_tracking_enabled: bool,
     ) -> Bundle<UserOperation> {
+        mock_make_bundle_with_policy(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            notify_condition_not_met,
+            actual_storage,
+            da_gas_tracking_enabled,
+            AggregatedOpsPolicy::Include,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_make_bundle_with_policy(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: u128,
+        max_priority_fee_per_gas: u128,
+        notify_condition_not_met: bool,
+        actual_storage: ExpectedStorage,
+        da_gas_tracking_enabled: bool,
+        aggregated_ops_policy: AggregatedOpsPolicy,
+    ) -> Bundle<UserOperation> {
+        mock_try_make_bundle(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            notify_condition_not_met,
+            actual_storage,
+            da_gas_tracking_enabled,
+            aggregated_ops_policy,
+            LastBundleBuildReport::default(),
+        )
+        .await
+        .expect("should make a bundle")
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_try_make_bundle(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: u128,
+        max_priority_fee_per_gas: u128,
+        notify_condition_not_met: bool,
+        actual_storage: ExpectedStorage,
+        da_gas_tracking_enabled: bool,
+        aggregated_ops_policy: AggregatedOpsPolicy,
+        last_build_report: LastBundleBuildReport,
+    ) -> BundleProposerResult<Bundle<UserOperation>> {
         let entry_point_address = address(123);
         let beneficiary = address(124);
         let current_block_hash = hash(125);
@@ -2685,6 +3466,8 @@ mod tests {
                 entry_point: entry_point_address,
                 sim_block_hash: current_block_hash,
                 sim_block_number: 0,
+                validation_gas_used: 0,
+                validation_block_number: 0,
                 account_is_staked: false,
                 valid_time_range: ValidTimeRange::default(),
                 entity_infos: EntityInfos::default(),
@@ -2810,18 +3593,18 @@ mod tests {
                 bundle_base_fee_overhead_percent: 27,
                 bundle_priority_fee_overhead_percent: 0,
                 da_gas_tracking_enabled,
+                aggregated_ops_policy,
+                aggregator_overhead_gas: 100_000,
             },
             event_sender,
+            last_build_report,
         );
 
         if notify_condition_not_met {
             proposer.notify_condition_not_met();
         }
 
-        proposer
-            .make_bundle(None, false)
-            .await
-            .expect("should make a bundle")
+        proposer.make_bundle(None, false).await
     }
 
     fn address(n: u8) -> Address {
@@ -13,7 +13,7 @@
 
 use std::{marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use futures::Stream;
@@ -22,7 +22,10 @@ use metrics::{Counter, Histogram};
 use metrics_derive::Metrics;
 #[cfg(test)]
 use mockall::automock;
-use rundler_provider::{BundleHandler, EntryPoint, TransactionRequest};
+use rundler_provider::{
+    AccountOverride, BundleHandler, EntryPoint, EvmProvider, ProviderError, StateOverride,
+    TransactionRequest,
+};
 use rundler_sim::ExpectedStorage;
 use rundler_task::TaskSpawner;
 use rundler_types::{
@@ -39,6 +42,7 @@ use tokio::{
         mpsc::{UnboundedReceiver, UnboundedSender},
         oneshot,
     },
+    time::MissedTickBehavior,
 };
 use tracing::{debug, error, info, instrument, warn};
 
@@ -58,6 +62,97 @@ pub(crate) struct Settings {
     pub(crate) max_replacement_underpriced_blocks: u64,
     pub(crate) max_cancellation_fee_increases: u64,
     pub(crate) max_blocks_to_wait_for_mine: u64,
+    pub(crate) trigger_mode: BundleTriggerMode,
+    pub(crate) build_interval: Duration,
+}
+
+/// Determines when the bundle sender attempts to build a bundle while in auto
+/// bundling mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum BundleTriggerMode {
+    /// Build on each new block, and when the build interval elapses without a new block
+    #[default]
+    BlockOrInterval,
+    /// Build only on each new block
+    Block,
+    /// Build only when the build interval elapses
+    Interval,
+}
+
+impl BundleTriggerMode {
+    fn on_block(self) -> bool {
+        matches!(self, Self::BlockOrInterval | Self::Block)
+    }
+
+    fn on_interval(self) -> bool {
+        matches!(self, Self::BlockOrInterval | Self::Interval)
+    }
+}
+
+/// Error returned when the bundle beneficiary cannot receive the funds paid to it
+/// by `handleOps`, which would cause every bundle to revert
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum BeneficiaryError {
+    #[error("beneficiary cannot be the zero address")]
+    ZeroAddress,
+    #[error("beneficiary {0} is a contract that cannot receive ETH")]
+    CannotReceiveEth(Address),
+}
+
+/// Checks that the beneficiary can be paid by the entry point before any bundles are sent.
+///
+/// If the beneficiary has code, a transfer of 1 wei from the entry point is simulated to
+/// check that the beneficiary accepts ETH. Only a revert of the transfer fails the check,
+/// provider errors are logged and the check is skipped so that a transient node issue
+/// doesn't prevent the builder from starting.
+pub(crate) async fn check_beneficiary<P: EvmProvider>(
+    provider: &P,
+    entry_point: Address,
+    beneficiary: Address,
+) -> Result<(), BeneficiaryError> {
+    if beneficiary.is_zero() {
+        return Err(BeneficiaryError::ZeroAddress);
+    }
+
+    let code = match provider.get_code(beneficiary, None).await {
+        Ok(code) => code,
+        Err(error) => {
+            warn!("Failed to get code of beneficiary {beneficiary:?}, skipping beneficiary check: {error:?}");
+            return Ok(());
+        }
+    };
+    if code.is_empty() {
+        return Ok(());
+    }
+
+    let tx = TransactionRequest::default()
+        .from(entry_point)
+        .to(beneficiary)
+        .value(U256::from(1));
+    let mut state_override = StateOverride::default();
+    state_override.insert(
+        entry_point,
+        AccountOverride {
+            balance: Some(U256::from(1)),
+            ..Default::default()
+        },
+    );
+
+    match provider.call(&tx, None, &state_override, None).await {
+        Ok(_) => Ok(()),
+        Err(ProviderError::RPC(error))
+            if error
+                .as_error_resp()
+                .is_some_and(|resp| resp.as_revert_data().is_some()) =>
+        {
+            Err(BeneficiaryError::CannotReceiveEth(beneficiary))
+        }
+        Err(error) => {
+            warn!("Failed to simulate a transfer to beneficiary {beneficiary:?}, skipping beneficiary check: {error:?}");
+            Ok(())
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -81,6 +176,7 @@ struct BundleTx {
     tx: TransactionRequest,
     expected_storage: ExpectedStorage,
     op_hashes: Vec<B256>,
+    op_revenues: Vec<U256>,
 }
 
 pub enum BundleSenderAction {
@@ -148,7 +244,8 @@ where
             &task_spawner,
             &self.pool,
             self.bundle_action_receiver.take().unwrap(),
-            Duration::from_millis(self.chain_spec.bundle_max_send_interval_millis),
+            self.settings.trigger_mode,
+            self.settings.build_interval,
         )
         .await
         .expect("Failed to create bundle sender trigger");
@@ -577,6 +674,7 @@ where
             tx,
             expected_storage,
             op_hashes,
+            op_revenues,
         } = bundle_tx;
 
         self.metrics.bundle_txns_sent.increment(1);
@@ -594,6 +692,7 @@ where
                         tx_hash,
                         tx,
                         op_hashes: Arc::new(op_hashes),
+                        op_revenues: Arc::new(op_revenues),
                     }),
                     nonce,
                     fee_increase_count,
@@ -676,12 +775,19 @@ where
             return Ok(None);
         }
         info!(
-            "Selected bundle with {} op(s), with {} rejected op(s) and {} updated entities",
+            "Selected bundle with {} op(s), with {} rejected op(s) and {} updated entities, expected revenue {}",
             bundle.len(),
             bundle.rejected_ops.len(),
-            bundle.entity_updates.len()
+            bundle.entity_updates.len(),
+            bundle.expected_revenue()
         );
         let op_hashes: Vec<_> = bundle.iter_ops().map(|op| self.op_hash(op)).collect();
+        if let Some((i, size)) = bundle.ops_by_calldata_size().first() {
+            debug!(
+                "Largest calldata contribution in bundle is {size} bytes from op {:?}",
+                op_hashes[*i]
+            );
+        }
         let mut tx = self.entry_point.get_send_bundle_transaction(
             bundle.ops_per_aggregator,
             self.beneficiary,
@@ -693,6 +799,7 @@ where
             tx,
             expected_storage: bundle.expected_storage,
             op_hashes,
+            op_revenues: bundle.op_revenues,
         }))
     }
 
@@ -1029,6 +1136,7 @@ trait Trigger {
 
 struct BundleSenderTrigger {
     bundling_mode: BundlingMode,
+    trigger_mode: BundleTriggerMode,
     block_rx: UnboundedReceiver<NewHead>,
     bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
     timer: tokio::time::Interval,
@@ -1045,9 +1153,9 @@ impl Trigger for BundleSenderTrigger {
         loop {
             // 3 triggers for loop logic:
             // 1 - new block
-            //      - If auto mode, send next bundle
+            //      - If auto mode and triggering on blocks, send next bundle
             // 2 - timer tick
-            //      - If auto mode, send next bundle
+            //      - If auto mode and triggering on the interval, send next bundle
             // 3 - action recv
             //      - If change mode, change and restart loop
             //      - If send bundle and manual mode, send next bundle
@@ -1061,14 +1169,14 @@ impl Trigger for BundleSenderTrigger {
                     self.last_block = b;
 
                     match self.bundling_mode {
-                        BundlingMode::Manual => continue,
-                        BundlingMode::Auto => break,
+                        BundlingMode::Auto if self.trigger_mode.on_block() => break,
+                        _ => continue,
                     }
                 },
                 _ = self.timer.tick() => {
                     match self.bundling_mode {
-                        BundlingMode::Manual => continue,
-                        BundlingMode::Auto => break,
+                        BundlingMode::Auto if self.trigger_mode.on_interval() => break,
+                        _ => continue,
                     }
                 },
                 a = self.bundle_action_receiver.recv() => {
@@ -1131,6 +1239,7 @@ impl BundleSenderTrigger {
         task_spawner: &T,
         pool_client: &P,
         bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
+        trigger_mode: BundleTriggerMode,
         timer_interval: Duration,
     ) -> anyhow::Result<Self> {
         let Ok(new_heads) = pool_client.subscribe_new_heads().await else {
@@ -1144,14 +1253,20 @@ impl BundleSenderTrigger {
             Box::pin(Self::block_stream_task(new_heads, block_tx)),
         );
 
+        // Don't burst missed ticks after a long bundle attempt
+        let mut timer = tokio::time::interval(timer_interval);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         Ok(Self {
             bundling_mode: BundlingMode::Auto,
+            trigger_mode,
             block_rx,
             bundle_action_receiver,
-            timer: tokio::time::interval(timer_interval),
+            timer,
             last_block: NewHead {
                 block_hash: B256::ZERO,
                 block_number: 0,
+                mined_ops: vec![],
             },
         })
     }
@@ -1261,7 +1376,7 @@ impl BuilderMetric {
 mod tests {
     use alloy_primitives::Bytes;
     use mockall::Sequence;
-    use rundler_provider::MockEntryPointV0_6;
+    use rundler_provider::{MockEntryPointV0_6, MockEvmProvider};
     use rundler_types::{
         chain::ChainSpec, pool::MockPool, v0_6::UserOperation, GasFees, UserOpsPerAggregator,
     };
@@ -1274,6 +1389,59 @@ mod tests {
         transaction_tracker::MockTransactionTracker,
     };
 
+    #[tokio::test]
+    async fn test_check_beneficiary_zero_address() {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_code().never();
+
+        let result = check_beneficiary(&provider, Address::repeat_byte(1), Address::ZERO).await;
+        assert!(matches!(result, Err(BeneficiaryError::ZeroAddress)));
+    }
+
+    #[tokio::test]
+    async fn test_check_beneficiary_eoa() {
+        let beneficiary = Address::repeat_byte(2);
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_code()
+            .withf(move |&address, _| address == beneficiary)
+            .times(1)
+            .returning(|_, _| Ok(Bytes::new()));
+        provider.expect_call().never();
+
+        check_beneficiary(&provider, Address::repeat_byte(1), beneficiary)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_beneficiary_provider_error() {
+        let beneficiary = Address::repeat_byte(2);
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::from(vec![1])));
+        provider
+            .expect_call()
+            .times(1)
+            .returning(|_, _, _, _| Err(ProviderError::Other(anyhow::anyhow!("connection reset"))));
+
+        check_beneficiary(&provider, Address::repeat_byte(1), beneficiary)
+            .await
+            .unwrap();
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_code()
+            .times(1)
+            .returning(|_, _| Err(ProviderError::Other(anyhow::anyhow!("connection reset"))));
+        provider.expect_call().never();
+
+        check_beneficiary(&provider, Address::repeat_byte(1), beneficiary)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_empty_send() {
         let Mocks {
@@ -1394,6 +1562,7 @@ mod tests {
                     Ok(NewHead {
                         block_number: 2,
                         block_hash: B256::ZERO,
+                        mined_ops: vec![],
                     })
                 })
             });
@@ -1585,6 +1754,7 @@ mod tests {
         mock_trigger.expect_last_block().return_const(NewHead {
             block_number: 0,
             block_hash: B256::ZERO,
+            mined_ops: vec![],
         });
 
         let mut state = SenderMachineState {
@@ -1727,6 +1897,62 @@ mod tests {
         ));
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_trigger_interval_mode() {
+        let interval = Duration::from_secs(2);
+        let (mut trigger, block_tx, _action_tx) =
+            new_trigger(BundleTriggerMode::Interval, interval);
+        // the first tick completes immediately
+        trigger.wait_for_trigger().await.unwrap();
+
+        let start = tokio::time::Instant::now();
+        block_tx.send(new_head(1)).unwrap();
+        trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(start.elapsed(), interval);
+        // blocks are still tracked, but don't trigger a bundle
+        assert_eq!(trigger.last_block().block_number, 1);
+
+        trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(start.elapsed(), interval * 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trigger_block_mode() {
+        let (mut trigger, block_tx, _action_tx) =
+            new_trigger(BundleTriggerMode::Block, Duration::from_millis(100));
+
+        // timer ticks never trigger a bundle
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), trigger.wait_for_trigger())
+                .await
+                .is_err()
+        );
+
+        let start = tokio::time::Instant::now();
+        block_tx.send(new_head(1)).unwrap();
+        trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(trigger.last_block().block_number, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trigger_block_or_interval_mode() {
+        let interval = Duration::from_secs(2);
+        let (mut trigger, block_tx, _action_tx) =
+            new_trigger(BundleTriggerMode::BlockOrInterval, interval);
+        trigger.wait_for_trigger().await.unwrap();
+
+        // a new block triggers before the interval elapses
+        let start = tokio::time::Instant::now();
+        block_tx.send(new_head(1)).unwrap();
+        trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // the interval triggers without a new block
+        trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(start.elapsed(), interval);
+    }
+
     struct Mocks {
         mock_proposer: MockBundleProposer,
         mock_entry_point: MockEntryPointV0_6,
@@ -1771,11 +1997,45 @@ mod tests {
                 max_cancellation_fee_increases: 3,
                 max_blocks_to_wait_for_mine: 3,
                 max_replacement_underpriced_blocks: 3,
+                trigger_mode: BundleTriggerMode::BlockOrInterval,
+                build_interval: Duration::from_millis(u64::MAX),
             },
             broadcast::channel(1000).0,
         )
     }
 
+    fn new_trigger(
+        trigger_mode: BundleTriggerMode,
+        interval: Duration,
+    ) -> (
+        BundleSenderTrigger,
+        UnboundedSender<NewHead>,
+        mpsc::Sender<BundleSenderAction>,
+    ) {
+        let (block_tx, block_rx) = mpsc::unbounded_channel();
+        let (action_tx, bundle_action_receiver) = mpsc::channel(1);
+        let mut timer = tokio::time::interval(interval);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let trigger = BundleSenderTrigger {
+            bundling_mode: BundlingMode::Auto,
+            trigger_mode,
+            block_rx,
+            bundle_action_receiver,
+            timer,
+            last_block: new_head(0),
+        };
+        (trigger, block_tx, action_tx)
+    }
+
+    fn new_head(block_number: u64) -> NewHead {
+        NewHead {
+            block_hash: B256::ZERO,
+            block_number,
+            mined_ops: vec![],
+        }
+    }
+
     fn add_trigger_no_update_last_block(
         mock_trigger: &mut MockTrigger,
         mock_tracker: &mut MockTransactionTracker,
@@ -1797,6 +2057,7 @@ mod tests {
             .return_const(NewHead {
                 block_number,
                 block_hash: B256::ZERO,
+                mined_ops: vec![],
             });
     }
 
@@ -1814,6 +2075,7 @@ mod tests {
                     Ok(NewHead {
                         block_number,
                         block_hash: B256::ZERO,
+                        mined_ops: vec![],
                     })
                 })
             });
@@ -1824,6 +2086,7 @@ mod tests {
             .return_const(NewHead {
                 block_number,
                 block_hash: B256::ZERO,
+                mined_ops: vec![],
             });
         mock_trigger
             .expect_builder_must_wait_for_trigger()
@@ -1837,6 +2100,7 @@ mod tests {
             expected_storage: Default::default(),
             rejected_ops: vec![],
             entity_updates: vec![],
+            op_revenues: vec![U256::ZERO],
             ops_per_aggregator: vec![UserOpsPerAggregator {
                 aggregator: Address::ZERO,
                 signature: Bytes::new(),
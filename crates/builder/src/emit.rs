@@ -13,7 +13,7 @@
 
 use std::{fmt::Display, sync::Arc};
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use rundler_provider::TransactionRequest;
 use rundler_sim::SimulationError;
 use rundler_types::{GasFees, ValidTimeRange};
@@ -163,13 +163,19 @@ pub struct BundleTxDetails {
     pub tx: TransactionRequest,
     /// Operation hashes included in the bundle
     pub op_hashes: Arc<Vec<B256>>,
+    /// Expected fee payment to the beneficiary for each operation, in the same order as `op_hashes`
+    pub op_revenues: Arc<Vec<U256>>,
 }
 
 /// Reason for skipping an operation in a bundle
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum SkipReason {
     /// Operation accessed another sender account included earlier in the bundle
     AccessedOtherSender { other_sender: Address },
+    /// Operation's validation storage access conflicts with an operation included
+    /// earlier in the bundle, i.e. one of the ops writes a slot the other accesses
+    ConflictingStorageAccess { address: Address, slot: B256 },
     /// Current time is outside of the operation's valid time range
     InvalidTimeRange { valid_range: ValidTimeRange },
     /// Operation did not bid high enough gas fees for inclusion in the bundle
@@ -184,6 +190,9 @@ pub enum SkipReason {
         required_pvg: u128,
         actual_pvg: u128,
     },
+    /// Operation's fees, together with the other operations using its aggregator, do
+    /// not cover the aggregator's signature verification overhead
+    AggregationOverhead { aggregator: Address },
     /// Bundle ran out of space by gas limit to include the operation
     GasLimit,
     /// Other reason, typically internal errors
@@ -191,7 +200,8 @@ pub enum SkipReason {
 }
 
 /// Reason for rejecting an operation from a bundle
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum OpRejectionReason {
     /// Operation failed its 2nd validation simulation attempt
     FailedRevalidation { error: SimulationError },
@@ -244,6 +254,7 @@ impl Display for BuilderEvent {
                                 "    Required maxFeePerGas: {}",
                                 "    Required maxPriorityFeePerGas: {}",
                                 "    Op hashes: {}",
+                                "    Expected revenue: {}",
                             ),
                             self.builder_index,
                             tx_details.tx_hash,
@@ -252,6 +263,7 @@ impl Display for BuilderEvent {
                             required_max_fee_per_gas,
                             required_max_priority_fee_per_gas,
                             op_hashes,
+                            tx_details.op_revenues.iter().sum::<U256>(),
                         )
                     }
                     None => write!(
@@ -20,7 +20,9 @@
 //! Bundle builder implementation for the Rundler.
 
 mod bundle_proposer;
+pub use bundle_proposer::AggregatedOpsPolicy;
 mod bundle_sender;
+pub use bundle_sender::BundleTriggerMode;
 
 mod emit;
 pub use emit::{BuilderEvent, BuilderEventKind};
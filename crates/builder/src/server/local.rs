@@ -18,16 +18,22 @@ use rundler_task::{
     server::{HealthCheck, ServerStatus},
     GracefulShutdown,
 };
-use rundler_types::builder::{Builder, BuilderError, BuilderResult, BundlingMode};
+use rundler_types::builder::{
+    Builder, BuilderError, BuilderResult, BundleBuildReport, BundlingMode,
+};
 use tokio::sync::{mpsc, oneshot};
 
-use crate::bundle_sender::{BundleSenderAction, SendBundleRequest, SendBundleResult};
+use crate::{
+    bundle_proposer::LastBundleBuildReport,
+    bundle_sender::{BundleSenderAction, SendBundleRequest, SendBundleResult},
+};
 
 /// Local builder server builder
 #[derive(Debug)]
 pub struct LocalBuilderBuilder {
     req_sender: mpsc::Sender<ServerRequest>,
     req_receiver: mpsc::Receiver<ServerRequest>,
+    last_build_report: LastBundleBuildReport,
 }
 
 impl LocalBuilderBuilder {
@@ -37,9 +43,15 @@ impl LocalBuilderBuilder {
         Self {
             req_sender,
             req_receiver,
+            last_build_report: LastBundleBuildReport::default(),
         }
     }
 
+    /// Get the store that bundle proposers record their most recent build report to
+    pub(crate) fn last_build_report(&self) -> LastBundleBuildReport {
+        self.last_build_report.clone()
+    }
+
     /// Get a handle to the local builder server
     pub fn get_handle(&self) -> LocalBuilderHandle {
         LocalBuilderHandle {
@@ -54,8 +66,12 @@ impl LocalBuilderBuilder {
         entry_points: Vec<Address>,
         shutdown: GracefulShutdown,
     ) -> BoxFuture<'static, ()> {
-        let runner =
-            LocalBuilderServerRunner::new(self.req_receiver, bundle_sender_actions, entry_points);
+        let runner = LocalBuilderServerRunner::new(
+            self.req_receiver,
+            bundle_sender_actions,
+            entry_points,
+            self.last_build_report,
+        );
         Box::pin(runner.run(shutdown))
     }
 }
@@ -70,6 +86,7 @@ struct LocalBuilderServerRunner {
     req_receiver: mpsc::Receiver<ServerRequest>,
     bundle_sender_actions: Vec<mpsc::Sender<BundleSenderAction>>,
     entry_points: Vec<Address>,
+    last_build_report: LastBundleBuildReport,
 }
 
 impl LocalBuilderHandle {
@@ -117,6 +134,15 @@ impl Builder for LocalBuilderHandle {
             _ => Err(BuilderError::UnexpectedResponse),
         }
     }
+
+    async fn debug_get_last_bundle_build_reports(&self) -> BuilderResult<Vec<BundleBuildReport>> {
+        let req = ServerRequestKind::DebugGetLastBundleBuildReports;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::DebugGetLastBundleBuildReports { reports } => Ok(reports),
+            _ => Err(BuilderError::UnexpectedResponse),
+        }
+    }
 }
 
 #[async_trait]
@@ -139,11 +165,13 @@ impl LocalBuilderServerRunner {
         req_receiver: mpsc::Receiver<ServerRequest>,
         bundle_sender_actions: Vec<mpsc::Sender<BundleSenderAction>>,
         entry_points: Vec<Address>,
+        last_build_report: LastBundleBuildReport,
     ) -> Self {
         Self {
             req_receiver,
             bundle_sender_actions,
             entry_points,
+            last_build_report,
         }
     }
 
@@ -202,6 +230,11 @@ impl LocalBuilderServerRunner {
 
                                 Ok(ServerResponse::DebugSetBundlingMode)
                             },
+                            ServerRequestKind::DebugGetLastBundleBuildReports => {
+                                Ok(ServerResponse::DebugGetLastBundleBuildReports {
+                                    reports: self.last_build_report.get()
+                                })
+                            },
                         }
                     };
 
@@ -219,6 +252,7 @@ enum ServerRequestKind {
     GetSupportedEntryPoints,
     DebugSendBundleNow,
     DebugSetBundlingMode { mode: BundlingMode },
+    DebugGetLastBundleBuildReports,
 }
 
 #[derive(Debug)]
@@ -232,4 +266,5 @@ enum ServerResponse {
     GetSupportedEntryPoints { entry_points: Vec<Address> },
     DebugSendBundleNow { hash: B256, block_number: u64 },
     DebugSetBundlingMode,
+    DebugGetLastBundleBuildReports { reports: Vec<BundleBuildReport> },
 }
@@ -19,7 +19,9 @@ use rundler_task::{
     grpc::protos::{from_bytes, ConversionError},
     server::{HealthCheck, ServerStatus},
 };
-use rundler_types::builder::{Builder, BuilderError, BuilderResult, BundlingMode};
+use rundler_types::builder::{
+    Builder, BuilderError, BuilderResult, BundleBuildReport, BundlingMode,
+};
 use tonic::transport::{Channel, Uri};
 use tonic_health::{
     pb::{health_client::HealthClient, HealthCheckRequest},
@@ -27,9 +29,10 @@ use tonic_health::{
 };
 
 use super::protos::{
-    builder_client::BuilderClient, debug_send_bundle_now_response,
-    debug_set_bundling_mode_response, BundlingMode as ProtoBundlingMode, DebugSendBundleNowRequest,
-    DebugSetBundlingModeRequest, GetSupportedEntryPointsRequest,
+    builder_client::BuilderClient, debug_get_last_bundle_build_reports_response,
+    debug_send_bundle_now_response, debug_set_bundling_mode_response,
+    BundlingMode as ProtoBundlingMode, DebugGetLastBundleBuildReportsRequest,
+    DebugSendBundleNowRequest, DebugSetBundlingModeRequest, GetSupportedEntryPointsRequest,
 };
 
 /// Remote builder client, used for communicating with a remote builder server
@@ -110,6 +113,32 @@ impl Builder for RemoteBuilderClient {
             )))?,
         }
     }
+
+    async fn debug_get_last_bundle_build_reports(&self) -> BuilderResult<Vec<BundleBuildReport>> {
+        let res = self
+            .grpc_client
+            .clone()
+            .debug_get_last_bundle_build_reports(DebugGetLastBundleBuildReportsRequest {})
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(debug_get_last_bundle_build_reports_response::Result::Success(s)) => Ok(s
+                .reports
+                .into_iter()
+                .map(BundleBuildReport::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::from)?),
+            Some(debug_get_last_bundle_build_reports_response::Result::Failure(f)) => {
+                Err(f.try_into()?)
+            }
+            None => Err(BuilderError::Other(anyhow::anyhow!(
+                "should have received result from builder"
+            )))?,
+        }
+    }
 }
 
 #[async_trait]
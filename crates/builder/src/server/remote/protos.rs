@@ -11,8 +11,10 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use rundler_task::grpc::protos::ConversionError;
-use rundler_types::builder::BundlingMode as RpcBundlingMode;
+use rundler_task::grpc::protos::{from_bytes, ConversionError, ToProtoBytes};
+use rundler_types::builder::{
+    BundleBuildReport as RpcBundleBuildReport, BundlingMode as RpcBundlingMode,
+};
 
 tonic::include_proto!("builder");
 
@@ -39,3 +41,31 @@ impl TryFrom<BundlingMode> for RpcBundlingMode {
         }
     }
 }
+
+impl From<RpcBundleBuildReport> for BundleBuildReport {
+    fn from(report: RpcBundleBuildReport) -> Self {
+        Self {
+            builder_index: report.builder_index,
+            entry_point: report.entry_point.to_proto_bytes(),
+            ops_considered: report.ops_considered,
+            ops_skipped: report.ops_skipped.into_iter().collect(),
+            ops_included: report.ops_included,
+            error: report.error,
+        }
+    }
+}
+
+impl TryFrom<BundleBuildReport> for RpcBundleBuildReport {
+    type Error = ConversionError;
+
+    fn try_from(report: BundleBuildReport) -> Result<Self, Self::Error> {
+        Ok(Self {
+            builder_index: report.builder_index,
+            entry_point: from_bytes(&report.entry_point)?,
+            ops_considered: report.ops_considered,
+            ops_skipped: report.ops_skipped.into_iter().collect(),
+            ops_included: report.ops_included,
+            error: report.error,
+        })
+    }
+}
@@ -19,7 +19,9 @@ use tonic::{async_trait, transport::Server, Request, Response, Status};
 
 use super::protos::{
     builder_server::{Builder as GrpcBuilder, BuilderServer as GrpcBuilderServer},
-    debug_send_bundle_now_response, debug_set_bundling_mode_response, BundlingMode,
+    debug_get_last_bundle_build_reports_response, debug_send_bundle_now_response,
+    debug_set_bundling_mode_response, BundlingMode, DebugGetLastBundleBuildReportsRequest,
+    DebugGetLastBundleBuildReportsResponse, DebugGetLastBundleBuildReportsSuccess,
     DebugSendBundleNowRequest, DebugSendBundleNowResponse, DebugSetBundlingModeRequest,
     DebugSetBundlingModeResponse, DebugSetBundlingModeSuccess, GetSupportedEntryPointsRequest,
     GetSupportedEntryPointsResponse, BUILDER_FILE_DESCRIPTOR_SET,
@@ -142,4 +144,32 @@ impl GrpcBuilder for GrpcBuilderServerImpl {
 
         Ok(Response::new(resp))
     }
+
+    async fn debug_get_last_bundle_build_reports(
+        &self,
+        _request: Request<DebugGetLastBundleBuildReportsRequest>,
+    ) -> tonic::Result<Response<DebugGetLastBundleBuildReportsResponse>> {
+        let resp = match self
+            .local_builder
+            .debug_get_last_bundle_build_reports()
+            .await
+        {
+            Ok(reports) => DebugGetLastBundleBuildReportsResponse {
+                result: Some(
+                    debug_get_last_bundle_build_reports_response::Result::Success(
+                        DebugGetLastBundleBuildReportsSuccess {
+                            reports: reports.into_iter().map(Into::into).collect(),
+                        },
+                    ),
+                ),
+            },
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "Failed to get last bundle build reports: {e}"
+                )));
+            }
+        };
+
+        Ok(Response::new(resp))
+    }
 }
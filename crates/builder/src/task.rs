@@ -15,7 +15,7 @@ use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use alloy_primitives::{Address, B256};
 use anyhow::Context;
-use rundler_provider::{Providers as ProvidersT, ProvidersWithEntryPointT};
+use rundler_provider::{EntryPoint, Providers as ProvidersT, ProvidersWithEntryPointT};
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl},
     simulation::{self, UnsafeSimulator},
@@ -33,8 +33,8 @@ use tokio::{
 use tracing::info;
 
 use crate::{
-    bundle_proposer::{self, BundleProposerImpl, BundleProposerProviders},
-    bundle_sender::{self, BundleSender, BundleSenderAction, BundleSenderImpl},
+    bundle_proposer::{self, AggregatedOpsPolicy, BundleProposerImpl, BundleProposerProviders},
+    bundle_sender::{self, BundleSender, BundleSenderAction, BundleSenderImpl, BundleTriggerMode},
     emit::BuilderEvent,
     sender::TransactionSenderArgs,
     server::{self, LocalBuilderBuilder},
@@ -71,6 +71,13 @@ pub struct Args {
     pub bundle_priority_fee_overhead_percent: u32,
     /// Priority fee mode to use for operation priority fee minimums
     pub priority_fee_mode: PriorityFeeMode,
+    /// Number of recent blocks sampled for the network priority fee when using the
+    /// percentile priority fee mode
+    pub priority_fee_percentile_blocks: u64,
+    /// How to handle aggregated ops whose fees don't cover the aggregation overhead
+    pub aggregated_ops_policy: AggregatedOpsPolicy,
+    /// Estimated gas overhead of verifying an aggregator's signature in a bundle
+    pub aggregator_overhead_gas: u128,
     /// Sender to be used by the builder
     pub sender_args: TransactionSenderArgs,
     /// Operation simulation settings
@@ -83,6 +90,10 @@ pub struct Args {
     pub max_cancellation_fee_increases: u64,
     /// Maximum amount of blocks to spend in a replacement underpriced state before moving to cancel
     pub max_replacement_underpriced_blocks: u64,
+    /// When to attempt building a bundle while in auto bundling mode
+    pub bundle_trigger_mode: BundleTriggerMode,
+    /// Interval in milliseconds between bundle attempts when the trigger mode uses an interval
+    pub bundle_build_interval_millis: u64,
     /// Address to bind the remote builder server to, if any. If none, no server is starter.
     pub remote_address: Option<SocketAddr>,
     /// Entry points to start builders for
@@ -344,6 +355,13 @@ where
             ret
         };
         let beneficiary = signer.address();
+        bundle_sender::check_beneficiary(
+            ep_providers.evm(),
+            *ep_providers.entry_point().address(),
+            beneficiary,
+        )
+        .await
+        .context("bundle beneficiary cannot receive funds from the entry point")?;
         let proposer_settings = bundle_proposer::Settings {
             chain_spec: self.args.chain_spec.clone(),
             max_bundle_size: self.args.max_bundle_size,
@@ -353,6 +371,8 @@ where
             bundle_base_fee_overhead_percent: self.args.bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
             da_gas_tracking_enabled: self.args.da_gas_tracking_enabled,
+            aggregated_ops_policy: self.args.aggregated_ops_policy,
+            aggregator_overhead_gas: self.args.aggregator_overhead_gas,
         };
 
         let transaction_sender = self
@@ -377,6 +397,8 @@ where
             max_replacement_underpriced_blocks: self.args.max_replacement_underpriced_blocks,
             max_cancellation_fee_increases: self.args.max_cancellation_fee_increases,
             max_blocks_to_wait_for_mine: self.args.max_blocks_to_wait_for_mine,
+            trigger_mode: self.args.bundle_trigger_mode,
+            build_interval: Duration::from_millis(self.args.bundle_build_interval_millis),
         };
 
         let fee_oracle = gas::get_fee_oracle(&self.args.chain_spec, ep_providers.evm().clone());
@@ -386,6 +408,7 @@ where
             proposer_settings.priority_fee_mode,
             proposer_settings.bundle_base_fee_overhead_percent,
             proposer_settings.bundle_priority_fee_overhead_percent,
+            self.args.priority_fee_percentile_blocks,
         );
 
         let proposer = BundleProposerImpl::new(
@@ -394,6 +417,7 @@ where
             BundleProposerProviders::new(self.pool.clone(), simulator, fee_estimator),
             proposer_settings,
             self.event_sender.clone(),
+            self.builder_builder.last_build_report(),
         );
 
         let builder = BundleSenderImpl::new(
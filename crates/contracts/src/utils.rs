@@ -33,3 +33,28 @@ sol!(
     StorageLoader,
     "contracts/out/utils/StorageLoader.sol/StorageLoader.json"
 );
+
+sol! {
+    /// Optional extension to the ERC-4337 aggregator interface for aggregators that
+    /// expose the length of the aggregated signatures they produce
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IAggregatorSignatureLength {
+        function aggregatedSignatureLength() external view returns (uint256 length);
+    }
+}
+
+sol! {
+    /// Common account execution functions, used to decode the target contracts of a
+    /// user operation's call data
+    #[allow(missing_docs)]
+    interface IAccountExecute {
+        function execute(address dest, uint256 value, bytes calldata func) external;
+        function executeBatch(address[] calldata dest, bytes[] calldata func) external;
+        function executeBatch(
+            address[] calldata dest,
+            uint256[] calldata value,
+            bytes[] calldata func
+        ) external;
+    }
+}
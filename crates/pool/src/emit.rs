@@ -140,6 +140,13 @@ pub enum OpRemovalReason {
         valid_until: Timestamp,
     },
     PoolSizeExceeded,
+    /// Op was removed because it failed re-validation at a new block
+    RevalidationFailed {
+        /// Block number the op was re-validated at
+        block_number: u64,
+        /// The re-validation error
+        error: String,
+    },
 }
 
 impl EntitySummary {
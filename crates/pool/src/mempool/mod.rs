@@ -35,9 +35,10 @@ use rundler_sim::{MempoolConfig, PrecheckSettings, SimulationSettings};
 use rundler_types::{
     chain::ChainSpec,
     pool::{
-        MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+        MempoolError, PaymasterMetadata, PoolOperation, PrecheckSettingsUpdate, Reputation,
+        ReputationStatus, StakeStatus,
     },
-    EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
+    Entity, EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
 };
 use tonic::async_trait;
 pub(crate) use uo_pool::{UoPool, UoPoolProviders};
@@ -66,6 +67,10 @@ pub trait Mempool: Send + Sync {
         op: UserOperationVariant,
     ) -> MempoolResult<B256>;
 
+    /// Runs all of the checks for adding a user operation to the pool without
+    /// adding it, returning the hash it would have been added under
+    async fn check_operation(&self, op: UserOperationVariant) -> MempoolResult<B256>;
+
     /// Removes a set of operations from the pool.
     fn remove_operations(&self, hashes: &[B256]);
 
@@ -95,6 +100,13 @@ pub trait Mempool: Send + Sync {
     /// Looks up a user operation by hash, returns None if not found
     fn get_user_operation_by_hash(&self, hash: B256) -> Option<Arc<PoolOperation>>;
 
+    /// Looks up a user operation by its ID, returns None if not found
+    fn get_user_operation_by_id(&self, id: &UserOperationId) -> Option<Arc<PoolOperation>>;
+
+    /// Returns the position of a user operation in the bundling order of the pool,
+    /// 0 being the next to be bundled. Returns None if not found or not eligible for bundling.
+    fn get_user_operation_queue_position(&self, hash: B256) -> Option<usize>;
+
     /// Debug methods
 
     /// Clears the mempool of UOs or reputation of all addresses
@@ -115,11 +127,24 @@ pub trait Mempool: Send + Sync {
     /// Get stake status for address
     async fn get_stake_status(&self, address: Address) -> MempoolResult<StakeStatus>;
 
+    /// Get stake status for each of the non-account entities (factory, paymaster,
+    /// aggregator) of a user operation
+    async fn get_entity_stake_statuses(
+        &self,
+        op: &UserOperationVariant,
+    ) -> MempoolResult<Vec<(Entity, StakeStatus)>>;
+
     /// Reset paymaster state
     async fn reset_confirmed_paymaster_balances(&self) -> MempoolResult<()>;
 
     /// Turns on and off tracking errors
     fn set_tracking(&self, paymaster: bool, reputation: bool);
+
+    /// Updates the precheck settings, returning the effective settings
+    fn set_precheck_settings(
+        &self,
+        settings: PrecheckSettingsUpdate,
+    ) -> MempoolResult<PrecheckSettingsUpdate>;
 }
 
 /// Config for the mempool
@@ -142,6 +167,8 @@ pub struct PoolConfig {
     pub blocklist: Option<HashSet<Address>>,
     /// Operations that are always allowed in the mempool, regardless of reputation
     pub allowlist: Option<HashSet<Address>>,
+    /// If set, only operations whose call data targets contracts in this set are accepted
+    pub call_target_allowlist: Option<HashSet<Address>>,
     /// Settings for precheck validation
     pub precheck_settings: PrecheckSettings,
     /// Settings for simulation validation
@@ -157,6 +184,8 @@ pub struct PoolConfig {
     pub throttled_entity_mempool_count: u64,
     /// The maximum number of blocks a user operation with a throttled entity can stay in the mempool
     pub throttled_entity_live_blocks: u64,
+    /// The maximum number of user operations with a throttled entity that can be included in a single bundle
+    pub throttled_entity_bundle_count: u64,
     /// Boolean field used to toggle the operation of the paymaster tracker
     pub paymaster_tracking_enabled: bool,
     /// Number of paymaster balances to cache
@@ -171,6 +200,24 @@ pub struct PoolConfig {
     /// Gas limit efficiency is defined as the ratio of the gas limit to the gas used.
     /// This applies to all the verification, call, and paymaster gas limits.
     pub gas_limit_efficiency_reject_threshold: f32,
+    /// Percentage to reduce an operation's bundling score by for every 1M gas used during
+    /// its validation simulation, excluding pre-verification gas. Deprioritizes operations
+    /// that are expensive to simulate.
+    /// 0 disables the penalty.
+    pub sim_gas_penalty_percent: u32,
+    /// Boolean field used to toggle re-validation of every operation in the mempool on each new block.
+    /// Operations that fail re-validation are dropped.
+    pub revalidation_enabled: bool,
+    /// The maximum number of operations to re-validate concurrently
+    pub revalidation_max_concurrency: usize,
+    /// Base fee above which the chain is considered congested. While congested, operations
+    /// below a raised fee floor are rejected. None disables the base fee check.
+    pub congestion_base_fee_threshold: Option<u128>,
+    /// Pool fill level, as a percentage of the max pool size, above which the pool is considered
+    /// congested. None disables the fill level check.
+    pub congestion_pool_fill_percent: Option<u32>,
+    /// Percentage to raise the required priority fee by while congested
+    pub congestion_fee_increase_percent: u32,
 }
 
 /// Origin of an operation.
@@ -215,6 +262,8 @@ mod tests {
             expected_code_hash: B256::random(),
             sim_block_hash: B256::random(),
             sim_block_number: 0,
+            validation_gas_used: 0,
+            validation_block_number: 0,
             account_is_staked: true,
             entity_infos: EntityInfos {
                 factory: Some(EntityInfo {
@@ -527,6 +527,8 @@ mod tests {
             expected_code_hash: B256::random(),
             sim_block_hash: B256::random(),
             sim_block_number: 0,
+            validation_gas_used: 0,
+            validation_block_number: 0,
             account_is_staked: true,
             entity_infos: EntityInfos::default(),
             da_gas_data: rundler_types::da::DAGasUOData::Empty,
@@ -29,6 +29,7 @@ use rundler_types::{
     da::DAGasBlockData,
     pool::{MempoolError, PoolOperation},
     Entity, EntityType, GasFees, Timestamp, UserOperation, UserOperationId, UserOperationVariant,
+    ValidTimeRange,
 };
 use rundler_utils::{emit::WithEntryPoint, math};
 use tokio::sync::broadcast;
@@ -37,6 +38,9 @@ use tracing::{info, warn};
 use super::{entity_tracker::EntityCounter, size::SizeTracker, MempoolResult, PoolConfig};
 use crate::{chain::MinedOp, emit::OpRemovalReason, PoolEvent};
 
+/// Amount of simulation gas that incurs `sim_gas_penalty_percent` of score penalty
+const SIM_GAS_PENALTY_UNIT: u128 = 1_000_000;
+
 #[derive(Debug, Clone)]
 pub(crate) struct PoolInnerConfig {
     chain_spec: ChainSpec,
@@ -46,6 +50,7 @@ pub(crate) struct PoolInnerConfig {
     throttled_entity_mempool_count: u64,
     throttled_entity_live_blocks: u64,
     da_gas_tracking_enabled: bool,
+    sim_gas_penalty_percent: u32,
 }
 
 impl From<PoolConfig> for PoolInnerConfig {
@@ -58,6 +63,7 @@ impl From<PoolConfig> for PoolInnerConfig {
             throttled_entity_mempool_count: config.throttled_entity_mempool_count,
             throttled_entity_live_blocks: config.throttled_entity_live_blocks,
             da_gas_tracking_enabled: config.da_gas_tracking_enabled,
+            sim_gas_penalty_percent: config.sim_gas_penalty_percent,
         }
     }
 }
@@ -73,7 +79,7 @@ pub(crate) struct PoolInner<D> {
     by_hash: HashMap<B256, Arc<OrderedPoolOperation>>,
     /// Operations by operation ID
     by_id: HashMap<UserOperationId, Arc<OrderedPoolOperation>>,
-    /// Best operations, sorted by gas price
+    /// Best operations, sorted by score
     best: BTreeSet<Arc<OrderedPoolOperation>>,
     /// Time to mine info
     time_to_mine: HashMap<B256, TimeToMineInfo>,
@@ -200,10 +206,12 @@ where
         };
 
         // only eligibility requirement is if the op has required pvg
+        let score = self.score(&op);
         let pool_op = Arc::new(OrderedPoolOperation::new(
             Arc::new(op),
             self.next_submission_id(),
             is_eligible,
+            score,
         ));
 
         let hash = self.add_operation_internal(pool_op)?;
@@ -211,6 +219,37 @@ where
         Ok(hash)
     }
 
+    /// Checks that an operation would not be discarded to keep the pool within its size
+    /// limit if it were added, without modifying the pool. Mirrors `enforce_size`.
+    pub(crate) fn check_size(&self, op: &PoolOperation) -> MempoolResult<()> {
+        let score = self.score(op);
+        let new_op =
+            OrderedPoolOperation::new(Arc::new(op.clone()), self.submission_id, true, score);
+
+        let mut pool_size = self.pool_size;
+        pool_size += new_op.mem_size();
+        let replaced = self
+            .check_replacement(&op.uo)?
+            .and_then(|hash| self.by_hash.get(&hash));
+        if let Some(replaced) = replaced {
+            pool_size -= replaced.mem_size();
+        }
+
+        let mut worst_first = self
+            .best
+            .iter()
+            .rev()
+            .filter(|o| !replaced.is_some_and(|r| Arc::ptr_eq(o, r)));
+        while pool_size > self.config.max_size_of_pool_bytes {
+            match worst_first.next() {
+                Some(worst) if new_op < **worst => pool_size -= worst.mem_size(),
+                _ => return Err(MempoolError::DiscardedOnInsert),
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn best_operations(&self) -> impl Iterator<Item = Arc<PoolOperation>> + '_ {
         self.best.iter().filter_map(|p| {
             if p.eligible() {
@@ -221,6 +260,12 @@ where
         })
     }
 
+    /// Returns all operations in the pool, including those that are currently
+    /// ineligible for bundling, along with their hashes.
+    pub(crate) fn all_operations(&self) -> impl Iterator<Item = (B256, Arc<PoolOperation>)> + '_ {
+        self.by_hash.iter().map(|(hash, op)| (*hash, op.po.clone()))
+    }
+
     /// Does maintenance on the pool.
     ///
     /// 1) Removes all operations using the given entity, returning the hashes of the removed operations.
@@ -326,6 +371,14 @@ where
         self.prev_sys_block_time = sys_block_time;
     }
 
+    /// Returns the size of the pool as a percentage of its maximum size
+    pub(crate) fn fill_percent(&self) -> u32 {
+        if self.config.max_size_of_pool_bytes == 0 {
+            return 100;
+        }
+        (self.pool_size.0.max(0) as usize * 100 / self.config.max_size_of_pool_bytes) as u32
+    }
+
     pub(crate) fn address_count(&self, address: &Address) -> usize {
         if let Some(entity) = self.count_by_address.get(address) {
             return entity.total();
@@ -338,6 +391,22 @@ where
         self.by_hash.get(&hash).map(|o| o.po.clone())
     }
 
+    /// Returns the number of eligible operations ordered ahead of the given operation,
+    /// i.e. its position in the bundling order. Returns None if the operation is not in
+    /// the pool or is not eligible for bundling.
+    pub(crate) fn queue_position(&self, hash: B256) -> Option<usize> {
+        let op = self.by_hash.get(&hash)?;
+        if !op.eligible() {
+            return None;
+        }
+        Some(
+            self.best
+                .range(..op.clone())
+                .filter(|o| o.eligible())
+                .count(),
+        )
+    }
+
     pub(crate) fn get_operation_by_id(&self, id: &UserOperationId) -> Option<Arc<PoolOperation>> {
         self.by_id.get(id).map(|o| o.po.clone())
     }
@@ -348,6 +417,39 @@ where
         ret
     }
 
+    /// Updates the valid time range and the latest validation of an operation in the pool
+    /// after it has been re-validated at a new block. Returns false if the operation is not
+    /// in the pool.
+    pub(crate) fn update_validation(
+        &mut self,
+        hash: B256,
+        valid_time_range: ValidTimeRange,
+        validation_gas_used: u128,
+        validation_block_number: u64,
+    ) -> bool {
+        let Some(op) = self.by_hash.get(&hash).cloned() else {
+            return false;
+        };
+
+        let po = Arc::new(PoolOperation {
+            valid_time_range,
+            validation_gas_used,
+            validation_block_number,
+            ..(*op.po).clone()
+        });
+        // submission id and score are unchanged, so the op keeps its position in `best`
+        let updated = Arc::new(OrderedPoolOperation::new(
+            po,
+            op.submission_id,
+            op.eligible(),
+            op.score,
+        ));
+        self.best.replace(updated.clone());
+        self.by_id.insert(updated.uo().id(), updated.clone());
+        self.by_hash.insert(hash, updated);
+        true
+    }
+
     // STO-040
     pub(crate) fn check_multiple_roles_violation(
         &self,
@@ -631,6 +733,21 @@ where
         }
     }
 
+    /// The score of an operation is its max fee per gas, reduced by a penalty proportional
+    /// to the verification gas used during its validation simulation. Ops that are expensive to simulate
+    /// are deprioritized relative to cheap ops at the same fee.
+    fn score(&self, op: &PoolOperation) -> u128 {
+        let max_fee_per_gas = op.uo.max_fee_per_gas();
+        if self.config.sim_gas_penalty_percent == 0 {
+            return max_fee_per_gas;
+        }
+
+        let penalty_percent = (op.validation_gas_used * self.config.sim_gas_penalty_percent as u128
+            / SIM_GAS_PENALTY_UNIT)
+            .min(100) as u32;
+        max_fee_per_gas - math::percent(max_fee_per_gas, penalty_percent)
+    }
+
     fn next_submission_id(&mut self) -> u64 {
         let id = self.submission_id;
         self.submission_id += 1;
@@ -667,14 +784,16 @@ struct OrderedPoolOperation {
     po: Arc<PoolOperation>,
     submission_id: u64,
     eligible: RwLock<bool>,
+    score: u128,
 }
 
 impl OrderedPoolOperation {
-    fn new(po: Arc<PoolOperation>, submission_id: u64, eligible: bool) -> Self {
+    fn new(po: Arc<PoolOperation>, submission_id: u64, eligible: bool, score: u128) -> Self {
         Self {
             po,
             submission_id,
             eligible: RwLock::new(eligible),
+            score,
         }
     }
 
@@ -703,11 +822,10 @@ impl Eq for OrderedPoolOperation {}
 
 impl Ord for OrderedPoolOperation {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Sort by gas price descending then by id ascending
+        // Sort by score descending then by id ascending
         other
-            .uo()
-            .max_fee_per_gas()
-            .cmp(&self.uo().max_fee_per_gas())
+            .score
+            .cmp(&self.score)
             .then_with(|| self.submission_id.cmp(&other.submission_id))
     }
 }
@@ -769,7 +887,6 @@ mod tests {
     use rundler_provider::MockDAGasOracleSync;
     use rundler_types::{
         v0_6::UserOperation, EntityInfo, EntityInfos, UserOperation as UserOperationTrait,
-        ValidTimeRange,
     };
 
     use super::*;
@@ -893,6 +1010,69 @@ mod tests {
         assert!(pool.remove_operation_by_hash(hashes[2]).is_none());
     }
 
+    #[test]
+    fn test_queue_position() {
+        let mut pool = pool();
+        let ops = vec![
+            create_op(Address::random(), 0, 1),
+            create_op(Address::random(), 0, 3),
+            create_op(Address::random(), 0, 2),
+        ];
+        let mut hashes = vec![];
+        for op in ops.iter() {
+            hashes.push(pool.add_operation(op.clone(), 0).unwrap());
+        }
+
+        // higher fee ops are ahead in the queue
+        assert_eq!(pool.queue_position(hashes[1]), Some(0));
+        assert_eq!(pool.queue_position(hashes[2]), Some(1));
+        assert_eq!(pool.queue_position(hashes[0]), Some(2));
+        assert_eq!(pool.queue_position(B256::random()), None);
+
+        // ineligible ops are not in the queue and don't count against others
+        pool.by_hash.get(&hashes[1]).unwrap().set_ineligible();
+        assert_eq!(pool.queue_position(hashes[1]), None);
+        assert_eq!(pool.queue_position(hashes[2]), Some(0));
+        assert_eq!(pool.queue_position(hashes[0]), Some(1));
+    }
+
+    #[test]
+    fn test_update_validation() {
+        let mut pool = pool();
+        let ops = vec![
+            create_op(Address::random(), 0, 2),
+            create_op(Address::random(), 0, 1),
+        ];
+        let mut hashes = vec![];
+        for op in ops.iter() {
+            hashes.push(pool.add_operation(op.clone(), 0).unwrap());
+        }
+
+        let valid_time_range = ValidTimeRange::new(10.into(), 20.into());
+        assert!(pool.update_validation(hashes[1], valid_time_range, 30_000, 5));
+
+        let updated = pool.get_operation_by_hash(hashes[1]).unwrap();
+        assert_eq!(updated.valid_time_range, valid_time_range);
+        assert_eq!(updated.validation_gas_used, 30_000);
+        assert_eq!(updated.validation_block_number, 5);
+        assert_eq!(
+            pool.get_operation_by_id(&ops[1].uo.id())
+                .unwrap()
+                .valid_time_range,
+            valid_time_range
+        );
+
+        // ordering is unchanged
+        assert_eq!(pool.best.len(), 2);
+        check_map_entry(pool.best.iter().next(), Some(&ops[0]));
+        assert_eq!(
+            pool.best.iter().nth(1).unwrap().po.valid_time_range,
+            valid_time_range
+        );
+
+        assert!(!pool.update_validation(B256::random(), valid_time_range, 30_000, 5));
+    }
+
     #[test]
     fn remove_account() {
         let mut pool = pool();
@@ -1110,6 +1290,28 @@ mod tests {
         assert!(result.is_ok(), "{:?}", result.err());
     }
 
+    #[test]
+    fn pool_full_check_size() {
+        let mut pool = pool();
+        for i in 0..20 {
+            let op = create_op(Address::random(), i, (i + 1) as u128);
+            pool.add_operation(op, 0).unwrap();
+        }
+
+        // on equal gas, the new op is discarded because the worst came first
+        let op = create_op(Address::random(), 4, 1);
+        assert!(matches!(
+            pool.check_size(&op),
+            Err(MempoolError::DiscardedOnInsert)
+        ));
+        assert!(pool.add_operation(op, 0).is_err());
+
+        let op = create_op(Address::random(), 4, 2);
+        pool.check_size(&op).unwrap();
+        assert_eq!(pool.by_hash.len(), 20);
+        pool.add_operation(op, 0).unwrap();
+    }
+
     #[test]
     fn replace_op_underpriced() {
         let mut pool = pool();
@@ -1135,7 +1337,7 @@ mod tests {
         assert_eq!(pool.address_count(&sender), 1);
         assert_eq!(
             pool.pool_size,
-            OrderedPoolOperation::new(Arc::new(po1), 0, true).mem_size(),
+            OrderedPoolOperation::new(Arc::new(po1), 0, true, 0).mem_size(),
         );
     }
 
@@ -1171,7 +1373,7 @@ mod tests {
         assert_eq!(pool.address_count(&paymaster2), 1);
         assert_eq!(
             pool.pool_size,
-            OrderedPoolOperation::new(Arc::new(po2), 0, true).mem_size()
+            OrderedPoolOperation::new(Arc::new(po2), 0, true, 0).mem_size()
         );
     }
 
@@ -1205,6 +1407,41 @@ mod tests {
         assert_eq!(None, pool.get_operation_by_hash(hash));
     }
 
+    #[test]
+    fn test_sim_gas_penalty() {
+        let mut conf = conf();
+        conf.sim_gas_penalty_percent = 10;
+        let mut pool = pool_with_conf(conf);
+
+        let mut expensive = create_op(Address::random(), 0, 1_000);
+        expensive.validation_gas_used = 5_000_000;
+        let mut cheap = create_op(Address::random(), 0, 1_000);
+        cheap.validation_gas_used = 100_000;
+        pool.add_operation(expensive.clone(), 0).unwrap();
+        pool.add_operation(cheap.clone(), 0).unwrap();
+
+        let best = pool.best_operations().collect::<Vec<_>>();
+        assert_eq!(*best[0], cheap);
+        assert_eq!(*best[1], expensive);
+    }
+
+    #[test]
+    fn test_sim_gas_penalty_disabled() {
+        let mut pool = pool();
+
+        let mut expensive = create_op(Address::random(), 0, 1_000);
+        expensive.validation_gas_used = 5_000_000;
+        let mut cheap = create_op(Address::random(), 0, 1_000);
+        cheap.validation_gas_used = 100_000;
+        pool.add_operation(expensive.clone(), 0).unwrap();
+        pool.add_operation(cheap.clone(), 0).unwrap();
+
+        // falls back to submission order
+        let best = pool.best_operations().collect::<Vec<_>>();
+        assert_eq!(*best[0], expensive);
+        assert_eq!(*best[1], cheap);
+    }
+
     #[test]
     fn test_multiple_expired() {
         let conf = conf();
@@ -1236,7 +1473,7 @@ mod tests {
 
         let po1 = create_op(Address::random(), 0, 10);
 
-        let hash = pool.add_operation(po1, 50_001).unwrap();
+        let hash = pool.add_operation(po1, 50_001, 0).unwrap();
 
         assert!(pool.get_operation_by_hash(hash).is_some());
         assert_eq!(pool.best_operations().collect::<Vec<_>>().len(), 0); // UO is ineligible due to pvg
@@ -1262,7 +1499,7 @@ mod tests {
 
         let mut pool = pool_with_conf_oracle(conf.clone(), oracle);
 
-        let hash = pool.add_operation(po1, pvg + 1).unwrap();
+        let hash = pool.add_operation(po1, pvg + 1, 0).unwrap();
 
         assert!(pool.get_operation_by_hash(hash).is_some());
         assert_eq!(pool.best_operations().collect::<Vec<_>>().len(), 0); // UO is ineligible due to pvg
@@ -1298,7 +1535,7 @@ mod tests {
 
         let mut pool = pool_with_conf_oracle(conf.clone(), oracle);
 
-        let hash = pool.add_operation(po1, pvg).unwrap();
+        let hash = pool.add_operation(po1, pvg, 0).unwrap();
 
         assert!(pool.get_operation_by_hash(hash).is_some());
         assert_eq!(pool.best_operations().collect::<Vec<_>>().len(), 1);
@@ -1334,7 +1571,7 @@ mod tests {
 
         let mut pool = pool_with_conf_oracle(conf.clone(), oracle);
 
-        let hash = pool.add_operation(po1, pvg).unwrap();
+        let hash = pool.add_operation(po1, pvg, 0).unwrap();
 
         assert!(pool.get_operation_by_hash(hash).is_some());
         assert_eq!(pool.best_operations().collect::<Vec<_>>().len(), 1);
@@ -1373,7 +1610,7 @@ mod tests {
 
         let mut pool = pool_with_conf_oracle(conf.clone(), oracle);
 
-        let hash = pool.add_operation(po1, pvg + 1).unwrap();
+        let hash = pool.add_operation(po1, pvg + 1, 0).unwrap();
 
         assert!(pool.get_operation_by_hash(hash).is_some());
         assert_eq!(pool.best_operations().collect::<Vec<_>>().len(), 0);
@@ -1398,6 +1635,7 @@ mod tests {
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
             da_gas_tracking_enabled: false,
+            sim_gas_penalty_percent: 0,
         }
     }
 
@@ -1417,7 +1655,8 @@ mod tests {
     }
 
     fn mem_size_of_ordered_pool_op() -> usize {
-        OrderedPoolOperation::new(Arc::new(create_op(Address::random(), 1, 1)), 1, true).mem_size()
+        OrderedPoolOperation::new(Arc::new(create_op(Address::random(), 1, 1)), 1, true, 1)
+            .mem_size()
     }
 
     fn create_op(sender: Address, nonce: usize, max_fee_per_gas: u128) -> PoolOperation {
@@ -1445,6 +1684,8 @@ mod tests {
             expected_code_hash: B256::random(),
             sim_block_hash: B256::random(),
             sim_block_number: 0,
+            validation_gas_used: 0,
+            validation_block_number: 0,
             account_is_staked: false,
             da_gas_data: Default::default(),
         }
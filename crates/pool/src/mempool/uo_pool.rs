@@ -11,28 +11,36 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashSet, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
 use alloy_primitives::{utils::format_units, Address, Bytes, B256, U256};
+use alloy_sol_types::SolInterface;
 use anyhow::Context;
-use futures::TryFutureExt;
+use futures::{StreamExt, TryFutureExt};
 use itertools::Itertools;
 use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 use parking_lot::RwLock;
+use rundler_contracts::utils::IAccountExecute::IAccountExecuteCalls;
 use rundler_provider::{
     DAGasOracleSync, EvmProvider, ProvidersWithEntryPointT, SimulationProvider, StateOverride,
 };
-use rundler_sim::{Prechecker, Simulator};
+use rundler_sim::{Prechecker, SimulationError, Simulator, ViolationError};
+use rundler_task::GracefulShutdown;
 use rundler_types::{
     pool::{
-        MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+        MempoolError, PaymasterMetadata, PoolOperation, PrecheckSettingsUpdate, PrecheckViolation,
+        Reputation, ReputationStatus, StakeStatus,
     },
-    Entity, EntityUpdate, EntityUpdateType, EntryPointVersion, GasFees, UserOperation,
+    Entity, EntityType, EntityUpdate, EntityUpdateType, EntryPointVersion, GasFees, UserOperation,
     UserOperationId, UserOperationVariant,
 };
-use rundler_utils::emit::WithEntryPoint;
-use tokio::sync::broadcast;
+use rundler_utils::{emit::WithEntryPoint, math};
+use tokio::sync::{broadcast, watch};
 use tonic::async_trait;
 use tracing::info;
 
@@ -45,6 +53,15 @@ use crate::{
     emit::{EntityReputation, EntityStatus, EntitySummary, OpPoolEvent, OpRemovalReason},
 };
 
+/// An operation that has passed all checks and is ready to be added to the pool
+struct ValidatedOperation {
+    pool_op: PoolOperation,
+    required_pre_verification_gas: u128,
+    entity_summary: EntitySummary,
+    throttled: bool,
+    replacement: bool,
+}
+
 /// User Operation Mempool
 ///
 /// Wrapper around a pool object that implements thread-safety
@@ -58,6 +75,7 @@ pub(crate) struct UoPool<UP: UoPoolProvidersT, EP: ProvidersWithEntryPointT> {
     paymaster: PaymasterTracker<EP::EntryPoint>,
     reputation: Arc<AddressReputation>,
     event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
+    revalidation_block: watch::Sender<Option<(u64, B256)>>,
     ep_specific_metrics: UoPoolMetricsEPSpecific,
     metrics: UoPoolMetrics,
 }
@@ -101,6 +119,7 @@ where
             reputation,
             paymaster,
             event_sender,
+            revalidation_block: watch::Sender::new(None),
             config,
             ep_specific_metrics: UoPoolMetricsEPSpecific::new_with_labels(&[("entry_point", ep)]),
             metrics: UoPoolMetrics::default(),
@@ -152,6 +171,127 @@ where
         self.ep_specific_metrics.removed_entities.increment(1);
     }
 
+    /// Runs re-validation in the background until shutdown.
+    ///
+    /// Re-validation is triggered by chain updates. At most one run is in flight at a time and
+    /// a newer block cancels the in-flight run, which is then restarted against the newer block.
+    pub(crate) async fn run_revalidation(&self, shutdown: GracefulShutdown) {
+        let mut blocks = self.revalidation_block.subscribe();
+        loop {
+            tokio::select! {
+                changed = blocks.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+                _ = shutdown.clone() => {
+                    info!("Shutting down re-validation for entry point {:?}", self.config.entry_point);
+                    return;
+                }
+            }
+
+            loop {
+                let Some((block_number, block_hash)) = *blocks.borrow_and_update() else {
+                    break;
+                };
+                tokio::select! {
+                    _ = self.revalidate_operations(block_number, block_hash) => break,
+                    changed = blocks.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                        tracing::debug!("Re-validation at block {block_number} superseded by a newer block");
+                    }
+                    _ = shutdown.clone() => {
+                        info!("Shutting down re-validation for entry point {:?}", self.config.entry_point);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Re-validates every operation in the pool at the given block with bounded concurrency.
+    ///
+    /// Operations that now fail simulation with a violation are removed from the pool. Operations
+    /// that pass have their valid time range and latest validation updated. Operations whose simulation fails for other
+    /// reasons (e.g. provider errors) are left untouched.
+    async fn revalidate_operations(&self, block_number: u64, block_hash: B256) {
+        let ops = self.state.read().pool.all_operations().collect::<Vec<_>>();
+        if ops.is_empty() {
+            return;
+        }
+
+        let start = Instant::now();
+        let results = futures::stream::iter(ops)
+            .map(|(hash, op)| async move {
+                let result = self
+                    .pool_providers
+                    .simulator()
+                    .simulate_validation(
+                        op.uo.clone().into(),
+                        block_hash,
+                        Some(op.expected_code_hash),
+                    )
+                    .await;
+                (hash, op.uo.pre_verification_gas(), result)
+            })
+            .buffer_unordered(self.config.revalidation_max_concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut removed = vec![];
+        {
+            let mut state = self.state.write();
+            for (hash, pre_verification_gas, result) in results {
+                match result {
+                    Ok(sim_result) => {
+                        state.pool.update_validation(
+                            hash,
+                            sim_result.valid_time_range,
+                            sim_result.pre_op_gas.saturating_sub(pre_verification_gas),
+                            block_number,
+                        );
+                    }
+                    Err(SimulationError {
+                        violation_error: ViolationError::Violations(violations),
+                        ..
+                    }) => {
+                        if let Some(op) = state.pool.remove_operation_by_hash(hash) {
+                            self.paymaster.remove_operation(&op.uo.id());
+                            state.throttled_ops.remove(&hash);
+                            removed.push((hash, violations));
+                        }
+                    }
+                    Err(SimulationError {
+                        violation_error: ViolationError::Other(error),
+                        ..
+                    }) => {
+                        tracing::warn!(
+                            "Failed to re-validate op {hash:?}, keeping in pool: {error:?}"
+                        );
+                    }
+                }
+            }
+        }
+
+        self.ep_specific_metrics
+            .removed_operations
+            .increment(removed.len() as u64);
+        for (hash, violations) in removed {
+            self.emit(OpPoolEvent::RemovedOp {
+                op_hash: hash,
+                reason: OpRemovalReason::RevalidationFailed {
+                    block_number,
+                    error: format!("{violations:?}"),
+                },
+            });
+        }
+        self.ep_specific_metrics
+            .revalidation_time
+            .record(start.elapsed().as_micros() as f64);
+    }
+
     async fn check_call_gas_limit_efficiency(
         &self,
         op: UserOperationVariant,
@@ -216,6 +356,262 @@ where
 
         Ok(())
     }
+
+    /// Checks an operation's fees against the raised fee floor applied while the chain
+    /// or the pool is congested
+    fn check_congestion_fees(&self, op: &UserOperationVariant) -> MempoolResult<()> {
+        let state = self.state.read();
+        let base_fee_congested = self
+            .config
+            .congestion_base_fee_threshold
+            .is_some_and(|threshold| state.base_fee > threshold);
+        let pool_congested = self
+            .config
+            .congestion_pool_fill_percent
+            .is_some_and(|threshold| state.pool.fill_percent() >= threshold);
+        if !base_fee_congested && !pool_congested {
+            return Ok(());
+        }
+
+        let min_priority_fee = math::increase_by_percent(
+            state.gas_fees.max_priority_fee_per_gas,
+            self.config.congestion_fee_increase_percent,
+        );
+        if op.max_priority_fee_per_gas() < min_priority_fee {
+            return Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::MaxPriorityFeePerGasTooLow(
+                    op.max_priority_fee_per_gas(),
+                    min_priority_fee,
+                ),
+            ));
+        }
+        let min_max_fee = state.base_fee + min_priority_fee;
+        if op.max_fee_per_gas() < min_max_fee {
+            return Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::MaxFeePerGasTooLow(op.max_fee_per_gas(), min_max_fee),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every contract an operation's call data targets is on the call
+    /// target allowlist, if configured
+    fn check_call_targets(&self, op: &UserOperationVariant) -> MempoolResult<()> {
+        let Some(allowlist) = &self.config.call_target_allowlist else {
+            return Ok(());
+        };
+
+        for target in call_targets(op.sender(), op.call_data()) {
+            if !allowlist.contains(&target) {
+                return Err(MempoolError::CallTargetNotAllowed(target));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that including an operation in a bundle doesn't exceed the per-bundle limit
+    /// for any of its throttled entities, counting it towards those limits if it doesn't
+    fn within_throttled_bundle_limit(
+        &self,
+        op: &PoolOperation,
+        throttled_counts: &mut HashMap<Address, u64>,
+    ) -> bool {
+        let throttled = op
+            .uo
+            .entities()
+            .into_iter()
+            .map(|entity| entity.address)
+            .filter(|address| self.reputation.status(*address) == ReputationStatus::Throttled)
+            .unique()
+            .collect::<Vec<_>>();
+
+        if throttled.iter().any(|address| {
+            throttled_counts.get(address).copied().unwrap_or_default()
+                >= self.config.throttled_entity_bundle_count
+        }) {
+            return false;
+        }
+
+        for address in throttled {
+            *throttled_counts.entry(address).or_default() += 1;
+        }
+        true
+    }
+
+    /// Runs all checks required to admit an operation to the pool, without
+    /// modifying any pool state.
+    async fn validate_operation(
+        &self,
+        op: UserOperationVariant,
+    ) -> MempoolResult<ValidatedOperation> {
+        // TODO(danc) aggregator reputation is not implemented
+        // TODO(danc) catch ops with aggregators prior to simulation and reject
+
+        // Reject v0.7 ops whose packed form doesn't match their fields, as the packed form is
+        // what gets bundled
+        if let UserOperationVariant::V0_7(uo) = &op {
+            uo.validate_packed_roundtrip()
+                .map_err(|e| MempoolError::PackedEncodingMismatch(e.to_string()))?;
+        }
+        self.check_call_targets(&op)?;
+
+        // Check reputation of entities in involved in the operation
+        // If throttled, entity can have THROTTLED_ENTITY_MEMPOOL_COUNT inflight operation at a time, else reject
+        // If banned, reject
+        let mut entity_summary = EntitySummary::default();
+        let mut throttled = false;
+
+        for entity in op.entities() {
+            let address = entity.address;
+            let reputation = match self.reputation.status(address) {
+                ReputationStatus::Ok => EntityReputation::Ok,
+                ReputationStatus::Throttled => {
+                    if self.state.read().pool.address_count(&address)
+                        >= self.config.throttled_entity_mempool_count as usize
+                    {
+                        return Err(MempoolError::EntityThrottled(entity));
+                    } else {
+                        throttled = true;
+                        EntityReputation::ThrottledButOk
+                    }
+                }
+                ReputationStatus::Banned => {
+                    return Err(MempoolError::EntityThrottled(entity));
+                }
+            };
+
+            entity_summary.set_status(
+                entity.kind,
+                EntityStatus {
+                    address,
+                    reputation,
+                },
+            );
+        }
+
+        // NOTE: We get the latest block from the provider here to avoid a race condition
+        // where the pool is still processing the previous block, but the user may have been
+        // notified of a new block.
+        //
+        // This doesn't clear all race conditions, as the pool may need to update its state before
+        // a UO can be valid, i.e. for replacement.
+        let (block_hash, block_number) = self
+            .ep_providers
+            .evm()
+            .get_latest_block_hash_and_number()
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        // Check if op is already known or replacing another, and if so, ensure its fees are high enough
+        // do this before simulation to save resources
+        let replacement = self.state.read().pool.check_replacement(&op)?;
+        // Check if op violates the STO-040 spec rule
+        self.state.read().pool.check_multiple_roles_violation(&op)?;
+        // Check if op pays enough to be accepted while congested
+        self.check_congestion_fees(&op)?;
+
+        // check if paymaster is present and exists in pool
+        // this is optimistic and could potentially lead to
+        // multiple user operations call this before they are
+        // added to the pool and can lead to an overdraft
+        self.paymaster.check_operation_cost(&op).await?;
+
+        // Prechecks
+        let versioned_op = op.clone().into();
+
+        let precheck_ret = self
+            .pool_providers
+            .prechecker()
+            .check(&versioned_op, block_hash.into())
+            .await?;
+
+        // Only let ops with successful simulations through
+        // Run simulation and call gas limit efficiency check in parallel
+        let sim_fut = self
+            .pool_providers
+            .simulator()
+            .simulate_validation(versioned_op, block_hash, None)
+            .map_err(Into::into);
+        let call_gas_check_future = self.check_call_gas_limit_efficiency(op.clone(), block_hash);
+        let (sim_result, _) = tokio::try_join!(sim_fut, call_gas_check_future)?;
+
+        // No aggregators supported for now
+        if let Some(agg) = &sim_result.aggregator {
+            return Err(MempoolError::UnsupportedAggregator(agg.address));
+        }
+
+        // Check if op violates the STO-041 spec rule
+        self.state
+            .read()
+            .pool
+            .check_associated_storage(&sim_result.associated_addresses, &op)?;
+
+        // Check pre op gas limit efficiency
+        let pre_op_gas_efficiency = sim_result.pre_op_gas as f32 / op.pre_op_gas_limit() as f32;
+        if pre_op_gas_efficiency < self.config.gas_limit_efficiency_reject_threshold {
+            return Err(MempoolError::PreOpGasLimitEfficiencyTooLow(
+                self.config.gas_limit_efficiency_reject_threshold,
+                pre_op_gas_efficiency,
+            ));
+        }
+
+        let valid_time_range = sim_result.valid_time_range;
+        let validation_gas_used = sim_result
+            .pre_op_gas
+            .saturating_sub(op.pre_verification_gas());
+        let pool_op = PoolOperation {
+            uo: op,
+            entry_point: self.config.entry_point,
+            aggregator: None,
+            valid_time_range,
+            expected_code_hash: sim_result.code_hash,
+            sim_block_hash: block_hash,
+            sim_block_number: block_number,
+            validation_gas_used,
+            validation_block_number: block_number,
+            account_is_staked: sim_result.account_is_staked,
+            entity_infos: sim_result.entity_infos,
+            da_gas_data: precheck_ret.da_gas_data,
+        };
+
+        // Check sender count in mempool. If sender has too many operations, must be staked
+        {
+            let state = self.state.read();
+            if !pool_op.account_is_staked
+                && state.pool.address_count(&pool_op.uo.sender())
+                    >= self.config.same_sender_mempool_count
+            {
+                return Err(MempoolError::MaxOperationsReached(
+                    self.config.same_sender_mempool_count,
+                    Entity::account(pool_op.uo.sender()),
+                ));
+            }
+
+            // Check unstaked non-sender entity counts in the mempool
+            for entity in pool_op
+                .unstaked_entities()
+                .filter(|e| e.address != pool_op.entity_infos.sender.address())
+            {
+                let ops_allowed = self.reputation.get_ops_allowed(entity.address);
+                if state.pool.address_count(&entity.address) >= ops_allowed as usize {
+                    return Err(MempoolError::MaxOperationsReached(
+                        ops_allowed as usize,
+                        entity,
+                    ));
+                }
+            }
+        }
+
+        Ok(ValidatedOperation {
+            pool_op,
+            required_pre_verification_gas: precheck_ret.required_pre_verification_gas,
+            entity_summary,
+            throttled,
+            replacement: replacement.is_some(),
+        })
+    }
 }
 
 #[async_trait]
@@ -458,6 +854,11 @@ where
         self.ep_specific_metrics
             .maintenance_time
             .record(maintenance_time.as_micros() as f64);
+
+        if self.config.revalidation_enabled {
+            self.revalidation_block
+                .send_replace(Some((update.latest_block_number, update.latest_block_hash)));
+        }
     }
 
     fn entry_point(&self) -> Address {
@@ -473,155 +874,20 @@ where
         origin: OperationOrigin,
         op: UserOperationVariant,
     ) -> MempoolResult<B256> {
-        // TODO(danc) aggregator reputation is not implemented
-        // TODO(danc) catch ops with aggregators prior to simulation and reject
-
-        // Check reputation of entities in involved in the operation
-        // If throttled, entity can have THROTTLED_ENTITY_MEMPOOL_COUNT inflight operation at a time, else reject
-        // If banned, reject
-        let mut entity_summary = EntitySummary::default();
-        let mut throttled = false;
-
-        for entity in op.entities() {
-            let address = entity.address;
-            let reputation = match self.reputation.status(address) {
-                ReputationStatus::Ok => EntityReputation::Ok,
-                ReputationStatus::Throttled => {
-                    if self.state.read().pool.address_count(&address)
-                        >= self.config.throttled_entity_mempool_count as usize
-                    {
-                        return Err(MempoolError::EntityThrottled(entity));
-                    } else {
-                        throttled = true;
-                        EntityReputation::ThrottledButOk
-                    }
-                }
-                ReputationStatus::Banned => {
-                    return Err(MempoolError::EntityThrottled(entity));
-                }
-            };
-
-            entity_summary.set_status(
-                entity.kind,
-                EntityStatus {
-                    address,
-                    reputation,
-                },
-            );
-        }
-
-        // NOTE: We get the latest block from the provider here to avoid a race condition
-        // where the pool is still processing the previous block, but the user may have been
-        // notified of a new block.
-        //
-        // This doesn't clear all race conditions, as the pool may need to update its state before
-        // a UO can be valid, i.e. for replacement.
-        let (block_hash, block_number) = self
-            .ep_providers
-            .evm()
-            .get_latest_block_hash_and_number()
-            .await
-            .map_err(anyhow::Error::from)?;
-
-        // Check if op is already known or replacing another, and if so, ensure its fees are high enough
-        // do this before simulation to save resources
-        let replacement = self.state.read().pool.check_replacement(&op)?;
-        // Check if op violates the STO-040 spec rule
-        self.state.read().pool.check_multiple_roles_violation(&op)?;
-
-        // check if paymaster is present and exists in pool
-        // this is optimistic and could potentially lead to
-        // multiple user operations call this before they are
-        // added to the pool and can lead to an overdraft
-        self.paymaster.check_operation_cost(&op).await?;
-
-        // Prechecks
-        let versioned_op = op.clone().into();
-
-        let precheck_ret = self
-            .pool_providers
-            .prechecker()
-            .check(&versioned_op, block_hash.into())
-            .await?;
-
-        // Only let ops with successful simulations through
-        // Run simulation and call gas limit efficiency check in parallel
-        let sim_fut = self
-            .pool_providers
-            .simulator()
-            .simulate_validation(versioned_op, block_hash, None)
-            .map_err(Into::into);
-        let call_gas_check_future = self.check_call_gas_limit_efficiency(op.clone(), block_hash);
-        let (sim_result, _) = tokio::try_join!(sim_fut, call_gas_check_future)?;
-
-        // No aggregators supported for now
-        if let Some(agg) = &sim_result.aggregator {
-            return Err(MempoolError::UnsupportedAggregator(agg.address));
-        }
-
-        // Check if op violates the STO-041 spec rule
-        self.state
-            .read()
-            .pool
-            .check_associated_storage(&sim_result.associated_addresses, &op)?;
-
-        // Check pre op gas limit efficiency
-        let pre_op_gas_efficiency = sim_result.pre_op_gas as f32 / op.pre_op_gas_limit() as f32;
-        if pre_op_gas_efficiency < self.config.gas_limit_efficiency_reject_threshold {
-            return Err(MempoolError::PreOpGasLimitEfficiencyTooLow(
-                self.config.gas_limit_efficiency_reject_threshold,
-                pre_op_gas_efficiency,
-            ));
-        }
-
-        let valid_time_range = sim_result.valid_time_range;
-        let pool_op = PoolOperation {
-            uo: op,
-            entry_point: self.config.entry_point,
-            aggregator: None,
-            valid_time_range,
-            expected_code_hash: sim_result.code_hash,
-            sim_block_hash: block_hash,
-            sim_block_number: block_number,
-            account_is_staked: sim_result.account_is_staked,
-            entity_infos: sim_result.entity_infos,
-            da_gas_data: precheck_ret.da_gas_data,
-        };
-
-        // Check sender count in mempool. If sender has too many operations, must be staked
-        {
-            let state = self.state.read();
-            if !pool_op.account_is_staked
-                && state.pool.address_count(&pool_op.uo.sender())
-                    >= self.config.same_sender_mempool_count
-            {
-                return Err(MempoolError::MaxOperationsReached(
-                    self.config.same_sender_mempool_count,
-                    Entity::account(pool_op.uo.sender()),
-                ));
-            }
-
-            // Check unstaked non-sender entity counts in the mempool
-            for entity in pool_op
-                .unstaked_entities()
-                .filter(|e| e.address != pool_op.entity_infos.sender.address())
-            {
-                let ops_allowed = self.reputation.get_ops_allowed(entity.address);
-                if state.pool.address_count(&entity.address) >= ops_allowed as usize {
-                    return Err(MempoolError::MaxOperationsReached(
-                        ops_allowed as usize,
-                        entity,
-                    ));
-                }
-            }
-        }
+        let ValidatedOperation {
+            pool_op,
+            required_pre_verification_gas,
+            entity_summary,
+            throttled,
+            replacement,
+        } = self.validate_operation(op).await?;
 
         // Add op to pool
         let hash = {
             let mut state = self.state.write();
             let hash = state
                 .pool
-                .add_operation(pool_op.clone(), precheck_ret.required_pre_verification_gas)?;
+                .add_operation(pool_op.clone(), required_pre_verification_gas)?;
 
             if throttled {
                 state.throttled_ops.insert(hash);
@@ -634,7 +900,7 @@ where
         self.paymaster.add_or_update_balance(&pool_op).await?;
 
         // Update reputation
-        if replacement.is_none() {
+        if !replacement {
             pool_op.entities().unique().for_each(|e| {
                 self.reputation.add_seen(e.address);
                 if self.reputation.status(e.address) == ReputationStatus::Throttled {
@@ -662,6 +928,15 @@ where
         Ok(hash)
     }
 
+    async fn check_operation(&self, op: UserOperationVariant) -> MempoolResult<B256> {
+        let ValidatedOperation { pool_op, .. } = self.validate_operation(op).await?;
+        // an op that would be discarded to keep the pool within its size limit isn't accepted
+        self.state.read().pool.check_size(&pool_op)?;
+        Ok(pool_op
+            .uo
+            .hash(self.config.entry_point, self.config.chain_spec.id))
+    }
+
     fn remove_operations(&self, hashes: &[B256]) {
         let mut count: u64 = 0;
         let mut removed_hashes = vec![];
@@ -768,6 +1043,8 @@ where
         let ordered_ops = state.pool.best_operations();
         // keep track of senders to avoid sending multiple ops from the same sender
         let mut senders = HashSet::<Address>::new();
+        // keep track of the number of ops returned for each throttled entity
+        let mut throttled_counts = HashMap::<Address, u64>::new();
 
         Ok(ordered_ops
             .into_iter()
@@ -784,7 +1061,9 @@ where
                     senders.insert(op.uo.sender())
                 } else {
                     true
-                }
+                } &&
+                // limit the number of ops from each throttled entity
+                self.within_throttled_bundle_limit(op, &mut throttled_counts)
             })
             .take(max)
             .map(Into::into)
@@ -799,6 +1078,14 @@ where
         self.state.read().pool.get_operation_by_hash(hash)
     }
 
+    fn get_user_operation_by_id(&self, id: &UserOperationId) -> Option<Arc<PoolOperation>> {
+        self.state.read().pool.get_operation_by_id(id)
+    }
+
+    fn get_user_operation_queue_position(&self, hash: B256) -> Option<usize> {
+        self.state.read().pool.queue_position(hash)
+    }
+
     // DEBUG METHODS
 
     fn clear_state(&self, clear_mempool: bool, clear_paymaster: bool, clear_reputation: bool) {
@@ -836,6 +1123,25 @@ where
         self.paymaster.get_stake_status(address).await
     }
 
+    async fn get_entity_stake_statuses(
+        &self,
+        op: &UserOperationVariant,
+    ) -> MempoolResult<Vec<(Entity, StakeStatus)>> {
+        let entities = op
+            .entities()
+            .into_iter()
+            .filter(|entity| entity.kind != EntityType::Account)
+            .collect::<Vec<_>>();
+        let statuses = futures::future::try_join_all(
+            entities
+                .iter()
+                .map(|entity| self.paymaster.get_stake_status(entity.address)),
+        )
+        .await?;
+
+        Ok(entities.into_iter().zip(statuses).collect())
+    }
+
     async fn reset_confirmed_paymaster_balances(&self) -> MempoolResult<()> {
         self.paymaster.reset_confirmed_balances().await
     }
@@ -844,6 +1150,29 @@ where
         self.paymaster.set_tracking(paymaster);
         self.reputation.set_tracking(reputation);
     }
+
+    fn set_precheck_settings(
+        &self,
+        settings: PrecheckSettingsUpdate,
+    ) -> MempoolResult<PrecheckSettingsUpdate> {
+        Ok(self.pool_providers.prechecker().update_settings(settings)?)
+    }
+}
+
+/// Returns the contracts targeted by an account's `execute` or `executeBatch` call data.
+///
+/// Call data that doesn't decode as either is treated as a call to the account itself.
+fn call_targets(sender: Address, call_data: &[u8]) -> Vec<Address> {
+    if call_data.is_empty() {
+        return vec![];
+    }
+
+    match IAccountExecuteCalls::abi_decode(call_data, true) {
+        Ok(IAccountExecuteCalls::execute(call)) => vec![call.dest],
+        Ok(IAccountExecuteCalls::executeBatch_0(call)) => call.dest,
+        Ok(IAccountExecuteCalls::executeBatch_1(call)) => call.dest,
+        Err(_) => vec![sender],
+    }
 }
 
 // Type erasure for UoPool providers
@@ -903,6 +1232,8 @@ struct UoPoolMetricsEPSpecific {
     removed_entities: Counter,
     #[metric(describe = "time to run pool maintenance in µs.")]
     maintenance_time: Histogram,
+    #[metric(describe = "time to re-validate all ops in the pool in µs.")]
+    revalidation_time: Histogram,
 }
 
 #[derive(Metrics)]
@@ -921,7 +1252,9 @@ mod tests {
     use std::{collections::HashMap, vec};
 
     use alloy_primitives::{uint, Bytes};
+    use alloy_sol_types::SolCall;
     use mockall::Sequence;
+    use rundler_contracts::utils::IAccountExecute;
     use rundler_provider::{
         DepositInfo, ExecutionResult, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider,
         ProvidersWithEntryPoint,
@@ -964,6 +1297,66 @@ mod tests {
         assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn check_single_op() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let hash = pool.check_operation(op.op.clone()).await.unwrap();
+        assert!(pool.all_operations(usize::MAX).is_empty());
+        assert!(pool.get_user_operation_by_hash(hash).is_none());
+
+        // the op is still accepted afterwards, under the same hash
+        let added = pool
+            .add_operation(OperationOrigin::Local, op.op)
+            .await
+            .unwrap();
+        assert_eq!(hash, added);
+    }
+
+    #[tokio::test]
+    async fn check_op_pool_full() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool_with_config(
+            PoolConfig {
+                max_size_of_pool_bytes: 1,
+                ..default_config()
+            },
+            vec![op.clone()],
+        );
+
+        assert!(matches!(
+            pool.check_operation(op.op.clone()).await,
+            Err(MempoolError::DiscardedOnInsert)
+        ));
+        assert!(matches!(
+            pool.add_operation(OperationOrigin::Local, op.op).await,
+            Err(MempoolError::DiscardedOnInsert)
+        ));
+    }
+
+    #[tokio::test]
+    async fn check_op_precheck_error() {
+        let sender = Address::random();
+        let op = create_op_with_errors(
+            sender,
+            0,
+            0,
+            Some(PrecheckViolation::SenderIsNotContractAndNoInitCode(sender)),
+            None,
+            false,
+        );
+        let pool = create_pool(vec![op.clone()]);
+
+        match pool.check_operation(op.op).await {
+            Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::SenderIsNotContractAndNoInitCode(_),
+            )) => {}
+            _ => panic!("Expected SenderIsNotContractAndNoInitCode error"),
+        }
+        assert!(pool.all_operations(usize::MAX).is_empty());
+    }
+
     #[tokio::test]
     async fn add_multiple_ops() {
         let ops = vec![
@@ -1353,6 +1746,34 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_throttled_paymaster_bundle_limit() {
+        let paymaster = Address::random();
+        let ops = vec![
+            create_op(Address::random(), 0, 3, Some(paymaster)),
+            create_op(Address::random(), 0, 2, Some(paymaster)),
+            create_op(Address::random(), 0, 1, Some(paymaster)),
+        ];
+
+        let mut entrypoint = MockEntryPointV0_6::new();
+        entrypoint
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::from(1000)));
+
+        let (pool, uos) = create_pool_with_entrypoint_insert_ops(ops, entrypoint).await;
+        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+
+        // Past throttle slack
+        let ops_seen = 100;
+        let ops_included = ops_seen / 10 - THROTTLE_SLACK - 1;
+        pool.set_reputation(paymaster, ops_seen, ops_included);
+
+        // Only the best op using the throttled paymaster should be bundled
+        check_ops(pool.best_operations(3, 0).unwrap(), vec![uos[0].clone()]);
+        // All ops remain in the mempool
+        check_ops(pool.all_operations(3), uos);
+    }
+
     #[tokio::test]
     async fn test_banned_account() {
         let address = Address::random();
@@ -1512,6 +1933,156 @@ mod tests {
         assert!(!status.is_staked);
     }
 
+    fn call_target_op(call_data: Bytes) -> OpWithErrors {
+        let mut op = create_op(Address::random(), 0, 0, None);
+        let uo: &mut UserOperation = op.op.as_mut();
+        uo.call_data = call_data;
+        op
+    }
+
+    fn call_target_pool(
+        allowed: Address,
+        ops: Vec<OpWithErrors>,
+    ) -> UoPool<impl UoPoolProvidersT, impl ProvidersWithEntryPointT> {
+        let mut config = default_config();
+        config.call_target_allowlist = Some(HashSet::from([allowed]));
+        create_pool_with_config(config, ops)
+    }
+
+    #[tokio::test]
+    async fn test_call_target_allowlisted() {
+        let allowed = Address::random();
+        let op = call_target_op(
+            IAccountExecute::executeCall {
+                dest: allowed,
+                value: U256::ZERO,
+                func: Bytes::new(),
+            }
+            .abi_encode()
+            .into(),
+        );
+        let pool = call_target_pool(allowed, vec![op.clone()]);
+
+        pool.add_operation(OperationOrigin::Local, op.op)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_target_not_allowlisted() {
+        let other = Address::random();
+        let op = call_target_op(
+            IAccountExecute::executeCall {
+                dest: other,
+                value: U256::ZERO,
+                func: Bytes::new(),
+            }
+            .abi_encode()
+            .into(),
+        );
+        let pool = call_target_pool(Address::random(), vec![op.clone()]);
+
+        let err = pool
+            .add_operation(OperationOrigin::Local, op.op)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MempoolError::CallTargetNotAllowed(target) if target == other));
+    }
+
+    #[tokio::test]
+    async fn test_call_target_batch() {
+        let allowed = Address::random();
+        let other = Address::random();
+
+        let op = call_target_op(
+            IAccountExecute::executeBatch_0Call {
+                dest: vec![allowed, allowed],
+                func: vec![Bytes::new(), Bytes::new()],
+            }
+            .abi_encode()
+            .into(),
+        );
+        let pool = call_target_pool(allowed, vec![op.clone()]);
+        pool.add_operation(OperationOrigin::Local, op.op)
+            .await
+            .unwrap();
+
+        let op = call_target_op(
+            IAccountExecute::executeBatch_1Call {
+                dest: vec![allowed, other],
+                value: vec![U256::ZERO, U256::ZERO],
+                func: vec![Bytes::new(), Bytes::new()],
+            }
+            .abi_encode()
+            .into(),
+        );
+        let pool = call_target_pool(allowed, vec![op.clone()]);
+        let err = pool
+            .add_operation(OperationOrigin::Local, op.op)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MempoolError::CallTargetNotAllowed(target) if target == other));
+    }
+
+    #[test]
+    fn test_call_targets_unrecognized_call_data() {
+        let sender = Address::random();
+        assert_eq!(call_targets(sender, &[]), vec![]);
+        assert_eq!(
+            call_targets(sender, &[0xde, 0xad, 0xbe, 0xef]),
+            vec![sender]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entity_stake_statuses() {
+        let factory = Address::random();
+        let paymaster = Address::random();
+
+        let mut entrypoint = MockEntryPointV0_6::new();
+        entrypoint
+            .expect_get_deposit_info()
+            .returning(move |address| {
+                // only the paymaster meets the default minimum stake and unstake delay
+                let (stake, unstake_delay_sec) = if address == paymaster {
+                    (uint!(2_000_000_000_000_000_000_U256), 84600)
+                } else {
+                    (U256::from(1000), 0)
+                };
+                Ok(DepositInfo {
+                    deposit: U256::from(1000),
+                    staked: stake > U256::from(1000),
+                    stake,
+                    unstake_delay_sec,
+                    withdraw_time: 0,
+                })
+            });
+        let pool = create_pool_with_entry_point(vec![], entrypoint);
+
+        let op = UserOperationVariant::from(UserOperation {
+            sender: Address::random(),
+            init_code: factory.to_vec().into(),
+            paymaster_and_data: paymaster.to_vec().into(),
+            ..Default::default()
+        });
+
+        let statuses = pool
+            .get_entity_stake_statuses(&op)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(entity, status)| (entity, status.is_staked))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (Entity::paymaster(paymaster), true),
+                (Entity::factory(factory), false),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_replacement() {
         let paymaster = Address::random();
@@ -1579,6 +2150,165 @@ mod tests {
         check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn test_revalidation_drops_invalidated_op() {
+        let mut op = create_op(Address::random(), 0, 0, None);
+        // state change after insertion causes the op to fail validation
+        op.revalidation_error = Some(SimulationViolation::UnintendedRevert(
+            EntityType::Account,
+            None,
+        ));
+        let pool = create_pool_with_config(
+            PoolConfig {
+                revalidation_enabled: true,
+                ..default_config()
+            },
+            vec![op.clone()],
+        );
+
+        let hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        run_triggered_revalidation(&pool).await;
+
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+        assert_eq!(pool.get_user_operation_by_hash(hash), None);
+    }
+
+    #[tokio::test]
+    async fn test_revalidation_releases_paymaster_balance() {
+        let paymaster = Address::random();
+        let mut op = create_op(Address::random(), 0, 5, Some(paymaster));
+        // pending max cost of 50
+        let uo: &mut UserOperation = op.op.as_mut();
+        uo.call_gas_limit = 10;
+        uo.verification_gas_limit = 10;
+        uo.pre_verification_gas = 10;
+        uo.max_fee_per_gas = 1;
+        op.revalidation_error = Some(SimulationViolation::UnintendedRevert(
+            EntityType::Paymaster,
+            Some(paymaster),
+        ));
+
+        let mut entrypoint = MockEntryPointV0_6::new();
+        entrypoint
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::from(1000)));
+        let pool = create_pool_with_entry_point_config(
+            PoolConfig {
+                revalidation_enabled: true,
+                ..default_config()
+            },
+            vec![op.clone()],
+            entrypoint,
+        );
+
+        pool.add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+        let metadata = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
+        assert_eq!(metadata.pending_balance, U256::from(950));
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        run_triggered_revalidation(&pool).await;
+
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+        let metadata = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
+        assert_eq!(metadata.pending_balance, U256::from(1000));
+    }
+
+    #[tokio::test]
+    async fn test_revalidation_keeps_valid_op() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool_with_config(
+            PoolConfig {
+                revalidation_enabled: true,
+                ..default_config()
+            },
+            vec![op.clone()],
+        );
+
+        let hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        run_triggered_revalidation(&pool).await;
+
+        let pool_op = pool.get_user_operation_by_hash(hash).unwrap();
+        assert_eq!(pool_op.validation_block_number, 1);
+        assert_eq!(
+            pool_op.validation_gas_used,
+            100_000 - op.op.pre_verification_gas()
+        );
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+    }
+
+    #[tokio::test]
+    async fn test_revalidation_disabled() {
+        let mut op = create_op(Address::random(), 0, 0, None);
+        op.revalidation_error = Some(SimulationViolation::UnintendedRevert(
+            EntityType::Account,
+            None,
+        ));
+        let pool = create_pool(vec![op.clone()]);
+
+        pool.add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        assert!(pool.revalidation_block.borrow().is_none());
+
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_queue_position() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 1, None),
+            create_op(Address::random(), 0, 2, None),
+        ])
+        .await;
+        let low_fee_hash = uos[0].hash(pool.config.entry_point, 0);
+        let high_fee_hash = uos[1].hash(pool.config.entry_point, 0);
+
+        assert_eq!(
+            pool.get_user_operation_queue_position(high_fee_hash),
+            Some(0)
+        );
+        assert_eq!(
+            pool.get_user_operation_queue_position(low_fee_hash),
+            Some(1)
+        );
+        assert_eq!(pool.get_user_operation_queue_position(B256::random()), None);
+    }
+
     #[tokio::test]
     async fn test_get_user_op_by_hash() {
         let op = create_op(Address::random(), 0, 0, None);
@@ -1698,6 +2428,110 @@ mod tests {
         check_ops(pool.best_operations(3, 0).unwrap(), uos);
     }
 
+    fn create_op_with_fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> OpWithErrors {
+        create_op_from_op_v0_6(UserOperation {
+            sender: Address::random(),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_congestion_base_fee_rejects_low_fee_op() {
+        let mut config = default_config();
+        config.congestion_base_fee_threshold = Some(100);
+        config.congestion_fee_increase_percent = 50;
+
+        let low_fee_op = create_op_with_fees(300, 10);
+        let high_fee_op = create_op_with_fees(300, 20);
+        let pool = create_pool_with_config(config, vec![low_fee_op.clone(), high_fee_op.clone()]);
+        {
+            let mut state = pool.state.write();
+            state.base_fee = 200;
+            state.gas_fees = GasFees {
+                max_fee_per_gas: 210,
+                max_priority_fee_per_gas: 10,
+            };
+        }
+
+        match pool
+            .add_operation(OperationOrigin::Local, low_fee_op.op)
+            .await
+        {
+            Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::MaxPriorityFeePerGasTooLow(10, 15),
+            )) => {}
+            r => panic!("Expected MaxPriorityFeePerGasTooLow error, got {r:?}"),
+        }
+        pool.add_operation(OperationOrigin::Local, high_fee_op.op)
+            .await
+            .unwrap();
+        assert_eq!(pool.all_operations(usize::MAX).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_congestion_base_fee_below_threshold() {
+        let mut config = default_config();
+        config.congestion_base_fee_threshold = Some(1000);
+        config.congestion_fee_increase_percent = 50;
+
+        let low_fee_op = create_op_with_fees(300, 10);
+        let pool = create_pool_with_config(config, vec![low_fee_op.clone()]);
+        {
+            let mut state = pool.state.write();
+            state.base_fee = 200;
+            state.gas_fees = GasFees {
+                max_fee_per_gas: 210,
+                max_priority_fee_per_gas: 10,
+            };
+        }
+
+        pool.add_operation(OperationOrigin::Local, low_fee_op.op)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_congestion_pool_fill_rejects_low_fee_op() {
+        let mut config = default_config();
+        config.congestion_pool_fill_percent = Some(1);
+        config.congestion_fee_increase_percent = 50;
+
+        let first_op = create_op_with_fees(300, 10);
+        let low_fee_op = create_op_with_fees(300, 10);
+        let high_fee_op = create_op_with_fees(300, 20);
+        let pool = create_pool_with_config(
+            config,
+            vec![first_op.clone(), low_fee_op.clone(), high_fee_op.clone()],
+        );
+        {
+            let mut state = pool.state.write();
+            state.base_fee = 200;
+            state.gas_fees = GasFees {
+                max_fee_per_gas: 210,
+                max_priority_fee_per_gas: 10,
+            };
+        }
+
+        // pool is empty, so not congested
+        pool.add_operation(OperationOrigin::Local, first_op.op)
+            .await
+            .unwrap();
+        assert!(pool.state.read().pool.fill_percent() >= 1);
+
+        assert!(matches!(
+            pool.add_operation(OperationOrigin::Local, low_fee_op.op)
+                .await,
+            Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::MaxPriorityFeePerGasTooLow(10, 15)
+            ))
+        ));
+        pool.add_operation(OperationOrigin::Local, high_fee_op.op)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_pre_op_gas_limit_reject() {
         let mut config = default_config();
@@ -1735,6 +2569,38 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_sim_gas_penalty_excludes_pre_verification_gas() {
+        let mut config = default_config();
+        config.sim_gas_penalty_percent = 10;
+
+        // both simulate with 100K pre-op gas, but most of the first op's is pre-verification gas
+        let expensive = create_op_from_op_v0_6(UserOperation {
+            sender: Address::random(),
+            max_fee_per_gas: 1_000,
+            ..Default::default()
+        });
+        let cheap = create_op_from_op_v0_6(UserOperation {
+            sender: Address::random(),
+            max_fee_per_gas: 1_000,
+            pre_verification_gas: 90_000,
+            ..Default::default()
+        });
+
+        let pool = create_pool_with_config(config, vec![expensive.clone(), cheap.clone()]);
+        pool.add_operation(OperationOrigin::Local, expensive.op.clone())
+            .await
+            .unwrap();
+        pool.add_operation(OperationOrigin::Local, cheap.op.clone())
+            .await
+            .unwrap();
+
+        check_ops(
+            pool.best_operations(2, 0).unwrap(),
+            vec![cheap.op, expensive.op],
+        );
+    }
+
     #[tokio::test]
     async fn test_call_gas_limit_reject() {
         let mut config = default_config();
@@ -1816,6 +2682,7 @@ mod tests {
         valid_time_range: ValidTimeRange,
         precheck_error: Option<PrecheckViolation>,
         simulation_error: Option<SimulationViolation>,
+        revalidation_error: Option<SimulationViolation>,
         staked: bool,
     }
 
@@ -1828,6 +2695,7 @@ mod tests {
             max_size_of_pool_bytes: 10000,
             blocklist: None,
             allowlist: None,
+            call_target_allowlist: None,
             precheck_settings: PrecheckSettings::default(),
             sim_settings: SimulationSettings::default(),
             mempool_channel_configs: HashMap::new(),
@@ -1835,12 +2703,19 @@ mod tests {
             same_sender_mempool_count: 4,
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
+            throttled_entity_bundle_count: 1,
             paymaster_tracking_enabled: true,
             da_gas_tracking_enabled: false,
             paymaster_cache_length: 100,
             reputation_tracking_enabled: true,
             drop_min_num_blocks: 10,
             gas_limit_efficiency_reject_threshold: 0.0,
+            sim_gas_penalty_percent: 0,
+            revalidation_enabled: false,
+            revalidation_max_concurrency: 4,
+            congestion_base_fee_threshold: None,
+            congestion_pool_fill_percent: None,
+            congestion_fee_increase_percent: 0,
         }
     }
 
@@ -1851,6 +2726,17 @@ mod tests {
         create_pool_with_entry_point(ops, entrypoint)
     }
 
+    /// Runs the re-validation triggered by the latest chain update, as the background runner would
+    async fn run_triggered_revalidation(
+        pool: &UoPool<impl UoPoolProvidersT, impl ProvidersWithEntryPointT>,
+    ) {
+        let (block_number, block_hash) = pool
+            .revalidation_block
+            .borrow()
+            .expect("chain update should trigger re-validation");
+        pool.revalidate_operations(block_number, block_hash).await;
+    }
+
     fn create_pool_with_config(
         args: PoolConfig,
         ops: Vec<OpWithErrors>,
@@ -1921,8 +2807,14 @@ mod tests {
             });
             simulator
                 .expect_simulate_validation()
-                .returning(move |_, _, _| {
-                    if let Some(error) = &op.simulation_error {
+                .returning(move |_, _, expected_code_hash| {
+                    // re-validation of an op already in the pool passes its expected code hash
+                    let error = if expected_code_hash.is_some() {
+                        &op.revalidation_error
+                    } else {
+                        &op.simulation_error
+                    };
+                    if let Some(error) = error {
                         Err(SimulationError {
                             violation_error: ViolationError::Violations(vec![error.clone()]),
                             entity_infos: None,
@@ -2011,6 +2903,7 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error: None,
             simulation_error: None,
+            revalidation_error: None,
             staked: false,
         }
     }
@@ -2034,6 +2927,7 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error,
             simulation_error,
+            revalidation_error: None,
             staked,
         }
     }
@@ -2044,6 +2938,7 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error: None,
             simulation_error: None,
+            revalidation_error: None,
             staked: false,
         }
     }
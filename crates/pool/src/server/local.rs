@@ -24,8 +24,9 @@ use rundler_task::{
 };
 use rundler_types::{
     pool::{
-        MempoolError, NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult,
-        Reputation, ReputationStatus, StakeStatus,
+        MempoolError, MinedUserOperation, NewHead, PaymasterMetadata, Pool, PoolError,
+        PoolOperation, PoolResult, PrecheckSettingsUpdate, Reputation, ReputationStatus,
+        StakeStatus,
     },
     EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
 };
@@ -143,6 +144,15 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn check_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256> {
+        let req = ServerRequestKind::CheckOp { entry_point, op };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::CheckOp { hash } => Ok(hash),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn get_ops(
         &self,
         entry_point: Address,
@@ -170,6 +180,28 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn get_op_by_id(
+        &self,
+        entry_point: Address,
+        id: UserOperationId,
+    ) -> PoolResult<Option<PoolOperation>> {
+        let req = ServerRequestKind::GetOpById { entry_point, id };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpById { op } => Ok(op),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
+    async fn get_op_queue_position(&self, hash: B256) -> PoolResult<Option<u64>> {
+        let req = ServerRequestKind::GetOpQueuePosition { hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpQueuePosition { position } => Ok(position),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let req = ServerRequestKind::RemoveOps { entry_point, ops };
         let resp = self.send(req).await?;
@@ -244,6 +276,22 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn admin_set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: PrecheckSettingsUpdate,
+    ) -> PoolResult<PrecheckSettingsUpdate> {
+        let req = ServerRequestKind::AdminSetPrecheckSettings {
+            entry_point,
+            settings,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::AdminSetPrecheckSettings { settings } => Ok(settings),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let req = ServerRequestKind::DebugDumpMempool { entry_point };
         let resp = self.send(req).await?;
@@ -406,6 +454,24 @@ impl LocalPoolServerRunner {
         Ok(None)
     }
 
+    fn get_op_by_id(
+        &self,
+        entry_point: Address,
+        id: &UserOperationId,
+    ) -> PoolResult<Option<PoolOperation>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.get_user_operation_by_id(id).map(|op| (*op).clone()))
+    }
+
+    fn get_op_queue_position(&self, hash: B256) -> PoolResult<Option<u64>> {
+        for mempool in self.mempools.values() {
+            if let Some(position) = mempool.get_user_operation_queue_position(hash) {
+                return Ok(Some(position as u64));
+            }
+        }
+        Ok(None)
+    }
+
     fn remove_ops(&self, entry_point: Address, ops: &[B256]) -> PoolResult<()> {
         let mempool = self.get_pool(entry_point)?;
         mempool.remove_operations(ops);
@@ -456,6 +522,15 @@ impl LocalPoolServerRunner {
         Ok(())
     }
 
+    fn admin_set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: PrecheckSettingsUpdate,
+    ) -> PoolResult<PrecheckSettingsUpdate> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.set_precheck_settings(settings)?)
+    }
+
     fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let mempool = self.get_pool(entry_point)?;
         Ok(mempool
@@ -546,6 +621,16 @@ impl LocalPoolServerRunner {
                             let _ = block_sender.send(NewHead {
                                 block_hash: chain_update.latest_block_hash,
                                 block_number: chain_update.latest_block_number,
+                                mined_ops: chain_update
+                                    .mined_ops
+                                    .iter()
+                                    .map(|op| MinedUserOperation {
+                                        hash: op.hash,
+                                        entry_point: op.entry_point,
+                                        sender: op.sender,
+                                        nonce: op.nonce,
+                                    })
+                                    .collect(),
                             });
                         }));
                     }
@@ -557,20 +642,8 @@ impl LocalPoolServerRunner {
                         ServerRequestKind::AddOp { entry_point, op, origin } => {
                             let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
                                 let resp = 'resp: {
-                                    match mempool.entry_point_version() {
-                                        EntryPointVersion::V0_6 => {
-                                            if !matches!(&op, UserOperationVariant::V0_6(_)){
-                                                 break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.6 {:?}", op.uo_type()).into());
-                                            }
-                                        }
-                                        EntryPointVersion::V0_7 => {
-                                            if !matches!(&op, UserOperationVariant::V0_7(_)){
-                                                break 'resp Err(anyhow::anyhow!("Invalid user operation version for mempool v0.7 {:?}", op.uo_type()).into());
-                                            }
-                                        }
-                                        EntryPointVersion::Unspecified => {
-                                            panic!("Found mempool with unspecified entry point version")
-                                        }
+                                    if let Err(e) = check_op_version(mempool.as_ref(), &op) {
+                                        break 'resp Err(e);
                                     }
 
                                     match mempool.add_operation(origin, op).await {
@@ -587,6 +660,27 @@ impl LocalPoolServerRunner {
                             self.get_pool_and_spawn(entry_point, req.response, fut);
                             continue;
                         },
+                        ServerRequestKind::CheckOp { entry_point, op } => {
+                            let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
+                                let resp = 'resp: {
+                                    if let Err(e) = check_op_version(mempool.as_ref(), &op) {
+                                        break 'resp Err(e);
+                                    }
+
+                                    match mempool.check_operation(op).await {
+                                        Ok(hash) => Ok(ServerResponse::CheckOp { hash }),
+                                        Err(e) => Err(e.into()),
+                                    }
+                                };
+
+                                if let Err(e) = response.send(resp) {
+                                    tracing::error!("Failed to send response: {:?}", e);
+                                }
+                            };
+
+                            self.get_pool_and_spawn(entry_point, req.response, fut);
+                            continue;
+                        },
                         ServerRequestKind::GetStakeStatus { entry_point, address }=> {
                             let fut = |mempool: Arc<dyn Mempool>, response: oneshot::Sender<Result<ServerResponse, PoolError>>| async move {
                                 let resp = match mempool.get_stake_status(address).await {
@@ -620,6 +714,18 @@ impl LocalPoolServerRunner {
                                 Err(e) => Err(e),
                             }
                         }
+                        ServerRequestKind::GetOpById { entry_point, id } => {
+                            match self.get_op_by_id(entry_point, &id) {
+                                Ok(op) => Ok(ServerResponse::GetOpById { op }),
+                                Err(e) => Err(e),
+                            }
+                        }
+                        ServerRequestKind::GetOpQueuePosition { hash } => {
+                            match self.get_op_queue_position(hash) {
+                                Ok(position) => Ok(ServerResponse::GetOpQueuePosition { position }),
+                                Err(e) => Err(e),
+                            }
+                        }
                         ServerRequestKind::RemoveOps { entry_point, ops } => {
                             match self.remove_ops(entry_point, &ops) {
                                 Ok(_) => Ok(ServerResponse::RemoveOps),
@@ -638,6 +744,12 @@ impl LocalPoolServerRunner {
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::AdminSetPrecheckSettings { entry_point, settings } => {
+                            match self.admin_set_precheck_settings(entry_point, settings) {
+                                Ok(settings) => Ok(ServerResponse::AdminSetPrecheckSettings { settings }),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::UpdateEntities { entry_point, entity_updates } => {
                             match self.update_entities(entry_point, &entity_updates) {
                                 Ok(_) => Ok(ServerResponse::UpdateEntities),
@@ -693,6 +805,33 @@ impl LocalPoolServerRunner {
     }
 }
 
+fn check_op_version(mempool: &dyn Mempool, op: &UserOperationVariant) -> PoolResult<()> {
+    match mempool.entry_point_version() {
+        EntryPointVersion::V0_6 => {
+            if !matches!(op, UserOperationVariant::V0_6(_)) {
+                return Err(anyhow::anyhow!(
+                    "Invalid user operation version for mempool v0.6 {:?}",
+                    op.uo_type()
+                )
+                .into());
+            }
+        }
+        EntryPointVersion::V0_7 => {
+            if !matches!(op, UserOperationVariant::V0_7(_)) {
+                return Err(anyhow::anyhow!(
+                    "Invalid user operation version for mempool v0.7 {:?}",
+                    op.uo_type()
+                )
+                .into());
+            }
+        }
+        EntryPointVersion::Unspecified => {
+            panic!("Found mempool with unspecified entry point version")
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct ServerRequest {
     request: ServerRequestKind,
@@ -707,6 +846,10 @@ enum ServerRequestKind {
         op: UserOperationVariant,
         origin: OperationOrigin,
     },
+    CheckOp {
+        entry_point: Address,
+        op: UserOperationVariant,
+    },
     GetOps {
         entry_point: Address,
         max_ops: u64,
@@ -715,6 +858,13 @@ enum ServerRequestKind {
     GetOpByHash {
         hash: B256,
     },
+    GetOpById {
+        entry_point: Address,
+        id: UserOperationId,
+    },
+    GetOpQueuePosition {
+        hash: B256,
+    },
     RemoveOps {
         entry_point: Address,
         ops: Vec<B256>,
@@ -737,6 +887,10 @@ enum ServerRequestKind {
         paymaster: bool,
         reputation: bool,
     },
+    AdminSetPrecheckSettings {
+        entry_point: Address,
+        settings: PrecheckSettingsUpdate,
+    },
     DebugDumpMempool {
         entry_point: Address,
     },
@@ -769,12 +923,21 @@ enum ServerResponse {
     AddOp {
         hash: B256,
     },
+    CheckOp {
+        hash: B256,
+    },
     GetOps {
         ops: Vec<PoolOperation>,
     },
     GetOpByHash {
         op: Option<PoolOperation>,
     },
+    GetOpById {
+        op: Option<PoolOperation>,
+    },
+    GetOpQueuePosition {
+        position: Option<u64>,
+    },
     RemoveOps,
     RemoveOpById {
         hash: Option<B256>,
@@ -782,6 +945,9 @@ enum ServerResponse {
     UpdateEntities,
     DebugClearState,
     AdminSetTracking,
+    AdminSetPrecheckSettings {
+        settings: PrecheckSettingsUpdate,
+    },
     DebugDumpMempool {
         ops: Vec<PoolOperation>,
     },
@@ -833,6 +999,26 @@ mod tests {
         assert_eq!(hash0, hash1);
     }
 
+    #[tokio::test]
+    async fn test_check_op() {
+        let mut mock_pool = MockMempool::new();
+        let hash0 = B256::random();
+        mock_pool
+            .expect_entry_point_version()
+            .returning(|| EntryPointVersion::V0_6);
+        mock_pool
+            .expect_check_operation()
+            .returning(move |_| Ok(hash0));
+        mock_pool.expect_add_operation().never();
+
+        let ep = Address::random();
+        let pool: Arc<dyn Mempool> = Arc::new(mock_pool);
+        let state = setup(HashMap::from([(ep, pool)]));
+
+        let hash1 = state.handle.check_op(ep, mock_op()).await.unwrap();
+        assert_eq!(hash0, hash1);
+    }
+
     #[tokio::test]
     async fn test_chain_update() {
         let mut mock_pool = MockMempool::new();
@@ -24,8 +24,8 @@ use rundler_task::{
 use rundler_types::{
     chain::ChainSpec,
     pool::{
-        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult, Reputation,
-        ReputationStatus, StakeStatus,
+        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult,
+        PrecheckSettingsUpdate, Reputation, ReputationStatus, StakeStatus,
     },
     EntityUpdate, UserOperationId, UserOperationVariant,
 };
@@ -42,16 +42,18 @@ use tonic_health::{
 };
 
 use super::protos::{
-    self, add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_paymaster_balances_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
-    op_pool_client::OpPoolClient, remove_op_by_id_response, remove_ops_response,
-    update_entities_response, AddOpRequest, AdminSetTrackingRequest, DebugClearStateRequest,
-    DebugDumpMempoolRequest, DebugDumpPaymasterBalancesRequest, DebugDumpReputationRequest,
-    DebugSetReputationRequest, GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest,
-    RemoveOpsRequest, ReputationStatus as ProtoReputationStatus, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
+    self, add_op_response, admin_set_precheck_settings_response, admin_set_tracking_response,
+    check_op_response, debug_clear_state_response, debug_dump_mempool_response,
+    debug_dump_paymaster_balances_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_op_by_hash_response, get_op_by_id_response,
+    get_op_queue_position_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response, op_pool_client::OpPoolClient, remove_op_by_id_response,
+    remove_ops_response, update_entities_response, AddOpRequest, AdminSetPrecheckSettingsRequest,
+    AdminSetTrackingRequest, CheckOpRequest, DebugClearStateRequest, DebugDumpMempoolRequest,
+    DebugDumpPaymasterBalancesRequest, DebugDumpReputationRequest, DebugSetReputationRequest,
+    GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest, RemoveOpsRequest,
+    ReputationStatus as ProtoReputationStatus, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse,
+    TryUoFromProto, UpdateEntitiesRequest,
 };
 
 /// Remote pool client
@@ -174,6 +176,28 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn check_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .check_op(CheckOpRequest {
+                entry_point: entry_point.to_vec(),
+                op: Some(protos::UserOperation::from(&op)),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(check_op_response::Result::Success(s)) => Ok(B256::from_slice(&s.hash)),
+            Some(check_op_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn get_ops(
         &self,
         entry_point: Address,
@@ -242,6 +266,67 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn get_op_by_id(
+        &self,
+        entry_point: Address,
+        id: UserOperationId,
+    ) -> PoolResult<Option<PoolOperation>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_op_by_id(protos::GetOpByIdRequest {
+                entry_point: entry_point.to_proto_bytes(),
+                sender: id.sender.to_proto_bytes(),
+                nonce: id.nonce.to_proto_bytes(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_op_by_id_response::Result::Success(s)) => Ok(s
+                .op
+                .map(|proto_uo| {
+                    PoolOperation::try_uo_from_proto(proto_uo, &self.chain_spec)
+                        .context("should convert proto uo to pool operation")
+                })
+                .transpose()?),
+            Some(get_op_by_id_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn get_op_queue_position(&self, hash: B256) -> PoolResult<Option<u64>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_op_queue_position(protos::GetOpQueuePositionRequest {
+                hash: hash.to_proto_bytes(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_op_queue_position_response::Result::Success(s)) => {
+                Ok(s.found.then_some(s.position))
+            }
+            Some(get_op_queue_position_response::Result::Failure(e)) => match e.error {
+                Some(_) => Err(e.try_into()?),
+                None => Err(PoolError::Other(anyhow::anyhow!(
+                    "should have received error from op pool"
+                )))?,
+            },
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -382,6 +467,36 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn admin_set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: PrecheckSettingsUpdate,
+    ) -> PoolResult<PrecheckSettingsUpdate> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .admin_set_precheck_settings(AdminSetPrecheckSettingsRequest {
+                entry_point: entry_point.to_vec(),
+                settings: Some(settings.into()),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(admin_set_precheck_settings_response::Result::Success(s)) => Ok(s
+                .settings
+                .context("should have received settings from op pool")?
+                .try_into()
+                .map_err(anyhow::Error::from)?),
+            Some(admin_set_precheck_settings_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let res = self
             .op_pool_client
@@ -24,23 +24,25 @@ use rundler_types::{
 use super::protos::{
     mempool_error, precheck_violation_error, simulation_violation_error, validation_revert,
     AccessedUndeployedContract, AccessedUnsupportedContractType, AggregatorValidationFailed,
-    AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender,
-    CallGasLimitEfficiencyTooLow, CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod,
-    CodeHashChanged, DidNotRevert, DiscardedOnInsertError, Entity, EntityThrottledError,
-    EntityType, EntryPointRevert, ExistingSenderWithInitCode, FactoryCalledCreate2Twice,
-    FactoryIsNotContract, InvalidAccountSignature, InvalidPaymasterSignature, InvalidSignature,
-    InvalidStorageAccess, InvalidTimeRange, MaxFeePerGasTooLow, MaxOperationsReachedError,
-    MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError, MultipleRolesViolation,
-    NotStaked, OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas,
-    PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract,
-    PreOpGasLimitEfficiencyTooLow, PreVerificationGasTooLow,
-    PrecheckViolationError as ProtoPrecheckViolationError, ReplacementUnderpricedError,
-    SenderAddressUsedAsAlternateEntity, SenderFundsTooLow, SenderIsNotContractAndNoInitCode,
-    SimulationViolationError as ProtoSimulationViolationError, TotalGasLimitTooHigh,
-    UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError, UnknownRevert,
-    UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregatorError, UsedForbiddenOpcode,
-    UsedForbiddenPrecompile, ValidationRevert as ProtoValidationRevert,
-    VerificationGasLimitBufferTooLow, VerificationGasLimitTooHigh, WrongNumberOfPhases,
+    AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender, CallDataTooLarge,
+    CallGasLimitEfficiencyTooLow, CallGasLimitTooLow, CallHadValue, CallTargetNotAllowed,
+    CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert, DiscardedOnInsertError, Entity,
+    EntityThrottledError, EntityType, EntryPointRevert, ExistingSenderWithInitCode,
+    FactoryCalledCreate2Twice, FactoryIsNotContract, InvalidAccountSignature,
+    InvalidPaymasterSignature, InvalidSignature, InvalidStorageAccess, InvalidTimeRange,
+    MaxFeePerGasTooHigh, MaxFeePerGasTooLow, MaxOperationsReachedError, MaxPriorityFeePerGasTooLow,
+    MempoolError as ProtoMempoolError, MultipleRolesViolation, NotStaked,
+    OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas,
+    PackedEncodingMismatch, PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow,
+    PaymasterIsNotContract, PaymasterVerificationGasLimitTooHigh, PreOpGasLimitEfficiencyTooLow,
+    PreVerificationGasTooLow, PrecheckViolationError as ProtoPrecheckViolationError,
+    ReplacementUnderpricedError, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
+    SenderIsNotContractAndNoInitCode, SimulationViolationError as ProtoSimulationViolationError,
+    TotalGasLimitTooHigh, TotalOpGasTooHigh, UnintendedRevert, UnintendedRevertWithMessage,
+    UnknownEntryPointError, UnknownRevert, UnstakedAggregator, UnstakedPaymasterContext,
+    UnsupportedAggregatorError, UsedForbiddenOpcode, UsedForbiddenPrecompile,
+    ValidationRevert as ProtoValidationRevert, VerificationGasLimitBufferTooLow,
+    VerificationGasLimitTooHigh, WrongNumberOfPhases,
 };
 
 impl TryFrom<ProtoMempoolError> for PoolError {
@@ -133,6 +135,12 @@ impl TryFrom<ProtoMempoolError> for MempoolError {
             Some(mempool_error::Error::CallGasLimitEfficiencyTooLow(e)) => {
                 MempoolError::CallGasLimitEfficiencyTooLow(e.required, e.actual)
             }
+            Some(mempool_error::Error::PackedEncodingMismatch(e)) => {
+                MempoolError::PackedEncodingMismatch(e.reason)
+            }
+            Some(mempool_error::Error::CallTargetNotAllowed(e)) => {
+                MempoolError::CallTargetNotAllowed(from_bytes(&e.target)?)
+            }
             None => bail!("unknown proto mempool error"),
         })
     }
@@ -247,6 +255,18 @@ impl From<MempoolError> for ProtoMempoolError {
                     CallGasLimitEfficiencyTooLow { required, actual },
                 )),
             },
+            MempoolError::PackedEncodingMismatch(reason) => ProtoMempoolError {
+                error: Some(mempool_error::Error::PackedEncodingMismatch(
+                    PackedEncodingMismatch { reason },
+                )),
+            },
+            MempoolError::CallTargetNotAllowed(target) => ProtoMempoolError {
+                error: Some(mempool_error::Error::CallTargetNotAllowed(
+                    CallTargetNotAllowed {
+                        target: target.to_proto_bytes(),
+                    },
+                )),
+            },
         }
     }
 }
@@ -289,6 +309,14 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::TotalOpGasTooHigh(actual, max) => ProtoPrecheckViolationError {
+                violation: Some(precheck_violation_error::Violation::TotalOpGasTooHigh(
+                    TotalOpGasTooHigh {
+                        actual_gas: actual.to_proto_bytes(),
+                        max_gas: max.to_proto_bytes(),
+                    },
+                )),
+            },
             PrecheckViolation::VerificationGasLimitTooHigh(actual, max) => {
                 ProtoPrecheckViolationError {
                     violation: Some(
@@ -301,6 +329,18 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     ),
                 }
             }
+            PrecheckViolation::PaymasterVerificationGasLimitTooHigh(actual, max) => {
+                ProtoPrecheckViolationError {
+                    violation: Some(
+                        precheck_violation_error::Violation::PaymasterVerificationGasLimitTooHigh(
+                            PaymasterVerificationGasLimitTooHigh {
+                                actual_gas: actual.to_proto_bytes(),
+                                max_gas: max.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
             PrecheckViolation::PreVerificationGasTooLow(actual, min) => {
                 ProtoPrecheckViolationError {
                     violation: Some(
@@ -344,6 +384,14 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::MaxFeePerGasTooHigh(actual, max) => ProtoPrecheckViolationError {
+                violation: Some(precheck_violation_error::Violation::MaxFeePerGasTooHigh(
+                    MaxFeePerGasTooHigh {
+                        actual_fee: actual.to_proto_bytes(),
+                        max_fee: max.to_proto_bytes(),
+                    },
+                )),
+            },
             PrecheckViolation::MaxPriorityFeePerGasTooLow(actual, min) => {
                 ProtoPrecheckViolationError {
                     violation: Some(
@@ -364,6 +412,14 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::CallDataTooLarge(actual, max) => ProtoPrecheckViolationError {
+                violation: Some(precheck_violation_error::Violation::CallDataTooLarge(
+                    CallDataTooLarge {
+                        actual_len: actual as u64,
+                        max_len: max as u64,
+                    },
+                )),
+            },
         }
     }
 }
@@ -388,12 +444,24 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
                     from_bytes(&e.max_gas)?,
                 )
             }
+            Some(precheck_violation_error::Violation::TotalOpGasTooHigh(e)) => {
+                PrecheckViolation::TotalOpGasTooHigh(
+                    from_bytes(&e.actual_gas)?,
+                    from_bytes(&e.max_gas)?,
+                )
+            }
             Some(precheck_violation_error::Violation::VerificationGasLimitTooHigh(e)) => {
                 PrecheckViolation::VerificationGasLimitTooHigh(
                     from_bytes(&e.actual_gas)?,
                     from_bytes(&e.max_gas)?,
                 )
             }
+            Some(precheck_violation_error::Violation::PaymasterVerificationGasLimitTooHigh(e)) => {
+                PrecheckViolation::PaymasterVerificationGasLimitTooHigh(
+                    from_bytes(&e.actual_gas)?,
+                    from_bytes(&e.max_gas)?,
+                )
+            }
             Some(precheck_violation_error::Violation::PreVerificationGasTooLow(e)) => {
                 PrecheckViolation::PreVerificationGasTooLow(
                     from_bytes(&e.actual_gas)?,
@@ -421,6 +489,12 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
                     from_bytes(&e.min_fee)?,
                 )
             }
+            Some(precheck_violation_error::Violation::MaxFeePerGasTooHigh(e)) => {
+                PrecheckViolation::MaxFeePerGasTooHigh(
+                    from_bytes(&e.actual_fee)?,
+                    from_bytes(&e.max_fee)?,
+                )
+            }
             Some(precheck_violation_error::Violation::MaxPriorityFeePerGasTooLow(e)) => {
                 PrecheckViolation::MaxPriorityFeePerGasTooLow(
                     from_bytes(&e.actual_fee)?,
@@ -433,6 +507,9 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
                     from_bytes(&e.min_gas_limit)?,
                 )
             }
+            Some(precheck_violation_error::Violation::CallDataTooLarge(e)) => {
+                PrecheckViolation::CallDataTooLarge(e.actual_len as usize, e.max_len as usize)
+            }
             None => {
                 bail!("unknown proto mempool precheck violation")
             }
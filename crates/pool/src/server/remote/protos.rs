@@ -17,11 +17,12 @@ use rundler_task::grpc::protos::{from_bytes, ConversionError, ToProtoBytes};
 use rundler_types::{
     chain::ChainSpec,
     da::{
-        BedrockDAGasUOData as RundlerBedrockDAGasUOData, DAGasUOData as RundlerDAGasUOData,
-        NitroDAGasUOData as RundlerNitroDAGasUOData,
+        BedrockDAGasUOData as RundlerBedrockDAGasUOData, BlobDAGasUOData as RundlerBlobDAGasUOData,
+        DAGasUOData as RundlerDAGasUOData, NitroDAGasUOData as RundlerNitroDAGasUOData,
     },
     pool::{
-        NewHead as PoolNewHead, PaymasterMetadata as PoolPaymasterMetadata, PoolOperation,
+        MinedUserOperation as PoolMinedUserOperation, NewHead as PoolNewHead,
+        PaymasterMetadata as PoolPaymasterMetadata, PoolOperation, PrecheckSettingsUpdate,
         Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
         StakeStatus as RundlerStakeStatus,
     },
@@ -370,6 +371,8 @@ impl From<&PoolOperation> for MempoolOp {
             sim_block_hash: op.sim_block_hash.to_proto_bytes(),
             account_is_staked: op.account_is_staked,
             da_gas_data: Some(DaGasUoData::from(&op.da_gas_data)),
+            validation_gas_used: op.validation_gas_used.to_proto_bytes(),
+            validation_block_number: op.validation_block_number,
         }
     }
 }
@@ -390,6 +393,11 @@ impl From<&RundlerDAGasUOData> for DaGasUoData {
                     uo_units: data.uo_units,
                 })),
             },
+            RundlerDAGasUOData::Blob(data) => DaGasUoData {
+                data: Some(da_gas_uo_data::Data::Blob(BlobDaGasUoData {
+                    blob_gas: data.blob_gas,
+                })),
+            },
         }
     }
 }
@@ -408,6 +416,9 @@ impl TryFrom<DaGasUoData> for RundlerDAGasUOData {
             Some(da_gas_uo_data::Data::Bedrock(BedrockDaGasUoData { uo_units })) => {
                 RundlerDAGasUOData::Bedrock(RundlerBedrockDAGasUOData { uo_units })
             }
+            Some(da_gas_uo_data::Data::Blob(BlobDaGasUoData { blob_gas })) => {
+                RundlerDAGasUOData::Blob(RundlerBlobDAGasUOData { blob_gas })
+            }
             None => RundlerDAGasUOData::Empty,
         };
 
@@ -444,6 +455,8 @@ impl TryUoFromProto<MempoolOp> for PoolOperation {
             expected_code_hash,
             sim_block_hash,
             sim_block_number: 0,
+            validation_gas_used: from_bytes(&op.validation_gas_used)?,
+            validation_block_number: op.validation_block_number,
             account_is_staked: op.account_is_staked,
             entity_infos: EntityInfos::default(),
             da_gas_data: op
@@ -461,6 +474,11 @@ impl TryFrom<NewHead> for PoolNewHead {
         Ok(Self {
             block_hash: from_bytes(&new_head.block_hash)?,
             block_number: new_head.block_number,
+            mined_ops: new_head
+                .mined_ops
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -470,6 +488,31 @@ impl From<PoolNewHead> for NewHead {
         Self {
             block_hash: head.block_hash.to_proto_bytes(),
             block_number: head.block_number,
+            mined_ops: head.mined_ops.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<MinedUserOperation> for PoolMinedUserOperation {
+    type Error = ConversionError;
+
+    fn try_from(op: MinedUserOperation) -> Result<Self, Self::Error> {
+        Ok(Self {
+            hash: from_bytes(&op.hash)?,
+            entry_point: from_bytes(&op.entry_point)?,
+            sender: from_bytes(&op.sender)?,
+            nonce: from_bytes(&op.nonce)?,
+        })
+    }
+}
+
+impl From<PoolMinedUserOperation> for MinedUserOperation {
+    fn from(op: PoolMinedUserOperation) -> Self {
+        Self {
+            hash: op.hash.to_proto_bytes(),
+            entry_point: op.entry_point.to_proto_bytes(),
+            sender: op.sender.to_proto_bytes(),
+            nonce: op.nonce.to_proto_bytes(),
         }
     }
 }
@@ -495,3 +538,33 @@ impl From<PoolPaymasterMetadata> for PaymasterBalance {
         }
     }
 }
+
+impl From<PrecheckSettingsUpdate> for PrecheckSettings {
+    fn from(settings: PrecheckSettingsUpdate) -> Self {
+        Self {
+            max_verification_gas: settings.max_verification_gas.map(|g| g.to_proto_bytes()),
+            max_total_execution_gas: settings.max_total_execution_gas.map(|g| g.to_proto_bytes()),
+            base_fee_accept_percent: settings.base_fee_accept_percent,
+            pre_verification_gas_accept_percent: settings.pre_verification_gas_accept_percent,
+        }
+    }
+}
+
+impl TryFrom<PrecheckSettings> for PrecheckSettingsUpdate {
+    type Error = ConversionError;
+
+    fn try_from(settings: PrecheckSettings) -> Result<Self, Self::Error> {
+        Ok(Self {
+            max_verification_gas: settings
+                .max_verification_gas
+                .map(|g| from_bytes(&g))
+                .transpose()?,
+            max_total_execution_gas: settings
+                .max_total_execution_gas
+                .map(|g| from_bytes(&g))
+                .transpose()?,
+            base_fee_accept_percent: settings.base_fee_accept_percent,
+            pre_verification_gas_accept_percent: settings.pre_verification_gas_accept_percent,
+        })
+    }
+}
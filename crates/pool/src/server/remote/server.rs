@@ -28,7 +28,7 @@ use rundler_task::{
 };
 use rundler_types::{
     chain::ChainSpec,
-    pool::{Pool, Reputation},
+    pool::{Pool, PrecheckSettingsUpdate, Reputation},
     EntityUpdate, UserOperationId, UserOperationVariant,
 };
 use tokio::sync::mpsc;
@@ -36,26 +36,32 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use tonic::{transport::Server, Request, Response, Result, Status};
 
 use super::protos::{
-    add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_paymaster_balances_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
+    add_op_response, admin_set_precheck_settings_response, admin_set_tracking_response,
+    check_op_response, debug_clear_state_response, debug_dump_mempool_response,
+    debug_dump_paymaster_balances_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_op_by_hash_response, get_op_by_id_response,
+    get_op_queue_position_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response,
     op_pool_server::{OpPool, OpPoolServer},
     remove_op_by_id_response, remove_ops_response, update_entities_response, AddOpRequest,
-    AddOpResponse, AddOpSuccess, AdminSetTrackingRequest, AdminSetTrackingResponse,
-    AdminSetTrackingSuccess, DebugClearStateRequest, DebugClearStateResponse,
-    DebugClearStateSuccess, DebugDumpMempoolRequest, DebugDumpMempoolResponse,
-    DebugDumpMempoolSuccess, DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
+    AddOpResponse, AddOpSuccess, AdminSetPrecheckSettingsRequest, AdminSetPrecheckSettingsResponse,
+    AdminSetPrecheckSettingsSuccess, AdminSetTrackingRequest, AdminSetTrackingResponse,
+    AdminSetTrackingSuccess, CheckOpRequest, CheckOpResponse, CheckOpSuccess,
+    DebugClearStateRequest, DebugClearStateResponse, DebugClearStateSuccess,
+    DebugDumpMempoolRequest, DebugDumpMempoolResponse, DebugDumpMempoolSuccess,
+    DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
     DebugDumpPaymasterBalancesSuccess, DebugDumpReputationRequest, DebugDumpReputationResponse,
     DebugDumpReputationSuccess, DebugSetReputationRequest, DebugSetReputationResponse,
     DebugSetReputationSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
-    GetOpsRequest, GetOpsResponse, GetOpsSuccess, GetReputationStatusRequest,
-    GetReputationStatusResponse, GetReputationStatusSuccess, GetStakeStatusRequest,
-    GetStakeStatusResponse, GetStakeStatusSuccess, GetSupportedEntryPointsRequest,
-    GetSupportedEntryPointsResponse, MempoolOp, RemoveOpByIdRequest, RemoveOpByIdResponse,
-    RemoveOpByIdSuccess, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess, ReputationStatus,
-    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
-    UpdateEntitiesResponse, UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
+    GetOpByIdRequest, GetOpByIdResponse, GetOpByIdSuccess, GetOpQueuePositionRequest,
+    GetOpQueuePositionResponse, GetOpQueuePositionSuccess, GetOpsRequest, GetOpsResponse,
+    GetOpsSuccess, GetReputationStatusRequest, GetReputationStatusResponse,
+    GetReputationStatusSuccess, GetStakeStatusRequest, GetStakeStatusResponse,
+    GetStakeStatusSuccess, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
+    MempoolOp, RemoveOpByIdRequest, RemoveOpByIdResponse, RemoveOpByIdSuccess, RemoveOpsRequest,
+    RemoveOpsResponse, RemoveOpsSuccess, ReputationStatus, SubscribeNewHeadsRequest,
+    SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest, UpdateEntitiesResponse,
+    UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
 };
 use crate::server::local::LocalPoolHandle;
 
@@ -174,6 +180,35 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn check_op(
+        &self,
+        request: Request<CheckOpRequest>,
+    ) -> Result<Response<CheckOpResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let proto_op = req
+            .op
+            .ok_or_else(|| Status::invalid_argument("Operation is required in CheckOpRequest"))?;
+        let uo =
+            UserOperationVariant::try_uo_from_proto(proto_op, &self.chain_spec).map_err(|e| {
+                Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
+            })?;
+
+        let resp = match self.local_pool.check_op(ep, uo).await {
+            Ok(hash) => CheckOpResponse {
+                result: Some(check_op_response::Result::Success(CheckOpSuccess {
+                    hash: hash.to_vec(),
+                })),
+            },
+            Err(error) => CheckOpResponse {
+                result: Some(check_op_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn get_ops(&self, request: Request<GetOpsRequest>) -> Result<Response<GetOpsResponse>> {
         let req = request.into_inner();
         let ep = self.get_entry_point(&req.entry_point)?;
@@ -222,6 +257,68 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_op_by_id(
+        &self,
+        request: Request<GetOpByIdRequest>,
+    ) -> Result<Response<GetOpByIdResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let resp = match self
+            .local_pool
+            .get_op_by_id(
+                ep,
+                UserOperationId {
+                    sender: from_bytes(&req.sender)
+                        .map_err(|e| Status::invalid_argument(format!("Invalid sender: {e}")))?,
+                    nonce: from_bytes(&req.nonce)
+                        .map_err(|e| Status::invalid_argument(format!("Invalid nonce: {e}")))?,
+                },
+            )
+            .await
+        {
+            Ok(op) => GetOpByIdResponse {
+                result: Some(get_op_by_id_response::Result::Success(GetOpByIdSuccess {
+                    op: op.map(|op| MempoolOp::from(&op)),
+                })),
+            },
+            Err(error) => GetOpByIdResponse {
+                result: Some(get_op_by_id_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn get_op_queue_position(
+        &self,
+        request: Request<GetOpQueuePositionRequest>,
+    ) -> Result<Response<GetOpQueuePositionResponse>> {
+        let req = request.into_inner();
+
+        let hash = from_bytes(&req.hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in GetOpQueuePositionRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.get_op_queue_position(hash).await {
+            Ok(position) => GetOpQueuePositionResponse {
+                result: Some(get_op_queue_position_response::Result::Success(
+                    GetOpQueuePositionSuccess {
+                        found: position.is_some(),
+                        position: position.unwrap_or_default(),
+                    },
+                )),
+            },
+            Err(error) => GetOpQueuePositionResponse {
+                result: Some(get_op_queue_position_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn remove_ops(
         &self,
         request: Request<RemoveOpsRequest>,
@@ -363,6 +460,42 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn admin_set_precheck_settings(
+        &self,
+        request: Request<AdminSetPrecheckSettingsRequest>,
+    ) -> Result<Response<AdminSetPrecheckSettingsResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let settings: PrecheckSettingsUpdate = req
+            .settings
+            .ok_or_else(|| {
+                Status::invalid_argument("Settings are required in AdminSetPrecheckSettingsRequest")
+            })?
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("Invalid precheck settings: {e}")))?;
+
+        let resp = match self
+            .local_pool
+            .admin_set_precheck_settings(ep, settings)
+            .await
+        {
+            Ok(settings) => AdminSetPrecheckSettingsResponse {
+                result: Some(admin_set_precheck_settings_response::Result::Success(
+                    AdminSetPrecheckSettingsSuccess {
+                        settings: Some(settings.into()),
+                    },
+                )),
+            },
+            Err(error) => AdminSetPrecheckSettingsResponse {
+                result: Some(admin_set_precheck_settings_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_dump_mempool(
         &self,
         request: Request<DebugDumpMempoolRequest>,
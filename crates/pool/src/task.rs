@@ -293,6 +293,7 @@ where
             pool_config
                 .precheck_settings
                 .bundle_priority_fee_overhead_percent,
+            pool_config.precheck_settings.priority_fee_percentile_blocks,
         );
 
         let prechecker = PrecheckerImpl::new(
@@ -326,15 +327,24 @@ where
             ),
         );
 
-        let uo_pool = UoPool::new(
+        let uo_pool = Arc::new(UoPool::new(
             pool_config.clone(),
             ep_providers,
             UoPoolProviders::new(simulator, prechecker),
             event_sender,
             paymaster,
             reputation,
-        );
+        ));
+
+        // Start re-validation runner
+        if pool_config.revalidation_enabled {
+            let revalidation_runner = Arc::clone(&uo_pool);
+            task_spawner.spawn_critical_with_graceful_shutdown_signal(
+                "uo pool revalidation",
+                |shutdown| async move { revalidation_runner.run_revalidation(shutdown).await },
+            );
+        }
 
-        Ok(Arc::new(uo_pool))
+        Ok(uo_pool)
     }
 }
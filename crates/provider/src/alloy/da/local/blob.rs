@@ -0,0 +1,212 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_sol_types::sol;
+use alloy_transport::Transport;
+use anyhow::Context;
+use rundler_types::da::{BlobDAGasBlockData, BlobDAGasUOData, DAGasBlockData, DAGasUOData};
+use rundler_utils::cache::LruMap;
+use tokio::sync::Mutex as TokioMutex;
+use BlobBaseFeeOracle::BlobBaseFeeOracleInstance;
+
+use crate::{BlockHashOrNumber, DAGasOracle, DAGasOracleSync, ProviderResult};
+
+sol! {
+    #[sol(rpc)]
+    interface BlobBaseFeeOracle {
+        function blobBaseFee() public view returns (uint256);
+    }
+}
+
+// Each blob is 4096 field elements of 32 bytes, of which only 31 bytes can carry data.
+// https://eips.ethereum.org/EIPS/eip-4844#parameters
+const BLOB_BYTES_PER_FIELD_ELEMENT: u64 = 32;
+const BLOB_USABLE_BYTES_PER_FIELD_ELEMENT: u64 = 31;
+
+/// Local blob DA gas oracle
+///
+/// Prices data as the L1 blob gas needed to post it, one blob gas per blob byte, at the L1
+/// blob base fee reported by the oracle contract. Unlike calldata pricing, the cost only
+/// depends on the length of the data and not on its contents.
+#[derive(Debug)]
+pub(crate) struct LocalBlobDAGasOracle<AP, T> {
+    oracle: BlobBaseFeeOracleInstance<T, AP>,
+    block_data_cache: TokioMutex<LruMap<BlockHashOrNumber, BlobDAGasBlockData>>,
+}
+
+impl<AP, T> LocalBlobDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(oracle_address: Address, provider: AP) -> Self {
+        Self {
+            oracle: BlobBaseFeeOracleInstance::new(oracle_address, provider),
+            block_data_cache: TokioMutex::new(LruMap::new(100)),
+        }
+    }
+
+    async fn get_block_data(&self, block: BlockHashOrNumber) -> ProviderResult<BlobDAGasBlockData> {
+        let blob_base_fee = self
+            .oracle
+            .blobBaseFee()
+            .block(block.into())
+            .call()
+            .await?
+            ._0
+            .try_into()
+            .context("blob_base_fee too large for u128")?;
+
+        Ok(BlobDAGasBlockData { blob_base_fee })
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for LocalBlobDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let block_data = self.block_data(block).await?;
+        let uo_data = self.uo_data(data, to, block).await?;
+        let da_gas = self.calc_da_gas_sync(&uo_data, &block_data, gas_price);
+        Ok((da_gas, uo_data, block_data))
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracleSync for LocalBlobDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn block_data(&self, block: BlockHashOrNumber) -> ProviderResult<DAGasBlockData> {
+        let mut cache = self.block_data_cache.lock().await;
+        match cache.get(&block) {
+            Some(block_data) => Ok(DAGasBlockData::Blob(block_data.clone())),
+            None => {
+                let block_data = self.get_block_data(block).await?;
+                cache.insert(block, block_data.clone());
+                Ok(DAGasBlockData::Blob(block_data))
+            }
+        }
+    }
+
+    async fn uo_data(
+        &self,
+        uo_data: Bytes,
+        _to: Address,
+        _block: BlockHashOrNumber,
+    ) -> ProviderResult<DAGasUOData> {
+        Ok(DAGasUOData::Blob(BlobDAGasUOData {
+            blob_gas: blob_gas_for_data(uo_data.len()),
+        }))
+    }
+
+    fn calc_da_gas_sync(
+        &self,
+        uo_data: &DAGasUOData,
+        block_data: &DAGasBlockData,
+        gas_price: u128,
+    ) -> u128 {
+        let block_da_data = match block_data {
+            DAGasBlockData::Blob(block_da_data) => block_da_data,
+            _ => panic!("LocalBlobDAGasOracle only supports Blob block data"),
+        };
+        let uo_data = match uo_data {
+            DAGasUOData::Blob(uo_data) => uo_data,
+            _ => panic!("LocalBlobDAGasOracle only supports Blob user operation data"),
+        };
+
+        calc_blob_da_gas(uo_data.blob_gas, block_da_data.blob_base_fee, gas_price)
+    }
+}
+
+/// The blob gas needed to post `data_len` bytes of data in blobs
+fn blob_gas_for_data(data_len: usize) -> u64 {
+    (data_len as u64 * BLOB_BYTES_PER_FIELD_ELEMENT).div_ceil(BLOB_USABLE_BYTES_PER_FIELD_ELEMENT)
+}
+
+/// The L2 gas needed to pay for `blob_gas` at the given L1 blob base fee and L2 gas price
+fn calc_blob_da_gas(blob_gas: u64, blob_base_fee: u128, gas_price: u128) -> u128 {
+    let l1_fee = blob_gas as u128 * blob_base_fee;
+    l1_fee.checked_div(gas_price).unwrap_or(u128::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_provider::{ProviderBuilder, RootProvider};
+    use alloy_transport::BoxTransport;
+
+    use super::*;
+
+    fn oracle() -> LocalBlobDAGasOracle<RootProvider<BoxTransport>, BoxTransport> {
+        let provider = ProviderBuilder::new()
+            .on_http("http://localhost:8545".parse().unwrap())
+            .boxed();
+        LocalBlobDAGasOracle::new(Address::ZERO, provider)
+    }
+
+    #[test]
+    fn test_blob_gas_for_data() {
+        assert_eq!(blob_gas_for_data(0), 0);
+        assert_eq!(blob_gas_for_data(1), 2);
+        assert_eq!(blob_gas_for_data(31), 32);
+        assert_eq!(blob_gas_for_data(310), 320);
+    }
+
+    #[tokio::test]
+    async fn test_da_gas_priced_by_blob_gas() {
+        let oracle = oracle();
+        let block_data = DAGasBlockData::Blob(BlobDAGasBlockData {
+            blob_base_fee: 1_000,
+        });
+        let gas_price = 10;
+
+        let zeros = Bytes::from(vec![0_u8; 310]);
+        let non_zeros = Bytes::from(vec![0xff_u8; 310]);
+        let zeros_data = oracle
+            .uo_data(zeros, Address::ZERO, 0.into())
+            .await
+            .unwrap();
+        let non_zeros_data = oracle
+            .uo_data(non_zeros, Address::ZERO, 0.into())
+            .await
+            .unwrap();
+
+        // calldata pricing would charge 4 gas per zero byte and 16 per non-zero byte,
+        // blob pricing only depends on the length of the data
+        assert_eq!(
+            zeros_data,
+            DAGasUOData::Blob(BlobDAGasUOData { blob_gas: 320 })
+        );
+        assert_eq!(zeros_data, non_zeros_data);
+
+        let da_gas = oracle.calc_da_gas_sync(&zeros_data, &block_data, gas_price);
+        assert_eq!(da_gas, 320 * 1_000 / 10);
+    }
+
+    #[test]
+    fn test_calc_blob_da_gas_zero_gas_price() {
+        assert_eq!(calc_blob_da_gas(320, 1_000, 0), u128::MAX);
+    }
+}
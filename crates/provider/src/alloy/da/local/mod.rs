@@ -14,6 +14,9 @@
 mod bedrock;
 pub(crate) use bedrock::LocalBedrockDAGasOracle;
 
+mod blob;
+pub(crate) use blob::LocalBlobDAGasOracle;
+
 mod nitro;
 pub(crate) use nitro::CachedNitroDAGasOracle;
 
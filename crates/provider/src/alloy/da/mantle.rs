@@ -0,0 +1,106 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_provider::Provider as AlloyProvider;
+use alloy_sol_types::sol;
+use alloy_transport::Transport;
+use anyhow::Context;
+use futures_util::future::try_join;
+use rundler_types::da::{DAGasBlockData, DAGasUOData};
+use MantleGasPriceOracle::MantleGasPriceOracleInstance;
+
+use super::DAGasOracle;
+use crate::{BlockHashOrNumber, ProviderResult};
+
+// Mantle's GasPriceOracle predeploy. `getL1Fee` returns the L1 fee in ETH, which is converted
+// to MNT, the L2 gas token, using `tokenRatio`.
+sol! {
+    #[sol(rpc)]
+    interface MantleGasPriceOracle {
+        function tokenRatio() public view returns (uint256);
+
+        function getL1Fee(bytes memory _data) external view returns (uint256);
+    }
+}
+
+pub(super) struct MantleDAGasOracle<AP, T> {
+    oracle: MantleGasPriceOracleInstance<T, AP>,
+}
+
+impl<AP, T> MantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    pub(crate) fn new(oracle_address: Address, provider: AP) -> Self {
+        let oracle = MantleGasPriceOracleInstance::new(oracle_address, provider);
+        Self { oracle }
+    }
+}
+
+#[async_trait::async_trait]
+impl<AP, T> DAGasOracle for MantleDAGasOracle<AP, T>
+where
+    AP: AlloyProvider<T>,
+    T: Transport + Clone,
+{
+    async fn estimate_da_gas(
+        &self,
+        data: Bytes,
+        _to: Address,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        if gas_price == 0 {
+            Err(anyhow::anyhow!("gas price cannot be zero"))?;
+        }
+
+        let l1_fee_call = self.oracle.getL1Fee(data).block(block.into());
+        let token_ratio_call = self.oracle.tokenRatio().block(block.into());
+        let (l1_fee, token_ratio) = try_join(l1_fee_call.call(), token_ratio_call.call()).await?;
+
+        let da_gas = calc_mantle_da_gas(l1_fee._0, token_ratio._0, gas_price)
+            .context("failed to convert DA fee to u128")?;
+
+        Ok((da_gas, DAGasUOData::Empty, DAGasBlockData::Empty))
+    }
+}
+
+/// The L2 gas needed to pay an L1 fee of `l1_fee` ETH, converted to the L2 gas token at
+/// `token_ratio`, at an L2 gas price of `gas_price`
+fn calc_mantle_da_gas(l1_fee: U256, token_ratio: U256, gas_price: u128) -> Option<u128> {
+    let l1_fee_in_token: u128 = l1_fee.saturating_mul(token_ratio).try_into().ok()?;
+    Some(l1_fee_in_token.checked_div(gas_price).unwrap_or(u128::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_mantle_da_gas() {
+        // 1000 wei of ETH is 4000 wei of MNT, which buys 400 gas at a gas price of 10
+        assert_eq!(
+            calc_mantle_da_gas(U256::from(1_000), U256::from(4), 10),
+            Some(400)
+        );
+        // a zero token ratio means no L1 fee
+        assert_eq!(
+            calc_mantle_da_gas(U256::from(1_000), U256::ZERO, 10),
+            Some(0)
+        );
+        // fees too large for u128 are an error
+        assert_eq!(calc_mantle_da_gas(U256::MAX, U256::from(2), 10), None);
+    }
+}
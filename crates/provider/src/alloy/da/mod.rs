@@ -25,10 +25,12 @@ use crate::{BlockHashOrNumber, DAGasOracle, DAGasOracleSync, ProviderResult};
 
 mod arbitrum;
 use arbitrum::ArbitrumNitroDAGasOracle;
+mod mantle;
+use mantle::MantleDAGasOracle;
 mod optimism;
 use optimism::OptimismBedrockDAGasOracle;
 mod local;
-use local::{CachedNitroDAGasOracle, LocalBedrockDAGasOracle};
+use local::{CachedNitroDAGasOracle, LocalBedrockDAGasOracle, LocalBlobDAGasOracle};
 
 struct ZeroDAGasOracle;
 
@@ -72,6 +74,13 @@ where
             ));
             (oracle, None)
         }
+        DAGasOracleType::MantleBedrock => {
+            let oracle = Arc::new(MantleDAGasOracle::new(
+                chain_spec.da_gas_oracle_contract_address,
+                provider,
+            ));
+            (oracle, None)
+        }
         DAGasOracleType::LocalBedrock => {
             let oracle = Arc::new(LocalBedrockDAGasOracle::new(
                 chain_spec.da_gas_oracle_contract_address,
@@ -86,6 +95,13 @@ where
             ));
             (oracle.clone(), Some(oracle))
         }
+        DAGasOracleType::LocalBlob => {
+            let oracle = Arc::new(LocalBlobDAGasOracle::new(
+                chain_spec.da_gas_oracle_contract_address,
+                provider,
+            ));
+            (oracle.clone(), Some(oracle))
+        }
         DAGasOracleType::None => (Arc::new(ZeroDAGasOracle), None),
     }
 }
@@ -102,6 +118,40 @@ mod tests {
     const OPT_ORACLE_ADDRESS: Address = address!("420000000000000000000000000000000000000F");
     const ARB_ORACLE_ADDRESS: Address = address!("00000000000000000000000000000000000000C8");
 
+    #[tokio::test]
+    async fn test_oracle_dispatch() {
+        let provider = ProviderBuilder::new()
+            .on_http("http://localhost:8545".parse().unwrap())
+            .boxed();
+        let oracle_for = |da_gas_oracle_type| {
+            let chain_spec = ChainSpec {
+                da_gas_oracle_type,
+                ..Default::default()
+            };
+            new_alloy_da_gas_oracle(&chain_spec, provider.clone())
+        };
+
+        // contract based oracles have no synchronous calculation
+        assert!(oracle_for(DAGasOracleType::ArbitrumNitro).1.is_none());
+        assert!(oracle_for(DAGasOracleType::OptimismBedrock).1.is_none());
+        assert!(oracle_for(DAGasOracleType::MantleBedrock).1.is_none());
+        // local oracles support synchronous calculation from cached data
+        assert!(oracle_for(DAGasOracleType::LocalBedrock).1.is_some());
+        assert!(oracle_for(DAGasOracleType::CachedNitro).1.is_some());
+        assert!(oracle_for(DAGasOracleType::LocalBlob).1.is_some());
+
+        // chains without DA costs never touch the provider
+        let (oracle, sync_oracle) = oracle_for(DAGasOracleType::None);
+        assert!(sync_oracle.is_none());
+        let (gas, uo_data, block_data) = oracle
+            .estimate_da_gas(test_uo_data_1(), Address::random(), 0.into(), 1)
+            .await
+            .unwrap();
+        assert_eq!(gas, 0);
+        assert_eq!(uo_data, DAGasUOData::Empty);
+        assert_eq!(block_data, DAGasBlockData::Empty);
+    }
+
     // Run these tests locally with `ALCHEMY_API_KEY=<key> cargo test -- --ignored`
 
     // This test may begin to fail if an optimism sepolia fork changes how the L1 gas oracle works.
@@ -12,9 +12,15 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_consensus::{transaction::SignableTransaction, TxEnvelope, TypedTransaction};
+use alloy_contract::Error as ContractError;
 use alloy_primitives::{address, Address, Bytes, Parity, Signature, U256};
+use alloy_provider::Provider as AlloyProvider;
 use alloy_rlp::Encodable;
 use alloy_rpc_types_eth::TransactionRequest;
+use alloy_transport::{Transport, TransportError};
+use rundler_contracts::utils::IAggregatorSignatureLength;
+
+use crate::ProviderResult;
 
 pub(crate) mod v0_6;
 pub(crate) mod v0_7;
@@ -60,3 +66,26 @@ fn max_bundle_transaction_data(to_address: Address, data: Bytes, gas_price: u128
 
     encoded.into()
 }
+
+async fn aggregated_signature_length<AP, T>(
+    provider: &AP,
+    aggregator_address: Address,
+) -> ProviderResult<Option<usize>>
+where
+    T: Transport + Clone,
+    AP: AlloyProvider<T>,
+{
+    let aggregator = IAggregatorSignatureLength::new(aggregator_address, provider);
+
+    match aggregator.aggregatedSignatureLength().call().await {
+        Ok(ret) => Ok(usize::try_from(ret.length).ok()),
+        // Aggregators that don't implement the extension either revert or return no data
+        Err(ContractError::TransportError(TransportError::ErrorResp(resp)))
+            if resp.as_revert_data().is_some() =>
+        {
+            Ok(None)
+        }
+        Err(ContractError::ZeroData(..) | ContractError::AbiError(_)) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -215,6 +215,13 @@ where
             Err(error) => Err(error.into()),
         }
     }
+
+    async fn aggregated_signature_length(
+        &self,
+        aggregator_address: Address,
+    ) -> ProviderResult<Option<usize>> {
+        super::aggregated_signature_length(self.i_entry_point.provider(), aggregator_address).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -323,6 +330,14 @@ where
         block: BlockHashOrNumber,
         gas_price: u128,
     ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let (bundle_data, to) = self.da_gas_transaction_data(user_op, gas_price);
+
+        self.da_gas_oracle
+            .estimate_da_gas(bundle_data, to, block, gas_price)
+            .await
+    }
+
+    fn da_gas_transaction_data(&self, user_op: UserOperation, gas_price: u128) -> (Bytes, Address) {
         let data = self
             .i_entry_point
             .handleOps(vec![user_op.into()], Address::random())
@@ -331,12 +346,8 @@ where
             .into_input()
             .unwrap();
 
-        let bundle_data =
-            super::max_bundle_transaction_data(*self.i_entry_point.address(), data, gas_price);
-
-        self.da_gas_oracle
-            .estimate_da_gas(bundle_data, *self.i_entry_point.address(), block, gas_price)
-            .await
+        let to = *self.i_entry_point.address();
+        (super::max_bundle_transaction_data(to, data, gas_price), to)
     }
 }
 
@@ -413,11 +424,17 @@ where
         }
     }
 
-    fn get_simulate_handle_op_call(&self, op: Self::UO, state_override: StateOverride) -> EvmCall {
+    fn get_simulate_handle_op_call(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        state_override: StateOverride,
+    ) -> EvmCall {
         let data = IEntryPoint::simulateHandleOpCall {
             op: op.into(),
-            target: Address::ZERO,
-            targetCallData: Bytes::new(),
+            target,
+            targetCallData: target_call_data,
         }
         .abi_encode()
         .into();
@@ -500,6 +517,12 @@ where
         Ok(ret)
     }
 
+    fn decode_simulate_handle_ops_output(
+        _output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        Err(anyhow::anyhow!("simulateHandleOp succeeded, but should always revert").into())
+    }
+
     fn simulation_should_revert(&self) -> bool {
         true
     }
@@ -15,10 +15,7 @@ use alloy_contract::Error as ContractError;
 use alloy_json_rpc::ErrorPayload;
 use alloy_primitives::{Address, Bytes, U256};
 use alloy_provider::Provider as AlloyProvider;
-use alloy_rpc_types_eth::{
-    state::{AccountOverride, StateOverride},
-    BlockId, TransactionRequest,
-};
+use alloy_rpc_types_eth::{state::StateOverride, BlockId, TransactionRequest};
 use alloy_sol_types::{
     ContractError as SolContractError, SolCall, SolError, SolInterface, SolValue,
 };
@@ -224,6 +221,13 @@ where
             Err(error) => Err(error.into()),
         }
     }
+
+    async fn aggregated_signature_length(
+        &self,
+        aggregator_address: Address,
+    ) -> ProviderResult<Option<usize>> {
+        super::aggregated_signature_length(self.i_entry_point.provider(), aggregator_address).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -311,6 +315,14 @@ where
         block: BlockHashOrNumber,
         gas_price: u128,
     ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        let (bundle_data, to) = self.da_gas_transaction_data(user_op, gas_price);
+
+        self.da_gas_oracle
+            .estimate_da_gas(bundle_data, to, block, gas_price)
+            .await
+    }
+
+    fn da_gas_transaction_data(&self, user_op: UserOperation, gas_price: u128) -> (Bytes, Address) {
         let data = self
             .i_entry_point
             .handleOps(vec![user_op.pack()], Address::random())
@@ -319,12 +331,8 @@ where
             .into_input()
             .unwrap();
 
-        let bundle_data =
-            super::max_bundle_transaction_data(*self.i_entry_point.address(), data, gas_price);
-
-        self.da_gas_oracle
-            .estimate_da_gas(bundle_data, *self.i_entry_point.address(), block, gas_price)
-            .await
+        let to = *self.i_entry_point.address();
+        (super::max_bundle_transaction_data(to, data, gas_price), to)
     }
 }
 
@@ -388,14 +396,16 @@ where
     fn get_simulate_handle_op_call(
         &self,
         op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
         mut state_override: StateOverride,
     ) -> EvmCall {
         add_simulations_override(&mut state_override, *self.i_entry_point.address());
 
         let data = IEntryPointSimulations::simulateHandleOpCall {
             op: op.pack(),
-            target: Address::ZERO,
-            targetCallData: Bytes::new(),
+            target,
+            targetCallData: target_call_data,
         }
         .abi_encode()
         .into();
@@ -451,6 +461,15 @@ where
         Ok(Err(decode_validation_revert(revert_data)))
     }
 
+    fn decode_simulate_handle_ops_output(
+        output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        let output =
+            IEntryPointSimulations::simulateHandleOpCall::abi_decode_returns(output, false)
+                .context("should decode simulateHandleOp output")?;
+        Ok(Ok(output._0.try_into()?))
+    }
+
     fn simulation_should_revert(&self) -> bool {
         false
     }
@@ -469,12 +488,12 @@ fn add_simulations_override(state_override: &mut StateOverride, addr: Address) {
     // We'll trust they know what they're doing and not replace their code.
     // This is needed for call gas estimation, where the entry point is
     // replaced with a proxy and the simulations bytecode is elsewhere.
+    // Any other caller provided overrides for the entry point are kept.
     state_override
         .entry(addr)
-        .or_insert_with(|| AccountOverride {
-            code: Some(ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone()),
-            ..Default::default()
-        });
+        .or_default()
+        .code
+        .get_or_insert_with(|| ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE.clone());
 }
 
 fn get_handle_ops_call<AP: AlloyProvider<T>, T: Transport + Clone>(
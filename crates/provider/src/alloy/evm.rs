@@ -16,6 +16,7 @@ use std::marker::PhantomData;
 use alloy_json_rpc::{RpcParam, RpcReturn};
 use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
 use alloy_provider::{ext::DebugApi, network::TransactionBuilder, Provider as AlloyProvider};
+use alloy_rpc_client::BatchRequest;
 use alloy_rpc_types_eth::{
     state::{AccountOverride, StateOverride},
     Block, BlockId, BlockNumberOrTag, BlockTransactionsKind, FeeHistory, Filter, Log, Transaction,
@@ -26,13 +27,14 @@ use alloy_rpc_types_trace::geth::{
 };
 use alloy_transport::Transport;
 use anyhow::Context;
+use futures_util::future::try_join_all;
 use rundler_contracts::utils::{
     GetCodeHashes::{self, GetCodeHashesInstance},
     GetGasUsed::{self, GasUsedResult},
     StorageLoader,
 };
 
-use crate::{EvmCall, EvmProvider, ProviderResult};
+use crate::{AccessListWithGasUsed, EvmCall, EvmProvider, ProviderResult, TransactionStatus};
 
 /// Evm Provider implementation using [alloy-provider](https://github.com/alloy-rs/alloy-rs)
 pub struct AlloyEvmProvider<AP, T> {
@@ -103,7 +105,17 @@ where
         tx: &TransactionRequest,
         block: Option<BlockId>,
         state_overrides: &StateOverride,
+        gas_limit: Option<u64>,
     ) -> ProviderResult<Bytes> {
+        let capped;
+        let tx = match gas_limit {
+            Some(gas_limit) => {
+                capped = tx.clone().with_gas_limit(gas_limit);
+                &capped
+            }
+            None => tx,
+        };
+
         let mut call = self.inner.call(tx);
         if let Some(block) = block {
             call = call.block(block);
@@ -113,6 +125,20 @@ where
         Ok(call.await?)
     }
 
+    async fn create_access_list(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+    ) -> ProviderResult<AccessListWithGasUsed> {
+        Ok(self
+            .inner
+            .raw_request(
+                "eth_createAccessList".into(),
+                (tx.clone(), block.unwrap_or_else(BlockId::latest)),
+            )
+            .await?)
+    }
+
     async fn get_block_number(&self) -> ProviderResult<u64> {
         Ok(self.inner.get_block_number().await?)
     }
@@ -124,6 +150,15 @@ where
             .await?)
     }
 
+    async fn get_blocks(&self, block_ids: Vec<BlockId>) -> ProviderResult<Vec<Option<Block>>> {
+        try_join_all(
+            block_ids
+                .into_iter()
+                .map(|block_id| self.get_block(block_id)),
+        )
+        .await
+    }
+
     async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
         let mut call = self.inner.get_balance(address);
         if let Some(block) = block {
@@ -133,10 +168,42 @@ where
         Ok(call.await?)
     }
 
+    async fn get_balances(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<U256>> {
+        if addresses.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let block = block.unwrap_or_else(BlockId::latest);
+        let mut batch = BatchRequest::new(self.inner.client());
+        let waiters = addresses
+            .into_iter()
+            .map(|address| batch.add_call("eth_getBalance", &(address, block)))
+            .collect::<Result<Vec<_>, _>>()?;
+        batch.send().await?;
+
+        // waiters are resolved by request id, so results align with the input order
+        Ok(try_join_all(waiters).await?)
+    }
+
     async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>> {
         Ok(self.inner.get_transaction_by_hash(tx).await?)
     }
 
+    async fn get_transaction_with_status(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<(Transaction, TransactionStatus)>> {
+        let tx = EvmProvider::get_transaction_by_hash(self, tx).await?;
+        Ok(tx.map(|tx| {
+            let status = TransactionStatus::from(&tx);
+            (tx, status)
+        }))
+    }
+
     async fn get_transaction_receipt(
         &self,
         tx: TxHash,
@@ -144,6 +211,25 @@ where
         Ok(self.inner.get_transaction_receipt(tx).await?)
     }
 
+    async fn get_transaction_receipts(
+        &self,
+        hashes: Vec<TxHash>,
+    ) -> ProviderResult<Vec<Option<TransactionReceipt>>> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut batch = BatchRequest::new(self.inner.client());
+        let waiters = hashes
+            .into_iter()
+            .map(|hash| batch.add_call("eth_getTransactionReceipt", &(hash,)))
+            .collect::<Result<Vec<_>, _>>()?;
+        batch.send().await?;
+
+        // waiters are resolved by request id, so results align with the input order
+        Ok(try_join_all(waiters).await?)
+    }
+
     async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(B256, u64)> {
         let latest_block = EvmProvider::get_block(self, BlockId::latest())
             .await?
@@ -175,6 +261,10 @@ where
         Ok(self.inner.get_transaction_count(address).await?)
     }
 
+    async fn get_pending_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        Ok(self.inner.get_transaction_count(address).pending().await?)
+    }
+
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
         Ok(self.inner.get_logs(filter).await?)
     }
@@ -301,11 +391,67 @@ where
 
 #[cfg(test)]
 mod tests {
-    use alloy_provider::ProviderBuilder;
+    use std::{
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use alloy_json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload};
+    use alloy_primitives::{Address, B256, U256};
+    use alloy_provider::{network::TransactionBuilder, Provider, ProviderBuilder, RootProvider};
+    use alloy_rpc_client::RpcClient;
+    use alloy_rpc_types_eth::{state::StateOverride, BlockNumberOrTag, TransactionRequest};
     use alloy_sol_macro::sol;
+    use alloy_transport::{TransportError, TransportFut};
+    use serde_json::{json, value::RawValue, Value};
+    use tower::Service;
 
     use crate::{AlloyEvmProvider, EvmProvider};
 
+    #[mockall::automock]
+    trait JsonRpcHandler {
+        /// Returns the result of a JSON-RPC request for `method` with `params`
+        fn handle(&self, method: &str, params: Value) -> Value;
+    }
+
+    /// Transport that answers requests with a mocked handler instead of a node
+    #[derive(Clone)]
+    struct MockTransport(Arc<MockJsonRpcHandler>);
+
+    impl Service<RequestPacket> for MockTransport {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            let RequestPacket::Single(request) = request else {
+                panic!("unexpected batch request");
+            };
+            let params = request.params().map_or(Value::Null, |params| {
+                serde_json::from_str(params.get()).unwrap()
+            });
+            let result = self.0.handle(request.method(), params);
+            let response = Response {
+                id: request.id().clone(),
+                payload: ResponsePayload::Success(
+                    RawValue::from_string(result.to_string()).unwrap(),
+                ),
+            };
+            Box::pin(async move { Ok(ResponsePacket::Single(response)) })
+        }
+    }
+
+    fn mock_evm_provider(
+        handler: MockJsonRpcHandler,
+    ) -> AlloyEvmProvider<RootProvider<MockTransport>, MockTransport> {
+        let client = RpcClient::new(MockTransport(Arc::new(handler)), true);
+        AlloyEvmProvider::new(ProviderBuilder::new().on_client(client))
+    }
+
     sol!(
         #[allow(missing_docs)]
         #[sol(rpc, bytecode="0x6080604052348015600f57600080fd5b506064431060635760405162461bcd60e51b815260206004820152601660248201527f73686f756c64206e6f74206265206465706c6f79656400000000000000000000604482015260640160405180910390fd5b6102b8806100726000396000f3fe608060405234801561001057600080fd5b506004361061002b5760003560e01c80637b34b62114610030575b600080fd5b61004361003e366004610159565b610055565b60405190815260200160405180910390f35b600080825167ffffffffffffffff81111561007257610072610127565b60405190808252806020026020018201604052801561009b578160200160208202803683370190505b50905060005b83518110156100f3578381815181106100bc576100bc610229565b60200260200101516001600160a01b03163f8282815181106100e0576100e0610229565b60209081029190910101526001016100a1565b50600081604051602001610107919061023f565b60408051601f198184030181529190528051602090910120949350505050565b634e487b7160e01b600052604160045260246000fd5b80356001600160a01b038116811461015457600080fd5b919050565b60006020828403121561016b57600080fd5b813567ffffffffffffffff81111561018257600080fd5b8201601f8101841361019357600080fd5b803567ffffffffffffffff8111156101ad576101ad610127565b8060051b604051601f19603f830116810181811067ffffffffffffffff821117156101da576101da610127565b6040529182526020818401810192908101878411156101f857600080fd5b6020850194505b8385101561021e576102108561013d565b8152602094850194016101ff565b509695505050505050565b634e487b7160e01b600052603260045260246000fd5b602080825282518282018190526000918401906040840190835b81811015610277578351835260209384019390920191600101610259565b50909594505050505056fea2646970667358221220b2b7c1db7d478df6ecb5a14ec979bf9abb43dd1f7b85388b1c502bf5099282d264736f6c634300081a0033")]
@@ -360,6 +506,149 @@ mod tests {
 
     );
 
+    #[tokio::test]
+    async fn test_get_blocks_preserves_order() {
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        // mine block 1
+        GetCodeHashes::deploy(alloy_provider.clone()).await.unwrap();
+
+        let evm_provider = AlloyEvmProvider::new(alloy_provider);
+        let blocks = evm_provider
+            .get_blocks(vec![
+                BlockNumberOrTag::Number(1).into(),
+                BlockNumberOrTag::Number(100).into(),
+                BlockNumberOrTag::Number(0).into(),
+            ])
+            .await
+            .unwrap();
+
+        let numbers = blocks
+            .iter()
+            .map(|block| block.as_ref().map(|block| block.header.number))
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![Some(1), None, Some(0)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_receipts_preserves_order() {
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        // mine blocks 1 and 2
+        GetCodeHashes::deploy(alloy_provider.clone()).await.unwrap();
+        GetGasUsed::deploy(alloy_provider.clone()).await.unwrap();
+
+        let evm_provider = AlloyEvmProvider::new(alloy_provider);
+        let mut tx_hashes = vec![];
+        for number in [1, 2] {
+            let block = evm_provider
+                .get_block(BlockNumberOrTag::Number(number).into())
+                .await
+                .unwrap()
+                .unwrap();
+            tx_hashes.push(block.transactions.as_hashes().unwrap()[0]);
+        }
+
+        let receipts = evm_provider
+            .get_transaction_receipts(vec![tx_hashes[1], B256::repeat_byte(1), tx_hashes[0]])
+            .await
+            .unwrap();
+
+        let hashes = receipts
+            .iter()
+            .map(|receipt| receipt.as_ref().map(|receipt| receipt.transaction_hash))
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, vec![Some(tx_hashes[1]), None, Some(tx_hashes[0])]);
+    }
+
+    #[tokio::test]
+    async fn test_get_balances_preserves_order() {
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        let funded = alloy_provider.get_accounts().await.unwrap()[0];
+        let funded_balance = alloy_provider.get_balance(funded).await.unwrap();
+        assert_ne!(funded_balance, U256::ZERO);
+
+        let evm_provider = AlloyEvmProvider::new(alloy_provider);
+        let empty = Address::random();
+        let balances = evm_provider
+            .get_balances(vec![empty, funded, empty], None)
+            .await
+            .unwrap();
+        assert_eq!(balances, vec![U256::ZERO, funded_balance, U256::ZERO]);
+
+        assert!(evm_provider
+            .get_balances(vec![], None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_gas_limit() {
+        let to = Address::random();
+        let mut handler = MockJsonRpcHandler::new();
+        handler
+            .expect_handle()
+            .withf(|method, params| method == "eth_call" && params[0].get("gas").is_none())
+            .times(1)
+            .returning(|_, _| json!("0x"));
+        handler
+            .expect_handle()
+            .withf(|method, params| method == "eth_call" && params[0]["gas"] == json!("0x7530"))
+            .times(1)
+            .returning(|_, _| json!("0x"));
+
+        let evm_provider = mock_evm_provider(handler);
+        let tx = TransactionRequest::default().to(to);
+        evm_provider
+            .call(&tx, None, &StateOverride::default(), None)
+            .await
+            .unwrap();
+        evm_provider
+            .call(&tx, None, &StateOverride::default(), Some(30_000))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_access_list() {
+        let to = Address::random();
+        let accessed = Address::random();
+        let mut handler = MockJsonRpcHandler::new();
+        handler
+            .expect_handle()
+            .withf(move |method, params| {
+                method == "eth_createAccessList"
+                    && params[0]["to"] == json!(to)
+                    && params[1] == json!("latest")
+            })
+            .times(1)
+            .returning(move |_, _| {
+                json!({
+                    "accessList": [{ "address": accessed, "storageKeys": [] }],
+                    "gasUsed": "0x5208",
+                })
+            });
+        handler
+            .expect_handle()
+            .withf(|method, params| method == "eth_createAccessList" && params[1] == json!("0x64"))
+            .times(1)
+            .returning(|_, _| json!({ "accessList": [], "gasUsed": "0x5208" }));
+
+        let evm_provider = mock_evm_provider(handler);
+        let tx = TransactionRequest::default().to(to);
+
+        // defaults to the latest block
+        let access_list = evm_provider.create_access_list(&tx, None).await.unwrap();
+        assert_eq!(access_list.access_list.len(), 1);
+        assert_eq!(access_list.access_list[0].address, accessed);
+        assert_eq!(access_list.gas_used, U256::from(21_000));
+
+        let access_list = evm_provider
+            .create_access_list(&tx, Some(BlockNumberOrTag::Number(100).into()))
+            .await
+            .unwrap();
+        assert!(access_list.access_list.is_empty());
+    }
+
     #[tokio::test]
     async fn test_get_code_hash_unorder_equal() {
         let alloy_provider = ProviderBuilder::new().on_anvil();
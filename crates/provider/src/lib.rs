@@ -34,10 +34,19 @@ pub use alloy::{
     new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider,
 };
 
+#[cfg(feature = "revm")]
+mod local;
+#[cfg(feature = "revm")]
+pub use local::{LocalEvmEntryPoint, LocalEvmProvider};
+
+mod retry;
+pub use retry::RetryProvider;
+
 mod traits;
 // re-export alloy RPC types
 use std::marker::PhantomData;
 
+pub use alloy_eips::eip2930::AccessListWithGasUsed;
 pub use alloy_json_rpc::{RpcParam, RpcReturn};
 pub use alloy_rpc_types_eth::{
     state::{AccountOverride, StateOverride},
@@ -47,7 +56,7 @@ pub use alloy_rpc_types_eth::{
     TransactionRequest,
 };
 pub use alloy_rpc_types_trace::geth::{
-    GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingCallOptions,
+    CallFrame, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingCallOptions,
     GethDebugTracingOptions, GethTrace,
 };
 // re-export contract types
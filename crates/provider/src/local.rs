@@ -0,0 +1,940 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use alloy_json_rpc::ErrorPayload;
+use alloy_primitives::{keccak256, Address, Bytes, TxHash, TxKind, B256, U256};
+use alloy_provider::network::TransactionBuilder;
+use alloy_rpc_types_eth::EIP1186AccountProofResponse;
+use alloy_sol_types::SolCall;
+use alloy_transport::TransportError;
+use anyhow::Context;
+use revm::{
+    db::CacheDB,
+    primitives::{
+        AccountInfo, Bytecode, ExecutionResult as EvmExecutionResult, SpecId, KECCAK_EMPTY,
+    },
+    Database, DatabaseRef, Evm,
+};
+use rundler_contracts::utils::GetGasUsed::{self, GasUsedResult};
+use rundler_types::{
+    chain::{ChainSpec, EvmHardfork},
+    da::{DAGasBlockData, DAGasUOData},
+    GasFees, UserOperation, UserOpsPerAggregator, ValidationOutput, ValidationRevert,
+};
+use tokio::runtime::Handle;
+
+use crate::{
+    AccessListWithGasUsed, AccountOverride, AggregatorOut, Block, BlockHashOrNumber, BlockHeader,
+    BlockId, BlockNumberOrTag, BundleHandler, DAGasProvider, DepositInfo, EntryPoint,
+    EntryPointProvider, EvmCall, EvmProvider, ExecutionResult, FeeHistory, Filter,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, HandleOpsOut, Log,
+    ProviderError, ProviderResult, RpcParam, RpcReturn, SignatureAggregator, SimulationProvider,
+    StateOverride, Transaction, TransactionReceipt, TransactionRequest, TransactionStatus,
+};
+
+/// An [`EvmProvider`] that executes calls in an embedded REVM instance instead of sending
+/// them to the node.
+///
+/// Account and storage state is fetched from the node with `eth_getProof` as the EVM
+/// touches it, and is cached for the block so that repeated calls against the same block,
+/// such as the rounds of a gas estimation, don't make further round trips. All other
+/// methods are forwarded to the inner provider.
+///
+/// Calls are executed with the rules of the chain spec's hardfork and a zero base fee,
+/// matching `eth_call`.
+#[derive(Clone, Debug)]
+pub struct LocalEvmProvider<P> {
+    inner: P,
+    chain_id: u64,
+    spec_id: SpecId,
+    enabled: bool,
+    cache: Arc<Mutex<StateCache>>,
+}
+
+impl<P> LocalEvmProvider<P> {
+    /// Create a new `LocalEvmProvider` reading state from `inner`
+    pub fn new(inner: P, chain_spec: &ChainSpec) -> Self {
+        Self {
+            inner,
+            chain_id: chain_spec.id,
+            spec_id: spec_id(chain_spec.evm_hardfork),
+            enabled: true,
+            cache: Arc::new(Mutex::new(StateCache::default())),
+        }
+    }
+
+    /// Set whether calls are executed locally. When disabled, calls are sent to the node
+    /// through the inner provider, allowing local execution to be toggled by configuration.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Whether calls are executed locally
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+fn spec_id(hardfork: EvmHardfork) -> SpecId {
+    match hardfork {
+        EvmHardfork::Shanghai => SpecId::SHANGHAI,
+        EvmHardfork::Cancun => SpecId::CANCUN,
+        EvmHardfork::Prague => SpecId::PRAGUE,
+    }
+}
+
+/// State fetched from the node for a single block
+#[derive(Debug, Default)]
+struct StateCache {
+    block_hash: B256,
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+    block_hashes: HashMap<u64, B256>,
+}
+
+#[async_trait::async_trait]
+impl<P: EvmProvider + Clone + 'static> EvmProvider for LocalEvmProvider<P> {
+    async fn request<Params, R>(&self, method: &'static str, params: Params) -> ProviderResult<R>
+    where
+        Params: RpcParam + 'static,
+        R: RpcReturn,
+    {
+        self.inner.request(method, params).await
+    }
+
+    async fn fee_history(
+        &self,
+        block_count: u64,
+        block_number: BlockNumberOrTag,
+        reward_percentiles: &[f64],
+    ) -> ProviderResult<FeeHistory> {
+        self.inner
+            .fee_history(block_count, block_number, reward_percentiles)
+            .await
+    }
+
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+        state_overrides: &StateOverride,
+        gas_limit: Option<u64>,
+    ) -> ProviderResult<Bytes> {
+        if !self.enabled {
+            return self.inner.call(tx, block, state_overrides, gas_limit).await;
+        }
+
+        let header = self
+            .inner
+            .get_block(block.unwrap_or(BlockNumberOrTag::Latest.into()))
+            .await?
+            .context("block should exist for local call")?
+            .header;
+
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.block_hash != header.hash {
+                *cache = StateCache {
+                    block_hash: header.hash,
+                    ..Default::default()
+                };
+            }
+        }
+
+        let db = NodeDb {
+            provider: self.inner.clone(),
+            block: header.number.into(),
+            block_hash: header.hash,
+            cache: self.cache.clone(),
+            handle: Handle::current(),
+        };
+        let chain_id = self.chain_id;
+        let spec_id = self.spec_id;
+        let tx = tx.clone();
+        let state_overrides = state_overrides.clone();
+
+        // the database blocks on requests to the node, so run the EVM off of the async runtime
+        tokio::task::spawn_blocking(move || {
+            execute(
+                db,
+                chain_id,
+                spec_id,
+                &header,
+                &tx,
+                &state_overrides,
+                gas_limit,
+            )
+        })
+        .await
+        .context("local call should not panic")?
+    }
+
+    async fn create_access_list(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+    ) -> ProviderResult<AccessListWithGasUsed> {
+        self.inner.create_access_list(tx, block).await
+    }
+
+    async fn get_block_number(&self) -> ProviderResult<u64> {
+        self.inner.get_block_number().await
+    }
+
+    async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>> {
+        self.inner.get_block(block_id).await
+    }
+
+    async fn get_blocks(&self, block_ids: Vec<BlockId>) -> ProviderResult<Vec<Option<Block>>> {
+        self.inner.get_blocks(block_ids).await
+    }
+
+    async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
+        self.inner.get_balance(address, block).await
+    }
+
+    async fn get_balances(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<U256>> {
+        self.inner.get_balances(addresses, block).await
+    }
+
+    async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>> {
+        self.inner.get_transaction_by_hash(tx).await
+    }
+
+    async fn get_transaction_with_status(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<(Transaction, TransactionStatus)>> {
+        self.inner.get_transaction_with_status(tx).await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<TransactionReceipt>> {
+        self.inner.get_transaction_receipt(tx).await
+    }
+
+    async fn get_transaction_receipts(
+        &self,
+        hashes: Vec<TxHash>,
+    ) -> ProviderResult<Vec<Option<TransactionReceipt>>> {
+        self.inner.get_transaction_receipts(hashes).await
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.inner
+            .debug_trace_transaction(tx_hash, trace_options)
+            .await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block_id: Option<BlockId>,
+        trace_options: GethDebugTracingCallOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.inner
+            .debug_trace_call(tx, block_id, trace_options)
+            .await
+    }
+
+    async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(B256, u64)> {
+        self.inner.get_latest_block_hash_and_number().await
+    }
+
+    async fn get_pending_base_fee(&self) -> ProviderResult<u128> {
+        self.inner.get_pending_base_fee().await
+    }
+
+    async fn get_max_priority_fee(&self) -> ProviderResult<u128> {
+        self.inner.get_max_priority_fee().await
+    }
+
+    async fn get_code(&self, address: Address, block: Option<BlockId>) -> ProviderResult<Bytes> {
+        self.inner.get_code(address, block).await
+    }
+
+    async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        self.inner.get_transaction_count(address).await
+    }
+
+    async fn get_pending_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        self.inner.get_pending_transaction_count(address).await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
+        self.inner.get_logs(filter).await
+    }
+
+    async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult> {
+        if !self.enabled {
+            return self.inner.get_gas_used(call).await;
+        }
+
+        let EvmCall {
+            to,
+            value,
+            data,
+            mut state_override,
+        } = call;
+
+        let helper_addr = Address::random();
+        let account = AccountOverride {
+            code: Some(GetGasUsed::DEPLOYED_BYTECODE.clone()),
+            ..Default::default()
+        };
+        state_override.insert(helper_addr, account);
+
+        let tx = TransactionRequest::default().to(helper_addr).with_input(
+            GetGasUsed::getGasCall {
+                target: to,
+                value,
+                data,
+            }
+            .abi_encode(),
+        );
+        let ret = self.call(&tx, None, &state_override, None).await?;
+
+        Ok(GetGasUsed::getGasCall::abi_decode_returns(&ret, true)
+            .context("should decode gas used result")?
+            ._0)
+    }
+
+    async fn batch_get_storage_at(
+        &self,
+        address: Address,
+        slots: Vec<B256>,
+    ) -> ProviderResult<Vec<B256>> {
+        self.inner.batch_get_storage_at(address, slots).await
+    }
+
+    async fn get_code_hash(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<B256> {
+        self.inner.get_code_hash(addresses, block).await
+    }
+}
+
+/// An entry point provider that runs `simulateHandleOp` calls, used throughout gas
+/// estimation, in the EVM of a [`LocalEvmProvider`]. All other methods are forwarded to the
+/// inner entry point provider.
+#[derive(Clone, Debug)]
+pub struct LocalEvmEntryPoint<EP, P> {
+    inner: EP,
+    evm: LocalEvmProvider<P>,
+    chain_spec: ChainSpec,
+    max_simulate_handle_op_gas: u64,
+}
+
+impl<EP, P> LocalEvmEntryPoint<EP, P> {
+    /// Create a new `LocalEvmEntryPoint` wrapping `inner`, executing simulations in `evm`
+    pub fn new(
+        inner: EP,
+        evm: LocalEvmProvider<P>,
+        chain_spec: ChainSpec,
+        max_simulate_handle_op_gas: u64,
+    ) -> Self {
+        Self {
+            inner,
+            evm,
+            chain_spec,
+            max_simulate_handle_op_gas,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<EP, P> EntryPoint for LocalEvmEntryPoint<EP, P>
+where
+    EP: EntryPoint,
+    P: Send + Sync,
+{
+    fn address(&self) -> &Address {
+        self.inner.address()
+    }
+
+    async fn balance_of(
+        &self,
+        address: Address,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        self.inner.balance_of(address, block_id).await
+    }
+
+    async fn get_deposit_info(&self, address: Address) -> ProviderResult<DepositInfo> {
+        self.inner.get_deposit_info(address).await
+    }
+
+    async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<U256>> {
+        self.inner.get_balances(addresses).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<EP, P> SignatureAggregator for LocalEvmEntryPoint<EP, P>
+where
+    EP: SignatureAggregator,
+    P: Send + Sync,
+{
+    type UO = EP::UO;
+
+    async fn aggregate_signatures(
+        &self,
+        aggregator_address: Address,
+        ops: Vec<Self::UO>,
+    ) -> ProviderResult<Option<Bytes>> {
+        self.inner
+            .aggregate_signatures(aggregator_address, ops)
+            .await
+    }
+
+    async fn validate_user_op_signature(
+        &self,
+        aggregator_address: Address,
+        user_op: Self::UO,
+    ) -> ProviderResult<AggregatorOut> {
+        self.inner
+            .validate_user_op_signature(aggregator_address, user_op)
+            .await
+    }
+
+    async fn aggregated_signature_length(
+        &self,
+        aggregator_address: Address,
+    ) -> ProviderResult<Option<usize>> {
+        self.inner
+            .aggregated_signature_length(aggregator_address)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl<EP, P> BundleHandler for LocalEvmEntryPoint<EP, P>
+where
+    EP: BundleHandler,
+    P: Send + Sync,
+{
+    type UO = EP::UO;
+
+    async fn call_handle_ops(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
+        beneficiary: Address,
+        gas_limit: Option<u64>,
+    ) -> ProviderResult<HandleOpsOut> {
+        self.inner
+            .call_handle_ops(ops_per_aggregator, beneficiary, gas_limit)
+            .await
+    }
+
+    fn get_send_bundle_transaction(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
+        beneficiary: Address,
+        gas_limit: u64,
+        gas_fees: GasFees,
+    ) -> TransactionRequest {
+        self.inner
+            .get_send_bundle_transaction(ops_per_aggregator, beneficiary, gas_limit, gas_fees)
+    }
+}
+
+#[async_trait::async_trait]
+impl<EP, P> DAGasProvider for LocalEvmEntryPoint<EP, P>
+where
+    EP: DAGasProvider,
+    P: Send + Sync,
+{
+    type UO = EP::UO;
+
+    async fn calc_da_gas(
+        &self,
+        uo: Self::UO,
+        block: BlockHashOrNumber,
+        gas_price: u128,
+    ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)> {
+        self.inner.calc_da_gas(uo, block, gas_price).await
+    }
+
+    fn da_gas_transaction_data(&self, uo: Self::UO, gas_price: u128) -> (Bytes, Address) {
+        self.inner.da_gas_transaction_data(uo, gas_price)
+    }
+}
+
+#[async_trait::async_trait]
+impl<EP, P> SimulationProvider for LocalEvmEntryPoint<EP, P>
+where
+    EP: SimulationProvider,
+    P: EvmProvider + Clone + 'static,
+{
+    type UO = EP::UO;
+
+    fn get_tracer_simulate_validation_call(
+        &self,
+        user_op: Self::UO,
+    ) -> ProviderResult<(TransactionRequest, StateOverride)> {
+        self.inner.get_tracer_simulate_validation_call(user_op)
+    }
+
+    async fn simulate_validation(
+        &self,
+        user_op: Self::UO,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>> {
+        self.inner.simulate_validation(user_op, block_id).await
+    }
+
+    fn get_simulate_handle_op_call(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        state_override: StateOverride,
+    ) -> EvmCall {
+        self.inner
+            .get_simulate_handle_op_call(op, target, target_call_data, state_override)
+    }
+
+    async fn simulate_handle_op(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        block_id: BlockId,
+        state_override: StateOverride,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        if !self.evm.enabled() {
+            return self
+                .inner
+                .simulate_handle_op(op, target, target_call_data, block_id, state_override)
+                .await;
+        }
+
+        let da_gas: u64 = op
+            .pre_verification_da_gas_limit(&self.chain_spec, Some(1))
+            .try_into()
+            .unwrap_or(u64::MAX);
+        let call =
+            self.inner
+                .get_simulate_handle_op_call(op, target, target_call_data, state_override);
+        let tx = TransactionRequest::default()
+            .to(call.to)
+            .with_input(call.data)
+            .value(call.value);
+
+        match self
+            .evm
+            .call(
+                &tx,
+                Some(block_id),
+                &call.state_override,
+                Some(self.max_simulate_handle_op_gas.saturating_add(da_gas)),
+            )
+            .await
+        {
+            Ok(output) => EP::decode_simulate_handle_ops_output(&output),
+            Err(ProviderError::RPC(TransportError::ErrorResp(resp))) => {
+                match resp.as_revert_data() {
+                    Some(revert_data) => EP::decode_simulate_handle_ops_revert(&revert_data),
+                    None => Ok(Err(ValidationRevert::Unknown(Bytes::default()))),
+                }
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn decode_simulate_handle_ops_revert(
+        revert_data: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        EP::decode_simulate_handle_ops_revert(revert_data)
+    }
+
+    fn decode_simulate_handle_ops_output(
+        output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
+        EP::decode_simulate_handle_ops_output(output)
+    }
+
+    fn simulation_should_revert(&self) -> bool {
+        self.inner.simulation_should_revert()
+    }
+}
+
+impl<UO, EP, P> EntryPointProvider<UO> for LocalEvmEntryPoint<EP, P>
+where
+    UO: UserOperation,
+    EP: EntryPointProvider<UO>,
+    P: EvmProvider + Clone + 'static,
+{
+}
+
+fn execute<P: EvmProvider>(
+    db: NodeDb<P>,
+    chain_id: u64,
+    spec_id: SpecId,
+    header: &BlockHeader,
+    tx: &TransactionRequest,
+    state_overrides: &StateOverride,
+    gas_limit: Option<u64>,
+) -> ProviderResult<Bytes> {
+    let mut db = CacheDB::new(db);
+    apply_state_overrides(&mut db, state_overrides)?;
+
+    let block_gas_limit = u64::try_from(header.gas_limit).unwrap_or(u64::MAX);
+    let gas_limit = gas_limit
+        .or_else(|| tx.gas.and_then(|gas| u64::try_from(gas).ok()))
+        .unwrap_or(block_gas_limit)
+        .min(block_gas_limit);
+
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(spec_id)
+        .modify_cfg_env(|cfg| {
+            cfg.chain_id = chain_id;
+            // eth_call allows any sender, including contracts
+            cfg.disable_eip3607 = true;
+        })
+        .modify_block_env(|env| {
+            env.number = U256::from(header.number);
+            env.timestamp = U256::from(header.timestamp);
+            env.coinbase = header.miner;
+            env.gas_limit = U256::from(block_gas_limit);
+            env.difficulty = header.difficulty;
+            env.prevrandao = Some(header.mix_hash.unwrap_or_default());
+            env.basefee = U256::ZERO;
+        })
+        .modify_tx_env(|env| {
+            env.caller = tx.from.unwrap_or_default();
+            env.transact_to = tx.to.unwrap_or(TxKind::Create);
+            env.data = tx.input.input().cloned().unwrap_or_default();
+            env.value = tx.value.unwrap_or_default();
+            env.gas_limit = gas_limit;
+            env.gas_price = U256::ZERO;
+            env.gas_priority_fee = None;
+            env.nonce = None;
+        })
+        .build();
+
+    let result = evm
+        .transact()
+        .map_err(|e| anyhow::anyhow!("local call failed: {e}"))?
+        .result;
+
+    match result {
+        EvmExecutionResult::Success { output, .. } => Ok(output.into_data()),
+        // mirror the node's error so that callers can decode the revert data
+        EvmExecutionResult::Revert { output, .. } => Err(TransportError::ErrorResp(ErrorPayload {
+            code: 3,
+            message: "execution reverted".into(),
+            data: serde_json::value::to_raw_value(&output).ok(),
+        })
+        .into()),
+        EvmExecutionResult::Halt { reason, .. } => {
+            Err(anyhow::anyhow!("local call halted: {reason:?}").into())
+        }
+    }
+}
+
+fn apply_state_overrides<P: EvmProvider>(
+    db: &mut CacheDB<NodeDb<P>>,
+    state_overrides: &StateOverride,
+) -> ProviderResult<()> {
+    for (address, account) in state_overrides {
+        let mut info = db.basic(*address)?.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = u64::try_from(nonce).unwrap_or(u64::MAX);
+        }
+        if let Some(code) = &account.code {
+            info.code_hash = keccak256(code);
+            info.code = Some(Bytecode::new_raw(code.clone()));
+        }
+        db.insert_account_info(*address, info);
+
+        if let Some(state) = &account.state {
+            db.replace_account_storage(
+                *address,
+                state
+                    .iter()
+                    .map(|(slot, value)| {
+                        (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))
+                    })
+                    .collect(),
+            )?;
+        }
+        if let Some(state_diff) = &account.state_diff {
+            for (slot, value) in state_diff {
+                db.insert_account_storage(
+                    *address,
+                    U256::from_be_bytes(slot.0),
+                    U256::from_be_bytes(value.0),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read only view of the node's state at a block, backing the local EVM
+struct NodeDb<P> {
+    provider: P,
+    block: BlockId,
+    block_hash: B256,
+    cache: Arc<Mutex<StateCache>>,
+    handle: Handle,
+}
+
+impl<P> NodeDb<P> {
+    fn cached<T>(&self, f: impl FnOnce(&StateCache) -> Option<T>) -> Option<T> {
+        let cache = self.cache.lock().unwrap();
+        // another call may have moved the cache on to a newer block
+        if cache.block_hash != self.block_hash {
+            return None;
+        }
+        f(&cache)
+    }
+
+    fn update_cache(&self, f: impl FnOnce(&mut StateCache)) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.block_hash == self.block_hash {
+            f(&mut cache);
+        }
+    }
+}
+
+impl<P: EvmProvider> NodeDb<P> {
+    fn get_proof(
+        &self,
+        address: Address,
+        slots: Vec<B256>,
+    ) -> ProviderResult<EIP1186AccountProofResponse> {
+        self.handle.block_on(
+            self.provider
+                .request("eth_getProof", (address, slots, self.block)),
+        )
+    }
+}
+
+impl<P: EvmProvider> DatabaseRef for NodeDb<P> {
+    type Error = ProviderError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.cached(|cache| cache.accounts.get(&address).cloned()) {
+            return Ok(Some(info));
+        }
+
+        let proof = self.get_proof(address, vec![])?;
+        // nodes report a zero code hash for accounts that don't exist
+        let code = if proof.code_hash == KECCAK_EMPTY || proof.code_hash == B256::ZERO {
+            Bytes::new()
+        } else {
+            self.handle
+                .block_on(self.provider.get_code(address, Some(self.block)))?
+        };
+        let info = AccountInfo::new(
+            proof.balance,
+            proof.nonce,
+            keccak256(&code),
+            Bytecode::new_raw(code),
+        );
+
+        self.update_cache(|cache| {
+            cache.accounts.insert(address, info.clone());
+        });
+        Ok(Some(info))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // code is loaded along with its account, so it is never looked up by hash
+        Err(anyhow::anyhow!("code for hash {code_hash} should be loaded with its account").into())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.cached(|cache| cache.storage.get(&(address, index)).copied()) {
+            return Ok(value);
+        }
+
+        let proof = self.get_proof(address, vec![B256::from(index)])?;
+        let value = proof
+            .storage_proof
+            .first()
+            .map(|slot| slot.value)
+            .unwrap_or_default();
+
+        self.update_cache(|cache| {
+            cache.storage.insert((address, index), value);
+        });
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.cached(|cache| cache.block_hashes.get(&number).copied()) {
+            return Ok(hash);
+        }
+
+        let hash = self
+            .handle
+            .block_on(self.provider.get_block(number.into()))?
+            .map(|block| block.header.hash)
+            .unwrap_or_default();
+
+        self.update_cache(|cache| {
+            cache.block_hashes.insert(number, hash);
+        });
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::RpcError;
+    use alloy_provider::ProviderBuilder;
+    use rundler_contracts::utils::{GetCodeHashes, StorageLoader};
+
+    use super::*;
+    use crate::AlloyEvmProvider;
+
+    fn anvil_chain_spec() -> ChainSpec {
+        ChainSpec {
+            id: 31337,
+            ..Default::default()
+        }
+    }
+
+    fn code_override(code: Bytes) -> AccountOverride {
+        AccountOverride {
+            code: Some(code),
+            ..Default::default()
+        }
+    }
+
+    fn revert_data(error: ProviderError) -> Option<Bytes> {
+        match error {
+            ProviderError::RPC(RpcError::ErrorResp(payload)) => payload.as_revert_data(),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gas_used_matches_rpc() {
+        let rpc_provider = AlloyEvmProvider::new(ProviderBuilder::new().on_anvil());
+        let local_provider = LocalEvmProvider::new(rpc_provider.clone(), &anvil_chain_spec());
+
+        let target = Address::random();
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            target,
+            code_override(GetCodeHashes::DEPLOYED_BYTECODE.clone()),
+        );
+        let call = EvmCall {
+            to: target,
+            value: U256::ZERO,
+            data: GetCodeHashes::getCodeHashesCall {
+                addresses: (0..5).map(|_| Address::random()).collect(),
+            }
+            .abi_encode()
+            .into(),
+            state_override,
+        };
+
+        let rpc_gas = rpc_provider.get_gas_used(call.clone()).await.unwrap();
+        let local_gas = local_provider.get_gas_used(call).await.unwrap();
+        assert!(rpc_gas.success);
+        assert!(local_gas.success);
+        assert_eq!(local_gas.gasUsed, rpc_gas.gasUsed);
+        assert_eq!(local_gas.result, rpc_gas.result);
+    }
+
+    #[tokio::test]
+    async fn test_storage_overrides_match_rpc() {
+        let rpc_provider = AlloyEvmProvider::new(ProviderBuilder::new().on_anvil());
+        let local_provider = LocalEvmProvider::new(rpc_provider.clone(), &anvil_chain_spec());
+
+        let target = Address::random();
+        let slot = B256::from(U256::from(1));
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            target,
+            AccountOverride {
+                state_diff: Some([(slot, B256::from(U256::from(42)))].into_iter().collect()),
+                ..code_override(StorageLoader::DEPLOYED_BYTECODE.clone())
+            },
+        );
+        // an unset slot is read from the node
+        let tx = TransactionRequest::default()
+            .to(target)
+            .with_input([slot.0, [2; 32]].concat());
+
+        let rpc_ret = rpc_provider
+            .call(&tx, None, &state_override, None)
+            .await
+            .unwrap();
+        let local_ret = local_provider
+            .call(&tx, None, &state_override, None)
+            .await
+            .unwrap();
+        assert_eq!(local_ret, rpc_ret);
+        assert_eq!(local_ret[..32], B256::from(U256::from(42))[..]);
+    }
+
+    #[tokio::test]
+    async fn test_revert_matches_rpc() {
+        let rpc_provider = AlloyEvmProvider::new(ProviderBuilder::new().on_anvil());
+        let local_provider = LocalEvmProvider::new(rpc_provider.clone(), &anvil_chain_spec());
+
+        // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 REVERT
+        let target = Address::random();
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            target,
+            code_override(const_hex::decode("602a60005260206000fd").unwrap().into()),
+        );
+        let tx = TransactionRequest::default().to(target);
+
+        let rpc_err = rpc_provider
+            .call(&tx, None, &state_override, None)
+            .await
+            .unwrap_err();
+        let local_err = local_provider
+            .call(&tx, None, &state_override, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            revert_data(local_err),
+            Some(B256::from(U256::from(42)).into())
+        );
+        assert_eq!(
+            revert_data(rpc_err),
+            Some(B256::from(U256::from(42)).into())
+        );
+    }
+}
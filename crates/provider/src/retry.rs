@@ -0,0 +1,335 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::future::Future;
+
+use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
+use rundler_contracts::utils::GetGasUsed::GasUsedResult;
+use rundler_utils::retry::{self, RetryOpts};
+
+use crate::{
+    AccessListWithGasUsed, Block, BlockId, BlockNumberOrTag, EvmCall, EvmProvider, FeeHistory,
+    Filter, GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log, ProviderError,
+    ProviderResult, RpcParam, RpcReturn, StateOverride, Transaction, TransactionReceipt,
+    TransactionRequest, TransactionStatus,
+};
+
+/// An [`EvmProvider`] that retries read methods of an inner provider on transient
+/// errors, such as the node rate limiting requests, using exponential backoff with jitter.
+///
+/// Errors that aren't transient are returned immediately. `request` is never retried, as
+/// the method it calls may not be idempotent.
+#[derive(Clone, Debug)]
+pub struct RetryProvider<P> {
+    inner: P,
+    opts: RetryOpts,
+}
+
+impl<P> RetryProvider<P> {
+    /// Create a new `RetryProvider` wrapping `inner`
+    ///
+    /// `opts.max_attempts` includes the first attempt.
+    pub fn new(inner: P, opts: RetryOpts) -> Self {
+        Self { inner, opts }
+    }
+
+    async fn retry<Func, Fut, Out>(&self, description: &str, func: Func) -> ProviderResult<Out>
+    where
+        Func: Fn() -> Fut,
+        Fut: Future<Output = ProviderResult<Out>>,
+    {
+        retry::with_retries_if(description, func, ProviderError::is_transient, self.opts).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: EvmProvider> EvmProvider for RetryProvider<P> {
+    async fn request<Params, R>(&self, method: &'static str, params: Params) -> ProviderResult<R>
+    where
+        Params: RpcParam + 'static,
+        R: RpcReturn,
+    {
+        self.inner.request(method, params).await
+    }
+
+    async fn fee_history(
+        &self,
+        block_count: u64,
+        block_number: BlockNumberOrTag,
+        reward_percentiles: &[f64],
+    ) -> ProviderResult<FeeHistory> {
+        self.retry("get fee history", || {
+            self.inner
+                .fee_history(block_count, block_number, reward_percentiles)
+        })
+        .await
+    }
+
+    async fn call(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+        state_overrides: &StateOverride,
+        gas_limit: Option<u64>,
+    ) -> ProviderResult<Bytes> {
+        self.retry("call", || {
+            self.inner.call(tx, block, state_overrides, gas_limit)
+        })
+        .await
+    }
+
+    async fn create_access_list(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+    ) -> ProviderResult<AccessListWithGasUsed> {
+        self.retry("create access list", || {
+            self.inner.create_access_list(tx, block)
+        })
+        .await
+    }
+
+    async fn get_block_number(&self) -> ProviderResult<u64> {
+        self.retry("get block number", || self.inner.get_block_number())
+            .await
+    }
+
+    async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>> {
+        self.retry("get block", || self.inner.get_block(block_id))
+            .await
+    }
+
+    async fn get_blocks(&self, block_ids: Vec<BlockId>) -> ProviderResult<Vec<Option<Block>>> {
+        self.retry("get blocks", || self.inner.get_blocks(block_ids.clone()))
+            .await
+    }
+
+    async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
+        self.retry("get balance", || self.inner.get_balance(address, block))
+            .await
+    }
+
+    async fn get_balances(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<U256>> {
+        self.retry("get balances", || {
+            self.inner.get_balances(addresses.clone(), block)
+        })
+        .await
+    }
+
+    async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>> {
+        self.retry("get transaction", || self.inner.get_transaction_by_hash(tx))
+            .await
+    }
+
+    async fn get_transaction_with_status(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<(Transaction, TransactionStatus)>> {
+        self.retry("get transaction with status", || {
+            self.inner.get_transaction_with_status(tx)
+        })
+        .await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<TransactionReceipt>> {
+        self.retry("get transaction receipt", || {
+            self.inner.get_transaction_receipt(tx)
+        })
+        .await
+    }
+
+    async fn get_transaction_receipts(
+        &self,
+        hashes: Vec<TxHash>,
+    ) -> ProviderResult<Vec<Option<TransactionReceipt>>> {
+        self.retry("get transaction receipts", || {
+            self.inner.get_transaction_receipts(hashes.clone())
+        })
+        .await
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.retry("debug trace transaction", || {
+            self.inner
+                .debug_trace_transaction(tx_hash, trace_options.clone())
+        })
+        .await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TransactionRequest,
+        block_id: Option<BlockId>,
+        trace_options: GethDebugTracingCallOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.retry("debug trace call", || {
+            self.inner
+                .debug_trace_call(tx.clone(), block_id, trace_options.clone())
+        })
+        .await
+    }
+
+    async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(B256, u64)> {
+        self.retry("get latest block hash and number", || {
+            self.inner.get_latest_block_hash_and_number()
+        })
+        .await
+    }
+
+    async fn get_pending_base_fee(&self) -> ProviderResult<u128> {
+        self.retry("get pending base fee", || self.inner.get_pending_base_fee())
+            .await
+    }
+
+    async fn get_max_priority_fee(&self) -> ProviderResult<u128> {
+        self.retry("get max priority fee", || self.inner.get_max_priority_fee())
+            .await
+    }
+
+    async fn get_code(&self, address: Address, block: Option<BlockId>) -> ProviderResult<Bytes> {
+        self.retry("get code", || self.inner.get_code(address, block))
+            .await
+    }
+
+    async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        self.retry("get transaction count", || {
+            self.inner.get_transaction_count(address)
+        })
+        .await
+    }
+
+    async fn get_pending_transaction_count(&self, address: Address) -> ProviderResult<u64> {
+        self.retry("get pending transaction count", || {
+            self.inner.get_pending_transaction_count(address)
+        })
+        .await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
+        self.retry("get logs", || self.inner.get_logs(filter)).await
+    }
+
+    async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult> {
+        self.retry("get gas used", || self.inner.get_gas_used(call.clone()))
+            .await
+    }
+
+    async fn batch_get_storage_at(
+        &self,
+        address: Address,
+        slots: Vec<B256>,
+    ) -> ProviderResult<Vec<B256>> {
+        self.retry("batch get storage", || {
+            self.inner.batch_get_storage_at(address, slots.clone())
+        })
+        .await
+    }
+
+    async fn get_code_hash(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<B256> {
+        self.retry("get code hash", || {
+            self.inner.get_code_hash(addresses.clone(), block)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_transport::{TransportError, TransportErrorKind};
+
+    use super::*;
+    use crate::MockEvmProvider;
+
+    fn test_opts() -> RetryOpts {
+        RetryOpts {
+            max_attempts: 5,
+            min_nonzero_wait: Duration::from_millis(1),
+            max_wait: Duration::from_millis(10),
+            max_jitter: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let attempts = AtomicUsize::new(0);
+        let mut inner = MockEvmProvider::new();
+        inner.expect_get_block_number().times(3).returning(move || {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(TransportErrorKind::custom_str("429 Too Many Requests").into())
+            } else {
+                Ok(100)
+            }
+        });
+
+        let provider = RetryProvider::new(inner, test_opts());
+        assert_eq!(provider.get_block_number().await.unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_non_transient_error_not_retried() {
+        let mut inner = MockEvmProvider::new();
+        inner.expect_call().times(1).returning(|_, _, _, _| {
+            Err(TransportError::ErrorResp(ErrorPayload {
+                code: 3,
+                message: "execution reverted".into(),
+                data: None,
+            })
+            .into())
+        });
+
+        let provider = RetryProvider::new(inner, test_opts());
+        let error = provider
+            .call(
+                &TransactionRequest::default(),
+                None,
+                &StateOverride::default(),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("execution reverted"));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let mut inner = MockEvmProvider::new();
+        inner
+            .expect_get_code()
+            .times(5)
+            .returning(|_, _| Err(TransportErrorKind::custom_str("connection reset").into()));
+
+        let provider = RetryProvider::new(inner, test_opts());
+        assert!(provider.get_code(Address::ZERO, None).await.is_err());
+    }
+}
@@ -125,6 +125,14 @@ pub trait SignatureAggregator: Send + Sync {
         aggregator_address: Address,
         user_op: Self::UO,
     ) -> ProviderResult<AggregatorOut>;
+
+    /// Get the length of the aggregated signature produced by an aggregator
+    ///
+    /// Returns `None` if the aggregator does not expose its signature length
+    async fn aggregated_signature_length(
+        &self,
+        aggregator_address: Address,
+    ) -> ProviderResult<Option<usize>>;
 }
 
 /// Trait for submitting bundles of operations to an entry point contract
@@ -176,6 +184,13 @@ pub trait DAGasProvider: Send + Sync {
         block: BlockHashOrNumber,
         gas_price: u128,
     ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)>;
+
+    /// Returns the bundle transaction data and destination address used to calculate the
+    /// DA portion of the gas for a user operation.
+    ///
+    /// This can be passed to a `DAGasOracleSync` to retrieve the user operation's DA data
+    /// separately from the block's DA data.
+    fn da_gas_transaction_data(&self, uo: Self::UO, gas_price: u128) -> (Bytes, Address);
 }
 
 /// Trait for simulating user operations on an entry point contract
@@ -198,8 +213,15 @@ pub trait SimulationProvider: Send + Sync {
         block_id: Option<BlockId>,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
 
-    /// Get call data and state overrides needed to call `simulateHandleOp`
-    fn get_simulate_handle_op_call(&self, op: Self::UO, state_override: StateOverride) -> EvmCall;
+    /// Get call data and state overrides needed to call `simulateHandleOp`, calling
+    /// `target` with `target_call_data` after the operation executes
+    fn get_simulate_handle_op_call(
+        &self,
+        op: Self::UO,
+        target: Address,
+        target_call_data: Bytes,
+        state_override: StateOverride,
+    ) -> EvmCall;
 
     /// Call the entry point contract's `simulateHandleOp` function
     /// with a spoofed state
@@ -217,6 +239,11 @@ pub trait SimulationProvider: Send + Sync {
         revert_data: &Bytes,
     ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
 
+    /// Decode the return data from a call to `simulateHandleOps` that didn't revert
+    fn decode_simulate_handle_ops_output(
+        output: &Bytes,
+    ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+
     /// Returns true if this entry point uses reverts to communicate simulation
     /// results.
     fn simulation_should_revert(&self) -> bool;
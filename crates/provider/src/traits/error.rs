@@ -12,6 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_contract::Error as ContractError;
+use alloy_json_rpc::RpcError;
 use alloy_transport::TransportError;
 
 /// Error enumeration for the Provider trait
@@ -28,6 +29,46 @@ pub enum ProviderError {
     Other(#[from] anyhow::Error),
 }
 
+/// Substrings of node error messages indicating that the state for the requested
+/// block is not available, typically because a non-archive node has pruned it.
+const PRUNED_STATE_ERROR_MESSAGES: &[&str] = &[
+    "missing trie node",
+    "header not found",
+    "historical state",
+    "state is not available",
+];
+
+/// JSON-RPC error codes that nodes and node providers use to signal rate limiting.
+const RATE_LIMIT_ERROR_CODES: &[i64] = &[429, -32005];
+
+impl ProviderError {
+    /// Returns true if the error indicates that the node no longer has the state
+    /// for the requested block, e.g. because it has been pruned.
+    pub fn is_pruned_state(&self) -> bool {
+        let message = match self {
+            ProviderError::Other(error) => format!("{error:#}"),
+            _ => self.to_string(),
+        }
+        .to_lowercase();
+        PRUNED_STATE_ERROR_MESSAGES
+            .iter()
+            .any(|pruned| message.contains(pruned))
+    }
+
+    /// Returns true if the error is likely transient, e.g. a dropped connection or
+    /// the node rate limiting requests, so that the request may succeed if retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ProviderError::RPC(RpcError::Transport(_)) => true,
+            ProviderError::RPC(RpcError::ErrorResp(payload)) => {
+                RATE_LIMIT_ERROR_CODES.contains(&payload.code)
+                    || payload.message.to_lowercase().contains("rate limit")
+            }
+            _ => false,
+        }
+    }
+}
+
 impl From<TransportError> for ProviderError {
     fn from(err: TransportError) -> Self {
         ProviderError::RPC(err)
@@ -42,3 +83,62 @@ impl From<ContractError> for ProviderError {
 
 /// Result of a provider method call
 pub type ProviderResult<T> = Result<T, ProviderError>;
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_transport::TransportErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_is_pruned_state() {
+        assert!(ProviderError::from(TransportErrorKind::custom_str(
+            "missing trie node 0x1234 (path ) state 0x1234 is not available"
+        ))
+        .is_pruned_state());
+        assert!(
+            ProviderError::from(TransportErrorKind::custom_str("header not found"))
+                .is_pruned_state()
+        );
+        assert!(ProviderError::Other(
+            anyhow::anyhow!("Header Not Found").context("should get block")
+        )
+        .is_pruned_state());
+
+        assert!(
+            !ProviderError::from(TransportErrorKind::custom_str("execution reverted"))
+                .is_pruned_state()
+        );
+        assert!(!ProviderError::Other(anyhow::anyhow!("timeout")).is_pruned_state());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(
+            ProviderError::from(TransportErrorKind::custom_str("connection reset")).is_transient()
+        );
+        assert!(ProviderError::from(TransportError::ErrorResp(ErrorPayload {
+            code: 429,
+            message: "Too Many Requests".into(),
+            data: None,
+        }))
+        .is_transient());
+        assert!(ProviderError::from(TransportError::ErrorResp(ErrorPayload {
+            code: -32000,
+            message: "rate limited, retry later".into(),
+            data: None,
+        }))
+        .is_transient());
+
+        assert!(
+            !ProviderError::from(TransportError::ErrorResp(ErrorPayload {
+                code: 3,
+                message: "execution reverted".into(),
+                data: None,
+            }))
+            .is_transient()
+        );
+        assert!(!ProviderError::Other(anyhow::anyhow!("timeout")).is_transient());
+    }
+}
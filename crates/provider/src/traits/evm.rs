@@ -16,14 +16,14 @@
 use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
 
 use crate::{
-    Block, BlockId, BlockNumberOrTag, FeeHistory, Filter, GasUsedResult,
+    AccessListWithGasUsed, Block, BlockId, BlockNumberOrTag, FeeHistory, Filter, GasUsedResult,
     GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log, ProviderResult, RpcParam,
     RpcReturn, StateOverride, Transaction, TransactionReceipt, TransactionRequest,
 };
 
 /// An EVM call, a subset of a transaction that is not meant to be executed onchain, but
 /// can be simulated via an eth_call, debug_traceCall, or similar.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct EvmCall {
     /// The address to call
     pub to: Address,
@@ -35,6 +35,32 @@ pub struct EvmCall {
     pub state_override: StateOverride,
 }
 
+/// The inclusion status of a transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// The transaction is known to the node but has not been included in a block
+    Pending,
+    /// The transaction has been included in a block
+    Mined {
+        /// The number of the block the transaction was included in
+        block_number: u64,
+        /// The hash of the block the transaction was included in
+        block_hash: B256,
+    },
+}
+
+impl From<&Transaction> for TransactionStatus {
+    fn from(tx: &Transaction) -> Self {
+        match (tx.block_number, tx.block_hash) {
+            (Some(block_number), Some(block_hash)) => Self::Mined {
+                block_number,
+                block_hash,
+            },
+            _ => Self::Pending,
+        }
+    }
+}
+
 /// Trait for interacting with chain data and contracts.
 #[async_trait::async_trait]
 #[auto_impl::auto_impl(&, &mut, Rc, Arc, Box)]
@@ -54,31 +80,74 @@ pub trait EvmProvider: Send + Sync {
     ) -> ProviderResult<FeeHistory>;
 
     /// Simulate a transaction via an eth_call
+    ///
+    /// If `gas_limit` is set, it overrides the transaction's gas limit, bounding the gas
+    /// the call can use.
     async fn call(
         &self,
         tx: &TransactionRequest,
         block: Option<BlockId>,
         state_overrides: &StateOverride,
+        gas_limit: Option<u64>,
     ) -> ProviderResult<Bytes>;
 
+    /// Generate the access list a transaction would use via an eth_createAccessList, along
+    /// with the gas it would use when sent with that access list
+    ///
+    /// Defaults to the latest block if `block` is not set.
+    async fn create_access_list(
+        &self,
+        tx: &TransactionRequest,
+        block: Option<BlockId>,
+    ) -> ProviderResult<AccessListWithGasUsed>;
+
     /// Get the current block number
     async fn get_block_number(&self) -> ProviderResult<u64>;
 
     /// Get a block by its hash or number
     async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>>;
 
+    /// Get multiple blocks by their hashes or numbers concurrently
+    ///
+    /// Results are returned in the same order as the provided block ids
+    async fn get_blocks(&self, block_ids: Vec<BlockId>) -> ProviderResult<Vec<Option<Block>>>;
+
     /// Get the balance of an address
     async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256>;
 
+    /// Get the balances of multiple addresses in a single batch request
+    ///
+    /// Results are returned in the same order as the provided addresses
+    async fn get_balances(
+        &self,
+        addresses: Vec<Address>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<U256>>;
+
     /// Get transaction by hash
     async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>>;
 
+    /// Get transaction by hash along with its inclusion status
+    async fn get_transaction_with_status(
+        &self,
+        tx: TxHash,
+    ) -> ProviderResult<Option<(Transaction, TransactionStatus)>>;
+
     /// Get transaction receipt by hash
     async fn get_transaction_receipt(
         &self,
         tx: TxHash,
     ) -> ProviderResult<Option<TransactionReceipt>>;
 
+    /// Get multiple transaction receipts by hash in a single batch request
+    ///
+    /// Results are returned in the same order as the provided hashes, with `None` for
+    /// transactions that are not found
+    async fn get_transaction_receipts(
+        &self,
+        hashes: Vec<TxHash>,
+    ) -> ProviderResult<Vec<Option<TransactionReceipt>>>;
+
     /// Debug trace a transaction
     async fn debug_trace_transaction(
         &self,
@@ -109,6 +178,9 @@ pub trait EvmProvider: Send + Sync {
     /// Get the nonce/transaction count of an address
     async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64>;
 
+    /// Get the nonce/transaction count of an address including transactions in the pending block
+    async fn get_pending_transaction_count(&self, address: Address) -> ProviderResult<u64>;
+
     /// Get the logs matching a filter
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>>;
 
@@ -130,3 +202,38 @@ pub trait EvmProvider: Send + Sync {
         block: Option<BlockId>,
     ) -> ProviderResult<B256>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_status_pending() {
+        let tx = Transaction::default();
+        assert_eq!(TransactionStatus::from(&tx), TransactionStatus::Pending);
+
+        // a block number without a block hash is still pending
+        let tx = Transaction {
+            block_number: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(TransactionStatus::from(&tx), TransactionStatus::Pending);
+    }
+
+    #[test]
+    fn test_transaction_status_mined() {
+        let block_hash = B256::repeat_byte(1);
+        let tx = Transaction {
+            block_number: Some(10),
+            block_hash: Some(block_hash),
+            ..Default::default()
+        };
+        assert_eq!(
+            TransactionStatus::from(&tx),
+            TransactionStatus::Mined {
+                block_number: 10,
+                block_hash,
+            }
+        );
+    }
+}
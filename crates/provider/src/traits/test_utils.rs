@@ -28,9 +28,10 @@ use rundler_types::{
 
 use super::error::ProviderResult;
 use crate::{
-    AggregatorOut, BlockHashOrNumber, BundleHandler, DAGasOracle, DAGasOracleSync, DAGasProvider,
-    DepositInfo, EntryPoint, EntryPointProvider, EvmCall, EvmProvider as EvmProviderTrait,
-    ExecutionResult, HandleOpsOut, SignatureAggregator, SimulationProvider,
+    AccessListWithGasUsed, AggregatorOut, BlockHashOrNumber, BundleHandler, DAGasOracle,
+    DAGasOracleSync, DAGasProvider, DepositInfo, EntryPoint, EntryPointProvider, EvmCall,
+    EvmProvider as EvmProviderTrait, ExecutionResult, HandleOpsOut, SignatureAggregator,
+    SimulationProvider, TransactionStatus,
 };
 
 mockall::mock! {
@@ -55,21 +56,46 @@ mockall::mock! {
             tx: &TransactionRequest,
             block: Option<BlockId>,
             state_overrides: &StateOverride,
+            gas_limit: Option<u64>,
         ) -> ProviderResult<Bytes>;
 
+        async fn create_access_list(
+            &self,
+            tx: &TransactionRequest,
+            block: Option<BlockId>,
+        ) -> ProviderResult<AccessListWithGasUsed>;
+
         async fn get_block_number(&self) -> ProviderResult<u64>;
 
         async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>>;
 
+        async fn get_blocks(&self, block_ids: Vec<BlockId>) -> ProviderResult<Vec<Option<Block>>>;
+
         async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256>;
 
+        async fn get_balances(
+            &self,
+            addresses: Vec<Address>,
+            block: Option<BlockId>,
+        ) -> ProviderResult<Vec<U256>>;
+
         async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>>;
 
+        async fn get_transaction_with_status(
+            &self,
+            tx: TxHash,
+        ) -> ProviderResult<Option<(Transaction, TransactionStatus)>>;
+
         async fn get_transaction_receipt(
             &self,
             tx: TxHash,
         ) -> ProviderResult<Option<TransactionReceipt>>;
 
+        async fn get_transaction_receipts(
+            &self,
+            hashes: Vec<TxHash>,
+        ) -> ProviderResult<Vec<Option<TransactionReceipt>>>;
+
         async fn debug_trace_transaction(
             &self,
             tx_hash: TxHash,
@@ -93,6 +119,8 @@ mockall::mock! {
 
         async fn get_transaction_count(&self, address: Address) -> ProviderResult<u64>;
 
+        async fn get_pending_transaction_count(&self, address: Address) -> ProviderResult<u64>;
+
         async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>>;
 
         async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult>;
@@ -136,6 +164,10 @@ mockall::mock! {
             aggregator_address: Address,
             user_op: v0_6::UserOperation,
         ) -> ProviderResult<AggregatorOut>;
+        async fn aggregated_signature_length(
+            &self,
+            aggregator_address: Address,
+        ) -> ProviderResult<Option<usize>>;
     }
 
     #[async_trait::async_trait]
@@ -153,6 +185,8 @@ mockall::mock! {
         fn get_simulate_handle_op_call(
             &self,
             op: v0_6::UserOperation,
+            target: Address,
+            target_call_data: Bytes,
             state_override: StateOverride,
         ) -> crate::EvmCall;
         async fn simulate_handle_op(
@@ -166,6 +200,9 @@ mockall::mock! {
         fn decode_simulate_handle_ops_revert(
             revert_data: &Bytes,
         ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+        fn decode_simulate_handle_ops_output(
+            output: &Bytes,
+        ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
         fn simulation_should_revert(&self) -> bool;
     }
 
@@ -178,6 +215,11 @@ mockall::mock! {
             block: BlockHashOrNumber,
             gas_price: u128,
         ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)>;
+        fn da_gas_transaction_data(
+            &self,
+            op: v0_6::UserOperation,
+            gas_price: u128,
+        ) -> (Bytes, Address);
     }
 
     #[async_trait::async_trait]
@@ -226,6 +268,10 @@ mockall::mock! {
             aggregator_address: Address,
             user_op: v0_7::UserOperation,
         ) -> ProviderResult<AggregatorOut>;
+        async fn aggregated_signature_length(
+            &self,
+            aggregator_address: Address,
+        ) -> ProviderResult<Option<usize>>;
     }
 
     #[async_trait::async_trait]
@@ -243,6 +289,8 @@ mockall::mock! {
         fn get_simulate_handle_op_call(
             &self,
             op: v0_7::UserOperation,
+            target: Address,
+            target_call_data: Bytes,
             state_override: StateOverride,
         ) -> crate::EvmCall;
         async fn simulate_handle_op(
@@ -256,6 +304,9 @@ mockall::mock! {
         fn decode_simulate_handle_ops_revert(
             revert_data: &Bytes,
         ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
+        fn decode_simulate_handle_ops_output(
+            output: &Bytes,
+        ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>>;
         fn simulation_should_revert(&self) -> bool;
     }
 
@@ -268,6 +319,11 @@ mockall::mock! {
             block: BlockHashOrNumber,
             gas_price: u128,
         ) -> ProviderResult<(u128, DAGasUOData, DAGasBlockData)>;
+        fn da_gas_transaction_data(
+            &self,
+            op: v0_7::UserOperation,
+            gas_price: u128,
+        ) -> (Bytes, Address);
     }
 
     #[async_trait::async_trait]
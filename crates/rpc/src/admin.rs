@@ -15,10 +15,11 @@ use alloy_primitives::Address;
 use anyhow::Context;
 use async_trait::async_trait;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
-use rundler_types::pool::Pool;
+use rundler_types::pool::{Pool, PrecheckSettingsUpdate};
 
 use crate::{
-    types::{RpcAdminClearState, RpcAdminSetTracking},
+    eth::{EthResult, EthRpcError},
+    types::{RpcAdminClearState, RpcAdminPrecheckSettings, RpcAdminSetTracking},
     utils::{self, InternalRpcResult},
 };
 
@@ -36,6 +37,14 @@ pub trait AdminApi {
         entry_point: Address,
         tracking_info: RpcAdminSetTracking,
     ) -> RpcResult<String>;
+
+    /// Updates the precheck settings used by the mempool, returning the effective settings
+    #[method(name = "setPrecheckSettings")]
+    async fn set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: RpcAdminPrecheckSettings,
+    ) -> RpcResult<RpcAdminPrecheckSettings>;
 }
 
 pub(crate) struct AdminApi<P> {
@@ -72,6 +81,18 @@ where
         )
         .await
     }
+
+    async fn set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: RpcAdminPrecheckSettings,
+    ) -> RpcResult<RpcAdminPrecheckSettings> {
+        utils::safe_call_rpc_handler(
+            "admin_setPrecheckSettings",
+            AdminApi::set_precheck_settings(self, entry_point, settings),
+        )
+        .await
+    }
 }
 
 impl<P> AdminApi<P>
@@ -107,4 +128,83 @@ where
 
         Ok("ok".to_string())
     }
+    async fn set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: RpcAdminPrecheckSettings,
+    ) -> EthResult<RpcAdminPrecheckSettings> {
+        let update = PrecheckSettingsUpdate::from(settings);
+        if let Some(err) = update.validate() {
+            return Err(EthRpcError::InvalidParams(err));
+        }
+
+        let effective = self
+            .pool
+            .admin_set_precheck_settings(entry_point, update)
+            .await?;
+
+        Ok(effective.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U128;
+    use rundler_types::pool::MockPool;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_precheck_settings_rejects_zero_gas_cap() {
+        let mut pool = MockPool::default();
+        pool.expect_admin_set_precheck_settings().never();
+        let api = AdminApi::new(pool);
+
+        let res = api
+            .set_precheck_settings(
+                Address::ZERO,
+                RpcAdminPrecheckSettings {
+                    max_verification_gas: Some(U128::ZERO),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(res, Err(EthRpcError::InvalidParams(_))));
+    }
+
+    #[tokio::test]
+    async fn test_set_precheck_settings_returns_effective_settings() {
+        let mut pool = MockPool::default();
+        pool.expect_admin_set_precheck_settings()
+            .withf(|_, s| {
+                *s == PrecheckSettingsUpdate {
+                    max_verification_gas: Some(400_000),
+                    ..Default::default()
+                }
+            })
+            .returning(|_, s| {
+                Ok(PrecheckSettingsUpdate {
+                    max_verification_gas: s.max_verification_gas,
+                    max_total_execution_gas: Some(10_000_000),
+                    base_fee_accept_percent: Some(50),
+                    pre_verification_gas_accept_percent: Some(100),
+                })
+            });
+        let api = AdminApi::new(pool);
+
+        let res = api
+            .set_precheck_settings(
+                Address::ZERO,
+                RpcAdminPrecheckSettings {
+                    max_verification_gas: Some(U128::from(400_000)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.max_verification_gas, Some(U128::from(400_000)));
+        assert_eq!(res.max_total_execution_gas, Some(U128::from(10_000_000)));
+        assert_eq!(res.base_fee_accept_percent, Some(50));
+        assert_eq!(res.pre_verification_gas_accept_percent, Some(100));
+    }
 }
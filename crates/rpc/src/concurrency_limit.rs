@@ -0,0 +1,74 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::sync::Arc;
+
+use futures_util::{future::BoxFuture, FutureExt};
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use tokio::sync::Semaphore;
+use tower::Layer;
+
+/// Limits the number of method calls executing at once.
+///
+/// Calls beyond the limit wait for a slot to free up rather than being rejected. Clones
+/// share the same limit, so a single layer can be applied to multiple servers.
+#[derive(Clone)]
+pub(crate) struct RpcConcurrencyLimitLayer {
+    semaphore: Arc<Semaphore>,
+}
+
+impl RpcConcurrencyLimitLayer {
+    pub(crate) fn new(max_concurrent_requests: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_requests)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcConcurrencyLimitLayer {
+    type Service = RpcConcurrencyLimit<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RpcConcurrencyLimit {
+            service,
+            semaphore: self.semaphore.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RpcConcurrencyLimit<S> {
+    service: S,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcConcurrencyLimit<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = BoxFuture<'a, MethodResponse>;
+
+    fn call(&self, req: Request<'a>) -> Self::Future {
+        let svc = self.service.clone();
+        let semaphore = self.semaphore.clone();
+
+        async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("semaphore should never be closed");
+            svc.call(req).await
+        }
+        .boxed()
+    }
+}
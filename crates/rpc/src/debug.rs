@@ -23,8 +23,8 @@ use rundler_types::{
 
 use crate::{
     types::{
-        RpcDebugPaymasterBalance, RpcReputationInput, RpcReputationOutput, RpcStakeInfo,
-        RpcStakeStatus, RpcUserOperation,
+        RpcBundleBuildReport, RpcDebugPaymasterBalance, RpcReputationInput, RpcReputationOutput,
+        RpcStakeInfo, RpcStakeStatus, RpcUserOperation,
     },
     utils::{self, InternalRpcResult},
 };
@@ -87,6 +87,11 @@ pub trait DebugApi {
     /// Clear the reputations of pool.
     #[method(name = "bundler_clearReputation")]
     async fn bundler_clear_reputation(&self) -> RpcResult<String>;
+
+    /// Returns a summary of the most recent bundle build attempt of each builder
+    /// and entry point.
+    #[method(name = "bundler_getLastBundleBuildReports")]
+    async fn bundler_get_last_bundle_build_reports(&self) -> RpcResult<Vec<RpcBundleBuildReport>>;
 }
 
 pub(crate) struct DebugApi<P, B> {
@@ -196,6 +201,14 @@ where
         )
         .await
     }
+
+    async fn bundler_get_last_bundle_build_reports(&self) -> RpcResult<Vec<RpcBundleBuildReport>> {
+        utils::safe_call_rpc_handler(
+            "bundler_getLastBundleBuildReports",
+            DebugApi::bundler_get_last_bundle_build_reports(self),
+        )
+        .await
+    }
 }
 
 impl<P, B> DebugApi<P, B>
@@ -386,4 +399,17 @@ where
 
         Ok("ok".to_string())
     }
+
+    async fn bundler_get_last_bundle_build_reports(
+        &self,
+    ) -> InternalRpcResult<Vec<RpcBundleBuildReport>> {
+        Ok(self
+            .builder
+            .debug_get_last_bundle_build_reports()
+            .await
+            .context("should get last bundle build reports")?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
 }
@@ -0,0 +1,190 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use http::{header, Request as httpRequest, StatusCode};
+use jsonrpsee::{
+    server::{middleware::rpc::RpcServiceT, HttpBody, HttpResponse},
+    types::{error::INTERNAL_ERROR_CODE, Request},
+    MethodResponse,
+};
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+use crate::error::rpc_err;
+
+/// Tracks in-flight method calls so that servers can be drained before they are stopped.
+///
+/// Once draining starts, new method calls are rejected while calls already in flight run
+/// to completion. Clones share the same state, so a single layer can be applied to
+/// multiple servers. See [`RpcDrainLayer::http`] for the matching HTTP layer, which stops
+/// the servers from accepting new connections while draining.
+#[derive(Clone)]
+pub(crate) struct RpcDrainLayer {
+    state: Arc<DrainState>,
+}
+
+struct DrainState {
+    draining: AtomicBool,
+    in_flight: watch::Sender<usize>,
+}
+
+impl RpcDrainLayer {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Arc::new(DrainState {
+                draining: AtomicBool::new(false),
+                in_flight: watch::Sender::new(0),
+            }),
+        }
+    }
+
+    /// Starts rejecting new method calls and waits up to `grace_period` for in-flight
+    /// calls to complete.
+    ///
+    /// Returns the number of calls still in flight when the wait ended.
+    pub(crate) async fn drain(&self, grace_period: Duration) -> usize {
+        self.state.draining.store(true, Ordering::SeqCst);
+        let mut in_flight = self.state.in_flight.subscribe();
+        let _ = tokio::time::timeout(grace_period, in_flight.wait_for(|n| *n == 0)).await;
+        *self.state.in_flight.borrow()
+    }
+
+    /// Returns an HTTP layer sharing this layer's state.
+    ///
+    /// While draining, it answers every HTTP request, including websocket upgrades and
+    /// health checks, with `503 Service Unavailable` and `Connection: close`. New
+    /// connections are refused and keep-alive connections are closed, so load balancers
+    /// route traffic away from the server.
+    pub(crate) fn http(&self) -> HttpDrainLayer {
+        HttpDrainLayer {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcDrainLayer {
+    type Service = RpcDrain<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RpcDrain {
+            service,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RpcDrain<S> {
+    service: S,
+    state: Arc<DrainState>,
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcDrain<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = BoxFuture<'a, MethodResponse>;
+
+    fn call(&self, req: Request<'a>) -> Self::Future {
+        let svc = self.service.clone();
+        let state = self.state.clone();
+
+        async move {
+            // count the call before checking the flag so a drain can't miss it
+            state.in_flight.send_modify(|n| *n += 1);
+            let _guard = InFlightGuard(&state);
+            if state.draining.load(Ordering::SeqCst) {
+                return MethodResponse::error(
+                    req.id(),
+                    rpc_err(INTERNAL_ERROR_CODE, "server is shutting down"),
+                );
+            }
+            svc.call(req).await
+        }
+        .boxed()
+    }
+}
+
+struct InFlightGuard<'a>(&'a DrainState);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.send_modify(|n| *n -= 1);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpDrainLayer {
+    state: Arc<DrainState>,
+}
+
+impl<S> Layer<S> for HttpDrainLayer {
+    type Service = HttpDrain<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        HttpDrain {
+            service,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpDrain<S> {
+    service: S,
+    state: Arc<DrainState>,
+}
+
+impl<S, R> Service<httpRequest<R>> for HttpDrain<S>
+where
+    S: Service<httpRequest<R>, Response = HttpResponse> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: httpRequest<R>) -> Self::Future {
+        if self.state.draining.load(Ordering::SeqCst) {
+            let response = shutting_down_response();
+            return async move { Ok(response) }.boxed();
+        }
+        self.service.call(req).boxed()
+    }
+}
+
+fn shutting_down_response() -> HttpResponse {
+    http::Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(header::CONNECTION, "close")
+        .body(HttpBody::from("server is shutting down"))
+        .expect("shutdown response should build")
+}
@@ -0,0 +1,293 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use alloy_primitives::{Address, B256, U128, U64};
+use metrics::Histogram;
+use metrics_derive::Metrics;
+use rundler_types::{Timestamp, UserOperationId};
+use rundler_utils::cache::LruMap;
+
+use crate::types::{
+    RpcEstimateHistoryEntry, RpcEstimationAccuracy, RpcGasEstimate, RpcUserOperationReceipt,
+};
+
+/// Maximum number of user operations to keep estimate history for
+const MAX_HISTORY_OPS: u32 = 1024;
+/// Maximum number of estimates to keep per user operation, the oldest are dropped first
+const MAX_ESTIMATES_PER_OP: usize = 32;
+
+#[derive(Metrics)]
+#[metrics(scope = "rpc_estimation")]
+struct EstimationAccuracyMetrics {
+    #[metric(describe = "the estimated gas minus the actual gas used by mined user operations.")]
+    gas_delta: Histogram,
+    #[metric(
+        describe = "the ratio of actual gas used to estimated gas for mined user operations."
+    )]
+    gas_used_ratio: Histogram,
+}
+
+/// Bounded in-memory record of the gas estimates returned for each user operation hash
+#[derive(Clone)]
+pub(crate) struct EstimateHistory {
+    entries: Arc<Mutex<LruMap<B256, VecDeque<RpcEstimateHistoryEntry>>>>,
+    /// Hash of the most recently estimated user operation for each entry point, sender and nonce
+    latest_by_id: Arc<Mutex<LruMap<(Address, UserOperationId), B256>>>,
+    /// Hashes of the mined user operations whose accuracy has been recorded in metrics
+    reported: Arc<Mutex<LruMap<B256, ()>>>,
+}
+
+impl Default for EstimateHistory {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(LruMap::new(MAX_HISTORY_OPS))),
+            latest_by_id: Arc::new(Mutex::new(LruMap::new(MAX_HISTORY_OPS))),
+            reported: Arc::new(Mutex::new(LruMap::new(MAX_HISTORY_OPS))),
+        }
+    }
+}
+
+impl EstimateHistory {
+    /// Records an estimate returned for the user operation with the given hash
+    pub(crate) fn record(
+        &self,
+        hash: B256,
+        id: UserOperationId,
+        entry_point: Address,
+        estimate: RpcGasEstimate,
+    ) {
+        let entry = RpcEstimateHistoryEntry {
+            timestamp: U64::from(Timestamp::now().seconds_since_epoch()),
+            entry_point,
+            estimate,
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(estimates) = entries.get_or_insert(hash, VecDeque::new) {
+            if estimates.len() == MAX_ESTIMATES_PER_OP {
+                estimates.pop_front();
+            }
+            estimates.push_back(entry);
+        }
+        drop(entries);
+
+        self.latest_by_id
+            .lock()
+            .unwrap()
+            .insert((entry_point, id), hash);
+    }
+
+    /// Returns the estimates recorded for the user operation with the given hash, oldest first
+    pub(crate) fn get(&self, hash: B256) -> Vec<RpcEstimateHistoryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&hash)
+            .map(|estimates| estimates.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if an estimate was recorded for the user operation with the given entry
+    /// point, sender and nonce
+    pub(crate) fn has_estimate(&self, entry_point: Address, id: UserOperationId) -> bool {
+        self.latest_by_id
+            .lock()
+            .unwrap()
+            .get(&(entry_point, id))
+            .is_some()
+    }
+
+    /// Compares the most recent estimate for a mined user operation's entry point, sender
+    /// and nonce to the gas it used.
+    ///
+    /// The mined operation's gas fields differ from those sent for estimation, so its
+    /// estimates are found by sender and nonce rather than by hash. Returns none if no
+    /// estimate was recorded.
+    pub(crate) fn accuracy(
+        &self,
+        receipt: &RpcUserOperationReceipt,
+    ) -> Option<RpcEstimationAccuracy> {
+        let key = (
+            receipt.entry_point.into(),
+            UserOperationId {
+                sender: receipt.sender.into(),
+                nonce: receipt.nonce,
+            },
+        );
+        let hash = *self.latest_by_id.lock().unwrap().get(&key)?;
+        let estimate = self.get(hash).pop()?;
+        Some(estimation_accuracy(estimate, receipt))
+    }
+
+    /// Records the estimation accuracy metrics for a newly mined user operation.
+    ///
+    /// Each mined operation is recorded at most once, e.g. if it is re-mined after a reorg.
+    pub(crate) fn record_mined(&self, receipt: &RpcUserOperationReceipt) {
+        let Some(accuracy) = self.accuracy(receipt) else {
+            return;
+        };
+
+        let first_report = {
+            let mut reported = self.reported.lock().unwrap();
+            reported.get(&receipt.user_op_hash).is_none()
+                && reported.insert(receipt.user_op_hash, ())
+        };
+        if !first_report {
+            return;
+        }
+
+        let metrics = EstimationAccuracyMetrics::default();
+        metrics.gas_delta.record(accuracy.gas_delta as f64);
+        if !accuracy.estimated_gas.is_zero() {
+            metrics.gas_used_ratio.record(
+                accuracy.actual_gas_used.to::<u128>() as f64
+                    / accuracy.estimated_gas.to::<u128>() as f64,
+            );
+        }
+    }
+}
+
+fn estimation_accuracy(
+    estimate: RpcEstimateHistoryEntry,
+    receipt: &RpcUserOperationReceipt,
+) -> RpcEstimationAccuracy {
+    let estimated_gas = estimate.estimate.total_gas_limit();
+    let actual_gas_used = receipt.actual_gas_used.to::<u128>();
+    RpcEstimationAccuracy {
+        estimate,
+        estimated_gas: U128::from(estimated_gas),
+        actual_gas_used: U128::from(actual_gas_used),
+        gas_delta: estimated_gas as i128 - actual_gas_used as i128,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use rundler_types::GasEstimate;
+
+    use super::*;
+    use crate::types::RpcGasEstimateV0_6;
+
+    fn id() -> UserOperationId {
+        UserOperationId {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+        }
+    }
+
+    fn receipt(id: UserOperationId, actual_gas_used: u128) -> RpcUserOperationReceipt {
+        RpcUserOperationReceipt {
+            user_op_hash: B256::random(),
+            entry_point: Address::ZERO.into(),
+            sender: id.sender.into(),
+            nonce: id.nonce,
+            paymaster: Address::ZERO.into(),
+            actual_gas_cost: U256::ZERO,
+            actual_gas_used: U128::from(actual_gas_used),
+            success: true,
+            reason: String::new(),
+            aggregator: None,
+            logs: vec![],
+            transaction_hash: B256::random(),
+            receipt: None,
+        }
+    }
+
+    fn estimate(call_gas_limit: u128) -> RpcGasEstimate {
+        RpcGasEstimateV0_6::from(GasEstimate {
+            pre_verification_gas: 50_000,
+            call_gas_limit,
+            verification_gas_limit: 100_000,
+            paymaster_verification_gas_limit: None,
+            block_number: None,
+            breakdown: None,
+            verification_gas_rounds: None,
+            call_gas_rounds: None,
+            warnings: vec![],
+        })
+        .into()
+    }
+
+    #[test]
+    fn test_estimate_history_bounded_per_op() {
+        let history = EstimateHistory::default();
+        let hash = B256::random();
+
+        for i in 0..MAX_ESTIMATES_PER_OP + 1 {
+            history.record(hash, id(), Address::ZERO, estimate(i as u128));
+        }
+
+        let entries = history.get(hash);
+        assert_eq!(entries.len(), MAX_ESTIMATES_PER_OP);
+        // the oldest estimate was dropped
+        assert_eq!(entries[0].estimate, estimate(1));
+        assert!(history.get(B256::random()).is_empty());
+    }
+
+    #[test]
+    fn test_estimation_accuracy() {
+        let history = EstimateHistory::default();
+        let op_id = id();
+        history.record(B256::random(), op_id, Address::ZERO, estimate(200_000));
+        // only the latest estimate for the sender and nonce is compared
+        history.record(B256::random(), op_id, Address::ZERO, estimate(100_000));
+
+        let accuracy = history.accuracy(&receipt(op_id, 180_000)).unwrap();
+        assert_eq!(accuracy.estimate.estimate, estimate(100_000));
+        assert_eq!(accuracy.estimated_gas, U128::from(250_000));
+        assert_eq!(accuracy.actual_gas_used, U128::from(180_000));
+        assert_eq!(accuracy.gas_delta, 70_000);
+
+        let accuracy = history.accuracy(&receipt(op_id, 300_000)).unwrap();
+        assert_eq!(accuracy.gas_delta, -50_000);
+
+        assert!(history.accuracy(&receipt(id(), 1)).is_none());
+    }
+
+    #[test]
+    fn test_estimation_accuracy_by_entry_point() {
+        let history = EstimateHistory::default();
+        let op_id = id();
+        history.record(B256::random(), op_id, Address::random(), estimate(100_000));
+
+        assert!(!history.has_estimate(Address::ZERO, op_id));
+        assert!(history.accuracy(&receipt(op_id, 180_000)).is_none());
+    }
+
+    #[test]
+    fn test_record_mined_once() {
+        let history = EstimateHistory::default();
+        let op_id = id();
+        history.record(B256::random(), op_id, Address::ZERO, estimate(100_000));
+        assert!(history.has_estimate(Address::ZERO, op_id));
+
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let mined = receipt(op_id, 180_000);
+        metrics::with_local_recorder(&recorder, || {
+            history.record_mined(&mined);
+            history.record_mined(&mined);
+            history.record_mined(&receipt(id(), 1));
+        });
+
+        let rendered = recorder.handle().render();
+        assert!(rendered.contains("rpc_estimation_gas_delta_count 1"));
+        assert!(rendered.contains("rpc_estimation_gas_used_ratio_count 1"));
+    }
+}
@@ -11,20 +11,22 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{future::Future, pin::Pin};
+use std::{future::Future, pin::Pin, sync::Mutex, time::Duration};
 
 use alloy_primitives::{Address, B256, U64};
 use futures_util::future;
 use rundler_provider::StateOverride;
+use rundler_sim::RecentValidation;
 use rundler_types::{
     chain::ChainSpec, pool::Pool, UserOperation, UserOperationOptionalGas, UserOperationVariant,
 };
-use rundler_utils::log::LogOnError;
+use rundler_utils::{cache::LruMap, log::LogOnError};
 use tracing::Level;
 
 use super::{
     error::{EthResult, EthRpcError},
     router::EntryPointRouter,
+    sender_limit::SenderRateLimiter,
 };
 use crate::types::{RpcGasEstimate, RpcUserOperationByHash, RpcUserOperationReceipt};
 
@@ -33,32 +35,78 @@ use crate::types::{RpcGasEstimate, RpcUserOperationByHash, RpcUserOperationRecei
 pub struct Settings {
     /// The number of blocks to look back for user operation events
     pub user_operation_event_block_distance: Option<u64>,
+    /// The maximum number of blocks to query for user operation events in a single request
+    pub user_operation_event_max_block_range: u64,
+    /// The maximum number of call frames to search when tracing a transaction for a user operation
+    pub user_operation_trace_max_frames: usize,
+    /// The maximum call depth to search when tracing a transaction for a user operation
+    pub user_operation_trace_max_depth: usize,
+    /// The maximum number of user operations accepted from a sender within
+    /// `sender_rate_limit_window`, unlimited if unset
+    pub max_ops_per_sender: Option<usize>,
+    /// The sliding window over which `max_ops_per_sender` is enforced
+    pub sender_rate_limit_window: Duration,
+    /// The maximum number of user operation receipts to cache, caching is disabled if zero
+    pub receipt_cache_size: u32,
 }
 
 impl Settings {
     /// Create new settings for the `eth_` API
-    pub fn new(block_distance: Option<u64>) -> Self {
+    pub fn new(
+        block_distance: Option<u64>,
+        max_block_range: u64,
+        trace_max_frames: usize,
+        trace_max_depth: usize,
+        max_ops_per_sender: Option<usize>,
+        sender_rate_limit_window: Duration,
+        receipt_cache_size: u32,
+    ) -> Self {
         Self {
             user_operation_event_block_distance: block_distance,
+            user_operation_event_max_block_range: max_block_range,
+            user_operation_trace_max_frames: trace_max_frames,
+            user_operation_trace_max_depth: trace_max_depth,
+            max_ops_per_sender,
+            sender_rate_limit_window,
+            receipt_cache_size,
         }
     }
 }
 
+#[cfg(test)]
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new(None, 10_000, 10_000, 64, None, Duration::from_secs(60), 0)
+    }
+}
+
 pub(crate) struct EthApi<P> {
     pub(crate) chain_spec: ChainSpec,
     pool: P,
     router: EntryPointRouter,
+    sender_limiter: Option<SenderRateLimiter>,
+    receipt_cache: Option<Mutex<LruMap<B256, RpcUserOperationReceipt>>>,
 }
 
 impl<P> EthApi<P>
 where
     P: Pool,
 {
-    pub(crate) fn new(chain_spec: ChainSpec, router: EntryPointRouter, pool: P) -> Self {
+    pub(crate) fn new(
+        chain_spec: ChainSpec,
+        router: EntryPointRouter,
+        pool: P,
+        settings: Settings,
+    ) -> Self {
         Self {
             router,
             pool,
             chain_spec,
+            sender_limiter: settings
+                .max_ops_per_sender
+                .map(|max_ops| SenderRateLimiter::new(max_ops, settings.sender_rate_limit_window)),
+            receipt_cache: (settings.receipt_cache_size > 0)
+                .then(|| Mutex::new(LruMap::new(settings.receipt_cache_size))),
         }
     }
 
@@ -77,6 +125,16 @@ where
 
         self.router.check_and_get_route(&entry_point, &op)?;
 
+        if let Some(limiter) = &self.sender_limiter {
+            if !limiter.try_acquire(op.sender()) {
+                return Err(EthRpcError::SenderRateLimited(
+                    op.sender(),
+                    limiter.max_ops(),
+                    limiter.window().as_secs(),
+                ));
+            }
+        }
+
         self.pool
             .add_op(entry_point, op)
             .await
@@ -87,6 +145,7 @@ where
     pub(crate) async fn estimate_user_operation_gas(
         &self,
         op: UserOperationOptionalGas,
+        aggregator: Option<Address>,
         entry_point: Address,
         state_override: Option<StateOverride>,
     ) -> EthResult<RpcGasEstimate> {
@@ -98,9 +157,51 @@ where
             )));
         }
 
+        let hash = op.hash(entry_point, &self.chain_spec);
+        let id = op.id();
+        // An operation replacing one pending in the pool, differing only in its gas and fee
+        // fields, reuses the pool's latest validation of the pending operation for its
+        // verification gas limit. Any other difference, or a state override, may change the
+        // gas used by validation.
+        let pending = if state_override.is_none() {
+            self.pool
+                .get_op_by_id(entry_point, id)
+                .await
+                .log_on_error_level(Level::DEBUG, "failed to get op by id from the mempool")
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+        let validation = pending
+            .filter(|po| po.aggregator == aggregator && op.matches_ignoring_gas(&po.uo))
+            .map(|po| RecentValidation {
+                verification_gas_used: po.validation_gas_used,
+                block_number: po.validation_block_number,
+            });
+        let estimate = match validation {
+            Some(validation) => {
+                self.router
+                    .estimate_gas_with_validation(
+                        &entry_point,
+                        op,
+                        aggregator,
+                        state_override,
+                        validation,
+                    )
+                    .await?
+            }
+            None => {
+                self.router
+                    .estimate_gas(&entry_point, op, aggregator, state_override)
+                    .await?
+            }
+        }
+        .without_rounds();
         self.router
-            .estimate_gas(&entry_point, op, state_override)
-            .await
+            .estimate_history()
+            .record(hash, id, entry_point, estimate.clone());
+        Ok(estimate)
     }
 
     pub(crate) async fn get_user_operation_by_hash(
@@ -138,13 +239,29 @@ where
             ));
         }
 
+        if let Some(cache) = &self.receipt_cache {
+            if let Some(receipt) = cache.lock().unwrap().get(&hash) {
+                return Ok(Some(receipt.clone()));
+            }
+        }
+
         let futs = self
             .router
             .entry_points()
             .map(|ep| self.router.get_receipt(ep, hash));
 
         let results = future::try_join_all(futs).await?;
-        Ok(results.into_iter().find_map(|x| x))
+        let receipt = results.into_iter().find_map(|x| x);
+
+        // receipts of mined operations don't change, but only cache complete receipts, as
+        // the transaction receipt may not be available yet when the event is first indexed
+        if let (Some(cache), Some(receipt)) = (&self.receipt_cache, &receipt) {
+            if receipt.receipt.is_some() {
+                cache.lock().unwrap().insert(hash, receipt.clone());
+            }
+        }
+
+        Ok(receipt)
     }
 
     pub(crate) async fn supported_entry_points(&self) -> EthResult<Vec<String>> {
@@ -181,23 +298,31 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
 
-    use alloy_primitives::{Log as PrimitiveLog, LogData, U256};
+    use alloy_primitives::{Bytes, Log as PrimitiveLog, LogData, U128, U256};
     use alloy_sol_types::SolInterface;
     use mockall::predicate::eq;
     use rundler_contracts::v0_6::IEntryPoint::{handleOpsCall, IEntryPointCalls};
-    use rundler_provider::{Log, MockEntryPointV0_6, MockEvmProvider, Transaction};
+    use rundler_provider::{
+        Log, MockEntryPointV0_6, MockEvmProvider, Transaction, TransactionReceipt,
+        TransactionReceiptEnvelope, TransactionReceiptWithBloom, TransactionStatus,
+    };
     use rundler_sim::MockGasEstimator;
     use rundler_types::{
         pool::{MockPool, PoolOperation},
-        v0_6::UserOperation,
-        EntityInfos, UserOperation as UserOperationTrait, ValidTimeRange,
+        v0_6::{UserOperation, UserOperationOptionalGas as UserOperationOptionalGasV0_6},
+        AccountType, EntityInfos, GasEstimate, UserOperation as UserOperationTrait, ValidTimeRange,
+        Warning,
     };
 
     use super::*;
     use crate::eth::{
-        EntryPointRouteImpl, EntryPointRouterBuilder, UserOperationEventProviderV0_6,
+        events::UserOperationEventProvider, EntryPointRouteImpl, EntryPointRouterBuilder,
+        UserOperationEventProviderV0_6,
     };
 
     #[tokio::test]
@@ -214,6 +339,8 @@ mod tests {
             expected_code_hash: B256::random(),
             sim_block_hash: B256::random(),
             sim_block_number: 1000,
+            validation_gas_used: 0,
+            validation_block_number: 1000,
             account_is_staked: false,
             entity_infos: EntityInfos::default(),
             da_gas_data: rundler_types::da::DAGasUOData::Empty,
@@ -291,9 +418,17 @@ mod tests {
             .expect_get_logs()
             .returning(move |_| Ok(vec![log.clone()]));
         provider
-            .expect_get_transaction_by_hash()
+            .expect_get_transaction_with_status()
             .with(eq(tx_hash))
-            .returning(move |_| Ok(Some(tx.clone())));
+            .returning(move |_| {
+                Ok(Some((
+                    tx.clone(),
+                    TransactionStatus::Mined {
+                        block_number,
+                        block_hash,
+                    },
+                )))
+            });
 
         let mut entry_point = MockEntryPointV0_6::default();
         entry_point.expect_address().return_const(ep);
@@ -334,6 +469,426 @@ mod tests {
         assert_eq!(res, None);
     }
 
+    #[tokio::test]
+    async fn test_send_user_operation_sender_rate_limited() {
+        let cs = ChainSpec {
+            id: 1,
+            ..Default::default()
+        };
+        let ep = cs.entry_point_address_v0_6;
+        let sender = Address::random();
+
+        let mut pool = MockPool::default();
+        pool.expect_add_op()
+            .times(2)
+            .returning(|_, _| Ok(B256::random()));
+
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(ep);
+
+        let mut api = create_api(
+            MockEvmProvider::default(),
+            entry_point,
+            pool,
+            MockGasEstimator::default(),
+        );
+        api.sender_limiter = Some(SenderRateLimiter::new(2, Duration::from_secs(60)));
+
+        for nonce in 0..2 {
+            let uo = UserOperation {
+                sender,
+                nonce: U256::from(nonce),
+                ..Default::default()
+            };
+            api.send_user_operation(uo.into(), ep).await.unwrap();
+        }
+        let uo = UserOperation {
+            sender,
+            nonce: U256::from(2),
+            ..Default::default()
+        };
+        let res = api.send_user_operation(uo.into(), ep).await;
+        assert!(matches!(
+            res,
+            Err(EthRpcError::SenderRateLimited(s, 2, 60)) if s == sender
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_operation_gas_warnings() {
+        let ep = Address::random();
+        let warning = Warning::ShortDummySignature {
+            account_type: AccountType::Ecdsa,
+            actual: 0,
+            expected: 65,
+        };
+
+        let mut gas_estimator = MockGasEstimator::default();
+        let estimate_warning = warning.clone();
+        gas_estimator
+            .expect_estimate_op_gas()
+            .returning(move |_, _| {
+                Ok(GasEstimate {
+                    pre_verification_gas: 50_000,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    paymaster_verification_gas_limit: None,
+                    block_number: None,
+                    breakdown: None,
+                    verification_gas_rounds: None,
+                    call_gas_rounds: None,
+                    warnings: vec![estimate_warning.clone()],
+                })
+            });
+
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(ep);
+
+        let mut pool = MockPool::default();
+        pool.expect_get_op_by_id().returning(|_, _| Ok(None));
+
+        let api = create_api(MockEvmProvider::default(), entry_point, pool, gas_estimator);
+
+        let op = UserOperationOptionalGasV0_6 {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        };
+        let estimate = api
+            .estimate_user_operation_gas(UserOperationOptionalGas::V0_6(op), None, ep, None)
+            .await
+            .unwrap();
+
+        let response = serde_json::to_value(estimate).unwrap();
+        assert_eq!(
+            response["warnings"],
+            serde_json::json!([warning.to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_operation_gas_reuses_pending_validation() {
+        let ep = Address::random();
+        let sender = Address::random();
+        let pending = UserOperation {
+            sender,
+            nonce: U256::from(3),
+            ..Default::default()
+        };
+
+        let mut pool = MockPool::default();
+        pool.expect_get_op_by_id()
+            .with(eq(ep), eq(pending.id()))
+            .times(1)
+            .returning(move |_, _| {
+                Ok(Some(PoolOperation {
+                    uo: pending.clone().into(),
+                    entry_point: ep,
+                    aggregator: None,
+                    valid_time_range: ValidTimeRange::default(),
+                    expected_code_hash: B256::random(),
+                    sim_block_hash: B256::random(),
+                    sim_block_number: 1000,
+                    validation_gas_used: 30_000,
+                    validation_block_number: 1002,
+                    account_is_staked: false,
+                    entity_infos: EntityInfos::default(),
+                    da_gas_data: rundler_types::da::DAGasUOData::Empty,
+                }))
+            });
+
+        let mut gas_estimator = MockGasEstimator::default();
+        gas_estimator
+            .expect_estimate_op_gas_with_validation()
+            .withf(|_, _, validation| {
+                *validation
+                    == RecentValidation {
+                        verification_gas_used: 30_000,
+                        block_number: 1002,
+                    }
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(GasEstimate {
+                    pre_verification_gas: 50_000,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 45_000,
+                    paymaster_verification_gas_limit: None,
+                    block_number: None,
+                    breakdown: None,
+                    verification_gas_rounds: None,
+                    call_gas_rounds: None,
+                    warnings: vec![],
+                })
+            });
+
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(ep);
+
+        let api = create_api(MockEvmProvider::default(), entry_point, pool, gas_estimator);
+
+        let op = UserOperationOptionalGasV0_6 {
+            sender,
+            nonce: U256::from(3),
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        };
+        let estimate = api
+            .estimate_user_operation_gas(UserOperationOptionalGas::V0_6(op), None, ep, None)
+            .await
+            .unwrap();
+
+        let response = serde_json::to_value(estimate).unwrap();
+        assert_eq!(response["verificationGasLimit"], "0xafc8");
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_operation_gas_reestimates_differing_op() {
+        let ep = Address::random();
+        let sender = Address::random();
+        let pending = UserOperation {
+            sender,
+            nonce: U256::from(3),
+            call_data: Bytes::from_static(&[1, 2, 3]),
+            ..Default::default()
+        };
+
+        let mut pool = MockPool::default();
+        // not looked up when a state override is given
+        pool.expect_get_op_by_id()
+            .with(eq(ep), eq(pending.id()))
+            .times(1)
+            .returning(move |_, _| {
+                Ok(Some(PoolOperation {
+                    uo: pending.clone().into(),
+                    entry_point: ep,
+                    aggregator: None,
+                    valid_time_range: ValidTimeRange::default(),
+                    expected_code_hash: B256::random(),
+                    sim_block_hash: B256::random(),
+                    sim_block_number: 1000,
+                    validation_gas_used: 30_000,
+                    validation_block_number: 1002,
+                    account_is_staked: false,
+                    entity_infos: EntityInfos::default(),
+                    da_gas_data: rundler_types::da::DAGasUOData::Empty,
+                }))
+            });
+
+        let mut gas_estimator = MockGasEstimator::default();
+        gas_estimator
+            .expect_estimate_op_gas_with_validation()
+            .never();
+        gas_estimator
+            .expect_estimate_op_gas()
+            .times(2)
+            .returning(|_, _| {
+                Ok(GasEstimate {
+                    pre_verification_gas: 50_000,
+                    call_gas_limit: 100_000,
+                    verification_gas_limit: 100_000,
+                    paymaster_verification_gas_limit: None,
+                    block_number: None,
+                    breakdown: None,
+                    verification_gas_rounds: None,
+                    call_gas_rounds: None,
+                    warnings: vec![],
+                })
+            });
+
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(ep);
+
+        let api = create_api(MockEvmProvider::default(), entry_point, pool, gas_estimator);
+
+        let op = UserOperationOptionalGasV0_6 {
+            sender,
+            nonce: U256::from(3),
+            init_code: Bytes::new(),
+            call_data: Bytes::from_static(&[1, 2, 3]),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        };
+
+        // the replacement calls the account differently
+        let replacement = UserOperationOptionalGasV0_6 {
+            call_data: Bytes::from_static(&[4, 5, 6]),
+            ..op.clone()
+        };
+        api.estimate_user_operation_gas(
+            UserOperationOptionalGas::V0_6(replacement),
+            None,
+            ep,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // the replacement is estimated against overridden state
+        api.estimate_user_operation_gas(
+            UserOperationOptionalGas::V0_6(op),
+            None,
+            ep,
+            Some(StateOverride::default()),
+        )
+        .await
+        .unwrap();
+    }
+
+    struct CountingReceipts {
+        calls: Arc<AtomicUsize>,
+        receipt: Option<RpcUserOperationReceipt>,
+    }
+
+    #[async_trait::async_trait]
+    impl UserOperationEventProvider for CountingReceipts {
+        async fn get_mined_by_hash(
+            &self,
+            _hash: B256,
+        ) -> anyhow::Result<Option<RpcUserOperationByHash>> {
+            Ok(None)
+        }
+
+        async fn get_receipt(
+            &self,
+            _hash: B256,
+        ) -> anyhow::Result<Option<RpcUserOperationReceipt>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.receipt.clone())
+        }
+    }
+
+    fn create_receipt_api(
+        receipt: Option<RpcUserOperationReceipt>,
+        receipt_cache_size: u32,
+    ) -> (EthApi<MockPool>, Arc<AtomicUsize>) {
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(Address::random());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = EntryPointRouterBuilder::default()
+            .v0_6(EntryPointRouteImpl::<UserOperation, _, _, _>::new(
+                entry_point,
+                MockGasEstimator::default(),
+                CountingReceipts {
+                    calls: calls.clone(),
+                    receipt,
+                },
+            ))
+            .build();
+        let settings = Settings {
+            receipt_cache_size,
+            ..Default::default()
+        };
+
+        let api = EthApi::new(ChainSpec::default(), router, MockPool::default(), settings);
+        (api, calls)
+    }
+
+    fn mined_receipt(
+        hash: B256,
+        tx_receipt: Option<TransactionReceipt>,
+    ) -> RpcUserOperationReceipt {
+        RpcUserOperationReceipt {
+            user_op_hash: hash,
+            entry_point: Address::ZERO.into(),
+            sender: Address::ZERO.into(),
+            nonce: U256::ZERO,
+            paymaster: Address::ZERO.into(),
+            actual_gas_cost: U256::ZERO,
+            actual_gas_used: U128::ZERO,
+            success: true,
+            reason: String::new(),
+            aggregator: None,
+            logs: vec![],
+            transaction_hash: B256::ZERO,
+            receipt: tx_receipt,
+        }
+    }
+
+    fn tx_receipt() -> TransactionReceipt {
+        TransactionReceipt {
+            inner: TransactionReceiptEnvelope::Legacy(TransactionReceiptWithBloom::default()),
+            transaction_hash: B256::ZERO,
+            transaction_index: None,
+            block_hash: None,
+            block_number: None,
+            gas_used: 0,
+            effective_gas_price: 0,
+            blob_gas_used: None,
+            blob_gas_price: None,
+            from: Address::ZERO,
+            to: None,
+            contract_address: None,
+            state_root: None,
+            authorization_list: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_receipt_cached() {
+        let hash = B256::random();
+        let receipt = mined_receipt(hash, Some(tx_receipt()));
+        let (api, calls) = create_receipt_api(Some(receipt), 10);
+
+        for _ in 0..2 {
+            let res = api.get_user_operation_receipt(hash).await.unwrap();
+            assert_eq!(res.unwrap().user_op_hash, hash);
+        }
+        // the second query is served from the cache
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_receipt_not_cached() {
+        let hash = B256::random();
+
+        // missing receipts are not cached
+        let (api, calls) = create_receipt_api(None, 10);
+        for _ in 0..2 {
+            assert!(api
+                .get_user_operation_receipt(hash)
+                .await
+                .unwrap()
+                .is_none());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // nor are receipts without their transaction receipt
+        let (api, calls) = create_receipt_api(Some(mined_receipt(hash, None)), 10);
+        for _ in 0..2 {
+            api.get_user_operation_receipt(hash).await.unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // nor is anything when caching is disabled
+        let receipt = mined_receipt(hash, Some(tx_receipt()));
+        let (api, calls) = create_receipt_api(Some(receipt), 0);
+        for _ in 0..2 {
+            api.get_user_operation_receipt(hash).await.unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
     fn create_api(
         provider: MockEvmProvider,
         ep: MockEntryPointV0_6,
@@ -351,7 +906,11 @@ mod tests {
             .v0_6(EntryPointRouteImpl::new(
                 ep.clone(),
                 gas_estimator,
-                UserOperationEventProviderV0_6::new(chain_spec.clone(), provider.clone(), None),
+                UserOperationEventProviderV0_6::new(
+                    chain_spec.clone(),
+                    provider.clone(),
+                    Settings::default(),
+                ),
             ))
             .build();
 
@@ -359,6 +918,8 @@ mod tests {
             router,
             chain_spec,
             pool,
+            sender_limiter: None,
+            receipt_cache: None,
         }
     }
 }
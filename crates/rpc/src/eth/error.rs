@@ -14,7 +14,7 @@
 use std::fmt::Display;
 
 use alloy_json_rpc::RpcError;
-use alloy_primitives::{Address, Bytes, U128, U256, U32};
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U32};
 use jsonrpsee::types::{
     error::{CALL_EXECUTION_FAILED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
     ErrorObjectOwned,
@@ -46,6 +46,9 @@ const SIGNATURE_CHECK_FAILED_CODE: i32 = -32507;
 const PAYMASTER_DEPOSIT_TOO_LOW: i32 = -32508;
 const EXECUTION_REVERTED: i32 = -32521;
 
+// EIP-1474 error codes
+const RESOURCE_UNAVAILABLE_CODE: i32 = -32002;
+
 pub(crate) type EthResult<T> = Result<T, EthRpcError>;
 
 /// Error returned by the RPC server eth namespace
@@ -99,6 +102,9 @@ pub enum EthRpcError {
     /// Entity throttled or banned
     #[error("{} {:#032x} throttled or banned", .0.kind, .0.address)]
     ThrottledOrBanned(Entity),
+    /// Sender submitted too many user operations within the rate limit window
+    #[error("sender {0} exceeded {1} user operations per {2} seconds")]
+    SenderRateLimited(Address, usize, u64),
     /// Entity stake/unstake delay too low
     #[error("entity stake/unstake delay too low")]
     StakeTooLow(Box<StakeTooLowData>),
@@ -136,6 +142,8 @@ pub enum EthRpcError {
     ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData),
     #[error("operation rejected by mempool: {0}")]
     OperationRejected(String),
+    #[error("state for block {:?} is unavailable from the provider, it may have been pruned. Retry the request", .0.block_hash)]
+    StateUnavailable(StateUnavailableData),
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -265,6 +273,80 @@ pub struct ExecutionRevertedWithBytesData {
     pub revert_data: Bytes,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateUnavailableData {
+    pub block_hash: B256,
+}
+
+/// The precheck that rejected an operation along with the offending values.
+///
+/// For limit violations `actual` is the value provided by the operation and `limit` is the
+/// minimum or maximum value allowed. For contract checks `address` is the offending address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecheckFailedData {
+    pub precheck: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<U256>,
+}
+
+impl PrecheckFailedData {
+    fn with_address(precheck: &'static str, address: Address) -> Self {
+        Self {
+            precheck,
+            address: Some(address),
+            actual: None,
+            limit: None,
+        }
+    }
+
+    fn with_values(precheck: &'static str, actual: U256, limit: U256) -> Self {
+        Self {
+            precheck,
+            address: None,
+            actual: Some(actual),
+            limit: Some(limit),
+        }
+    }
+}
+
+impl From<&PrecheckViolation> for PrecheckFailedData {
+    fn from(value: &PrecheckViolation) -> Self {
+        let precheck = value.into();
+        match *value {
+            PrecheckViolation::SenderIsNotContractAndNoInitCode(address)
+            | PrecheckViolation::ExistingSenderWithInitCode(address)
+            | PrecheckViolation::FactoryIsNotContract(address)
+            | PrecheckViolation::PaymasterIsNotContract(address) => {
+                Self::with_address(precheck, address)
+            }
+            PrecheckViolation::TotalGasLimitTooHigh(actual, limit)
+            | PrecheckViolation::TotalOpGasTooHigh(actual, limit)
+            | PrecheckViolation::VerificationGasLimitTooHigh(actual, limit)
+            | PrecheckViolation::PaymasterVerificationGasLimitTooHigh(actual, limit)
+            | PrecheckViolation::PreVerificationGasTooLow(actual, limit)
+            | PrecheckViolation::MaxPriorityFeePerGasTooLow(actual, limit)
+            | PrecheckViolation::MaxFeePerGasTooLow(actual, limit)
+            | PrecheckViolation::MaxFeePerGasTooHigh(actual, limit)
+            | PrecheckViolation::CallGasLimitTooLow(actual, limit) => {
+                Self::with_values(precheck, U256::from(actual), U256::from(limit))
+            }
+            PrecheckViolation::CallDataTooLarge(actual, limit) => {
+                Self::with_values(precheck, U256::from(actual), U256::from(limit))
+            }
+            PrecheckViolation::PaymasterDepositTooLow(actual, limit)
+            | PrecheckViolation::SenderFundsTooLow(actual, limit) => {
+                Self::with_values(precheck, actual, limit)
+            }
+        }
+    }
+}
+
 impl From<PoolError> for EthRpcError {
     fn from(value: PoolError) -> Self {
         match value {
@@ -317,6 +399,8 @@ impl From<MempoolError> for EthRpcError {
             MempoolError::CallGasLimitEfficiencyTooLow(_, _) => {
                 Self::InvalidParams(value.to_string())
             }
+            MempoolError::PackedEncodingMismatch(_) => Self::InvalidParams(value.to_string()),
+            MempoolError::CallTargetNotAllowed(_) => Self::OperationRejected(value.to_string()),
         }
     }
 }
@@ -411,6 +495,7 @@ impl From<EthRpcError> for ErrorObjectOwned {
             EthRpcError::ThrottledOrBanned(data) => {
                 rpc_err_with_data(THROTTLED_OR_BANNED_CODE, msg, data)
             }
+            EthRpcError::SenderRateLimited(_, _, _) => rpc_err(THROTTLED_OR_BANNED_CODE, msg),
             EthRpcError::StakeTooLow(data) => rpc_err_with_data(OPCODE_VIOLATION_CODE, msg, data),
             EthRpcError::UnsupportedAggregator(data) => {
                 rpc_err_with_data(UNSUPORTED_AGGREGATOR_CODE, msg, data)
@@ -425,7 +510,11 @@ impl From<EthRpcError> for ErrorObjectOwned {
             | EthRpcError::PaymasterSignatureCheckFailed => {
                 rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg)
             }
-            EthRpcError::PrecheckFailed(_) => rpc_err(CALL_EXECUTION_FAILED_CODE, msg),
+            EthRpcError::PrecheckFailed(violation) => rpc_err_with_data(
+                CALL_EXECUTION_FAILED_CODE,
+                msg,
+                PrecheckFailedData::from(&violation),
+            ),
             EthRpcError::ExecutionReverted(_) => rpc_err(EXECUTION_REVERTED, msg),
             EthRpcError::ExecutionRevertedWithBytes(data) => {
                 rpc_err_with_data(EXECUTION_REVERTED, msg, data)
@@ -434,6 +523,9 @@ impl From<EthRpcError> for ErrorObjectOwned {
                 rpc_err_with_data(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg, data)
             }
             EthRpcError::OperationRejected(_) => rpc_err(INVALID_PARAMS_CODE, msg),
+            EthRpcError::StateUnavailable(data) => {
+                rpc_err_with_data(RESOURCE_UNAVAILABLE_CODE, msg, data)
+            }
         }
     }
 }
@@ -511,15 +603,28 @@ impl From<GasEstimationError> for EthRpcError {
             GasEstimationError::RevertInCallWithBytes(b) => {
                 Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData { revert_data: b })
             }
+            error @ GasEstimationError::CallGasLimitTooLow(_) => {
+                Self::ExecutionReverted(error.to_string())
+            }
             error @ GasEstimationError::GasUsedTooLarge => {
                 Self::EntryPointValidationRejected(error.to_string())
             }
             error @ GasEstimationError::GasTotalTooLarge(_, _) => {
                 Self::InvalidParams(error.to_string())
             }
+            error @ GasEstimationError::PreVerificationGasTooHigh(_, _) => {
+                Self::InvalidParams(error.to_string())
+            }
             error @ GasEstimationError::GasFieldTooLarge(_, _) => {
                 Self::InvalidParams(error.to_string())
             }
+            error @ GasEstimationError::EmptySignature => Self::InvalidParams(error.to_string()),
+            error @ GasEstimationError::UnexpectedSimulationSuccess(_) => {
+                Self::Internal(anyhow::anyhow!(error.to_string()))
+            }
+            GasEstimationError::PrunedState(block_hash) => {
+                Self::StateUnavailable(StateUnavailableData { block_hash })
+            }
             GasEstimationError::ProviderError(provider_error) => {
                 EthRpcError::from(ProviderErrorWithContext::from(provider_error))
             }
@@ -536,3 +641,137 @@ impl From<GasEstimationError> for EthRpcError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precheck_data(violation: PrecheckViolation) -> PrecheckFailedData {
+        PrecheckFailedData::from(&violation)
+    }
+
+    #[test]
+    fn test_precheck_failed_address_data() {
+        let address = Address::random();
+        for (violation, precheck) in [
+            (
+                PrecheckViolation::SenderIsNotContractAndNoInitCode(address),
+                "SenderIsNotContractAndNoInitCode",
+            ),
+            (
+                PrecheckViolation::ExistingSenderWithInitCode(address),
+                "ExistingSenderWithInitCode",
+            ),
+            (
+                PrecheckViolation::FactoryIsNotContract(address),
+                "FactoryIsNotContract",
+            ),
+            (
+                PrecheckViolation::PaymasterIsNotContract(address),
+                "PaymasterIsNotContract",
+            ),
+        ] {
+            assert_eq!(
+                precheck_data(violation),
+                PrecheckFailedData::with_address(precheck, address)
+            );
+        }
+    }
+
+    #[test]
+    fn test_precheck_failed_value_data() {
+        let actual = U256::from(1_000);
+        let limit = U256::from(2_000);
+        for (violation, precheck) in [
+            (
+                PrecheckViolation::TotalGasLimitTooHigh(1_000, 2_000),
+                "TotalGasLimitTooHigh",
+            ),
+            (
+                PrecheckViolation::TotalOpGasTooHigh(1_000, 2_000),
+                "TotalOpGasTooHigh",
+            ),
+            (
+                PrecheckViolation::VerificationGasLimitTooHigh(1_000, 2_000),
+                "VerificationGasLimitTooHigh",
+            ),
+            (
+                PrecheckViolation::PaymasterVerificationGasLimitTooHigh(1_000, 2_000),
+                "PaymasterVerificationGasLimitTooHigh",
+            ),
+            (
+                PrecheckViolation::PreVerificationGasTooLow(1_000, 2_000),
+                "PreVerificationGasTooLow",
+            ),
+            (
+                PrecheckViolation::PaymasterDepositTooLow(actual, limit),
+                "PaymasterDepositTooLow",
+            ),
+            (
+                PrecheckViolation::SenderFundsTooLow(actual, limit),
+                "SenderFundsTooLow",
+            ),
+            (
+                PrecheckViolation::MaxPriorityFeePerGasTooLow(1_000, 2_000),
+                "MaxPriorityFeePerGasTooLow",
+            ),
+            (
+                PrecheckViolation::MaxFeePerGasTooLow(1_000, 2_000),
+                "MaxFeePerGasTooLow",
+            ),
+            (
+                PrecheckViolation::MaxFeePerGasTooHigh(1_000, 2_000),
+                "MaxFeePerGasTooHigh",
+            ),
+            (
+                PrecheckViolation::CallGasLimitTooLow(1_000, 2_000),
+                "CallGasLimitTooLow",
+            ),
+            (
+                PrecheckViolation::CallDataTooLarge(1_000, 2_000),
+                "CallDataTooLarge",
+            ),
+        ] {
+            assert_eq!(
+                precheck_data(violation),
+                PrecheckFailedData::with_values(precheck, actual, limit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_precheck_failed_rpc_error() {
+        let error: ErrorObjectOwned =
+            EthRpcError::from(PrecheckViolation::MaxFeePerGasTooLow(1_000, 2_000)).into();
+        assert_eq!(error.code(), CALL_EXECUTION_FAILED_CODE);
+        assert_eq!(
+            error.message(),
+            "precheck failed: maxFeePerGas is 1000 but must be at least 2000"
+        );
+        assert_eq!(
+            error.data().unwrap().get(),
+            r#"{"precheck":"MaxFeePerGasTooLow","actual":"0x3e8","limit":"0x7d0"}"#
+        );
+    }
+
+    #[test]
+    fn test_pruned_state_rpc_error() {
+        let block_hash = B256::repeat_byte(1);
+        let error: ErrorObjectOwned =
+            EthRpcError::from(GasEstimationError::PrunedState(block_hash)).into();
+        assert_eq!(error.code(), RESOURCE_UNAVAILABLE_CODE);
+        assert_eq!(
+            error.data().unwrap().get(),
+            format!(r#"{{"blockHash":"{block_hash}"}}"#)
+        );
+    }
+
+    #[test]
+    fn test_unexpected_simulation_success_rpc_error() {
+        let error: ErrorObjectOwned = EthRpcError::from(
+            GasEstimationError::UnexpectedSimulationSuccess(Address::ZERO),
+        )
+        .into();
+        assert_eq!(error.code(), INTERNAL_ERROR_CODE);
+    }
+}
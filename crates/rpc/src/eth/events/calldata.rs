@@ -0,0 +1,275 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Bytes, FixedBytes};
+use alloy_sol_types::SolCall;
+use rundler_contracts::{
+    v0_6::IEntryPoint::{
+        handleAggregatedOpsCall as HandleAggregatedOpsCallV0_6, handleOpsCall as HandleOpsCallV0_6,
+    },
+    v0_7::IEntryPoint::{
+        handleAggregatedOpsCall as HandleAggregatedOpsCallV0_7, handleOpsCall as HandleOpsCallV0_7,
+    },
+};
+use rundler_types::{chain::ChainSpec, UserOperationVariant};
+
+use super::{common::EntryPointEvents, v0_6::EntryPointFiltersV0_6, v0_7::EntryPointFiltersV0_7};
+
+/// Error decoding user operations from entry point calldata
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    /// Calldata is too short to contain a function selector
+    #[error("calldata is too short to contain a function selector")]
+    MissingSelector,
+    /// Selector is not a `handleOps` or `handleAggregatedOps` selector of a supported entry point version
+    #[error("unsupported entry point function selector {0}")]
+    UnknownSelector(FixedBytes<4>),
+}
+
+/// Decodes the user operations from the calldata of an entry point `handleOps` or
+/// `handleAggregatedOps` call.
+///
+/// Both v0.6 and v0.7 entry point selectors are supported, and the returned operations
+/// are tagged with the version matching the selector. Operations from `handleAggregatedOps`
+/// are flattened across aggregator groups, in calldata order. Operations are decoded the same
+/// way as when looking up a mined operation, so calldata that fails to ABI decode yields no
+/// operations, and operations with invalid fields are skipped.
+pub fn decode_handle_ops_calldata(
+    data: &Bytes,
+    chain_spec: &ChainSpec,
+) -> Result<Vec<UserOperationVariant>, DecodeError> {
+    if data.len() < 4 {
+        return Err(DecodeError::MissingSelector);
+    }
+    let selector: [u8; 4] = data[..4].try_into().expect("slice is 4 bytes");
+
+    match selector {
+        HandleOpsCallV0_6::SELECTOR | HandleAggregatedOpsCallV0_6::SELECTOR => {
+            Ok(decode_with::<EntryPointFiltersV0_6>(
+                data.clone(),
+                chain_spec,
+            ))
+        }
+        HandleOpsCallV0_7::SELECTOR | HandleAggregatedOpsCallV0_7::SELECTOR => {
+            Ok(decode_with::<EntryPointFiltersV0_7>(
+                data.clone(),
+                chain_spec,
+            ))
+        }
+        _ => Err(DecodeError::UnknownSelector(selector.into())),
+    }
+}
+
+fn decode_with<E: EntryPointEvents>(
+    data: Bytes,
+    chain_spec: &ChainSpec,
+) -> Vec<UserOperationVariant> {
+    E::get_user_operations_from_tx_data(data, chain_spec)
+        .into_iter()
+        .map(Into::into)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, b256, bytes, uint, Address, U256};
+    use rundler_contracts::{
+        v0_6::{IEntryPoint::balanceOfCall, UserOpsPerAggregator as UserOpsPerAggregatorV0_6},
+        v0_7::{PackedUserOperation, UserOpsPerAggregator as UserOpsPerAggregatorV0_7},
+    };
+    use rundler_types::{v0_6, UserOperation};
+
+    use super::*;
+
+    const BENEFICIARY: Address = address!("1111111111111111111111111111111111111111");
+
+    // Operation hash verified against the entrypoint contract getUserOpHash() function with
+    // entrypoint address at 0x66a15edcc3b50a663e72f1457ffd49b9ae284ddc and chain ID 1337.
+    fn verified_op_v0_6() -> v0_6::UserOperation {
+        v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: address!("1306b01bc3e4ad202612d3843387e94737673f53"),
+                nonce: U256::from(8942),
+                init_code: bytes!("6942069420694206942069420694206942069420"),
+                call_data: bytes!("0000000000000000000000000000000000000000080085"),
+                call_gas_limit: 10_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 100,
+                max_fee_per_gas: 99_999,
+                max_priority_fee_per_gas: 9_999_999,
+                paymaster_and_data: bytes!(
+                    "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+                ),
+                signature: bytes!("da0929f527cded8d0a1eaf2e8861d7f7e2d8160b7b13942f99dd367df4473a"),
+            },
+        )
+        .build()
+    }
+
+    // From https://sepolia.etherscan.io/tx/0x51c1f40ce6e997a54b39a0eb783e472c2afa4ed3f2f11f97986f7f3a347b9d50
+    fn sepolia_op_v0_7() -> PackedUserOperation {
+        PackedUserOperation {
+            sender: address!("b292Cf4a8E1fF21Ac27C4f94071Cd02C022C414b"),
+            nonce: uint!(0xF83D07238A7C8814A48535035602123AD6DBFA63000000000000000000000001_U256),
+            initCode: Bytes::default(),
+            callData: bytes!("e9ae5c530000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001d8b292cf4a8e1ff21ac27c4f94071cd02c022c414b00000000000000000000000000000000000000000000000000000000000000009517e29f0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000ad6330089d9a1fe89f4020292e1afe9969a5a2fc00000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000120000000000000000000000000000000000000000000000000000000000001518000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000018e2fbe8980000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000800000000000000000000000002372912728f93ab3daaaebea4f87e6e28476d987000000000000000000000000000000000000000000000000002386f26fc10000000000000000000000000000000000000000000000000000000000000000006000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+            accountGasLimits: b256!("000000000000000000000000000114fc0000000000000000000000000012c9b5"),
+            preVerificationGas: U256::from(48916),
+            gasFees: b256!("000000000000000000000000524121000000000000000000000000109a4a441a"),
+            paymasterAndData: Bytes::default(),
+            signature: bytes!("3c7bfe22c9c2ef8994a9637bcc4df1741c5dc0c25b209545a7aeb20f7770f351479b683bd17c4d55bc32e2a649c8d2dff49dcfcc1f3fd837bcd88d1e69a434cf1c"),
+        }
+    }
+
+    fn sepolia_chain_spec() -> ChainSpec {
+        ChainSpec {
+            id: 11155111,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_handle_ops_v0_6() {
+        let cs = ChainSpec::default();
+        let data: Bytes = HandleOpsCallV0_6 {
+            ops: vec![verified_op_v0_6().into()],
+            beneficiary: BENEFICIARY,
+        }
+        .abi_encode()
+        .into();
+
+        let decoded = decode_handle_ops_calldata(&data, &cs).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert!(matches!(decoded[0], UserOperationVariant::V0_6(_)));
+        assert_eq!(
+            decoded[0].hash(address!("66a15edcc3b50a663e72f1457ffd49b9ae284ddc"), 1337),
+            b256!("484add9e4d8c3172d11b5feb6a3cc712280e176d278027cfa02ee396eb28afa1")
+        );
+    }
+
+    #[test]
+    fn test_decode_handle_ops_v0_7() {
+        let cs = sepolia_chain_spec();
+        let data: Bytes = HandleOpsCallV0_7 {
+            ops: vec![sepolia_op_v0_7()],
+            beneficiary: BENEFICIARY,
+        }
+        .abi_encode()
+        .into();
+
+        let decoded = decode_handle_ops_calldata(&data, &cs).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert!(matches!(decoded[0], UserOperationVariant::V0_7(_)));
+        assert_eq!(
+            decoded[0].hash(cs.entry_point_address_v0_7, cs.id),
+            b256!("e486401370d145766c3cf7ba089553214a1230d38662ae532c9b62eb6dadcf7e")
+        );
+    }
+
+    #[test]
+    fn test_decode_handle_aggregated_ops_flattens_groups() {
+        let cs = ChainSpec::default();
+        let other_op_v0_6 = v0_6::UserOperation {
+            nonce: U256::from(8943),
+            ..verified_op_v0_6()
+        };
+        let v0_6_data: Bytes = HandleAggregatedOpsCallV0_6 {
+            opsPerAggregator: vec![
+                UserOpsPerAggregatorV0_6 {
+                    userOps: vec![verified_op_v0_6().into()],
+                    aggregator: Address::random(),
+                    signature: Bytes::new(),
+                },
+                UserOpsPerAggregatorV0_6 {
+                    userOps: vec![other_op_v0_6.clone().into()],
+                    aggregator: Address::random(),
+                    signature: Bytes::new(),
+                },
+            ],
+            beneficiary: BENEFICIARY,
+        }
+        .abi_encode()
+        .into();
+
+        let decoded = decode_handle_ops_calldata(&v0_6_data, &cs).unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                UserOperationVariant::from(verified_op_v0_6()),
+                UserOperationVariant::from(other_op_v0_6)
+            ]
+        );
+
+        let cs = sepolia_chain_spec();
+        let v0_7_data: Bytes = HandleAggregatedOpsCallV0_7 {
+            opsPerAggregator: vec![
+                UserOpsPerAggregatorV0_7 {
+                    userOps: vec![sepolia_op_v0_7()],
+                    aggregator: Address::random(),
+                    signature: Bytes::new(),
+                },
+                UserOpsPerAggregatorV0_7 {
+                    userOps: vec![sepolia_op_v0_7()],
+                    aggregator: Address::random(),
+                    signature: Bytes::new(),
+                },
+            ],
+            beneficiary: BENEFICIARY,
+        }
+        .abi_encode()
+        .into();
+
+        let decoded = decode_handle_ops_calldata(&v0_7_data, &cs).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded
+            .iter()
+            .all(|op| op.hash(cs.entry_point_address_v0_7, cs.id)
+                == b256!("e486401370d145766c3cf7ba089553214a1230d38662ae532c9b62eb6dadcf7e")));
+    }
+
+    #[test]
+    fn test_decode_rejects_other_calls() {
+        let cs = ChainSpec::default();
+        let data: Bytes = balanceOfCall {
+            account: Address::random(),
+        }
+        .abi_encode()
+        .into();
+
+        assert!(matches!(
+            decode_handle_ops_calldata(&data, &cs),
+            Err(DecodeError::UnknownSelector(s)) if s == FixedBytes::from(balanceOfCall::SELECTOR)
+        ));
+        assert!(matches!(
+            decode_handle_ops_calldata(&bytes!("1fad94"), &cs),
+            Err(DecodeError::MissingSelector)
+        ));
+    }
+
+    #[test]
+    fn test_decode_truncated_calldata_yields_no_operations() {
+        let cs = ChainSpec::default();
+        let data = HandleOpsCallV0_6 {
+            ops: vec![verified_op_v0_6().into()],
+            beneficiary: BENEFICIARY,
+        }
+        .abi_encode();
+
+        assert!(
+            decode_handle_ops_calldata(&data[..data.len() - 32].to_vec().into(), &cs)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -11,29 +11,57 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::VecDeque, marker::PhantomData};
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use alloy_primitives::{Address, Bytes, B256, U256};
 use alloy_sol_types::SolEvent;
-use anyhow::Context;
+use anyhow::{bail, Context};
+use futures_util::future;
 use rundler_provider::{
     EvmProvider, Filter, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingOptions,
-    GethTrace, Log, TransactionReceipt,
+    GethTrace, Log, TransactionReceipt, TransactionStatus,
 };
 use rundler_types::{chain::ChainSpec, UserOperation, UserOperationVariant};
-use rundler_utils::log::LogOnError;
+use rundler_utils::{cache::LruMap, log::LogOnError};
 
 use super::UserOperationEventProvider;
-use crate::types::{RpcUserOperationByHash, RpcUserOperationReceipt};
+use crate::{
+    eth::EthApiSettings,
+    types::{RpcUserOperationByHash, RpcUserOperationReceipt},
+};
+
+/// How long a user operation hash whose event wasn't found is remembered. Repeat lookups
+/// within this window only scan blocks newer than the previous scan.
+const NOT_FOUND_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Maximum number of not found user operation hashes to remember
+const NOT_FOUND_CACHE_SIZE: u32 = 1024;
+/// Number of most recent blocks that are rescanned on repeat lookups of a not found hash,
+/// as the node's log index may lag behind its latest block
+const NOT_FOUND_RESCAN_BLOCKS: u64 = 5;
 
 #[derive(Debug)]
 pub(crate) struct UserOperationEventProviderImpl<P, F> {
     chain_spec: ChainSpec,
     provider: P,
-    event_block_distance: Option<u64>,
+    settings: EthApiSettings,
+    not_found_cache: Mutex<LruMap<B256, NotFoundEntry>>,
     _f_type: PhantomData<F>,
 }
 
+/// A user operation hash whose event was not found when last scanned
+#[derive(Debug, Clone, Copy)]
+struct NotFoundEntry {
+    /// Latest block included in the scans so far
+    scanned_to_block: u64,
+    /// Time of the first scan, entries expire relative to this so that blocks are eventually rescanned
+    first_scanned_at: Instant,
+}
+
 pub(crate) trait EntryPointEvents: Send + Sync {
     type UO: UserOperation + Into<UserOperationVariant>;
     type UserOperationEvent: SolEvent;
@@ -43,11 +71,25 @@ pub(crate) trait EntryPointEvents: Send + Sync {
         event: Self::UserOperationEvent,
         hash: B256,
         entry_point: Address,
+        tx_hash: B256,
+        aggregator: Option<Address>,
         logs: Vec<Log>,
-        tx_receipt: TransactionReceipt,
+        tx_receipt: Option<TransactionReceipt>,
     ) -> RpcUserOperationReceipt;
 
-    fn get_user_operations_from_tx_data(tx_data: Bytes, chain_spec: &ChainSpec) -> Vec<Self::UO>;
+    /// Decodes the user operations from `handleOps` or `handleAggregatedOps` calldata, each
+    /// paired with the aggregator that validated it, if any
+    fn get_user_operations_with_aggregator_from_tx_data(
+        tx_data: Bytes,
+        chain_spec: &ChainSpec,
+    ) -> Vec<(Self::UO, Option<Address>)>;
+
+    fn get_user_operations_from_tx_data(tx_data: Bytes, chain_spec: &ChainSpec) -> Vec<Self::UO> {
+        Self::get_user_operations_with_aggregator_from_tx_data(tx_data, chain_spec)
+            .into_iter()
+            .map(|(uo, _)| uo)
+            .collect()
+    }
 
     fn address(chain_spec: &ChainSpec) -> Address;
 }
@@ -75,17 +117,21 @@ where
             .transaction_hash
             .context("tx_hash should be present")?;
 
-        let tx = self
+        let (tx, status) = self
             .provider
-            .get_transaction_by_hash(transaction_hash)
+            .get_transaction_with_status(transaction_hash)
             .await
             .context("should have fetched tx from provider")?
             .context("should have found tx")?;
 
         // We should return null if the tx isn't included in the block yet
-        if tx.block_hash.is_none() && tx.block_number.is_none() {
+        let TransactionStatus::Mined {
+            block_number,
+            block_hash,
+        } = status
+        else {
             return Ok(None);
-        }
+        };
         let to = tx
             .to
             .context("tx.to should be present on transaction containing user operation event")?;
@@ -101,13 +147,14 @@ where
                 .await
                 .context("error running trace")?
                 .context("should have found user operation in trace")?
+                .0
         };
 
         Ok(Some(RpcUserOperationByHash {
             user_operation: user_operation.into().into(),
             entry_point: event.address().into(),
-            block_number: Some(tx.block_number.map(|n| U256::from(n)).unwrap_or_default()),
-            block_hash: Some(tx.block_hash.unwrap_or_default()),
+            block_number: Some(U256::from(block_number)),
+            block_hash: Some(block_hash),
             transaction_hash: Some(transaction_hash),
         }))
     }
@@ -125,17 +172,37 @@ where
             .transaction_hash
             .context("tx_hash should be present")?;
 
-        // get transaction receipt
-        let tx_receipt = self
-            .provider
-            .get_transaction_receipt(tx_hash)
-            .await
-            .context("should have fetched tx receipt")?
-            .context("Failed to fetch tx receipt")?;
+        // get transaction receipt, this may not be available yet if the node has indexed
+        // the event but not the receipt
+        let (tx_receipt, tx) = future::join(
+            self.provider.get_transaction_receipt(tx_hash),
+            self.provider.get_transaction_by_hash(tx_hash),
+        )
+        .await;
+        let tx_receipt = tx_receipt.context("should have fetched tx receipt")?;
+        // the transaction is only needed to find the aggregator, don't fail the receipt on it
+        let tx = tx.unwrap_or_else(|e| {
+            tracing::warn!("failed to fetch tx {tx_hash} for user operation receipt: {e:?}");
+            None
+        });
+
+        // find the aggregator from the calldata, only available if the transaction
+        // called the entry point directly
+        let aggregator = tx
+            .filter(|tx| tx.to == Some(entry_point))
+            .and_then(|tx| {
+                E::get_user_operations_with_aggregator_from_tx_data(tx.input, &self.chain_spec)
+                    .into_iter()
+                    .find(|(op, _)| op.hash(entry_point, self.chain_spec.id) == hash)
+            })
+            .and_then(|(_, aggregator)| aggregator);
 
         // filter receipt logs
-        let filtered_logs = super::filter_receipt_logs_matching_user_op(&event, &tx_receipt)
-            .context("should have found receipt logs matching user op")?;
+        let filtered_logs = match &tx_receipt {
+            Some(tx_receipt) => super::filter_receipt_logs_matching_user_op(&event, tx_receipt)
+                .context("should have found receipt logs matching user op")?,
+            None => vec![],
+        };
 
         // decode uo event
         let uo_event = self
@@ -146,6 +213,8 @@ where
             uo_event,
             hash,
             entry_point,
+            tx_hash,
+            aggregator,
             filtered_logs,
             tx_receipt,
         )))
@@ -157,15 +226,12 @@ where
     P: EvmProvider,
     E: EntryPointEvents,
 {
-    pub(crate) fn new(
-        chain_spec: ChainSpec,
-        provider: P,
-        event_block_distance: Option<u64>,
-    ) -> Self {
+    pub(crate) fn new(chain_spec: ChainSpec, provider: P, settings: EthApiSettings) -> Self {
         Self {
             chain_spec,
             provider,
-            event_block_distance,
+            settings,
+            not_found_cache: Mutex::new(LruMap::new(NOT_FOUND_CACHE_SIZE)),
             _f_type: PhantomData,
         }
     }
@@ -173,20 +239,111 @@ where
     async fn get_event_by_hash(&self, hash: B256) -> anyhow::Result<Option<Log>> {
         let to_block = self.provider.get_block_number().await?;
 
-        let from_block = match self.event_block_distance {
+        let mut from_block = match self.settings.user_operation_event_block_distance {
             Some(distance) => to_block.saturating_sub(distance),
             None => 0,
         };
 
-        let filter = Filter::new()
-            .address(E::address(&self.chain_spec))
-            .event_signature(E::UserOperationEvent::SIGNATURE_HASH)
-            .from_block(from_block)
-            .to_block(to_block)
-            .topic1(hash);
+        // If this hash was recently not found, only scan the blocks mined since, plus the
+        // most recent blocks of the previous scan whose logs may not have been indexed yet
+        let not_found = self.get_not_found(hash);
+        if let Some(entry) = not_found {
+            from_block = from_block.max(entry.scanned_to_block.saturating_add(1));
+        }
+        if from_block > to_block {
+            return Ok(None);
+        }
 
-        let logs = self.provider.get_logs(&filter).await?;
-        Ok(logs.into_iter().next())
+        let log = self.scan_for_event(hash, from_block, to_block).await?;
+
+        let mut cache = self.not_found_cache.lock().unwrap();
+        if log.is_some() {
+            cache.remove(&hash);
+        } else {
+            cache.insert(
+                hash,
+                NotFoundEntry {
+                    scanned_to_block: to_block.saturating_sub(NOT_FOUND_RESCAN_BLOCKS),
+                    first_scanned_at: not_found
+                        .map_or_else(Instant::now, |entry| entry.first_scanned_at),
+                },
+            );
+        }
+
+        Ok(log)
+    }
+
+    fn get_not_found(&self, hash: B256) -> Option<NotFoundEntry> {
+        let mut cache = self.not_found_cache.lock().unwrap();
+        let entry = *cache.get(&hash)?;
+        if entry.first_scanned_at.elapsed() >= NOT_FOUND_CACHE_TTL {
+            cache.remove(&hash);
+            return None;
+        }
+        Some(entry)
+    }
+
+    async fn scan_for_event(
+        &self,
+        hash: B256,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Option<Log>> {
+        // Providers commonly cap the block range of a single `eth_getLogs` call, so walk
+        // the range in windows of at most `max_block_range` blocks, newest first, since
+        // recently mined operations are the most likely to be queried.
+        let window = self.settings.user_operation_event_max_block_range.max(1);
+        let mut window_end = to_block;
+        loop {
+            let window_start = window_end.saturating_sub(window - 1).max(from_block);
+
+            // Not filtered by address, as operations relayed through a proxy that
+            // delegatecalls into the entry point's code emit their events from the proxy
+            let filter = Filter::new()
+                .event_signature(E::UserOperationEvent::SIGNATURE_HASH)
+                .from_block(window_start)
+                .to_block(window_end)
+                .topic1(hash);
+
+            let logs = self.provider.get_logs(&filter).await?;
+            if let Some(log) = self.select_event_log(hash, logs).await {
+                return Ok(Some(log));
+            }
+
+            if window_start <= from_block {
+                return Ok(None);
+            }
+            window_end = window_start - 1;
+        }
+    }
+
+    /// Selects the user operation event among logs matching the event signature and hash.
+    ///
+    /// Events emitted by the entry point are preferred. Events emitted by any other address
+    /// are only trusted if tracing their transaction shows the operation was executed by that
+    /// address delegatecalling into the entry point's code.
+    async fn select_event_log(&self, hash: B256, logs: Vec<Log>) -> Option<Log> {
+        let entry_point = E::address(&self.chain_spec);
+        let (direct, delegated): (Vec<_>, Vec<_>) = logs
+            .into_iter()
+            .partition(|log| log.address() == entry_point);
+        if let Some(log) = direct.into_iter().next() {
+            return Some(log);
+        }
+
+        for log in delegated {
+            let Some(tx_hash) = log.transaction_hash else {
+                continue;
+            };
+            match self.trace_find_user_operation(tx_hash, hash).await {
+                Ok(Some((_, executed_by))) if executed_by == log.address() => return Some(log),
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("failed to trace tx {tx_hash} for user operation event: {e:?}")
+                }
+            }
+        }
+        None
     }
 
     fn decode_user_operation_event(&self, log: Log) -> anyhow::Result<E::UserOperationEvent> {
@@ -195,7 +352,8 @@ where
             .context("log should be a user operation event")
     }
 
-    /// This method takes a transaction hash and a user operation hash and returns the full user operation if it exists.
+    /// This method takes a transaction hash and a user operation hash and returns the full user operation if it exists,
+    /// along with the address that executed the entry point's code for it.
     /// This is meant to be used when a user operation event is found in the logs of a transaction, but the top level call
     /// wasn't to an entrypoint, so we need to trace the transaction to find the user operation by inspecting each call frame
     /// and returning the user operation that matches the hash.
@@ -203,7 +361,7 @@ where
         &self,
         tx_hash: B256,
         user_op_hash: B256,
-    ) -> anyhow::Result<Option<E::UO>> {
+    ) -> anyhow::Result<Option<(E::UO, Address)>> {
         // initial call wasn't to an entrypoint, so we need to trace the transaction to find the user operation
         let trace_options = GethDebugTracingOptions {
             tracer: Some(GethDebugTracerType::BuiltInTracer(
@@ -217,32 +375,667 @@ where
             .await
             .context("should have fetched trace from provider")?;
 
-        // breadth first search for the user operation in the trace
+        // breadth first search for the user operation in the trace, bounded so that deeply
+        // nested or very wide traces can't exhaust memory
+        let max_frames = self.settings.user_operation_trace_max_frames;
+        let max_depth = self.settings.user_operation_trace_max_depth;
         let mut frame_queue = VecDeque::new();
+        let mut frames_seen = 0;
 
         if let GethTrace::CallTracer(call_frame) = trace {
-            frame_queue.push_back(call_frame);
+            frame_queue.push_back((call_frame, 0));
+            frames_seen += 1;
         }
 
-        while let Some(call_frame) = frame_queue.pop_front() {
-            // check if the call is to an entrypoint, if not enqueue the child calls if any
-            if let Some(to) = call_frame
-                .to
-                .filter(|to| *to == E::address(&self.chain_spec))
-            {
-                // check if the user operation is in the call frame
+        let entry_point = E::address(&self.chain_spec);
+        let mut entry_point_code_hash = None;
+        while let Some((call_frame, depth)) = frame_queue.pop_front() {
+            // the address the entry point's code executes as in this frame, if any
+            let executed_by = match call_frame.to {
+                // relayers may route `handleOps` through a proxy that delegatecalls into the
+                // entry point's code, in which case the code executes as the proxy
+                Some(to) if call_frame.typ.eq_ignore_ascii_case("DELEGATECALL") => self
+                    .has_entry_point_code(to, &mut entry_point_code_hash)
+                    .await?
+                    .then_some(call_frame.from),
+                Some(to) if to == entry_point => Some(entry_point),
+                _ => None,
+            };
+
+            // check if the user operation is in the call frame
+            if let Some(executed_by) = executed_by {
                 if let Some(uo) =
-                    E::get_user_operations_from_tx_data(call_frame.input, &self.chain_spec)
+                    E::get_user_operations_from_tx_data(call_frame.input.clone(), &self.chain_spec)
                         .into_iter()
-                        .find(|op| op.hash(to, self.chain_spec.id) == user_op_hash)
+                        .find(|op| op.hash(executed_by, self.chain_spec.id) == user_op_hash)
                 {
-                    return Ok(Some(uo));
+                    return Ok(Some((uo, executed_by)));
                 }
-            } else {
-                frame_queue.extend(call_frame.calls)
+            } else if !call_frame.calls.is_empty() {
+                // if the call is not to an entrypoint, enqueue the child calls if any
+                if depth >= max_depth {
+                    bail!(
+                        "trace of tx {tx_hash} exceeded the maximum call depth of {max_depth} \
+                         while searching for user operation"
+                    );
+                }
+                frames_seen += call_frame.calls.len();
+                if frames_seen > max_frames {
+                    bail!(
+                        "trace of tx {tx_hash} exceeded the maximum of {max_frames} call frames \
+                         while searching for user operation"
+                    );
+                }
+                frame_queue.extend(call_frame.calls.into_iter().map(|c| (c, depth + 1)))
             }
         }
 
         Ok(None)
     }
+
+    /// Returns true if `address` has the same code as the entry point, fetching the entry
+    /// point's code hash into `entry_point_code_hash` on first use.
+    async fn has_entry_point_code(
+        &self,
+        address: Address,
+        entry_point_code_hash: &mut Option<B256>,
+    ) -> anyhow::Result<bool> {
+        let entry_point = E::address(&self.chain_spec);
+        if address == entry_point {
+            return Ok(true);
+        }
+        let expected = match *entry_point_code_hash {
+            Some(hash) => hash,
+            None => *entry_point_code_hash.insert(
+                self.provider
+                    .get_code_hash(vec![entry_point], None)
+                    .await
+                    .context("should have fetched entry point code hash")?,
+            ),
+        };
+        let code_hash = self
+            .provider
+            .get_code_hash(vec![address], None)
+            .await
+            .context("should have fetched delegatecall target code hash")?;
+        Ok(code_hash == expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use alloy_primitives::{address, Log as PrimitiveLog};
+    use alloy_sol_types::SolCall;
+    use rundler_contracts::v0_6::{
+        IEntryPoint::{handleAggregatedOpsCall, handleOpsCall, UserOperationEvent},
+        UserOpsPerAggregator,
+    };
+    use rundler_provider::{CallFrame, MockEvmProvider, ProviderError, Transaction};
+    use rundler_types::v0_6::{
+        UserOperation as UserOperationV0_6, UserOperationBuilder, UserOperationRequiredFields,
+    };
+
+    use super::*;
+    use crate::eth::events::UserOperationEventProviderV0_6;
+
+    const UO_HASH: B256 = B256::repeat_byte(1);
+    const TX_HASH: B256 = B256::repeat_byte(2);
+
+    #[tokio::test]
+    async fn test_get_receipt_event_found_receipt_pending() {
+        let chain_spec = ChainSpec::default();
+        let event = given_event_log(&chain_spec);
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event.clone()]));
+        provider
+            .expect_get_transaction_receipt()
+            .withf(|tx_hash| *tx_hash == TX_HASH)
+            .returning(|_| Ok(None));
+        provider
+            .expect_get_transaction_by_hash()
+            .returning(|_| Ok(None));
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec.clone(),
+            provider,
+            EthApiSettings::default(),
+        );
+        let receipt = event_provider
+            .get_receipt(UO_HASH)
+            .await
+            .unwrap()
+            .expect("should return a partial receipt");
+
+        assert_eq!(receipt.user_op_hash, UO_HASH);
+        assert_eq!(receipt.transaction_hash, TX_HASH);
+        assert_eq!(receipt.actual_gas_cost, U256::from(1000));
+        assert!(receipt.success);
+        assert!(receipt.receipt.is_none());
+        assert!(receipt.logs.is_empty());
+
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["transactionHash"], serde_json::json!(TX_HASH));
+        assert!(json["receipt"].is_null());
+        assert!(json.get("aggregator").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_receipt_tx_fetch_failure() {
+        let chain_spec = ChainSpec::default();
+        let event = given_event_log(&chain_spec);
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event.clone()]));
+        provider
+            .expect_get_transaction_receipt()
+            .returning(|_| Ok(None));
+        provider
+            .expect_get_transaction_by_hash()
+            .returning(|_| Err(ProviderError::Other(anyhow::anyhow!("tx unavailable"))));
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec.clone(),
+            provider,
+            EthApiSettings::default(),
+        );
+        let receipt = event_provider
+            .get_receipt(UO_HASH)
+            .await
+            .unwrap()
+            .expect("should return a receipt without the aggregator");
+
+        assert_eq!(receipt.user_op_hash, UO_HASH);
+        assert!(receipt.aggregator.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_receipt_aggregated_ops_surfaces_aggregator() {
+        let chain_spec = ChainSpec::default();
+        let entry_point = chain_spec.entry_point_address_v0_6;
+        let aggregator = address!("00000000000000000000000000000000000000aa");
+        let uo = given_user_operation(&chain_spec);
+        let uo_hash = uo.hash(entry_point, chain_spec.id);
+        let event = given_event_log_for_hash(&chain_spec, uo_hash);
+
+        let input: Bytes = handleAggregatedOpsCall {
+            opsPerAggregator: vec![UserOpsPerAggregator {
+                userOps: vec![uo.into()],
+                aggregator,
+                signature: Bytes::new(),
+            }],
+            beneficiary: Address::ZERO,
+        }
+        .abi_encode()
+        .into();
+        let tx = Transaction {
+            to: Some(entry_point),
+            input,
+            ..Default::default()
+        };
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event.clone()]));
+        provider
+            .expect_get_transaction_receipt()
+            .returning(|_| Ok(None));
+        provider
+            .expect_get_transaction_by_hash()
+            .withf(|tx_hash| *tx_hash == TX_HASH)
+            .returning(move |_| Ok(Some(tx.clone())));
+
+        let event_provider =
+            UserOperationEventProviderV0_6::new(chain_spec, provider, EthApiSettings::default());
+        let receipt = event_provider.get_receipt(uo_hash).await.unwrap().unwrap();
+
+        assert_eq!(receipt.aggregator, Some(aggregator.into()));
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(
+            json["aggregator"],
+            serde_json::json!(aggregator.to_checksum(None))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_mined_by_hash_pending_tx() {
+        let chain_spec = ChainSpec::default();
+        let event = given_event_log(&chain_spec);
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event.clone()]));
+        provider
+            .expect_get_transaction_with_status()
+            .withf(|tx_hash| *tx_hash == TX_HASH)
+            .returning(|_| Ok(Some((Transaction::default(), TransactionStatus::Pending))));
+
+        let event_provider =
+            UserOperationEventProviderV0_6::new(chain_spec, provider, EthApiSettings::default());
+        assert!(event_provider
+            .get_mined_by_hash(UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_receipt_event_not_found() {
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider.expect_get_logs().returning(|_| Ok(vec![]));
+        provider.expect_get_transaction_receipt().never();
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            provider,
+            EthApiSettings::default(),
+        );
+        assert!(event_provider.get_receipt(UO_HASH).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_event_by_hash_scans_windows_newest_first() {
+        let chain_spec = ChainSpec::default();
+        let event = given_event_log(&chain_spec);
+        let queried = Arc::new(Mutex::new(vec![]));
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        let queried_clone = queried.clone();
+        provider.expect_get_logs().returning(move |filter| {
+            let window = (
+                filter.get_from_block().unwrap(),
+                filter.get_to_block().unwrap(),
+            );
+            queried_clone.lock().unwrap().push(window);
+            // the event is only in an older window
+            if window.0 <= 20 && 20 <= window.1 {
+                Ok(vec![event.clone()])
+            } else {
+                Ok(vec![])
+            }
+        });
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec,
+            provider,
+            EthApiSettings {
+                user_operation_event_max_block_range: 30,
+                ..Default::default()
+            },
+        );
+        let log = event_provider.get_event_by_hash(UO_HASH).await.unwrap();
+
+        assert_eq!(log.unwrap().transaction_hash, Some(TX_HASH));
+        assert_eq!(
+            *queried.lock().unwrap(),
+            vec![(71, 100), (41, 70), (11, 40)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_event_by_hash_scans_until_block_distance() {
+        let queried = Arc::new(Mutex::new(vec![]));
+
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(100));
+        let queried_clone = queried.clone();
+        provider.expect_get_logs().returning(move |filter| {
+            queried_clone.lock().unwrap().push((
+                filter.get_from_block().unwrap(),
+                filter.get_to_block().unwrap(),
+            ));
+            Ok(vec![])
+        });
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            provider,
+            EthApiSettings {
+                user_operation_event_block_distance: Some(50),
+                user_operation_event_max_block_range: 20,
+                ..Default::default()
+            },
+        );
+        assert!(event_provider
+            .get_event_by_hash(UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            *queried.lock().unwrap(),
+            vec![(81, 100), (61, 80), (50, 60)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_mined_by_hash_not_found_rescans_recent_blocks() {
+        let queried = Arc::new(Mutex::new(vec![]));
+        let block_number = Arc::new(AtomicU64::new(100));
+
+        let mut provider = MockEvmProvider::default();
+        let block_number_clone = block_number.clone();
+        provider
+            .expect_get_block_number()
+            .returning(move || Ok(block_number_clone.load(Ordering::SeqCst)));
+        let queried_clone = queried.clone();
+        provider.expect_get_logs().returning(move |filter| {
+            queried_clone.lock().unwrap().push((
+                filter.get_from_block().unwrap(),
+                filter.get_to_block().unwrap(),
+            ));
+            Ok(vec![])
+        });
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            provider,
+            EthApiSettings {
+                user_operation_event_max_block_range: 30,
+                ..Default::default()
+            },
+        );
+
+        assert!(event_provider
+            .get_mined_by_hash(UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(queried.lock().unwrap().len(), 4);
+
+        // no new blocks, only the most recent blocks are rescanned
+        assert!(event_provider
+            .get_mined_by_hash(UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(queried.lock().unwrap().len(), 5);
+
+        // the new block and the most recent blocks are scanned
+        block_number.store(101, Ordering::SeqCst);
+        assert!(event_provider
+            .get_mined_by_hash(UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            *queried.lock().unwrap(),
+            vec![(71, 100), (41, 70), (11, 40), (0, 10), (96, 100), (96, 101)]
+        );
+    }
+
+    fn nested_call_frame(depth: usize) -> CallFrame {
+        let mut frame = CallFrame::default();
+        for _ in 0..depth {
+            frame = CallFrame {
+                calls: vec![frame],
+                ..Default::default()
+            };
+        }
+        frame
+    }
+
+    fn trace_provider(frame: CallFrame) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::default();
+        provider
+            .expect_debug_trace_transaction()
+            .returning(move |_, _| Ok(GethTrace::CallTracer(frame.clone())));
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_trace_find_user_operation_within_limits() {
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            trace_provider(nested_call_frame(10)),
+            EthApiSettings {
+                user_operation_trace_max_depth: 10,
+                user_operation_trace_max_frames: 11,
+                ..Default::default()
+            },
+        );
+        assert!(event_provider
+            .trace_find_user_operation(TX_HASH, UO_HASH)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trace_find_user_operation_max_depth() {
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            trace_provider(nested_call_frame(1_000)),
+            EthApiSettings {
+                user_operation_trace_max_depth: 10,
+                ..Default::default()
+            },
+        );
+        let err = event_provider
+            .trace_find_user_operation(TX_HASH, UO_HASH)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("maximum call depth of 10"));
+    }
+
+    #[tokio::test]
+    async fn test_trace_find_user_operation_max_frames() {
+        // a shallow but wide tree: 20 frames each with 20 children
+        let frame = CallFrame {
+            calls: vec![
+                CallFrame {
+                    calls: vec![CallFrame::default(); 20],
+                    ..Default::default()
+                };
+                20
+            ],
+            ..Default::default()
+        };
+        let event_provider = UserOperationEventProviderV0_6::new(
+            ChainSpec::default(),
+            trace_provider(frame),
+            EthApiSettings {
+                user_operation_trace_max_frames: 100,
+                ..Default::default()
+            },
+        );
+        let err = event_provider
+            .trace_find_user_operation(TX_HASH, UO_HASH)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("maximum of 100 call frames"));
+    }
+
+    #[tokio::test]
+    async fn test_trace_find_user_operation_delegatecall() {
+        let chain_spec = ChainSpec::default();
+        let uo = given_user_operation(&chain_spec);
+        // the entry point's code executes as the proxy, which is part of the hash
+        let proxy = address!("0000000000000000000000000000000000000002");
+        let uo_hash = uo.hash(proxy, chain_spec.id);
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec,
+            delegatecall_trace_provider(&uo, proxy, B256::repeat_byte(9)),
+            EthApiSettings::default(),
+        );
+        let found = event_provider
+            .trace_find_user_operation(TX_HASH, uo_hash)
+            .await
+            .unwrap();
+        assert_eq!(found, Some((uo, proxy)));
+    }
+
+    #[tokio::test]
+    async fn test_trace_find_user_operation_delegatecall_to_other_code() {
+        let chain_spec = ChainSpec::default();
+        let uo = given_user_operation(&chain_spec);
+        let proxy = address!("0000000000000000000000000000000000000002");
+        let uo_hash = uo.hash(proxy, chain_spec.id);
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec,
+            delegatecall_trace_provider(&uo, proxy, B256::repeat_byte(8)),
+            EthApiSettings::default(),
+        );
+        let found = event_provider
+            .trace_find_user_operation(TX_HASH, uo_hash)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_event_by_hash_delegated_event() {
+        let chain_spec = ChainSpec::default();
+        let uo = given_user_operation(&chain_spec);
+        let proxy = address!("0000000000000000000000000000000000000002");
+        let uo_hash = uo.hash(proxy, chain_spec.id);
+        let mut event = given_event_log_for_hash(&chain_spec, uo_hash);
+        event.inner.address = proxy;
+
+        let mut provider = delegatecall_trace_provider(&uo, proxy, B256::repeat_byte(9));
+        provider.expect_get_block_number().returning(|| Ok(100));
+        let event_clone = event.clone();
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event_clone.clone()]));
+
+        let event_provider =
+            UserOperationEventProviderV0_6::new(chain_spec, provider, EthApiSettings::default());
+        assert_eq!(
+            event_provider.get_event_by_hash(uo_hash).await.unwrap(),
+            Some(event)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_event_by_hash_ignores_unverified_event() {
+        let chain_spec = ChainSpec::default();
+        let uo = given_user_operation(&chain_spec);
+        let proxy = address!("0000000000000000000000000000000000000002");
+        let uo_hash = uo.hash(proxy, chain_spec.id);
+        // emitted by an address that doesn't delegatecall into the entry point's code
+        let mut event = given_event_log_for_hash(&chain_spec, uo_hash);
+        event.inner.address = proxy;
+
+        let mut provider = delegatecall_trace_provider(&uo, proxy, B256::repeat_byte(8));
+        provider.expect_get_block_number().returning(|| Ok(100));
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![event.clone()]));
+
+        let event_provider = UserOperationEventProviderV0_6::new(
+            chain_spec,
+            provider,
+            EthApiSettings {
+                user_operation_event_block_distance: Some(10),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            event_provider.get_event_by_hash(uo_hash).await.unwrap(),
+            None
+        );
+    }
+
+    /// Provider tracing relayer -> proxy (CALL) -> entry point code (DELEGATECALL), where
+    /// neither frame's `to` is the entry point and the delegatecall target has the given
+    /// code hash.
+    fn delegatecall_trace_provider(
+        uo: &UserOperationV0_6,
+        proxy: Address,
+        target_code_hash: B256,
+    ) -> MockEvmProvider {
+        let target = address!("0000000000000000000000000000000000000003");
+        let frame = CallFrame {
+            to: Some(proxy),
+            calls: vec![CallFrame {
+                typ: "DELEGATECALL".to_string(),
+                from: proxy,
+                to: Some(target),
+                input: handleOpsCall {
+                    ops: vec![uo.clone().into()],
+                    beneficiary: Address::ZERO,
+                }
+                .abi_encode()
+                .into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut provider = trace_provider(frame);
+        provider
+            .expect_get_code_hash()
+            .returning(move |addresses, _| {
+                Ok(if addresses == [target] {
+                    target_code_hash
+                } else {
+                    B256::repeat_byte(9)
+                })
+            });
+        provider
+    }
+
+    fn given_user_operation(chain_spec: &ChainSpec) -> UserOperationV0_6 {
+        UserOperationBuilder::new(
+            chain_spec,
+            UserOperationRequiredFields {
+                sender: address!("0000000000000000000000000000000000000001"),
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 50_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            },
+        )
+        .build()
+    }
+
+    fn given_event_log(chain_spec: &ChainSpec) -> Log {
+        given_event_log_for_hash(chain_spec, UO_HASH)
+    }
+
+    fn given_event_log_for_hash(chain_spec: &ChainSpec, user_op_hash: B256) -> Log {
+        let event = UserOperationEvent {
+            userOpHash: user_op_hash,
+            sender: address!("0000000000000000000000000000000000000001"),
+            paymaster: Address::ZERO,
+            nonce: U256::ZERO,
+            success: true,
+            actualGasCost: U256::from(1000),
+            actualGasUsed: U256::from(100),
+        };
+
+        Log {
+            inner: PrimitiveLog {
+                address: chain_spec.entry_point_address_v0_6,
+                data: event.encode_log_data(),
+            },
+            transaction_hash: Some(TX_HASH),
+            ..Default::default()
+        }
+    }
 }
@@ -17,6 +17,8 @@ use rundler_provider::{Log, TransactionReceipt};
 
 use crate::types::{RpcUserOperationByHash, RpcUserOperationReceipt};
 
+mod calldata;
+pub use calldata::{decode_handle_ops_calldata, DecodeError};
 mod common;
 
 mod v0_6;
@@ -39,8 +39,10 @@ impl EntryPointEvents for EntryPointFiltersV0_7 {
         event: Self::UserOperationEvent,
         hash: B256,
         entry_point: Address,
+        tx_hash: B256,
+        aggregator: Option<Address>,
         logs: Vec<Log>,
-        tx_receipt: TransactionReceipt,
+        tx_receipt: Option<TransactionReceipt>,
     ) -> RpcUserOperationReceipt {
         // get failure reason
         let reason: String = if event.success {
@@ -70,13 +72,18 @@ impl EntryPointEvents for EntryPointFiltersV0_7 {
             actual_gas_cost: event.actualGasCost,
             actual_gas_used: U128::uint_try_from(event.actualGasUsed).unwrap_or(U128::MAX),
             success: event.success,
+            aggregator: aggregator.map(Into::into),
             logs,
+            transaction_hash: tx_hash,
             receipt: tx_receipt,
             reason,
         }
     }
 
-    fn get_user_operations_from_tx_data(tx_data: Bytes, chain_spec: &ChainSpec) -> Vec<Self::UO> {
+    fn get_user_operations_with_aggregator_from_tx_data(
+        tx_data: Bytes,
+        chain_spec: &ChainSpec,
+    ) -> Vec<(Self::UO, Option<Address>)> {
         let entry_point_calls = match IEntryPointCalls::abi_decode(&tx_data, false) {
             Ok(entry_point_calls) => entry_point_calls,
             Err(_) => return vec![],
@@ -89,7 +96,7 @@ impl EntryPointEvents for EntryPointFiltersV0_7 {
                 .filter_map(|op| {
                     UserOperationBuilder::from_packed(op, chain_spec)
                         .ok()
-                        .map(|uo| uo.build())
+                        .map(|uo| (uo.build(), None))
                 })
                 .collect(),
             IEntryPointCalls::handleAggregatedOps(handle_aggregated_ops_call) => {
@@ -97,10 +104,11 @@ impl EntryPointEvents for EntryPointFiltersV0_7 {
                     .opsPerAggregator
                     .into_iter()
                     .flat_map(|ops| {
-                        ops.userOps.into_iter().filter_map(|op| {
+                        let aggregator = ops.aggregator;
+                        ops.userOps.into_iter().filter_map(move |op| {
                             UserOperationBuilder::from_packed(op, chain_spec)
                                 .ok()
-                                .map(|uo| uo.build())
+                                .map(|uo| (uo.build(), Some(aggregator)))
                         })
                     })
                     .collect()
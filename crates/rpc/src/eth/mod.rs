@@ -21,6 +21,8 @@ pub(crate) use router::*;
 mod error;
 pub(crate) use error::{EthResult, EthRpcError};
 mod events;
+mod sender_limit;
+pub use events::{decode_handle_ops_calldata, DecodeError};
 pub(crate) use events::{UserOperationEventProviderV0_6, UserOperationEventProviderV0_7};
 mod server;
 
@@ -11,21 +11,25 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+use std::{fmt::Debug, future::Future, marker::PhantomData, sync::Arc};
 
-use alloy_primitives::{Address, B256};
-use rundler_provider::{EntryPoint, SimulationProvider, StateOverride};
-use rundler_sim::{GasEstimationError, GasEstimator};
+use alloy_primitives::{Address, B256, U64};
+use futures_util::{Stream, StreamExt};
+use rundler_provider::{EntryPoint, SignatureAggregator, SimulationProvider, StateOverride};
+use rundler_sim::{GasEstimationError, GasEstimator, RecentValidation};
 use rundler_types::{
-    EntryPointVersion, GasEstimate, UserOperation, UserOperationOptionalGas, UserOperationVariant,
+    aggregated_signature_gas, chain::ChainSpec, pool::NewHead, EntryPointVersion, GasEstimate,
+    UserOperation, UserOperationId, UserOperationOptionalGas, UserOperationVariant,
+    ValidationOutput, ValidationRevert,
 };
 
 use super::events::UserOperationEventProvider;
 use crate::{
+    estimate_history::EstimateHistory,
     eth::{error::EthResult, EthRpcError},
     types::{
-        RpcGasEstimate, RpcGasEstimateV0_6, RpcGasEstimateV0_7, RpcUserOperationByHash,
-        RpcUserOperationReceipt,
+        RpcEntryPointCompatibility, RpcGasEstimate, RpcGasEstimateV0_6, RpcGasEstimateV0_7,
+        RpcUserOperationByHash, RpcUserOperationReceipt, RpcValidationOutput,
     },
 };
 
@@ -34,6 +38,8 @@ pub(crate) struct EntryPointRouterBuilder {
     entry_points: Vec<Address>,
     v0_6: Option<(Address, Arc<dyn EntryPointRoute>)>,
     v0_7: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    single_version_fallback: bool,
+    chain_spec: ChainSpec,
 }
 
 impl EntryPointRouterBuilder {
@@ -69,11 +75,30 @@ impl EntryPointRouterBuilder {
         self
     }
 
+    /// When set, requests that don't specify an entry point (the zero address) are routed to
+    /// the only enabled entry point version instead of erroring. Requests are still rejected
+    /// when multiple versions are enabled, as the intended version is ambiguous, and requests
+    /// for any other unknown address are always rejected.
+    pub(crate) fn single_version_fallback(mut self, enabled: bool) -> Self {
+        self.single_version_fallback = enabled;
+        self
+    }
+
+    /// The chain the entry points are deployed on, used to price the calldata of aggregated
+    /// signatures during gas estimation
+    pub(crate) fn chain_spec(mut self, chain_spec: ChainSpec) -> Self {
+        self.chain_spec = chain_spec;
+        self
+    }
+
     pub(crate) fn build(self) -> EntryPointRouter {
         EntryPointRouter {
             entry_points: self.entry_points,
             v0_6: self.v0_6,
             v0_7: self.v0_7,
+            single_version_fallback: self.single_version_fallback,
+            chain_spec: self.chain_spec,
+            estimate_history: EstimateHistory::default(),
         }
     }
 }
@@ -83,6 +108,9 @@ pub(crate) struct EntryPointRouter {
     entry_points: Vec<Address>,
     v0_6: Option<(Address, Arc<dyn EntryPointRoute>)>,
     v0_7: Option<(Address, Arc<dyn EntryPointRoute>)>,
+    single_version_fallback: bool,
+    chain_spec: ChainSpec,
+    estimate_history: EstimateHistory,
 }
 
 impl EntryPointRouter {
@@ -90,6 +118,42 @@ impl EntryPointRouter {
         self.entry_points.iter()
     }
 
+    /// History of the gas estimates returned by the APIs sharing this router
+    pub(crate) fn estimate_history(&self) -> &EstimateHistory {
+        &self.estimate_history
+    }
+
+    /// Records the estimation accuracy metrics of estimated user operations as they are
+    /// mined, until the stream ends.
+    pub(crate) fn track_mined_estimates(
+        &self,
+        mut new_heads: impl Stream<Item = NewHead> + Unpin,
+    ) -> impl Future<Output = ()> {
+        let router = self.clone();
+        async move {
+            while let Some(head) = new_heads.next().await {
+                for op in head.mined_ops {
+                    let id = UserOperationId {
+                        sender: op.sender,
+                        nonce: op.nonce,
+                    };
+                    // only fetch receipts for operations that were estimated
+                    if !router.estimate_history.has_estimate(op.entry_point, id) {
+                        continue;
+                    }
+                    match router.get_receipt(&op.entry_point, op.hash).await {
+                        Ok(Some(receipt)) => router.estimate_history.record_mined(&receipt),
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!(
+                            "Failed to get receipt of mined user operation {:?}: {e:?}",
+                            op.hash
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn check_and_get_route(
         &self,
         entry_point: &Address,
@@ -140,51 +204,119 @@ impl EntryPointRouter {
             .map_err(Into::into)
     }
 
+    /// Estimates gas for a user operation, pricing the aggregated signature of `aggregator`
+    /// into the pre-verification gas if the operation will be bundled with one.
     pub(crate) async fn estimate_gas(
         &self,
         entry_point: &Address,
         uo: UserOperationOptionalGas,
+        aggregator: Option<Address>,
         state_override: Option<StateOverride>,
     ) -> EthResult<RpcGasEstimate> {
-        match self.get_ep_version(entry_point)? {
-            EntryPointVersion::V0_6 => {
-                if !matches!(uo, UserOperationOptionalGas::V0_6(_)) {
-                    return Err(EthRpcError::InvalidParams(format!(
-                        "Invalid user operation for entry point: {:?}",
-                        entry_point
-                    )));
-                }
+        self.estimate_gas_inner(
+            entry_point,
+            uo,
+            aggregator,
+            state_override,
+            EstimateAt::Latest,
+        )
+        .await
+    }
 
-                let e = self
-                    .v0_6
-                    .as_ref()
-                    .unwrap()
-                    .1
-                    .estimate_gas(uo, state_override)
-                    .await?;
+    /// Estimates gas against `block`, a block hash and number pair already known to the
+    /// caller, so that multiple estimates can share a single block.
+    pub(crate) async fn estimate_gas_with_block(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationOptionalGas,
+        aggregator: Option<Address>,
+        state_override: Option<StateOverride>,
+        block: (B256, u64),
+    ) -> EthResult<RpcGasEstimate> {
+        self.estimate_gas_inner(
+            entry_point,
+            uo,
+            aggregator,
+            state_override,
+            EstimateAt::Block(block),
+        )
+        .await
+    }
+
+    /// Estimates gas reusing `validation`, a recent validation of the same operation, for the
+    /// verification gas limit instead of searching for it.
+    pub(crate) async fn estimate_gas_with_validation(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationOptionalGas,
+        aggregator: Option<Address>,
+        state_override: Option<StateOverride>,
+        validation: RecentValidation,
+    ) -> EthResult<RpcGasEstimate> {
+        self.estimate_gas_inner(
+            entry_point,
+            uo,
+            aggregator,
+            state_override,
+            EstimateAt::Validation(validation),
+        )
+        .await
+    }
 
-                Ok(RpcGasEstimateV0_6::from(e).into())
+    async fn estimate_gas_inner(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationOptionalGas,
+        aggregator: Option<Address>,
+        state_override: Option<StateOverride>,
+        at: EstimateAt,
+    ) -> EthResult<RpcGasEstimate> {
+        let version = self.get_ep_version(entry_point)?;
+        let matches = match version {
+            EntryPointVersion::V0_6 => matches!(uo, UserOperationOptionalGas::V0_6(_)),
+            EntryPointVersion::V0_7 => matches!(uo, UserOperationOptionalGas::V0_7(_)),
+            EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
+        };
+        if !matches {
+            return Err(EthRpcError::InvalidParams(format!(
+                "Invalid user operation for entry point: {:?}",
+                entry_point
+            )));
+        }
+
+        let route = self.get_route(entry_point)?;
+        let e = match at {
+            EstimateAt::Latest => route.estimate_gas(uo, state_override).await?,
+            EstimateAt::Block(block) => {
+                route
+                    .estimate_gas_with_block(uo, state_override, block)
+                    .await?
             }
-            EntryPointVersion::V0_7 => {
-                if !matches!(uo, UserOperationOptionalGas::V0_7(_)) {
+            EstimateAt::Validation(validation) => {
+                route
+                    .estimate_gas_with_validation(uo, state_override, validation)
+                    .await?
+            }
+        };
+        let e = match aggregator {
+            Some(aggregator) => {
+                let Some(length) = route.aggregated_signature_length(aggregator).await? else {
                     return Err(EthRpcError::InvalidParams(format!(
-                        "Invalid user operation for entry point: {:?}",
-                        entry_point
+                        "Aggregator {aggregator:?} does not expose its signature length"
                     )));
-                }
-
-                let e = self
-                    .v0_7
-                    .as_ref()
-                    .unwrap()
-                    .1
-                    .estimate_gas(uo, state_override)
-                    .await?;
-
-                Ok(RpcGasEstimateV0_7::from(e).into())
+                };
+                with_pre_verification_gas_added(
+                    e,
+                    aggregated_signature_gas(&self.chain_spec, length),
+                )
             }
-            EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
-        }
+            None => e,
+        };
+
+        Ok(match version {
+            EntryPointVersion::V0_6 => RpcGasEstimateV0_6::from(e).into(),
+            _ => RpcGasEstimateV0_7::from(e).into(),
+        })
     }
 
     pub(crate) async fn check_signature(
@@ -198,6 +330,70 @@ impl EntryPointRouter {
             .map_err(Into::into)
     }
 
+    pub(crate) async fn simulate_validation(
+        &self,
+        entry_point: &Address,
+        uo: UserOperationVariant,
+    ) -> EthResult<RpcValidationOutput> {
+        let output = self
+            .check_and_get_route(entry_point, &uo)?
+            .simulate_validation(uo)
+            .await?
+            .map_err(|revert| EthRpcError::ValidationRevert(revert.into()))?;
+
+        Ok(output.into())
+    }
+
+    /// Checks a user operation against every enabled entry point version, reporting which
+    /// versions accept it.
+    ///
+    /// A version accepts the operation if the operation has that version's fields and passes
+    /// that entry point's validation simulation, including the account's signature check.
+    pub(crate) async fn check_compatibility(
+        &self,
+        uo: &UserOperationVariant,
+    ) -> EthResult<Vec<RpcEntryPointCompatibility>> {
+        let mut results = vec![];
+        for (address, route) in [&self.v0_6, &self.v0_7].into_iter().flatten() {
+            let version = route.version();
+            let reason = if uo.uo_type() != version {
+                Some(format!(
+                    "user operation fields do not match entry point {}",
+                    version_name(version)
+                ))
+            } else {
+                match route.simulate_validation(uo.clone()).await? {
+                    Ok(output) if output.return_info.account_sig_failed => {
+                        Some("account signature validation failed".to_string())
+                    }
+                    Ok(_) => None,
+                    Err(revert) => Some(revert.to_string()),
+                }
+            };
+
+            results.push(RpcEntryPointCompatibility {
+                entry_point: *address,
+                version: version_name(version).to_string(),
+                compatible: reason.is_none(),
+                reason,
+            });
+        }
+
+        Ok(results)
+    }
+
+    pub(crate) async fn aggregated_signature_length(
+        &self,
+        entry_point: &Address,
+        aggregator: Address,
+    ) -> EthResult<Option<U64>> {
+        let length = self
+            .get_route(entry_point)?
+            .aggregated_signature_length(aggregator)
+            .await?;
+        Ok(length.map(U64::from))
+    }
+
     fn get_ep_version(&self, entry_point: &Address) -> EthResult<EntryPointVersion> {
         if let Some((addr, _)) = self.v0_6 {
             if addr == *entry_point {
@@ -210,6 +406,23 @@ impl EntryPointRouter {
             }
         }
 
+        // the version can only be inferred when the caller didn't name an entry point, an
+        // address that doesn't match a configured entry point is never rerouted
+        if self.single_version_fallback && entry_point.is_zero() {
+            match (&self.v0_6, &self.v0_7) {
+                (Some(_), None) => return Ok(EntryPointVersion::V0_6),
+                (None, Some(_)) => return Ok(EntryPointVersion::V0_7),
+                (Some(_), Some(_)) => {
+                    return Err(EthRpcError::InvalidParams(
+                        "No entry point specified, version is ambiguous with multiple entry \
+                         point versions enabled"
+                            .to_string(),
+                    ))
+                }
+                (None, None) => {}
+            }
+        }
+
         Err(EthRpcError::InvalidParams(format!(
             "No entry point found for address: {:?}",
             entry_point
@@ -227,6 +440,38 @@ impl EntryPointRouter {
     }
 }
 
+/// The state a gas estimate is run against
+enum EstimateAt {
+    /// The latest block
+    Latest,
+    /// A block hash and number pair already known to the caller
+    Block((B256, u64)),
+    /// The latest block, reusing a recent validation of the operation
+    Validation(RecentValidation),
+}
+
+fn with_pre_verification_gas_added(estimate: GasEstimate, gas: u128) -> GasEstimate {
+    let breakdown = estimate.breakdown.map(|mut breakdown| {
+        breakdown.pre_verification_gas.after_buffer += gas;
+        breakdown.pre_verification_gas.after_clamp += gas;
+        breakdown
+    });
+    GasEstimate {
+        pre_verification_gas: estimate.pre_verification_gas + gas,
+        breakdown,
+        ..estimate
+    }
+}
+
+fn version_name(version: EntryPointVersion) -> &'static str {
+    match version {
+        EntryPointVersion::V0_6 => "v0.6",
+        EntryPointVersion::V0_7 => "v0.7",
+        EntryPointVersion::Unspecified => unreachable!("unspecified entry point version"),
+    }
+}
+
+#[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub(crate) trait EntryPointRoute: Send + Sync {
     fn version(&self) -> EntryPointVersion;
@@ -244,7 +489,31 @@ pub(crate) trait EntryPointRoute: Send + Sync {
         state_override: Option<StateOverride>,
     ) -> Result<GasEstimate, GasEstimationError>;
 
+    async fn estimate_gas_with_block(
+        &self,
+        uo: UserOperationOptionalGas,
+        state_override: Option<StateOverride>,
+        block: (B256, u64),
+    ) -> Result<GasEstimate, GasEstimationError>;
+
+    async fn estimate_gas_with_validation(
+        &self,
+        uo: UserOperationOptionalGas,
+        state_override: Option<StateOverride>,
+        validation: RecentValidation,
+    ) -> Result<GasEstimate, GasEstimationError>;
+
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool>;
+
+    async fn simulate_validation(
+        &self,
+        uo: UserOperationVariant,
+    ) -> anyhow::Result<Result<ValidationOutput, ValidationRevert>>;
+
+    async fn aggregated_signature_length(
+        &self,
+        aggregator: Address,
+    ) -> anyhow::Result<Option<usize>>;
 }
 
 #[derive(Debug)]
@@ -259,7 +528,7 @@ pub(crate) struct EntryPointRouteImpl<UO, E, G, EV> {
 impl<UO, E, G, EV> EntryPointRoute for EntryPointRouteImpl<UO, E, G, EV>
 where
     UO: UserOperation + From<UserOperationVariant>,
-    E: EntryPoint + SimulationProvider<UO = UO>,
+    E: EntryPoint + SimulationProvider<UO = UO> + SignatureAggregator<UO = UO>,
     G: GasEstimator<UserOperationOptionalGas = UO::OptionalGas>,
     G::UserOperationOptionalGas: From<UserOperationOptionalGas>,
     EV: UserOperationEventProvider,
@@ -293,6 +562,32 @@ where
             .await
     }
 
+    async fn estimate_gas_with_block(
+        &self,
+        uo: UserOperationOptionalGas,
+        state_override: Option<StateOverride>,
+        block: (B256, u64),
+    ) -> Result<GasEstimate, GasEstimationError> {
+        self.gas_estimator
+            .estimate_op_gas_with_block(uo.into(), state_override.unwrap_or_default(), block)
+            .await
+    }
+
+    async fn estimate_gas_with_validation(
+        &self,
+        uo: UserOperationOptionalGas,
+        state_override: Option<StateOverride>,
+        validation: RecentValidation,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        self.gas_estimator
+            .estimate_op_gas_with_validation(
+                uo.into(),
+                state_override.unwrap_or_default(),
+                validation,
+            )
+            .await
+    }
+
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool> {
         let output = self
             .entry_point
@@ -301,6 +596,26 @@ where
 
         Ok(!output.return_info.account_sig_failed)
     }
+
+    async fn simulate_validation(
+        &self,
+        uo: UserOperationVariant,
+    ) -> anyhow::Result<Result<ValidationOutput, ValidationRevert>> {
+        Ok(self
+            .entry_point
+            .simulate_validation(uo.into(), None)
+            .await?)
+    }
+
+    async fn aggregated_signature_length(
+        &self,
+        aggregator: Address,
+    ) -> anyhow::Result<Option<usize>> {
+        Ok(self
+            .entry_point
+            .aggregated_signature_length(aggregator)
+            .await?)
+    }
 }
 
 impl<UO, E, G, EP> EntryPointRouteImpl<UO, E, G, EP> {
@@ -313,3 +628,378 @@ impl<UO, E, G, EP> EntryPointRouteImpl<UO, E, G, EP> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Bytes, U128, U256};
+    use rundler_provider::MockEntryPointV0_6;
+    use rundler_sim::MockGasEstimator;
+    use rundler_types::{
+        pool::MinedUserOperation, v0_6, GasEstimateBreakdown, GasEstimateStages, StakeInfo,
+        ValidationReturnInfo,
+    };
+
+    use super::*;
+
+    struct NoEvents;
+
+    #[async_trait::async_trait]
+    impl UserOperationEventProvider for NoEvents {
+        async fn get_mined_by_hash(
+            &self,
+            _hash: B256,
+        ) -> anyhow::Result<Option<RpcUserOperationByHash>> {
+            Ok(None)
+        }
+
+        async fn get_receipt(
+            &self,
+            _hash: B256,
+        ) -> anyhow::Result<Option<RpcUserOperationReceipt>> {
+            Ok(None)
+        }
+    }
+
+    fn router_with_entry_point(entry_point: MockEntryPointV0_6) -> EntryPointRouter {
+        EntryPointRouterBuilder::default()
+            .v0_6(EntryPointRouteImpl::<v0_6::UserOperation, _, _, _>::new(
+                entry_point,
+                MockGasEstimator::new(),
+                NoEvents,
+            ))
+            .build()
+    }
+
+    fn stub_route(version: EntryPointVersion) -> MockEntryPointRoute {
+        let mut route = MockEntryPointRoute::new();
+        route.expect_version().return_const(version);
+        route.expect_address().return_const(Address::random());
+        // identify the route by its version
+        route
+            .expect_aggregated_signature_length()
+            .returning(move |_| {
+                Ok(Some(match version {
+                    EntryPointVersion::V0_6 => 6,
+                    _ => 7,
+                }))
+            });
+        route
+    }
+
+    #[tokio::test]
+    async fn test_single_version_fallback() {
+        let router = EntryPointRouterBuilder::default()
+            .v0_7(stub_route(EntryPointVersion::V0_7))
+            .single_version_fallback(true)
+            .build();
+
+        assert_eq!(
+            router
+                .aggregated_signature_length(&Address::ZERO, Address::random())
+                .await
+                .unwrap(),
+            Some(U64::from(7))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_single_version_fallback_rejects_unknown_address() {
+        let router = EntryPointRouterBuilder::default()
+            .v0_7(stub_route(EntryPointVersion::V0_7))
+            .single_version_fallback(true)
+            .build();
+
+        assert!(router
+            .aggregated_signature_length(&Address::random(), Address::random())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_single_version_fallback_disabled() {
+        let router = EntryPointRouterBuilder::default()
+            .v0_7(stub_route(EntryPointVersion::V0_7))
+            .build();
+
+        assert!(router
+            .aggregated_signature_length(&Address::ZERO, Address::random())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_single_version_fallback_ambiguous() {
+        let v0_6 = stub_route(EntryPointVersion::V0_6);
+        let v0_6_address = v0_6.address();
+        let router = EntryPointRouterBuilder::default()
+            .v0_6(v0_6)
+            .v0_7(stub_route(EntryPointVersion::V0_7))
+            .single_version_fallback(true)
+            .build();
+
+        let err = router
+            .aggregated_signature_length(&Address::ZERO, Address::random())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EthRpcError::InvalidParams(msg) if msg.contains("ambiguous")),);
+
+        // known entry points still route by address
+        assert_eq!(
+            router
+                .aggregated_signature_length(&v0_6_address, Address::random())
+                .await
+                .unwrap(),
+            Some(U64::from(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_signature_length() {
+        let entry_point_address = Address::random();
+        let aggregator = Address::random();
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_aggregated_signature_length()
+            .returning(move |address| Ok((address == aggregator).then_some(96)));
+        let router = router_with_entry_point(entry_point);
+
+        assert_eq!(
+            router
+                .aggregated_signature_length(&entry_point_address, aggregator)
+                .await
+                .unwrap(),
+            Some(U64::from(96))
+        );
+        assert_eq!(
+            router
+                .aggregated_signature_length(&entry_point_address, Address::random())
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(router
+            .aggregated_signature_length(&Address::random(), aggregator)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_prices_aggregated_signature() {
+        let entry_point = Address::random();
+        let aggregator = Address::random();
+
+        let mut route = MockEntryPointRoute::new();
+        route.expect_version().return_const(EntryPointVersion::V0_6);
+        route.expect_address().return_const(entry_point);
+        route.expect_estimate_gas().returning(|_, _| {
+            Ok(GasEstimate {
+                pre_verification_gas: 50_000,
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                paymaster_verification_gas_limit: None,
+                block_number: None,
+                breakdown: Some(GasEstimateBreakdown {
+                    pre_verification_gas: GasEstimateStages::unadjusted(50_000),
+                    ..Default::default()
+                }),
+                verification_gas_rounds: None,
+                call_gas_rounds: None,
+                warnings: vec![],
+            })
+        });
+        route
+            .expect_aggregated_signature_length()
+            .returning(move |address| Ok((address == aggregator).then_some(48)));
+        let router = EntryPointRouterBuilder::default().v0_6(route).build();
+
+        let op = UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        });
+
+        let estimate = |aggregator| {
+            let router = router.clone();
+            let op = op.clone();
+            async move {
+                let estimate = router
+                    .estimate_gas(&entry_point, op, aggregator, None)
+                    .await?;
+                Ok::<_, EthRpcError>(serde_json::to_value(estimate).unwrap())
+            }
+        };
+
+        let unaggregated = estimate(None).await.unwrap();
+        assert_eq!(unaggregated["preVerificationGas"], "0xc350");
+
+        // 48 byte signature padded to 64 bytes, plus the aggregator, offset and length words
+        let signature_gas = aggregated_signature_gas(&ChainSpec::default(), 48);
+        assert_eq!(signature_gas, 160 * 16);
+        let aggregated = estimate(Some(aggregator)).await.unwrap();
+        let expected = serde_json::to_value(U128::from(50_000 + signature_gas)).unwrap();
+        assert_eq!(aggregated["preVerificationGas"], expected);
+        assert_eq!(
+            aggregated["breakdown"]["preVerificationGas"]["afterClamp"],
+            expected
+        );
+
+        assert!(matches!(
+            estimate(Some(Address::random())).await,
+            Err(EthRpcError::InvalidParams(_))
+        ));
+    }
+
+    fn validation_output(account_sig_failed: bool) -> ValidationOutput {
+        ValidationOutput {
+            return_info: ValidationReturnInfo {
+                account_sig_failed,
+                ..Default::default()
+            },
+            sender_info: StakeInfo::default(),
+            factory_info: StakeInfo::default(),
+            paymaster_info: StakeInfo::default(),
+            aggregator_info: None,
+        }
+    }
+
+    fn compatibility_router(entry_point: MockEntryPointV0_6) -> (EntryPointRouter, Address) {
+        let v0_7 = stub_route(EntryPointVersion::V0_7);
+        let v0_7_address = v0_7.address();
+        let router = EntryPointRouterBuilder::default()
+            .v0_6(EntryPointRouteImpl::<v0_6::UserOperation, _, _, _>::new(
+                entry_point,
+                MockGasEstimator::new(),
+                NoEvents,
+            ))
+            .v0_7(v0_7)
+            .build();
+        (router, v0_7_address)
+    }
+
+    #[tokio::test]
+    async fn test_check_compatibility_v0_6_only() {
+        let v0_6_address = Address::random();
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point.expect_address().return_const(v0_6_address);
+        entry_point
+            .expect_simulate_validation()
+            .times(1)
+            .returning(|_, _| Ok(Ok(validation_output(false))));
+        let (router, v0_7_address) = compatibility_router(entry_point);
+
+        let uo = UserOperationVariant::V0_6(v0_6::UserOperation::default());
+        let results = router.check_compatibility(&uo).await.unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                RpcEntryPointCompatibility {
+                    entry_point: v0_6_address,
+                    version: "v0.6".to_string(),
+                    compatible: true,
+                    reason: None,
+                },
+                RpcEntryPointCompatibility {
+                    entry_point: v0_7_address,
+                    version: "v0.7".to_string(),
+                    compatible: false,
+                    reason: Some("user operation fields do not match entry point v0.7".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_compatibility_validation_failures() {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point.expect_address().return_const(Address::random());
+        let mut seq = mockall::Sequence::new();
+        entry_point
+            .expect_simulate_validation()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(Ok(validation_output(true))));
+        entry_point
+            .expect_simulate_validation()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(Err(ValidationRevert::EntryPoint("AA23 reverted".into()))));
+        let (router, _) = compatibility_router(entry_point);
+
+        let uo = UserOperationVariant::V0_6(v0_6::UserOperation::default());
+        for expected in ["account signature validation failed", "AA23 reverted"] {
+            let results = router.check_compatibility(&uo).await.unwrap();
+            assert!(!results[0].compatible);
+            assert_eq!(results[0].reason.as_deref(), Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_track_mined_estimates_fetches_estimated_receipts() {
+        let entry_point = Address::random();
+        let estimated = MinedUserOperation {
+            hash: B256::random(),
+            entry_point,
+            sender: Address::random(),
+            nonce: U256::ZERO,
+        };
+        let not_estimated = MinedUserOperation {
+            hash: B256::random(),
+            sender: Address::random(),
+            ..estimated.clone()
+        };
+
+        let mut route = MockEntryPointRoute::new();
+        route.expect_version().return_const(EntryPointVersion::V0_6);
+        route.expect_address().return_const(entry_point);
+        let estimated_hash = estimated.hash;
+        route
+            .expect_get_receipt()
+            .withf(move |hash| *hash == estimated_hash)
+            .times(1)
+            .returning(|_| Ok(None));
+        let router = EntryPointRouterBuilder::default().v0_6(route).build();
+
+        router.estimate_history().record(
+            B256::random(),
+            UserOperationId {
+                sender: estimated.sender,
+                nonce: estimated.nonce,
+            },
+            entry_point,
+            RpcGasEstimateV0_6::from(GasEstimate {
+                pre_verification_gas: 50_000,
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                paymaster_verification_gas_limit: None,
+                block_number: None,
+                breakdown: None,
+                verification_gas_rounds: None,
+                call_gas_rounds: None,
+                warnings: vec![],
+            })
+            .into(),
+        );
+
+        let head = NewHead {
+            block_hash: B256::random(),
+            block_number: 1,
+            mined_ops: vec![not_estimated, estimated],
+        };
+        router
+            .track_mined_estimates(futures_util::stream::iter([head]))
+            .await;
+    }
+}
@@ -0,0 +1,106 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::Address;
+use rundler_utils::cache::LruMap;
+
+/// Maximum number of senders to track submissions for, the least recently seen are dropped first
+const MAX_TRACKED_SENDERS: u32 = 100_000;
+
+/// Sliding window limit on the number of user operations accepted from each sender
+pub(crate) struct SenderRateLimiter {
+    max_ops: usize,
+    window: Duration,
+    submissions: Mutex<LruMap<Address, VecDeque<Instant>>>,
+}
+
+impl SenderRateLimiter {
+    pub(crate) fn new(max_ops: usize, window: Duration) -> Self {
+        Self {
+            max_ops,
+            window,
+            submissions: Mutex::new(LruMap::new(MAX_TRACKED_SENDERS)),
+        }
+    }
+
+    pub(crate) fn max_ops(&self) -> usize {
+        self.max_ops
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a submission from `sender`, returning false if the sender has already reached
+    /// the limit within the current window.
+    pub(crate) fn try_acquire(&self, sender: Address) -> bool {
+        self.try_acquire_at(sender, Instant::now())
+    }
+
+    fn try_acquire_at(&self, sender: Address, now: Instant) -> bool {
+        let mut submissions = self.submissions.lock().unwrap();
+        let Some(times) = submissions.get_or_insert(sender, VecDeque::new) else {
+            return true;
+        };
+        while times
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) >= self.window)
+        {
+            times.pop_front();
+        }
+        if times.len() >= self.max_ops {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_over_limit_within_window() {
+        let limiter = SenderRateLimiter::new(3, Duration::from_secs(60));
+        let sender = Address::random();
+        let start = Instant::now();
+
+        for i in 0..3 {
+            assert!(limiter.try_acquire_at(sender, start + Duration::from_secs(i)));
+        }
+        assert!(!limiter.try_acquire_at(sender, start + Duration::from_secs(59)));
+        // other senders are limited separately
+        assert!(limiter.try_acquire_at(Address::random(), start + Duration::from_secs(59)));
+    }
+
+    #[test]
+    fn test_window_slides() {
+        let limiter = SenderRateLimiter::new(2, Duration::from_secs(60));
+        let sender = Address::random();
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at(sender, start));
+        assert!(limiter.try_acquire_at(sender, start + Duration::from_secs(30)));
+        assert!(!limiter.try_acquire_at(sender, start + Duration::from_secs(45)));
+        // the first submission has left the window, the second has not
+        assert!(limiter.try_acquire_at(sender, start + Duration::from_secs(60)));
+        assert!(!limiter.try_acquire_at(sender, start + Duration::from_secs(61)));
+    }
+}
@@ -52,9 +52,16 @@ where
         entry_point: Address,
         state_override: Option<StateOverride>,
     ) -> RpcResult<RpcGasEstimate> {
+        let aggregator = op.aggregator();
         utils::safe_call_rpc_handler(
             "eth_estimateUserOperationGas",
-            EthApi::estimate_user_operation_gas(self, op.into(), entry_point, state_override),
+            EthApi::estimate_user_operation_gas(
+                self,
+                op.into(),
+                aggregator,
+                entry_point,
+                state_override,
+            ),
         )
         .await
     }
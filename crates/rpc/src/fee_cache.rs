@@ -0,0 +1,215 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::{Stream, StreamExt};
+use rundler_sim::FeeEstimator;
+use rundler_types::{pool::NewHead, GasFees};
+
+#[derive(Debug, Default)]
+struct CachedFees {
+    /// Incremented on every new head, so that fees fetched before a new head are not cached
+    generation: u64,
+    fees: Option<(GasFees, u128)>,
+}
+
+/// Fee estimator that caches the current required bundle fees until the next block.
+///
+/// Only requests without minimum fees are cached, as those are the requests made by the
+/// RPC APIs.
+#[derive(Clone)]
+pub(crate) struct CachedFeeEstimator<F> {
+    inner: F,
+    cache: Arc<Mutex<CachedFees>>,
+}
+
+impl<F: FeeEstimator> CachedFeeEstimator<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(CachedFees::default())),
+        }
+    }
+
+    /// Fetches the current fees into the cache so that the first requests after startup
+    /// don't have to.
+    ///
+    /// Best-effort: failures and timeouts are logged, the fees are then fetched by the
+    /// first request that needs them.
+    pub(crate) async fn warm(&self, timeout: Duration) {
+        match tokio::time::timeout(timeout, self.required_bundle_fees(None)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to warm fee cache: {e:?}"),
+            Err(_) => tracing::warn!("Timed out warming fee cache after {timeout:?}"),
+        }
+    }
+
+    /// Invalidates the cached fees on every new head, until the stream ends.
+    pub(crate) fn track_new_heads(
+        &self,
+        mut new_heads: impl Stream<Item = NewHead> + Unpin,
+    ) -> impl Future<Output = ()> {
+        let cache = self.cache.clone();
+        async move {
+            while new_heads.next().await.is_some() {
+                let mut cache = cache.lock().unwrap();
+                cache.generation += 1;
+                cache.fees = None;
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: FeeEstimator> FeeEstimator for CachedFeeEstimator<F> {
+    async fn required_bundle_fees(
+        &self,
+        min_fees: Option<GasFees>,
+    ) -> anyhow::Result<(GasFees, u128)> {
+        if min_fees.is_some() {
+            return self.inner.required_bundle_fees(min_fees).await;
+        }
+
+        let generation = {
+            let cache = self.cache.lock().unwrap();
+            if let Some(fees) = cache.fees {
+                return Ok(fees);
+            }
+            cache.generation
+        };
+
+        let fees = self.inner.required_bundle_fees(None).await?;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.generation == generation {
+            cache.fees = Some(fees);
+        }
+        Ok(fees)
+    }
+
+    fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+        self.inner.required_op_fees(bundle_fees)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use rundler_sim::MockFeeEstimator;
+
+    use super::*;
+
+    fn fees(max_fee_per_gas: u128) -> GasFees {
+        GasFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: 1,
+        }
+    }
+
+    fn new_head() -> NewHead {
+        NewHead {
+            block_hash: B256::random(),
+            block_number: 1,
+            mined_ops: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fees_cached_until_new_head() {
+        let mut inner = MockFeeEstimator::new();
+        let mut seq = mockall::Sequence::new();
+        inner
+            .expect_required_bundle_fees()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok((fees(10), 5)));
+        inner
+            .expect_required_bundle_fees()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok((fees(20), 6)));
+        let estimator = CachedFeeEstimator::new(inner);
+
+        estimator.warm(Duration::from_secs(1)).await;
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(10), 5)
+        );
+
+        estimator
+            .track_new_heads(futures_util::stream::iter([new_head()]))
+            .await;
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(20), 6)
+        );
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(20), 6)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_min_fees_not_cached() {
+        let mut inner = MockFeeEstimator::new();
+        inner
+            .expect_required_bundle_fees()
+            .times(2)
+            .returning(|min_fees| Ok((min_fees.unwrap_or(fees(10)), 5)));
+        let estimator = CachedFeeEstimator::new(inner);
+
+        assert_eq!(
+            estimator
+                .required_bundle_fees(Some(fees(30)))
+                .await
+                .unwrap(),
+            (fees(30), 5)
+        );
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(10), 5)
+        );
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(10), 5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warm_best_effort() {
+        let mut inner = MockFeeEstimator::new();
+        let mut seq = mockall::Sequence::new();
+        inner
+            .expect_required_bundle_fees()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(anyhow::anyhow!("fees")));
+        inner
+            .expect_required_bundle_fees()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok((fees(10), 5)));
+        let estimator = CachedFeeEstimator::new(inner);
+
+        // failures are logged, not cached
+        estimator.warm(Duration::from_secs(1)).await;
+        assert_eq!(
+            estimator.required_bundle_fees(None).await.unwrap(),
+            (fees(10), 5)
+        );
+    }
+}
@@ -11,9 +11,17 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
 use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
+use rundler_provider::EvmProvider;
 use rundler_task::server::{HealthCheck, ServerStatus};
+use rundler_types::pool::NewHead;
 
 use crate::error::rpc_err;
 
@@ -37,19 +45,175 @@ impl HealthChecker {
 impl SystemApiServer for HealthChecker {
     async fn get_health(&self) -> RpcResult<String> {
         let mut errors = Vec::new();
+        let mut details = Vec::new();
         for server in &self.servers {
-            match server.status().await {
+            let (status, server_details) = server.status_with_details().await;
+            match status {
                 ServerStatus::Serving => {}
                 ServerStatus::NotServing => errors.push(server.name()),
             }
+            if let Some(server_details) = server_details {
+                details.push(format!("{}: {}", server.name(), server_details));
+            }
         }
         if errors.is_empty() {
-            Ok("ok".to_owned())
+            return Ok("ok".to_string());
+        }
+        let details = if details.is_empty() {
+            String::new()
         } else {
-            Err(rpc_err(
-                INTERNAL_ERROR_CODE,
-                format!("Some servers are not serving {}", errors.join(", ")),
-            ))
+            format!(" ({})", details.join(", "))
+        };
+        Err(rpc_err(
+            INTERNAL_ERROR_CODE,
+            format!(
+                "Some servers are not serving {}{details}",
+                errors.join(", ")
+            ),
+        ))
+    }
+}
+
+/// Sentinel for a block number that is not known yet
+const UNKNOWN_BLOCK_NUMBER: u64 = u64::MAX;
+
+/// Reports unhealthy when the provider's latest block is too far ahead of the last block
+/// processed by the pool, reporting the lag in blocks as its details.
+///
+/// Until the pool reports its first head, the lag is measured from the provider's block
+/// at the first check, so a pool that never processes a block is eventually reported.
+pub(crate) struct ProviderBlockLagCheck<P> {
+    provider: P,
+    pool_block_number: Arc<AtomicU64>,
+    max_block_lag: u64,
+}
+
+impl<P> ProviderBlockLagCheck<P> {
+    pub(crate) fn new(provider: P, max_block_lag: u64) -> Self {
+        Self {
+            provider,
+            pool_block_number: Arc::new(AtomicU64::new(UNKNOWN_BLOCK_NUMBER)),
+            max_block_lag,
+        }
+    }
+
+    /// Records the heads processed by the pool until the stream ends.
+    pub(crate) fn track_pool_heads(
+        &self,
+        mut new_heads: impl Stream<Item = NewHead> + Unpin,
+    ) -> impl std::future::Future<Output = ()> {
+        let pool_block_number = self.pool_block_number.clone();
+        async move {
+            while let Some(head) = new_heads.next().await {
+                pool_block_number.store(head.block_number, Ordering::Relaxed);
+            }
         }
     }
 }
+
+#[async_trait]
+impl<P: EvmProvider + 'static> HealthCheck for ProviderBlockLagCheck<P> {
+    fn name(&self) -> &'static str {
+        "ProviderBlockLag"
+    }
+
+    async fn status(&self) -> ServerStatus {
+        self.status_with_details().await.0
+    }
+
+    async fn status_with_details(&self) -> (ServerStatus, Option<String>) {
+        let provider_block_number = match self.provider.get_block_number().await {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::warn!("Failed to get block number for health check: {e:?}");
+                return (ServerStatus::NotServing, None);
+            }
+        };
+        let pool_block_number = match self.pool_block_number.compare_exchange(
+            UNKNOWN_BLOCK_NUMBER,
+            provider_block_number,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => provider_block_number,
+            Err(pool_block_number) => pool_block_number,
+        };
+        let lag = provider_block_number.saturating_sub(pool_block_number);
+        let status = if lag > self.max_block_lag {
+            ServerStatus::NotServing
+        } else {
+            ServerStatus::Serving
+        };
+        (status, Some(lag.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use rundler_provider::MockEvmProvider;
+
+    use super::*;
+
+    fn new_head(block_number: u64) -> NewHead {
+        NewHead {
+            block_hash: B256::random(),
+            block_number,
+            mined_ops: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_block_lag() {
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(110));
+        let check = ProviderBlockLagCheck::new(provider, 5);
+
+        check
+            .track_pool_heads(futures_util::stream::iter([new_head(100), new_head(104)]))
+            .await;
+        let checker = HealthChecker::new(vec![Box::new(check)]);
+        let err = checker.get_health().await.unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Some servers are not serving ProviderBlockLag (ProviderBlockLag: 6)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_provider_block_lag_within_limit() {
+        let mut provider = MockEvmProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(110));
+        let check = ProviderBlockLagCheck::new(provider, 5);
+
+        check
+            .track_pool_heads(futures_util::stream::iter([new_head(108)]))
+            .await;
+        let checker = HealthChecker::new(vec![Box::new(check)]);
+        assert_eq!(checker.get_health().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_provider_block_lag_before_first_pool_head() {
+        let provider_block_number = Arc::new(AtomicU64::new(110));
+        let mut provider = MockEvmProvider::default();
+        let block_number = provider_block_number.clone();
+        provider
+            .expect_get_block_number()
+            .returning(move || Ok(block_number.load(Ordering::Relaxed)));
+        let checker = HealthChecker::new(vec![Box::new(ProviderBlockLagCheck::new(provider, 5))]);
+
+        // healthy at startup, the lag is measured from the first check
+        assert_eq!(checker.get_health().await.unwrap(), "ok");
+        provider_block_number.store(115, Ordering::Relaxed);
+        assert_eq!(checker.get_health().await.unwrap(), "ok");
+
+        // unhealthy if the pool still hasn't processed a block
+        provider_block_number.store(116, Ordering::Relaxed);
+        let err = checker.get_health().await.unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Some servers are not serving ProviderBlockLag (ProviderBlockLag: 6)"
+        );
+    }
+}
@@ -27,8 +27,12 @@ pub use admin::AdminApiClient;
 
 mod error;
 
+mod estimate_history;
+
+mod fee_cache;
+
 mod eth;
-pub use eth::{EthApiClient, EthApiSettings};
+pub use eth::{decode_handle_ops_calldata, DecodeError, EthApiClient, EthApiSettings};
 
 mod health;
 
@@ -38,6 +42,10 @@ pub use rundler::{RundlerApiClient, Settings as RundlerApiSettings};
 mod task;
 pub use task::{Args as RpcTaskArgs, RpcTask};
 
+mod concurrency_limit;
+mod drain;
+mod method_timeout;
+mod prometheus;
 mod rpc_metrics;
 mod types;
 mod utils;
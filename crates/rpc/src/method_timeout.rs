@@ -0,0 +1,95 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use jsonrpsee::{
+    server::middleware::rpc::RpcServiceT,
+    types::{error::INTERNAL_ERROR_CODE, Request},
+    MethodResponse,
+};
+use tower::Layer;
+
+use crate::error::rpc_err;
+
+/// Applies a timeout to each method call, using the timeout configured for the method
+/// if there is one and the default timeout otherwise.
+#[derive(Clone)]
+pub(crate) struct RpcMethodTimeoutLayer {
+    default_timeout: Duration,
+    method_timeouts: Arc<HashMap<String, Duration>>,
+}
+
+impl RpcMethodTimeoutLayer {
+    pub(crate) fn new(
+        default_timeout: Duration,
+        method_timeouts: HashMap<String, Duration>,
+    ) -> Self {
+        Self {
+            default_timeout,
+            method_timeouts: Arc::new(method_timeouts),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcMethodTimeoutLayer {
+    type Service = RpcMethodTimeout<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RpcMethodTimeout {
+            service,
+            default_timeout: self.default_timeout,
+            method_timeouts: self.method_timeouts.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RpcMethodTimeout<S> {
+    service: S,
+    default_timeout: Duration,
+    method_timeouts: Arc<HashMap<String, Duration>>,
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcMethodTimeout<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = BoxFuture<'a, MethodResponse>;
+
+    fn call(&self, req: Request<'a>) -> Self::Future {
+        let timeout = self
+            .method_timeouts
+            .get(req.method_name())
+            .copied()
+            .unwrap_or(self.default_timeout);
+        let id = req.id();
+        let method = req.method_name().to_string();
+        let svc = self.service.clone();
+
+        async move {
+            match tokio::time::timeout(timeout, svc.call(req)).await {
+                Ok(rp) => rp,
+                Err(_) => MethodResponse::error(
+                    id,
+                    rpc_err(
+                        INTERNAL_ERROR_CODE,
+                        format!("{method} timed out after {}ms", timeout.as_millis()),
+                    ),
+                ),
+            }
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,173 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use futures_util::{future::BoxFuture, FutureExt};
+use http::{header, Method, Request as httpRequest, StatusCode};
+use jsonrpsee::server::{HttpBody, HttpResponse};
+use metrics_exporter_prometheus::PrometheusHandle;
+use tower::{Layer, Service};
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Layer that serves the metrics recorded by the process in Prometheus
+/// exposition format on `GET {path}`. All other requests are passed through.
+#[derive(Clone)]
+pub(crate) struct PrometheusMetricsLayer {
+    path: String,
+    handle: Option<PrometheusHandle>,
+}
+
+impl PrometheusMetricsLayer {
+    pub(crate) fn new(path: impl Into<String>, handle: Option<PrometheusHandle>) -> Self {
+        Self {
+            path: path.into(),
+            handle,
+        }
+    }
+}
+
+impl<S> Layer<S> for PrometheusMetricsLayer {
+    type Service = PrometheusMetrics<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        PrometheusMetrics {
+            service,
+            path: self.path.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct PrometheusMetrics<S> {
+    service: S,
+    path: String,
+    handle: Option<PrometheusHandle>,
+}
+
+impl<S, R> Service<httpRequest<R>> for PrometheusMetrics<S>
+where
+    S: Service<httpRequest<R>, Response = HttpResponse> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: httpRequest<R>) -> Self::Future {
+        match &self.handle {
+            Some(handle) if req.method() == Method::GET && req.uri().path() == self.path => {
+                let response = metrics_response(handle);
+                async move { Ok(response) }.boxed()
+            }
+            _ => self.service.call(req).boxed(),
+        }
+    }
+}
+
+fn metrics_response(handle: &PrometheusHandle) -> HttpResponse {
+    http::Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+        .body(HttpBody::from(handle.render()))
+        .expect("metrics response should build")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+    };
+
+    use http_body_util::BodyExt;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct NotFound;
+
+    impl Service<httpRequest<HttpBody>> for NotFound {
+        type Response = HttpResponse;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: httpRequest<HttpBody>) -> Self::Future {
+            ready(Ok(http::Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(HttpBody::empty())
+                .unwrap()))
+        }
+    }
+
+    fn get(path: &str) -> httpRequest<HttpBody> {
+        httpRequest::builder()
+            .method(Method::GET)
+            .uri(path)
+            .body(HttpBody::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("test_estimation_errors").increment(3);
+            metrics::gauge!("test_mempool_size").set(7.0);
+        });
+
+        let mut service = PrometheusMetricsLayer::new("/metrics", Some(handle)).layer(NotFound);
+        let response = service.call(get("/metrics")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROMETHEUS_CONTENT_TYPE
+        );
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("test_estimation_errors 3"));
+        assert!(body.contains("test_mempool_size 7"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_passthrough() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let mut service =
+            PrometheusMetricsLayer::new("/metrics", Some(recorder.handle())).layer(NotFound);
+        let response = service.call(get("/other")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut service = PrometheusMetricsLayer::new("/metrics", None).layer(NotFound);
+        let response = service.call(get("/metrics")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -11,19 +11,44 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, B256, U128};
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
 use anyhow::Context;
 use async_trait::async_trait;
-use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use futures_util::StreamExt;
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    proc_macros::rpc,
+    PendingSubscriptionSink, SubscriptionMessage,
+};
+use rundler_provider::{BlockId, EvmProvider, StateOverride};
 use rundler_sim::{gas, FeeEstimator};
-use rundler_types::{chain::ChainSpec, pool::Pool, UserOperation, UserOperationVariant};
+use rundler_types::{
+    chain::ChainSpec,
+    pool::{Pool, PoolError},
+    GasFees, UserOperation, UserOperationOptionalGas, UserOperationVariant,
+};
 
 use crate::{
     eth::{EntryPointRouter, EthResult, EthRpcError},
-    types::{FromRpc, RpcUserOperation},
+    types::{
+        FromRpc, RpcCheckUserOperationResult, RpcEntryPointCompatibility, RpcEstimateHistoryEntry,
+        RpcEstimationAccuracy, RpcGasEstimate, RpcGasEstimateResult, RpcInclusionTimeEstimate,
+        RpcMinedUserOperation, RpcUserOperation, RpcUserOperationFilter,
+        RpcUserOperationOptionalGas, RpcValidationOutput,
+    },
     utils,
 };
 
+/// Number of recent blocks used to measure the chain's block cadence
+const BLOCK_TIME_SAMPLE_SIZE: u64 = 10;
+/// Maximum number of blocks an inclusion time estimate can cover. Operations that
+/// would take longer than this to become includable are reported as not includable.
+const MAX_INCLUSION_BLOCKS: u64 = 256;
+/// Maximum number of user operations in a single batch estimation request
+const MAX_ESTIMATE_BATCH_SIZE: usize = 32;
+/// Maximum number of user operations in a batch estimated concurrently
+const ESTIMATE_BATCH_CONCURRENCY: usize = 8;
+
 /// Settings for the `rundler_` API
 #[derive(Copy, Clone, Debug)]
 pub struct Settings {
@@ -32,11 +57,18 @@ pub struct Settings {
     /// If using a bundle priority fee, the percentage to add to the network/oracle
     /// provided value as a safety margin for fast inclusion.
     pub bundle_priority_fee_overhead_percent: u32,
+    /// Whether to include the number of binary search rounds taken in gas estimates
+    /// returned by `rundler_estimateUserOperationGasWithCallData`, for debugging.
+    pub estimation_rounds_enabled: bool,
 }
 
 #[rpc(client, server, namespace = "rundler")]
 pub trait RundlerApi {
     /// Returns the maximum priority fee per gas required by Rundler
+    ///
+    /// Reflects the bundler's own bundle submission strategy: the network priority fee is
+    /// increased by the bundle priority fee overhead, then the priority fee mode is applied
+    /// to get the fee an operation must pay to be included.
     #[method(name = "maxPriorityFeePerGas")]
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U128>;
 
@@ -54,20 +86,151 @@ pub trait RundlerApi {
         uo: RpcUserOperation,
         entry_point: Address,
     ) -> RpcResult<Option<B256>>;
+
+    /// Returns the position of a pending user operation in the bundler's queue, 0 being
+    /// the next to be bundled.
+    ///
+    /// Returns none if the user operation is not pending in the mempool.
+    #[method(name = "getUserOperationQueuePosition")]
+    async fn get_user_operation_queue_position(&self, hash: B256) -> RpcResult<Option<U64>>;
+
+    /// Estimates the time until a user operation with the given fees is included onchain,
+    /// based on current network fees and the recent block cadence.
+    ///
+    /// Returns none if the user operation is not expected to be included at current fees.
+    #[method(name = "estimateInclusionTime")]
+    async fn estimate_inclusion_time(
+        &self,
+        uo: RpcUserOperation,
+    ) -> RpcResult<Option<RpcInclusionTimeEstimate>>;
+
+    /// Runs the entry point's validation simulation for a user operation and returns its
+    /// full structured output, including prefund, return info, and entity stake infos.
+    ///
+    /// Intended for debugging. The user operation is not added to the mempool.
+    #[method(name = "validateUserOperation")]
+    async fn validate_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcValidationOutput>;
+
+    /// Checks whether a user operation would be accepted by the mempool, running the same
+    /// checks as `eth_sendUserOperation`.
+    ///
+    /// The user operation is not added to the mempool.
+    #[method(name = "checkUserOperation")]
+    async fn check_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcCheckUserOperationResult>;
+
+    /// Checks a user operation against each enabled entry point version and reports which
+    /// versions accept it.
+    ///
+    /// Each version runs a structural check of the operation's fields followed by a dry run
+    /// of its validation simulation. The user operation is not added to the mempool.
+    #[method(name = "checkEntryPointCompatibility")]
+    async fn check_entry_point_compatibility(
+        &self,
+        uo: RpcUserOperation,
+    ) -> RpcResult<Vec<RpcEntryPointCompatibility>>;
+
+    /// Estimates the gas fields for a user operation with its call data replaced by `call_data`.
+    ///
+    /// Allows estimating different calls from the same account without re-signing, as the
+    /// signature is a dummy value during estimation.
+    #[method(name = "estimateUserOperationGasWithCallData")]
+    async fn estimate_user_operation_gas_with_call_data(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        call_data: Bytes,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<RpcGasEstimate>;
+
+    /// Estimates the gas fields for a batch of user operations against a single entry point.
+    ///
+    /// Operations are estimated concurrently and results are returned in the order of the
+    /// input operations. An operation failing estimation doesn't fail the batch, its error is
+    /// returned in place of its estimate.
+    #[method(name = "estimateUserOperationGasBatch")]
+    async fn estimate_user_operation_gas_batch(
+        &self,
+        ops: Vec<RpcUserOperationOptionalGas>,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<Vec<RpcGasEstimateResult>>;
+
+    /// Returns the length in bytes of the aggregated signature produced by a signature
+    /// aggregator, for sizing the signature of user operations that use it.
+    ///
+    /// Returns none if the aggregator does not expose its signature length.
+    #[method(name = "getAggregatorSignatureLength")]
+    async fn get_aggregator_signature_length(
+        &self,
+        aggregator: Address,
+        entry_point: Address,
+    ) -> RpcResult<Option<U64>>;
+
+    /// Returns the gas estimates recently returned for a user operation, oldest first.
+    ///
+    /// The user operation is identified by its hash as sent for estimation, with any unset
+    /// gas and fee fields set to zero.
+    #[method(name = "getEstimateHistory")]
+    async fn get_estimate_history(&self, hash: B256) -> RpcResult<Vec<RpcEstimateHistoryEntry>>;
+
+    /// Compares the gas most recently estimated for a mined user operation's sender and
+    /// nonce to the gas it actually used, for calibrating estimation buffers.
+    ///
+    /// Returns none if the user operation has not been mined or no estimate was recorded.
+    #[method(name = "getEstimationAccuracy")]
+    async fn get_estimation_accuracy(&self, hash: B256)
+        -> RpcResult<Option<RpcEstimationAccuracy>>;
+
+    /// Returns the deposit, in wei, that the entry point requires the account to have to
+    /// cover a user operation that doesn't use a paymaster.
+    ///
+    /// The entry point requires the prefund at the operation's `maxFeePerGas`, so this is the
+    /// exact amount to deposit for the operation to pass validation.
+    #[method(name = "getRequiredPrefund")]
+    async fn get_required_prefund(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<U256>;
+
+    /// Subscribes to user operations as they are mined, optionally filtered by sender or
+    /// user operation hash.
+    ///
+    /// Only available over WebSocket.
+    #[subscription(
+        name = "subscribeUserOperations" => "userOperations",
+        unsubscribe = "unsubscribeUserOperations",
+        item = RpcMinedUserOperation
+    )]
+    async fn subscribe_user_operations(
+        &self,
+        filter: Option<RpcUserOperationFilter>,
+    ) -> SubscriptionResult;
 }
 
-pub(crate) struct RundlerApi<P, F> {
+pub(crate) struct RundlerApi<P, F, E> {
     chain_spec: ChainSpec,
     fee_estimator: F,
     pool_server: P,
     entry_point_router: EntryPointRouter,
+    evm: E,
+    estimation_rounds_enabled: bool,
 }
 
 #[async_trait]
-impl<P, F> RundlerApiServer for RundlerApi<P, F>
+impl<P, F, E> RundlerApiServer for RundlerApi<P, F, E>
 where
     P: Pool + 'static,
     F: FeeEstimator + 'static,
+    E: EvmProvider + 'static,
 {
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U128> {
         utils::safe_call_rpc_handler(
@@ -88,24 +251,198 @@ where
         )
         .await
     }
+
+    async fn get_user_operation_queue_position(&self, hash: B256) -> RpcResult<Option<U64>> {
+        utils::safe_call_rpc_handler(
+            "rundler_getUserOperationQueuePosition",
+            RundlerApi::get_user_operation_queue_position(self, hash),
+        )
+        .await
+    }
+
+    async fn estimate_inclusion_time(
+        &self,
+        uo: RpcUserOperation,
+    ) -> RpcResult<Option<RpcInclusionTimeEstimate>> {
+        utils::safe_call_rpc_handler(
+            "rundler_estimateInclusionTime",
+            RundlerApi::estimate_inclusion_time(self, uo),
+        )
+        .await
+    }
+
+    async fn validate_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcValidationOutput> {
+        utils::safe_call_rpc_handler(
+            "rundler_validateUserOperation",
+            RundlerApi::validate_user_operation(self, uo, entry_point),
+        )
+        .await
+    }
+
+    async fn check_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcCheckUserOperationResult> {
+        utils::safe_call_rpc_handler(
+            "rundler_checkUserOperation",
+            RundlerApi::check_user_operation(self, uo, entry_point),
+        )
+        .await
+    }
+
+    async fn check_entry_point_compatibility(
+        &self,
+        uo: RpcUserOperation,
+    ) -> RpcResult<Vec<RpcEntryPointCompatibility>> {
+        utils::safe_call_rpc_handler(
+            "rundler_checkEntryPointCompatibility",
+            RundlerApi::check_entry_point_compatibility(self, uo),
+        )
+        .await
+    }
+
+    async fn estimate_user_operation_gas_with_call_data(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        call_data: Bytes,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<RpcGasEstimate> {
+        let aggregator = op.aggregator();
+        utils::safe_call_rpc_handler(
+            "rundler_estimateUserOperationGasWithCallData",
+            RundlerApi::estimate_user_operation_gas_with_call_data(
+                self,
+                op.into(),
+                aggregator,
+                entry_point,
+                call_data,
+                state_override,
+            ),
+        )
+        .await
+    }
+
+    async fn estimate_user_operation_gas_batch(
+        &self,
+        ops: Vec<RpcUserOperationOptionalGas>,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<Vec<RpcGasEstimateResult>> {
+        utils::safe_call_rpc_handler(
+            "rundler_estimateUserOperationGasBatch",
+            RundlerApi::estimate_user_operation_gas_batch(
+                self,
+                ops.into_iter()
+                    .map(|op| (op.aggregator(), op.into()))
+                    .collect(),
+                entry_point,
+                state_override,
+            ),
+        )
+        .await
+    }
+
+    async fn get_aggregator_signature_length(
+        &self,
+        aggregator: Address,
+        entry_point: Address,
+    ) -> RpcResult<Option<U64>> {
+        utils::safe_call_rpc_handler(
+            "rundler_getAggregatorSignatureLength",
+            self.entry_point_router
+                .aggregated_signature_length(&entry_point, aggregator),
+        )
+        .await
+    }
+
+    async fn get_estimate_history(&self, hash: B256) -> RpcResult<Vec<RpcEstimateHistoryEntry>> {
+        utils::safe_call_rpc_handler(
+            "rundler_getEstimateHistory",
+            RundlerApi::get_estimate_history(self, hash),
+        )
+        .await
+    }
+
+    async fn get_estimation_accuracy(
+        &self,
+        hash: B256,
+    ) -> RpcResult<Option<RpcEstimationAccuracy>> {
+        utils::safe_call_rpc_handler(
+            "rundler_getEstimationAccuracy",
+            RundlerApi::get_estimation_accuracy(self, hash),
+        )
+        .await
+    }
+
+    async fn get_required_prefund(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<U256> {
+        utils::safe_call_rpc_handler(
+            "rundler_getRequiredPrefund",
+            RundlerApi::get_required_prefund(self, uo, entry_point),
+        )
+        .await
+    }
+
+    async fn subscribe_user_operations(
+        &self,
+        pending: PendingSubscriptionSink,
+        filter: Option<RpcUserOperationFilter>,
+    ) -> SubscriptionResult {
+        let new_heads = match self.pool_server.subscribe_new_heads().await {
+            Ok(new_heads) => new_heads,
+            Err(e) => {
+                tracing::error!("Error subscribing to pool new heads: {e:?}");
+                pending.reject(EthRpcError::from(e)).await;
+                return Ok(());
+            }
+        };
+        let sink = pending.accept().await?;
+        let filter = filter.unwrap_or_default();
+
+        let mut new_heads = new_heads.take_until(sink.closed());
+        while let Some(head) = new_heads.next().await {
+            for op in head.mined_ops.iter().filter(|op| filter.matches(op)) {
+                let msg =
+                    SubscriptionMessage::from_json(&RpcMinedUserOperation::new(op.clone(), &head))?;
+                if sink.send(msg).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
-impl<P, F> RundlerApi<P, F>
+impl<P, F, E> RundlerApi<P, F, E>
 where
     P: Pool,
     F: FeeEstimator,
+    E: EvmProvider,
 {
     pub(crate) fn new(
         chain_spec: &ChainSpec,
         entry_point_router: EntryPointRouter,
         pool_server: P,
         fee_estimator: F,
+        evm: E,
+        estimation_rounds_enabled: bool,
     ) -> Self {
         Self {
             chain_spec: chain_spec.clone(),
             entry_point_router,
             pool_server,
             fee_estimator,
+            evm,
+            estimation_rounds_enabled,
         }
     }
 
@@ -160,4 +497,931 @@ where
 
         Ok(ret)
     }
+    async fn get_user_operation_queue_position(&self, hash: B256) -> EthResult<Option<U64>> {
+        let position = self
+            .pool_server
+            .get_op_queue_position(hash)
+            .await
+            .map_err(EthRpcError::from)?;
+        Ok(position.map(U64::from))
+    }
+
+    async fn estimate_inclusion_time(
+        &self,
+        uo: RpcUserOperation,
+    ) -> EthResult<Option<RpcInclusionTimeEstimate>> {
+        let uo = UserOperationVariant::from_rpc(uo, &self.chain_spec);
+
+        let (bundle_fees, base_fee) = self
+            .fee_estimator
+            .required_bundle_fees(None)
+            .await
+            .context("should get required fees")?;
+        let required_fees = self.fee_estimator.required_op_fees(bundle_fees);
+
+        let Some(blocks) = estimate_inclusion_blocks(
+            GasFees {
+                max_fee_per_gas: uo.max_fee_per_gas(),
+                max_priority_fee_per_gas: uo.max_priority_fee_per_gas(),
+            },
+            base_fee,
+            required_fees.max_priority_fee_per_gas,
+        ) else {
+            return Ok(None);
+        };
+
+        let block_time = self.average_block_time().await?;
+        Ok(Some(RpcInclusionTimeEstimate {
+            blocks: U64::from(blocks),
+            seconds: U64::from(blocks * block_time),
+        }))
+    }
+
+    async fn validate_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> EthResult<RpcValidationOutput> {
+        let uo = UserOperationVariant::from_rpc(uo, &self.chain_spec);
+        self.entry_point_router
+            .simulate_validation(&entry_point, uo)
+            .await
+    }
+
+    async fn check_user_operation(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> EthResult<RpcCheckUserOperationResult> {
+        let uo = UserOperationVariant::from_rpc(uo, &self.chain_spec);
+        let hash = uo.hash(entry_point, self.chain_spec.id);
+
+        match self.pool_server.check_op(entry_point, uo).await {
+            Ok(hash) => Ok(RpcCheckUserOperationResult {
+                accepted: true,
+                hash,
+                reason: None,
+            }),
+            Err(PoolError::MempoolError(e)) => Ok(RpcCheckUserOperationResult {
+                accepted: false,
+                hash,
+                reason: Some(EthRpcError::from(e).to_string()),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn check_entry_point_compatibility(
+        &self,
+        uo: RpcUserOperation,
+    ) -> EthResult<Vec<RpcEntryPointCompatibility>> {
+        let uo = UserOperationVariant::from_rpc(uo, &self.chain_spec);
+        self.entry_point_router.check_compatibility(&uo).await
+    }
+
+    async fn get_required_prefund(
+        &self,
+        uo: RpcUserOperation,
+        entry_point: Address,
+    ) -> EthResult<U256> {
+        let uo = UserOperationVariant::from_rpc(uo, &self.chain_spec);
+        self.entry_point_router
+            .check_and_get_route(&entry_point, &uo)?;
+
+        if uo.paymaster().is_some() {
+            return Err(EthRpcError::InvalidParams(
+                "User operation uses a paymaster, which pays its prefund".to_string(),
+            ));
+        }
+        if uo.checked_max_gas_cost().is_none() {
+            return Err(EthRpcError::InvalidParams(
+                "User operation gas fields overflow".to_string(),
+            ));
+        }
+        Ok(uo.required_prefund(false))
+    }
+
+    async fn estimate_user_operation_gas_with_call_data(
+        &self,
+        op: UserOperationOptionalGas,
+        aggregator: Option<Address>,
+        entry_point: Address,
+        call_data: Bytes,
+        state_override: Option<StateOverride>,
+    ) -> EthResult<RpcGasEstimate> {
+        self.estimate_user_operation_gas_inner(
+            op.with_call_data(call_data),
+            aggregator,
+            entry_point,
+            state_override,
+            None,
+        )
+        .await
+    }
+
+    /// Each operation is paired with the signature aggregator it will be bundled with, if any
+    async fn estimate_user_operation_gas_batch(
+        &self,
+        ops: Vec<(Option<Address>, UserOperationOptionalGas)>,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+    ) -> EthResult<Vec<RpcGasEstimateResult>> {
+        if ops.len() > MAX_ESTIMATE_BATCH_SIZE {
+            return Err(EthRpcError::InvalidParams(format!(
+                "Batch of {} user operations exceeds the max batch size of {}",
+                ops.len(),
+                MAX_ESTIMATE_BATCH_SIZE
+            )));
+        }
+
+        // estimate every op against the same block so the results are consistent with each other
+        let block = self
+            .evm
+            .get_latest_block_hash_and_number()
+            .await
+            .context("should get latest block")?;
+
+        Ok(futures_util::stream::iter(ops)
+            .map(|(aggregator, op)| {
+                self.estimate_user_operation_gas_inner(
+                    op,
+                    aggregator,
+                    entry_point,
+                    state_override.clone(),
+                    Some(block),
+                )
+            })
+            .buffered(ESTIMATE_BATCH_CONCURRENCY)
+            .map(RpcGasEstimateResult::from)
+            .collect()
+            .await)
+    }
+
+    async fn estimate_user_operation_gas_inner(
+        &self,
+        op: UserOperationOptionalGas,
+        aggregator: Option<Address>,
+        entry_point: Address,
+        state_override: Option<StateOverride>,
+        block: Option<(B256, u64)>,
+    ) -> EthResult<RpcGasEstimate> {
+        let bundle_size = op.single_uo_bundle_size_bytes();
+        if bundle_size > self.chain_spec.max_transaction_size_bytes {
+            return Err(EthRpcError::InvalidParams(format!(
+                "User operation in bundle size {} exceeds max transaction size {}",
+                bundle_size, self.chain_spec.max_transaction_size_bytes
+            )));
+        }
+
+        let hash = op.hash(entry_point, &self.chain_spec);
+        let id = op.id();
+        let estimate = match block {
+            Some(block) => {
+                self.entry_point_router
+                    .estimate_gas_with_block(&entry_point, op, aggregator, state_override, block)
+                    .await?
+            }
+            None => {
+                self.entry_point_router
+                    .estimate_gas(&entry_point, op, aggregator, state_override)
+                    .await?
+            }
+        };
+        let estimate = if self.estimation_rounds_enabled {
+            estimate
+        } else {
+            estimate.without_rounds()
+        };
+        self.entry_point_router
+            .estimate_history()
+            .record(hash, id, entry_point, estimate.clone());
+        Ok(estimate)
+    }
+
+    async fn get_estimate_history(&self, hash: B256) -> EthResult<Vec<RpcEstimateHistoryEntry>> {
+        Ok(self.entry_point_router.estimate_history().get(hash))
+    }
+
+    async fn get_estimation_accuracy(
+        &self,
+        hash: B256,
+    ) -> EthResult<Option<RpcEstimationAccuracy>> {
+        for ep in self.entry_point_router.entry_points() {
+            if let Some(receipt) = self.entry_point_router.get_receipt(ep, hash).await? {
+                return Ok(self
+                    .entry_point_router
+                    .estimate_history()
+                    .accuracy(&receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Average time between the most recent blocks, in seconds
+    async fn average_block_time(&self) -> EthResult<u64> {
+        let latest = self
+            .evm
+            .get_block(BlockId::latest())
+            .await
+            .context("should get latest block")?
+            .context("latest block should exist")?;
+        let sample_start = latest.header.number.saturating_sub(BLOCK_TIME_SAMPLE_SIZE);
+        if sample_start == latest.header.number {
+            return Ok(0);
+        }
+
+        let earlier = self
+            .evm
+            .get_block(sample_start.into())
+            .await
+            .context("should get block")?
+            .context("block should exist")?;
+        Ok(latest
+            .header
+            .timestamp
+            .saturating_sub(earlier.header.timestamp)
+            .div_ceil(latest.header.number - sample_start))
+    }
+}
+
+/// Estimates the number of blocks until an operation with the given fees is includable.
+///
+/// An operation paying at least the required priority fee is includable once the base fee
+/// falls to within its max fee. The base fee is assumed to decrease at the maximum rate
+/// allowed by EIP-1559 (12.5% per block), so this is a lower bound on the wait for operations
+/// priced below the current base fee.
+///
+/// Returns None if the operation's priority fee is too low or if it would not become
+/// includable within `MAX_INCLUSION_BLOCKS`.
+fn estimate_inclusion_blocks(
+    op_fees: GasFees,
+    base_fee: u128,
+    required_priority_fee: u128,
+) -> Option<u64> {
+    if op_fees.max_priority_fee_per_gas < required_priority_fee {
+        return None;
+    }
+
+    let mut base_fee = base_fee;
+    let mut blocks = 1;
+    while op_fees.max_fee_per_gas < base_fee + required_priority_fee {
+        if blocks >= MAX_INCLUSION_BLOCKS {
+            return None;
+        }
+        base_fee -= base_fee / 8;
+        blocks += 1;
+    }
+    Some(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use jsonrpsee::rpc_params;
+    use rundler_provider::MockEvmProvider;
+    use rundler_sim::{GasEstimationError, MockFeeEstimator};
+    use rundler_types::{
+        pool::{MempoolError, MinedUserOperation, MockPool, NewHead},
+        v0_6, AggregatorInfo, EntryPointVersion, GasEstimate, StakeInfo, Timestamp,
+        ValidationOutput, ValidationReturnInfo,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        eth::{EntryPointRouterBuilder, MockEntryPointRoute},
+        types::RpcGasEstimateV0_6,
+    };
+
+    /// Call data that makes estimation on `call_data_route` revert
+    const REVERT_CALL_DATA: [u8; 1] = [0xff];
+
+    /// Route whose call gas estimate grows with the length of the op's call data, reverting
+    /// for ops with `REVERT_CALL_DATA`
+    fn call_data_route() -> MockEntryPointRoute {
+        let mut route = MockEntryPointRoute::new();
+        route.expect_version().return_const(EntryPointVersion::V0_6);
+        route.expect_address().return_const(Address::ZERO);
+        route
+            .expect_estimate_gas()
+            .returning(|uo, _| call_data_route_estimate(uo));
+        route
+    }
+
+    fn call_data_route_estimate(
+        uo: UserOperationOptionalGas,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let UserOperationOptionalGas::V0_6(uo) = uo else {
+            panic!("expected v0.6 op");
+        };
+        if uo.call_data[..] == REVERT_CALL_DATA {
+            return Err(GasEstimationError::RevertInCallWithMessage(
+                "reverted".to_string(),
+            ));
+        }
+        Ok(call_data_estimate(uo.call_data.len()))
+    }
+
+    fn call_data_estimate(call_data_len: usize) -> GasEstimate {
+        GasEstimate {
+            pre_verification_gas: 50_000,
+            call_gas_limit: 21_000 + 16 * call_data_len as u128,
+            verification_gas_limit: 100_000,
+            paymaster_verification_gas_limit: None,
+            block_number: None,
+            breakdown: None,
+            verification_gas_rounds: Some(12),
+            call_gas_rounds: Some(20),
+            warnings: vec![],
+        }
+    }
+
+    fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> GasFees {
+        GasFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    #[test]
+    fn test_inclusion_blocks_sufficient_fees() {
+        assert_eq!(estimate_inclusion_blocks(fees(120, 10), 100, 10), Some(1));
+        assert_eq!(estimate_inclusion_blocks(fees(110, 10), 100, 10), Some(1));
+    }
+
+    #[test]
+    fn test_inclusion_blocks_higher_fee_is_faster() {
+        let low = estimate_inclusion_blocks(fees(50, 10), 100, 10).unwrap();
+        let high = estimate_inclusion_blocks(fees(90, 10), 100, 10).unwrap();
+        assert!(high < low);
+        assert!(high > 1);
+    }
+
+    #[test]
+    fn test_inclusion_blocks_priority_fee_too_low() {
+        assert_eq!(estimate_inclusion_blocks(fees(1000, 5), 100, 10), None);
+    }
+
+    #[test]
+    fn test_inclusion_blocks_never_includable() {
+        assert_eq!(estimate_inclusion_blocks(fees(10, 10), 1_000_000, 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_operation_queue_position() {
+        let hash = B256::random();
+        let mut pool = MockPool::default();
+        pool.expect_get_op_queue_position()
+            .returning(move |h| Ok((h == hash).then_some(3)));
+
+        let api = RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default().build(),
+            pool,
+            MockFeeEstimator::new(),
+            MockEvmProvider::new(),
+            false,
+        );
+
+        assert_eq!(
+            api.get_user_operation_queue_position(hash).await.unwrap(),
+            Some(U64::from(3))
+        );
+        assert_eq!(
+            api.get_user_operation_queue_position(B256::random())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_priority_fee_per_gas() {
+        let mut fee_estimator = MockFeeEstimator::new();
+        // bundle priority fee already includes the configured overhead
+        fee_estimator
+            .expect_required_bundle_fees()
+            .withf(|min_fees| min_fees.is_none())
+            .returning(|_| Ok((fees(1_150, 110), 1_000)));
+        // priority fee mode requires ops to pay the bundle priority fee plus 10%
+        fee_estimator
+            .expect_required_op_fees()
+            .withf(|bundle_fees| *bundle_fees == fees(1_150, 110))
+            .returning(|bundle_fees| {
+                fees(
+                    bundle_fees.max_fee_per_gas,
+                    bundle_fees.max_priority_fee_per_gas * 110 / 100,
+                )
+            });
+
+        let api = RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default().build(),
+            MockPool::default(),
+            fee_estimator,
+            MockEvmProvider::new(),
+            false,
+        );
+
+        assert_eq!(
+            api.max_priority_fee_per_gas().await.unwrap(),
+            U128::from(121)
+        );
+    }
+
+    fn check_api(pool: MockPool) -> RundlerApi<MockPool, MockFeeEstimator, MockEvmProvider> {
+        RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default().build(),
+            pool,
+            MockFeeEstimator::new(),
+            MockEvmProvider::new(),
+            false,
+        )
+    }
+
+    fn check_op() -> v0_6::UserOperation {
+        v0_6::UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_user_operation_accepted() {
+        let uo = check_op();
+        let expected_hash = uo.hash(Address::ZERO, ChainSpec::default().id);
+
+        let mut pool = MockPool::default();
+        pool.expect_check_op()
+            .returning(|ep, op| Ok(op.hash(ep, ChainSpec::default().id)));
+        pool.expect_add_op().never();
+
+        let result = check_api(pool)
+            .check_user_operation(UserOperationVariant::V0_6(uo).into(), Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            RpcCheckUserOperationResult {
+                accepted: true,
+                hash: expected_hash,
+                reason: None,
+            }
+        );
+
+        let json = serde_json::to_value(result).unwrap();
+        assert!(json.get("reason").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_user_operation_rejected() {
+        let uo = check_op();
+        let expected_hash = uo.hash(Address::ZERO, ChainSpec::default().id);
+
+        let mut pool = MockPool::default();
+        pool.expect_check_op()
+            .returning(|_, _| Err(MempoolError::OperationAlreadyKnown.into()));
+
+        let result = check_api(pool)
+            .check_user_operation(UserOperationVariant::V0_6(uo).into(), Address::ZERO)
+            .await
+            .unwrap();
+        assert!(!result.accepted);
+        assert_eq!(result.hash, expected_hash);
+        assert_eq!(
+            result.reason,
+            Some(EthRpcError::OperationAlreadyKnown.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_user_operation_internal_error() {
+        let mut pool = MockPool::default();
+        pool.expect_check_op()
+            .returning(|_, _| Err(PoolError::UnexpectedResponse));
+
+        assert!(check_api(pool)
+            .check_user_operation(UserOperationVariant::V0_6(check_op()).into(), Address::ZERO)
+            .await
+            .is_err());
+    }
+
+    fn prefund_api() -> RundlerApi<MockPool, MockFeeEstimator, MockEvmProvider> {
+        RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default()
+                .v0_6(call_data_route())
+                .build(),
+            MockPool::default(),
+            MockFeeEstimator::new(),
+            MockEvmProvider::new(),
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_required_prefund() {
+        let uo = v0_6::UserOperation {
+            sender: Address::random(),
+            call_gas_limit: 50_000,
+            verification_gas_limit: 100_000,
+            pre_verification_gas: 45_000,
+            max_fee_per_gas: 3_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            ..Default::default()
+        };
+
+        let prefund = prefund_api()
+            .get_required_prefund(UserOperationVariant::V0_6(uo).into(), Address::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            prefund,
+            U256::from((50_000 + 100_000 + 45_000) * 3_000_000_000_u128)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_required_prefund_rejects_paymaster() {
+        let uo = v0_6::UserOperation {
+            sender: Address::random(),
+            paymaster_and_data: Address::random().to_vec().into(),
+            max_fee_per_gas: 1,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            prefund_api()
+                .get_required_prefund(UserOperationVariant::V0_6(uo).into(), Address::ZERO)
+                .await,
+            Err(EthRpcError::InvalidParams(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_required_prefund_unknown_entry_point() {
+        assert!(prefund_api()
+            .get_required_prefund(
+                UserOperationVariant::V0_6(check_op()).into(),
+                Address::random()
+            )
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_validation_output_serialization() {
+        let output = ValidationOutput {
+            return_info: ValidationReturnInfo {
+                pre_op_gas: 50_000,
+                prefund: U256::from(1_000_000),
+                account_sig_failed: false,
+                paymaster_sig_failed: true,
+                valid_after: Timestamp::new(100),
+                valid_until: Timestamp::new(200),
+                paymaster_context: Bytes::from(vec![0x12, 0x34]),
+            },
+            sender_info: StakeInfo::default(),
+            factory_info: StakeInfo {
+                stake: U256::from(100),
+                unstake_delay_sec: 86_400,
+            },
+            paymaster_info: StakeInfo::default(),
+            aggregator_info: Some(AggregatorInfo {
+                address: Address::repeat_byte(0x11),
+                stake_info: StakeInfo {
+                    stake: U256::from(200),
+                    unstake_delay_sec: 60,
+                },
+            }),
+        };
+
+        let json = serde_json::to_value(RpcValidationOutput::from(output)).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "returnInfo": {
+                    "preOpGas": "0xc350",
+                    "prefund": "0xf4240",
+                    "accountSigFailed": false,
+                    "paymasterSigFailed": true,
+                    "validAfter": "0x64",
+                    "validUntil": "0xc8",
+                    "paymasterContext": "0x1234",
+                },
+                "senderInfo": { "stake": "0x0", "unstakeDelaySec": "0x0" },
+                "factoryInfo": { "stake": "0x64", "unstakeDelaySec": "0x15180" },
+                "paymasterInfo": { "stake": "0x0", "unstakeDelaySec": "0x0" },
+                "aggregatorInfo": {
+                    "aggregator": "0x1111111111111111111111111111111111111111",
+                    "stakeInfo": { "stake": "0xc8", "unstakeDelaySec": "0x3c" },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_validation_output_serialization_without_aggregator() {
+        let output = ValidationOutput {
+            return_info: ValidationReturnInfo::default(),
+            sender_info: StakeInfo::default(),
+            factory_info: StakeInfo::default(),
+            paymaster_info: StakeInfo::default(),
+            aggregator_info: None,
+        };
+
+        let json = serde_json::to_value(RpcValidationOutput::from(output)).unwrap();
+        assert_eq!(json["aggregatorInfo"], serde_json::Value::Null);
+        assert_eq!(json["returnInfo"]["paymasterContext"], "0x");
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_with_call_data() {
+        let api = RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default()
+                .v0_6(call_data_route())
+                .build(),
+            MockPool::default(),
+            MockFeeEstimator::new(),
+            MockEvmProvider::new(),
+            false,
+        );
+        let op = UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::from(vec![1; 4]),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        });
+
+        let original = api
+            .estimate_user_operation_gas_with_call_data(
+                op.clone(),
+                None,
+                Address::ZERO,
+                Bytes::from(vec![1; 4]),
+                None,
+            )
+            .await
+            .unwrap();
+        let substituted = api
+            .estimate_user_operation_gas_with_call_data(
+                op,
+                None,
+                Address::ZERO,
+                Bytes::from(vec![1; 100]),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            original,
+            RpcGasEstimate::from(RpcGasEstimateV0_6::from(call_data_estimate(4))).without_rounds()
+        );
+        assert_eq!(
+            substituted,
+            RpcGasEstimate::from(RpcGasEstimateV0_6::from(call_data_estimate(100)))
+                .without_rounds()
+        );
+        assert_ne!(original, substituted);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_with_call_data_rounds() {
+        let estimate = |estimation_rounds_enabled| async move {
+            let api = RundlerApi::new(
+                &ChainSpec::default(),
+                EntryPointRouterBuilder::default()
+                    .v0_6(call_data_route())
+                    .build(),
+                MockPool::default(),
+                MockFeeEstimator::new(),
+                MockEvmProvider::new(),
+                estimation_rounds_enabled,
+            );
+            let op = UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+                sender: Address::random(),
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: Bytes::new(),
+                call_gas_limit: None,
+                verification_gas_limit: None,
+                pre_verification_gas: None,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            });
+            let estimate = api
+                .estimate_user_operation_gas_with_call_data(
+                    op,
+                    None,
+                    Address::ZERO,
+                    Bytes::from(vec![1; 4]),
+                    None,
+                )
+                .await
+                .unwrap();
+            serde_json::to_value(estimate).unwrap()
+        };
+
+        let enabled = estimate(true).await;
+        assert_eq!(enabled["verificationGasRounds"], "0xc");
+        assert_eq!(enabled["callGasRounds"], "0x14");
+
+        let disabled = estimate(false).await;
+        assert!(disabled.get("verificationGasRounds").is_none());
+        assert!(disabled.get("callGasRounds").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_history() {
+        let chain_spec = ChainSpec::default();
+        let api = RundlerApi::new(
+            &chain_spec,
+            EntryPointRouterBuilder::default()
+                .v0_6(call_data_route())
+                .build(),
+            MockPool::default(),
+            MockFeeEstimator::new(),
+            MockEvmProvider::new(),
+            false,
+        );
+        let op = UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+            sender: Address::random(),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        });
+        let call_data = Bytes::from(vec![1; 4]);
+        let hash = op
+            .clone()
+            .with_call_data(call_data.clone())
+            .hash(Address::ZERO, &chain_spec);
+
+        for _ in 0..2 {
+            api.estimate_user_operation_gas_with_call_data(
+                op.clone(),
+                None,
+                Address::ZERO,
+                call_data.clone(),
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let history = RundlerApiServer::get_estimate_history(&api, hash)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        for entry in history {
+            assert_eq!(entry.entry_point, Address::ZERO);
+            assert_eq!(
+                entry.estimate,
+                RpcGasEstimate::from(RpcGasEstimateV0_6::from(call_data_estimate(4)))
+                    .without_rounds()
+            );
+        }
+
+        assert!(RundlerApiServer::get_estimate_history(&api, B256::random())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_operation_gas_batch() {
+        // every op is estimated against the block fetched once for the batch
+        let block = (B256::random(), 10);
+        let mut evm = MockEvmProvider::new();
+        evm.expect_get_latest_block_hash_and_number()
+            .times(1)
+            .returning(move || Ok(block));
+        let mut route = MockEntryPointRoute::new();
+        route.expect_version().return_const(EntryPointVersion::V0_6);
+        route.expect_address().return_const(Address::ZERO);
+        route
+            .expect_estimate_gas_with_block()
+            .withf(move |_, _, b| *b == block)
+            .times(2)
+            .returning(|uo, _, _| call_data_route_estimate(uo));
+
+        let api = RundlerApi::new(
+            &ChainSpec::default(),
+            EntryPointRouterBuilder::default().v0_6(route).build(),
+            MockPool::default(),
+            MockFeeEstimator::new(),
+            evm,
+            false,
+        );
+        let op = |call_data: &[u8]| {
+            UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+                sender: Address::random(),
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: Bytes::copy_from_slice(call_data),
+                call_gas_limit: None,
+                verification_gas_limit: None,
+                pre_verification_gas: None,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                paymaster_and_data: Bytes::new(),
+                signature: Bytes::new(),
+            })
+        };
+
+        let results = api
+            .estimate_user_operation_gas_batch(
+                vec![(None, op(&REVERT_CALL_DATA)), (None, op(&[1; 4]))],
+                Address::ZERO,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        let RpcGasEstimateResult::Error(error) = &results[0] else {
+            panic!("expected first op to fail estimation");
+        };
+        assert_eq!(
+            error.message,
+            EthRpcError::from(GasEstimationError::RevertInCallWithMessage(
+                "reverted".to_string()
+            ))
+            .to_string()
+        );
+        assert_eq!(
+            results[1],
+            RpcGasEstimateResult::Estimate(
+                RpcGasEstimate::from(RpcGasEstimateV0_6::from(call_data_estimate(4)))
+                    .without_rounds()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_user_operations() {
+        let sender = Address::random();
+        let mined_op = |sender| MinedUserOperation {
+            hash: B256::random(),
+            entry_point: Address::ZERO,
+            sender,
+            nonce: U256::ZERO,
+        };
+        let matching = mined_op(sender);
+        let heads = vec![
+            NewHead {
+                block_hash: B256::random(),
+                block_number: 1,
+                mined_ops: vec![mined_op(Address::random())],
+            },
+            NewHead {
+                block_hash: B256::random(),
+                block_number: 2,
+                mined_ops: vec![mined_op(Address::random()), matching.clone()],
+            },
+        ];
+
+        let mut pool = MockPool::default();
+        let stream_heads = heads.clone();
+        pool.expect_subscribe_new_heads()
+            .returning(move || Ok(Box::pin(futures_util::stream::iter(stream_heads.clone()))));
+
+        let module = check_api(pool).into_rpc();
+        let mut sub = module
+            .subscribe_unbounded(
+                "rundler_subscribeUserOperations",
+                rpc_params![RpcUserOperationFilter {
+                    sender: Some(sender),
+                    user_op_hash: None,
+                }],
+            )
+            .await
+            .unwrap();
+
+        let (notification, _) = sub.next::<RpcMinedUserOperation>().await.unwrap().unwrap();
+        assert_eq!(
+            notification,
+            RpcMinedUserOperation::new(matching, &heads[1])
+        );
+        // ops from other senders are filtered out
+        assert!(sub.next::<RpcMinedUserOperation>().await.is_none());
+    }
 }
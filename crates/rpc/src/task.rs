@@ -11,14 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Context;
-use futures_util::FutureExt;
 use jsonrpsee::{
-    server::{middleware::http::ProxyGetRequestLayer, RpcServiceBuilder, ServerBuilder},
+    server::{
+        middleware::http::ProxyGetRequestLayer, RpcServiceBuilder, ServerBuilder, ServerHandle,
+    },
     RpcModule,
 };
+use metrics_exporter_prometheus::PrometheusHandle;
 use rundler_provider::Providers as ProvidersT;
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl, FeeOracle},
@@ -26,24 +28,33 @@ use rundler_sim::{
 };
 use rundler_task::{
     server::{format_socket_addr, HealthCheck},
-    TaskSpawner,
+    TaskSpawnerExt,
 };
 use rundler_types::{builder::Builder as BuilderT, chain::ChainSpec, pool::Pool as PoolT};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tracing::info;
 
 use crate::{
     admin::{AdminApi, AdminApiServer},
+    concurrency_limit::RpcConcurrencyLimitLayer,
     debug::{DebugApi, DebugApiServer},
+    drain::RpcDrainLayer,
     eth::{
         EntryPointRouteImpl, EntryPointRouter, EntryPointRouterBuilder, EthApi, EthApiServer,
         EthApiSettings, UserOperationEventProviderV0_6, UserOperationEventProviderV0_7,
     },
-    health::{HealthChecker, SystemApiServer},
+    fee_cache::CachedFeeEstimator,
+    health::{HealthChecker, ProviderBlockLagCheck, SystemApiServer},
+    method_timeout::RpcMethodTimeoutLayer,
+    prometheus::PrometheusMetricsLayer,
     rpc_metrics::{HttpMetricMiddlewareLayer, RpcMetricsMiddlewareLayer},
     rundler::{RundlerApi, RundlerApiServer, Settings as RundlerApiSettings},
     types::ApiNamespace,
 };
 
+/// Max time to spend warming the caches after the server starts
+const WARM_CACHE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// RPC server arguments.
 #[derive(Debug)]
 pub struct Args {
@@ -69,12 +80,34 @@ pub struct Args {
     pub estimation_settings: EstimationSettings,
     /// RPC timeout.
     pub rpc_timeout: Duration,
+    /// Timeouts for specific methods, overriding `rpc_timeout`.
+    pub method_timeouts: HashMap<String, Duration>,
     /// Max number of connections.
     pub max_connections: u32,
+    /// Max number of method calls executing at once, across all connections. Calls beyond
+    /// the limit are queued until a slot frees up.
+    pub max_concurrent_requests: usize,
+    /// Max number of active subscriptions per connection.
+    pub max_subscriptions_per_connection: u32,
     /// Whether to enable entry point v0.6.
     pub entry_point_v0_6_enabled: bool,
     /// Whether to enable entry point v0.7.
     pub entry_point_v0_7_enabled: bool,
+    /// Handle to the metrics recorder, if set metrics are served on GET /metrics.
+    pub metrics_handle: Option<PrometheusHandle>,
+    /// Whether to serve WebSocket connections in addition to HTTP.
+    pub ws_enabled: bool,
+    /// Port to serve WebSocket connections on. If unset or equal to `port`, WebSocket
+    /// connections are served on the same port as HTTP.
+    pub ws_port: Option<u16>,
+    /// Whether to route requests for unknown entry points to the only enabled entry point
+    /// version. Has no effect when multiple versions are enabled.
+    pub entry_point_fallback: bool,
+    /// Max time to wait for in-flight requests to complete on shutdown before stopping
+    /// the server.
+    pub shutdown_grace_period: Duration,
+    /// Max number of blocks the pool may lag behind the provider before reporting unhealthy.
+    pub max_provider_block_lag: u64,
 }
 
 /// JSON-RPC server task.
@@ -105,16 +138,28 @@ where
     Providers: ProvidersT + 'static,
 {
     /// Spawns the RPC server task on the given task spawner.
-    pub async fn spawn<T: TaskSpawner>(self, task_spawner: T) -> anyhow::Result<()> {
+    ///
+    /// When `shutdown` is cancelled the server stops accepting new requests, waits up to
+    /// the configured grace period for in-flight requests to complete, and then stops.
+    /// Each server is tracked by `drained` until it has stopped, so callers can wait for
+    /// the drain to finish before shutting down the tasks the server depends on.
+    pub async fn spawn<T: TaskSpawnerExt>(
+        self,
+        task_spawner: T,
+        shutdown: CancellationToken,
+        drained: TaskTracker,
+    ) -> anyhow::Result<()> {
         let addr: SocketAddr = format_socket_addr(&self.args.host, self.args.port).parse()?;
         tracing::info!("Starting rpc server on {}", addr);
 
-        let mut router_builder = EntryPointRouterBuilder::default();
+        let mut router_builder = EntryPointRouterBuilder::default()
+            .single_version_fallback(self.args.entry_point_fallback)
+            .chain_spec(self.args.chain_spec.clone());
         let fee_oracle = Arc::<dyn FeeOracle>::from(gas::get_fee_oracle(
             &self.args.chain_spec,
             self.providers.evm().clone(),
         ));
-        let fee_estimator = FeeEstimatorImpl::new(
+        let fee_estimator = CachedFeeEstimator::new(FeeEstimatorImpl::new(
             self.providers.evm().clone(),
             fee_oracle,
             self.args.precheck_settings.priority_fee_mode,
@@ -122,7 +167,14 @@ where
             self.args
                 .precheck_settings
                 .bundle_priority_fee_overhead_percent,
-        );
+            self.args.precheck_settings.priority_fee_percentile_blocks,
+        ));
+        let new_heads = self
+            .pool
+            .subscribe_new_heads()
+            .await
+            .context("should subscribe to pool new heads")?;
+        task_spawner.spawn(Box::pin(fee_estimator.track_new_heads(new_heads)));
 
         if self.args.entry_point_v0_6_enabled {
             let ep = self
@@ -143,9 +195,7 @@ where
                 UserOperationEventProviderV0_6::new(
                     self.args.chain_spec.clone(),
                     self.providers.evm().clone(),
-                    self.args
-                        .eth_api_settings
-                        .user_operation_event_block_distance,
+                    self.args.eth_api_settings,
                 ),
             ));
         }
@@ -169,65 +219,105 @@ where
                 UserOperationEventProviderV0_7::new(
                     self.args.chain_spec.clone(),
                     self.providers.evm().clone(),
-                    self.args
-                        .eth_api_settings
-                        .user_operation_event_block_distance,
+                    self.args.eth_api_settings,
                 ),
             ));
         }
 
         // create the entry point router
         let router = router_builder.build();
+        let new_heads = self
+            .pool
+            .subscribe_new_heads()
+            .await
+            .context("should subscribe to pool new heads")?;
+        task_spawner.spawn(Box::pin(router.track_mined_estimates(new_heads)));
 
         let mut module = RpcModule::new(());
-        self.attach_namespaces(router, fee_estimator, &mut module)?;
+        self.attach_namespaces(router, fee_estimator.clone(), &mut module)?;
 
-        let servers: Vec<Box<dyn HealthCheck>> =
-            vec![Box::new(self.pool.clone()), Box::new(self.builder.clone())];
+        let block_lag_check = ProviderBlockLagCheck::new(
+            self.providers.evm().clone(),
+            self.args.max_provider_block_lag,
+        );
+        let new_heads = self
+            .pool
+            .subscribe_new_heads()
+            .await
+            .context("should subscribe to pool new heads")?;
+        task_spawner.spawn(Box::pin(block_lag_check.track_pool_heads(new_heads)));
+
+        let servers: Vec<Box<dyn HealthCheck>> = vec![
+            Box::new(self.pool.clone()),
+            Box::new(self.builder.clone()),
+            Box::new(block_lag_check),
+        ];
         let health_checker = HealthChecker::new(servers);
         module.merge(health_checker.into_rpc())?;
 
-        // Set up health check endpoint via GET /health registers the jsonrpc handler
-        let http_middleware = tower::ServiceBuilder::new()
-            // Proxy `GET /health` requests to internal `system_health` method.
-            .layer(ProxyGetRequestLayer::new("/health", "system_health")?)
-            .timeout(self.args.rpc_timeout)
-            .layer(HttpMetricMiddlewareLayer::new(
-                "rundler-rpc-service-http".to_string(),
-            ));
-
-        let rpc_metric_middleware = RpcServiceBuilder::new().layer(RpcMetricsMiddlewareLayer::new(
-            "rundler-rpc-service".to_string(),
-        ));
-
-        let server = ServerBuilder::default()
-            .set_rpc_middleware(rpc_metric_middleware)
-            .set_http_middleware(http_middleware)
-            .max_connections(self.args.max_connections)
+        let server_settings = ServerSettings {
+            rpc_timeout: self.args.rpc_timeout,
+            method_timeouts: self.args.method_timeouts.clone(),
+            max_connections: self.args.max_connections,
+            // shared by all servers so the limit applies across transports
+            concurrency_limit: RpcConcurrencyLimitLayer::new(self.args.max_concurrent_requests),
+            max_subscriptions_per_connection: self.args.max_subscriptions_per_connection,
+            // shared by all servers so they drain together
+            drain: RpcDrainLayer::new(),
             // Set max request body size to 2x the max transaction size as none of our
             // APIs should require more than that.
-            .max_request_body_size(
-                (self.args.chain_spec.max_transaction_size_bytes * 2)
-                    .try_into()
-                    .expect("max_transaction_size_bytes * 2 overflowed u32"),
-            )
-            .http_only()
-            .build(addr)
-            .await?;
-
-        let handle = server.start(module);
+            max_request_body_size: (self.args.chain_spec.max_transaction_size_bytes * 2)
+                .try_into()
+                .expect("max_transaction_size_bytes * 2 overflowed u32"),
+            metrics_handle: self.args.metrics_handle.clone(),
+        };
 
-        task_spawner.spawn_critical(
-            "rpc server",
-            async move {
-                handle.stopped().await;
-                tracing::error!("RPC server stopped");
+        let mut listeners = vec![];
+        match self.args.ws_port {
+            Some(ws_port) if self.args.ws_enabled && ws_port != self.args.port => {
+                let ws_addr: SocketAddr = format_socket_addr(&self.args.host, ws_port).parse()?;
+                listeners.push((addr, Transport::Http));
+                listeners.push((ws_addr, Transport::Ws));
             }
-            .boxed(),
-        );
+            _ if self.args.ws_enabled => listeners.push((addr, Transport::HttpAndWs)),
+            _ => listeners.push((addr, Transport::Http)),
+        }
+
+        for (addr, transport) in listeners {
+            let (handle, local_addr) =
+                start_server(addr, transport, module.clone(), &server_settings).await?;
+            info!("Serving {transport:?} JSON-RPC on {local_addr}");
+
+            let drain = server_settings.drain.clone();
+            let grace_period = self.args.shutdown_grace_period;
+            let shutdown = shutdown.clone();
+            let drain_token = drained.token();
+            task_spawner.spawn_critical_with_graceful_shutdown_signal(
+                "rpc server",
+                |graceful_shutdown| async move {
+                    tokio::select! {
+                        _ = handle.clone().stopped() => {
+                            tracing::error!("RPC server stopped");
+                        }
+                        _ = shutdown.cancelled() => {}
+                        _ = graceful_shutdown.clone() => {}
+                    }
+                    // holding the graceful shutdown signal keeps the task manager waiting
+                    // until the server has drained
+                    drain_and_stop(handle, &drain, grace_period).await;
+                    drop(graceful_shutdown);
+                    drop(drain_token);
+                },
+            );
+        }
 
         info!("Started RPC server");
 
+        // warm after binding so that a slow node doesn't delay startup
+        task_spawner.spawn(Box::pin(async move {
+            fee_estimator.warm(WARM_CACHE_TIMEOUT).await;
+        }));
+
         Ok(())
     }
 
@@ -243,6 +333,7 @@ where
                     self.args.chain_spec.clone(),
                     entry_point_router.clone(),
                     self.pool.clone(),
+                    self.args.eth_api_settings,
                 )
                 .into_rpc(),
             )?
@@ -263,6 +354,8 @@ where
                     entry_point_router,
                     self.pool.clone(),
                     fee_estimator,
+                    self.providers.evm().clone(),
+                    self.args.rundler_api_settings.estimation_rounds_enabled,
                 )
                 .into_rpc(),
             )?;
@@ -271,3 +364,464 @@ where
         Ok(())
     }
 }
+
+/// Rejects new requests, waits up to `grace_period` for in-flight requests to complete,
+/// and then stops the server.
+async fn drain_and_stop(handle: ServerHandle, drain: &RpcDrainLayer, grace_period: Duration) {
+    if handle.is_stopped() {
+        return;
+    }
+    info!("Draining RPC server for up to {grace_period:?}");
+    let remaining = drain.drain(grace_period).await;
+    if remaining > 0 {
+        tracing::warn!("Stopping RPC server with {remaining} requests still in flight");
+    }
+    let _ = handle.stop();
+    handle.stopped().await;
+    info!("RPC server stopped");
+}
+
+/// Transports served by a JSON-RPC server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Http,
+    Ws,
+    HttpAndWs,
+}
+
+/// Settings shared by all JSON-RPC servers
+struct ServerSettings {
+    rpc_timeout: Duration,
+    method_timeouts: HashMap<String, Duration>,
+    max_connections: u32,
+    concurrency_limit: RpcConcurrencyLimitLayer,
+    max_subscriptions_per_connection: u32,
+    drain: RpcDrainLayer,
+    max_request_body_size: u32,
+    metrics_handle: Option<PrometheusHandle>,
+}
+
+/// Builds and starts a JSON-RPC server for `module` on `addr`, returning its handle
+/// and the address it is bound to.
+///
+/// Subscriptions are only available to clients connected over WebSocket.
+async fn start_server(
+    addr: SocketAddr,
+    transport: Transport,
+    module: RpcModule<()>,
+    settings: &ServerSettings,
+) -> anyhow::Result<(ServerHandle, SocketAddr)> {
+    // Method calls are timed out individually by the RPC middleware, the HTTP timeout is
+    // a backstop that must allow for the longest method timeout.
+    let http_timeout = settings
+        .method_timeouts
+        .values()
+        .copied()
+        .fold(settings.rpc_timeout, Duration::max);
+
+    // Set up health check endpoint via GET /health registers the jsonrpc handler
+    // and metrics endpoint via GET /metrics
+    let http_middleware = tower::ServiceBuilder::new()
+        // Refuse new connections while draining, including health checks.
+        .layer(settings.drain.http())
+        // Proxy `GET /health` requests to internal `system_health` method.
+        .layer(ProxyGetRequestLayer::new("/health", "system_health")?)
+        // Serve `GET /metrics` in Prometheus exposition format.
+        .layer(PrometheusMetricsLayer::new(
+            "/metrics",
+            settings.metrics_handle.clone(),
+        ))
+        .timeout(http_timeout)
+        .layer(HttpMetricMiddlewareLayer::new(
+            "rundler-rpc-service-http".to_string(),
+        ));
+
+    let rpc_metric_middleware = RpcServiceBuilder::new()
+        .layer(RpcMetricsMiddlewareLayer::new(
+            "rundler-rpc-service".to_string(),
+        ))
+        .layer(settings.drain.clone())
+        .layer(RpcMethodTimeoutLayer::new(
+            settings.rpc_timeout,
+            settings.method_timeouts.clone(),
+        ))
+        .layer(settings.concurrency_limit.clone());
+
+    let builder = ServerBuilder::default()
+        .set_rpc_middleware(rpc_metric_middleware)
+        .set_http_middleware(http_middleware)
+        .max_connections(settings.max_connections)
+        .max_subscriptions_per_connection(settings.max_subscriptions_per_connection)
+        .max_request_body_size(settings.max_request_body_size);
+    let builder = match transport {
+        Transport::Http => builder.http_only(),
+        Transport::Ws => builder.ws_only(),
+        Transport::HttpAndWs => builder,
+    };
+
+    let server = builder.build(addr).await?;
+    let local_addr = server.local_addr()?;
+    Ok((server.start(module), local_addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Instant,
+    };
+
+    use futures_util::future;
+    use jsonrpsee::{
+        core::{
+            client::{ClientT, SubscriptionClientT},
+            SubscriptionResult,
+        },
+        http_client::HttpClientBuilder,
+        rpc_params,
+        ws_client::WsClientBuilder,
+        PendingSubscriptionSink, SubscriptionMessage,
+    };
+
+    use super::*;
+
+    async fn hello_subscription(pending: PendingSubscriptionSink) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+        sink.send(SubscriptionMessage::from_json(&"hello")?).await?;
+        // hold the subscription open until the client unsubscribes
+        sink.closed().await;
+        Ok(())
+    }
+
+    fn test_module() -> RpcModule<()> {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("test_hello", |_, _, _| "hello".to_string())
+            .unwrap();
+        module
+            .register_subscription(
+                "test_subscribeHello",
+                "test_hello",
+                "test_unsubscribeHello",
+                |_, pending, _, _| hello_subscription(pending),
+            )
+            .unwrap();
+        module
+    }
+
+    fn test_settings() -> ServerSettings {
+        ServerSettings {
+            rpc_timeout: Duration::from_secs(5),
+            method_timeouts: HashMap::new(),
+            max_connections: 10,
+            concurrency_limit: RpcConcurrencyLimitLayer::new(10),
+            max_subscriptions_per_connection: 10,
+            drain: RpcDrainLayer::new(),
+            max_request_body_size: 1024 * 1024,
+            metrics_handle: None,
+        }
+    }
+
+    async fn start_test_server(transport: Transport) -> (ServerHandle, SocketAddr) {
+        start_test_server_with_settings(transport, test_settings()).await
+    }
+
+    async fn start_test_server_with_settings(
+        transport: Transport,
+        settings: ServerSettings,
+    ) -> (ServerHandle, SocketAddr) {
+        start_server(
+            "127.0.0.1:0".parse().unwrap(),
+            transport,
+            test_module(),
+            &settings,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_method_over_http_and_ws() {
+        let (_handle, addr) = start_test_server(Transport::HttpAndWs).await;
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{addr}"))
+            .unwrap();
+        let res: String = http.request("test_hello", rpc_params![]).await.unwrap();
+        assert_eq!(res, "hello");
+
+        let ws = WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        let res: String = ws.request("test_hello", rpc_params![]).await.unwrap();
+        assert_eq!(res, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_only_over_ws() {
+        let (_handle, addr) = start_test_server(Transport::HttpAndWs).await;
+
+        let ws = WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        let mut sub = ws
+            .subscribe::<String, _>(
+                "test_subscribeHello",
+                rpc_params![],
+                "test_unsubscribeHello",
+            )
+            .await
+            .unwrap();
+        assert_eq!(sub.next().await.unwrap().unwrap(), "hello");
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{addr}"))
+            .unwrap();
+        let res: Result<String, _> = http.request("test_subscribeHello", rpc_params![]).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_separate_transports() {
+        let (_http_handle, http_addr) = start_test_server(Transport::Http).await;
+        let (_ws_handle, ws_addr) = start_test_server(Transport::Ws).await;
+
+        assert!(WsClientBuilder::default()
+            .build(format!("ws://{http_addr}"))
+            .await
+            .is_err());
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{http_addr}"))
+            .unwrap();
+        let res: String = http.request("test_hello", rpc_params![]).await.unwrap();
+        assert_eq!(res, "hello");
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{ws_addr}"))
+            .unwrap();
+        let res: Result<String, _> = http.request("test_hello", rpc_params![]).await;
+        assert!(res.is_err());
+        let ws = WsClientBuilder::default()
+            .build(format!("ws://{ws_addr}"))
+            .await
+            .unwrap();
+        let res: String = ws.request("test_hello", rpc_params![]).await.unwrap();
+        assert_eq!(res, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_max_subscriptions_per_connection() {
+        let (_handle, addr) = start_test_server_with_settings(
+            Transport::Ws,
+            ServerSettings {
+                max_subscriptions_per_connection: 2,
+                ..test_settings()
+            },
+        )
+        .await;
+
+        let ws = WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        let mut subs = vec![];
+        for _ in 0..2 {
+            subs.push(
+                ws.subscribe::<String, _>(
+                    "test_subscribeHello",
+                    rpc_params![],
+                    "test_unsubscribeHello",
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let res = ws
+            .subscribe::<String, _>(
+                "test_subscribeHello",
+                rpc_params![],
+                "test_unsubscribeHello",
+            )
+            .await;
+        assert!(res.is_err());
+
+        // the limit is per connection
+        let other = WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        assert!(other
+            .subscribe::<String, _>(
+                "test_subscribeHello",
+                rpc_params![],
+                "test_unsubscribeHello",
+            )
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests_queues() {
+        const MAX_CONCURRENT: usize = 2;
+        const DELAY: Duration = Duration::from_millis(200);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let mut module = RpcModule::new(());
+        {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            module
+                .register_async_method("test_slow", move |_, _, _| {
+                    let in_flight = in_flight.clone();
+                    let max_in_flight = max_in_flight.clone();
+                    async move {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_in_flight.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(DELAY).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        "done".to_string()
+                    }
+                })
+                .unwrap();
+        }
+        let (_handle, addr) = start_server(
+            "127.0.0.1:0".parse().unwrap(),
+            Transport::Http,
+            module,
+            &ServerSettings {
+                concurrency_limit: RpcConcurrencyLimitLayer::new(MAX_CONCURRENT),
+                ..test_settings()
+            },
+        )
+        .await
+        .unwrap();
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{addr}"))
+            .unwrap();
+        let start = Instant::now();
+        let results = future::join_all(
+            (0..=MAX_CONCURRENT).map(|_| http.request::<String, _>("test_slow", rpc_params![])),
+        )
+        .await;
+        let elapsed = start.elapsed();
+
+        // the extra call is queued, not dropped, and only runs once a slot frees up
+        assert!(results.into_iter().all(|r| r.unwrap() == "done"));
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), MAX_CONCURRENT);
+        assert!(elapsed >= DELAY * 2);
+    }
+
+    #[tokio::test]
+    async fn test_method_timeouts() {
+        let mut module = RpcModule::new(());
+        for (method, delay) in [
+            ("test_fast", Duration::from_millis(50)),
+            ("test_slow", Duration::from_millis(500)),
+        ] {
+            module
+                .register_async_method(method, move |_, _, _| async move {
+                    tokio::time::sleep(delay).await;
+                    "done".to_string()
+                })
+                .unwrap();
+        }
+        let (_handle, addr) = start_server(
+            "127.0.0.1:0".parse().unwrap(),
+            Transport::Http,
+            module,
+            &ServerSettings {
+                method_timeouts: HashMap::from([
+                    ("test_fast".to_string(), Duration::from_secs(1)),
+                    ("test_slow".to_string(), Duration::from_millis(100)),
+                ]),
+                ..test_settings()
+            },
+        )
+        .await
+        .unwrap();
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{addr}"))
+            .unwrap();
+        let res: String = http.request("test_fast", rpc_params![]).await.unwrap();
+        assert_eq!(res, "done");
+        let err = http
+            .request::<String, _>("test_slow", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("test_slow timed out after 100ms"));
+    }
+
+    #[tokio::test]
+    async fn test_drain_and_stop() {
+        const DELAY: Duration = Duration::from_millis(200);
+
+        let mut module = RpcModule::new(());
+        module
+            .register_async_method("test_slow", |_, _, _| async move {
+                tokio::time::sleep(DELAY).await;
+                "done".to_string()
+            })
+            .unwrap();
+        let settings = test_settings();
+        let drain = settings.drain.clone();
+        let (handle, addr) = start_server(
+            "127.0.0.1:0".parse().unwrap(),
+            Transport::Http,
+            module,
+            &settings,
+        )
+        .await
+        .unwrap();
+
+        let http = HttpClientBuilder::default()
+            .build(format!("http://{addr}"))
+            .unwrap();
+        let before = http.request::<String, _>("test_slow", rpc_params![]);
+        let shutdown = async {
+            // let the first request reach the server before shutting down
+            tokio::time::sleep(DELAY / 4).await;
+            let drained = drain_and_stop(handle.clone(), &drain, Duration::from_secs(5));
+            let after = async {
+                tokio::time::sleep(DELAY / 4).await;
+                http.request::<String, _>("test_slow", rpc_params![]).await
+            };
+            future::join(drained, after).await.1
+        };
+        let (before, after) = future::join(before, shutdown).await;
+
+        assert_eq!(before.unwrap(), "done");
+        // rejected at the HTTP layer with 503 Service Unavailable
+        assert!(after.unwrap_err().to_string().contains("503"));
+        assert!(handle.is_stopped());
+    }
+
+    #[tokio::test]
+    async fn test_drain_refuses_new_connections() {
+        let settings = test_settings();
+        let drain = settings.drain.clone();
+        let (_handle, addr) = start_test_server_with_settings(Transport::HttpAndWs, settings).await;
+
+        let open = WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        assert_eq!(drain.drain(Duration::ZERO).await, 0);
+
+        assert!(WsClientBuilder::default()
+            .build(format!("ws://{addr}"))
+            .await
+            .is_err());
+        // connections opened before the drain stay up but reject new calls
+        assert!(open
+            .request::<String, _>("test_hello", rpc_params![])
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("server is shutting down"));
+    }
+}
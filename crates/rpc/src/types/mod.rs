@@ -11,17 +11,24 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, B256, U128, U256, U64};
+use std::collections::BTreeMap;
+
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
+use jsonrpsee::types::ErrorObjectOwned;
 use rundler_provider::{Log, TransactionReceipt};
 use rundler_types::{
+    builder::BundleBuildReport,
     chain::ChainSpec,
-    pool::{Reputation, ReputationStatus},
+    pool::{MinedUserOperation, NewHead, PrecheckSettingsUpdate, Reputation, ReputationStatus},
     v0_6::UserOperation as UserOperationV0_6,
     v0_7::UserOperation as UserOperationV0_7,
-    UserOperationOptionalGas, UserOperationVariant,
+    AggregatorInfo, GasEstimateBreakdown, GasEstimateStages, StakeInfo, UserOperationOptionalGas,
+    UserOperationVariant, ValidationOutput, ValidationReturnInfo,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::eth::EthResult;
+
 mod v0_6;
 pub(crate) use v0_6::{
     RpcGasEstimate as RpcGasEstimateV0_6, RpcUserOperation as RpcUserOperationV0_6,
@@ -150,6 +157,16 @@ pub(crate) enum RpcUserOperationOptionalGas {
     V0_7(RpcUserOperationOptionalGasV0_7),
 }
 
+impl RpcUserOperationOptionalGas {
+    /// The signature aggregator the operation will be bundled with, if any
+    pub(crate) fn aggregator(&self) -> Option<Address> {
+        match self {
+            RpcUserOperationOptionalGas::V0_6(op) => op.aggregator(),
+            RpcUserOperationOptionalGas::V0_7(op) => op.aggregator(),
+        }
+    }
+}
+
 impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
     fn from(op: RpcUserOperationOptionalGas) -> Self {
         match op {
@@ -166,6 +183,24 @@ pub(crate) enum RpcGasEstimate {
     V0_7(RpcGasEstimateV0_7),
 }
 
+impl RpcGasEstimate {
+    /// Sum of all the estimated gas limits, the most gas the user operation can be charged for
+    pub(crate) fn total_gas_limit(&self) -> u128 {
+        match self {
+            RpcGasEstimate::V0_6(estimate) => estimate.total_gas_limit(),
+            RpcGasEstimate::V0_7(estimate) => estimate.total_gas_limit(),
+        }
+    }
+
+    /// Removes the binary search round counts, which are only returned for debugging
+    pub(crate) fn without_rounds(self) -> Self {
+        match self {
+            RpcGasEstimate::V0_6(estimate) => estimate.without_rounds().into(),
+            RpcGasEstimate::V0_7(estimate) => estimate.without_rounds().into(),
+        }
+    }
+}
+
 impl From<RpcGasEstimateV0_6> for RpcGasEstimate {
     fn from(estimate: RpcGasEstimateV0_6) -> Self {
         RpcGasEstimate::V0_6(estimate)
@@ -178,6 +213,92 @@ impl From<RpcGasEstimateV0_7> for RpcGasEstimate {
     }
 }
 
+/// Result of estimating gas for a single user operation in a batch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum RpcGasEstimateResult {
+    /// The user operation's gas estimate
+    Estimate(RpcGasEstimate),
+    /// The error estimation failed with
+    Error(RpcErrorObject),
+}
+
+impl From<EthResult<RpcGasEstimate>> for RpcGasEstimateResult {
+    fn from(result: EthResult<RpcGasEstimate>) -> Self {
+        match result {
+            Ok(estimate) => RpcGasEstimateResult::Estimate(estimate),
+            Err(error) => RpcGasEstimateResult::Error(ErrorObjectOwned::from(error).into()),
+        }
+    }
+}
+
+/// A JSON-RPC error object returned as part of a successful response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct RpcErrorObject {
+    /// Error code
+    pub code: i32,
+    /// Error message
+    pub message: String,
+    /// Additional error data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl From<ErrorObjectOwned> for RpcErrorObject {
+    fn from(error: ErrorObjectOwned) -> Self {
+        Self {
+            code: error.code(),
+            message: error.message().to_string(),
+            data: error
+                .data()
+                .and_then(|d| serde_json::from_str(d.get()).ok()),
+        }
+    }
+}
+
+/// Stages of adjustment applied to an estimated gas value
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcGasEstimateStages {
+    raw: U128,
+    after_buffer: U128,
+    after_clamp: U128,
+}
+
+impl From<GasEstimateStages> for RpcGasEstimateStages {
+    fn from(stages: GasEstimateStages) -> Self {
+        Self {
+            raw: U128::from(stages.raw),
+            after_buffer: U128::from(stages.after_buffer),
+            after_clamp: U128::from(stages.after_clamp),
+        }
+    }
+}
+
+/// Breakdown of the buffers and clamps applied to each field of a gas estimate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcGasEstimateBreakdown {
+    pre_verification_gas: RpcGasEstimateStages,
+    call_gas_limit: RpcGasEstimateStages,
+    verification_gas_limit: RpcGasEstimateStages,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paymaster_verification_gas_limit: Option<RpcGasEstimateStages>,
+}
+
+impl From<GasEstimateBreakdown> for RpcGasEstimateBreakdown {
+    fn from(breakdown: GasEstimateBreakdown) -> Self {
+        Self {
+            pre_verification_gas: breakdown.pre_verification_gas.into(),
+            call_gas_limit: breakdown.call_gas_limit.into(),
+            verification_gas_limit: breakdown.verification_gas_limit.into(),
+            paymaster_verification_gas_limit: breakdown
+                .paymaster_verification_gas_limit
+                .map(Into::into),
+        }
+    }
+}
+
 /// User operation receipt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -200,10 +321,16 @@ pub struct RpcUserOperationReceipt {
     pub success: bool,
     /// If not successful, the revert reason string
     pub reason: String,
-    /// Logs emitted by this operation
+    /// The aggregator that validated this operation, if it was submitted via `handleAggregatedOps`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregator: Option<RpcAddress>,
+    /// Logs emitted by this operation, empty if the transaction receipt is not yet available
     pub logs: Vec<Log>,
-    /// The receipt of the transaction that included this operation
-    pub receipt: TransactionReceipt,
+    /// The hash of the transaction that included this operation
+    pub transaction_hash: B256,
+    /// The receipt of the transaction that included this operation, null if the
+    /// operation's event has been indexed but the transaction receipt is not yet available
+    pub receipt: Option<TransactionReceipt>,
 }
 
 /// Reputation of an entity
@@ -278,6 +405,49 @@ pub struct RpcAdminClearState {
     pub clear_reputation: Option<bool>,
 }
 
+/// Precheck settings that can be updated on a running mempool
+///
+/// Unset fields are left unchanged. When returned, all fields are set to the
+/// effective values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAdminPrecheckSettings {
+    /// Maximum verification gas allowed for a user operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_verification_gas: Option<U128>,
+    /// Maximum total execution gas allowed for a user operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_execution_gas: Option<U128>,
+    /// Percentage of the current network base fee that a user operation must have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_accept_percent: Option<u32>,
+    /// Percentage of the required preVerificationGas that a user operation must have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_verification_gas_accept_percent: Option<u32>,
+}
+
+impl From<RpcAdminPrecheckSettings> for PrecheckSettingsUpdate {
+    fn from(settings: RpcAdminPrecheckSettings) -> Self {
+        Self {
+            max_verification_gas: settings.max_verification_gas.map(|g| g.to()),
+            max_total_execution_gas: settings.max_total_execution_gas.map(|g| g.to()),
+            base_fee_accept_percent: settings.base_fee_accept_percent,
+            pre_verification_gas_accept_percent: settings.pre_verification_gas_accept_percent,
+        }
+    }
+}
+
+impl From<PrecheckSettingsUpdate> for RpcAdminPrecheckSettings {
+    fn from(settings: PrecheckSettingsUpdate) -> Self {
+        Self {
+            max_verification_gas: settings.max_verification_gas.map(U128::from),
+            max_total_execution_gas: settings.max_total_execution_gas.map(U128::from),
+            base_fee_accept_percent: settings.base_fee_accept_percent,
+            pre_verification_gas_accept_percent: settings.pre_verification_gas_accept_percent,
+        }
+    }
+}
+
 /// Paymaster balance
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -289,3 +459,252 @@ pub struct RpcDebugPaymasterBalance {
     /// Paymaster confirmed balance onchain
     pub confirmed_balance: U256,
 }
+
+/// Summary of the most recent bundle build attempt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBundleBuildReport {
+    /// Index of the builder that made the attempt
+    pub builder_index: U64,
+    /// Entry point the bundle was built for
+    pub entry_point: Address,
+    /// Number of ops received from the pool
+    pub ops_considered: U64,
+    /// Number of ops left out of the bundle, keyed by reason
+    pub ops_skipped: BTreeMap<String, U64>,
+    /// Number of ops included in the bundle
+    pub ops_included: U64,
+    /// Error that ended the attempt, if any
+    pub error: Option<String>,
+}
+
+impl From<BundleBuildReport> for RpcBundleBuildReport {
+    fn from(report: BundleBuildReport) -> Self {
+        Self {
+            builder_index: U64::from(report.builder_index),
+            entry_point: report.entry_point,
+            ops_considered: U64::from(report.ops_considered),
+            ops_skipped: report
+                .ops_skipped
+                .into_iter()
+                .map(|(reason, count)| (reason, U64::from(count)))
+                .collect(),
+            ops_included: U64::from(report.ops_included),
+            error: report.error,
+        }
+    }
+}
+
+/// Estimated time until a user operation is included onchain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcInclusionTimeEstimate {
+    /// Estimated number of blocks until inclusion
+    pub blocks: U64,
+    /// Estimated number of seconds until inclusion
+    pub seconds: U64,
+}
+
+/// A gas estimate previously returned for a user operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEstimateHistoryEntry {
+    /// Unix timestamp of the estimate, in seconds
+    pub timestamp: U64,
+    /// Entry point the user operation was estimated against
+    pub entry_point: Address,
+    /// The returned gas estimate
+    pub estimate: RpcGasEstimate,
+}
+
+/// Comparison of the gas estimated for a user operation to the gas it used when mined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEstimationAccuracy {
+    /// The most recent estimate returned for the user operation's sender and nonce
+    pub estimate: RpcEstimateHistoryEntry,
+    /// Sum of the estimated gas limits
+    pub estimated_gas: U128,
+    /// The gas used by the user operation, from its receipt
+    pub actual_gas_used: U128,
+    /// Estimated gas minus actual gas used, negative if the estimate was too low
+    pub gas_delta: i128,
+}
+
+/// Filter for user operation subscriptions. Unset fields match any user operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperationFilter {
+    /// Only match user operations from this sender
+    #[serde(default)]
+    pub sender: Option<Address>,
+    /// Only match the user operation with this hash
+    #[serde(default)]
+    pub user_op_hash: Option<B256>,
+}
+
+impl RpcUserOperationFilter {
+    pub(crate) fn matches(&self, op: &MinedUserOperation) -> bool {
+        self.sender.map_or(true, |sender| sender == op.sender)
+            && self.user_op_hash.map_or(true, |hash| hash == op.hash)
+    }
+}
+
+/// A user operation mined onchain, sent to user operation subscribers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMinedUserOperation {
+    /// The hash of the user operation
+    pub user_op_hash: B256,
+    /// The entry point the user operation was mined through
+    pub entry_point: Address,
+    /// The sender of the user operation
+    pub sender: Address,
+    /// The nonce of the user operation
+    pub nonce: U256,
+    /// The hash of the block the user operation was mined in
+    pub block_hash: B256,
+    /// The number of the block the user operation was mined in
+    pub block_number: U64,
+}
+
+impl RpcMinedUserOperation {
+    pub(crate) fn new(op: MinedUserOperation, head: &NewHead) -> Self {
+        Self {
+            user_op_hash: op.hash,
+            entry_point: op.entry_point,
+            sender: op.sender,
+            nonce: op.nonce,
+            block_hash: head.block_hash,
+            block_number: U64::from(head.block_number),
+        }
+    }
+}
+
+/// Result of checking whether a user operation would be accepted by the mempool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCheckUserOperationResult {
+    /// Whether the user operation would be accepted
+    pub accepted: bool,
+    /// The hash of the user operation
+    pub hash: B256,
+    /// The reason the user operation would be rejected, if not accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Whether an enabled entry point version accepts a user operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEntryPointCompatibility {
+    /// The address of the entry point
+    pub entry_point: Address,
+    /// The version of the entry point, e.g. `v0.6`
+    pub version: String,
+    /// Whether the user operation is compatible with the entry point
+    pub compatible: bool,
+    /// The reason the user operation is incompatible, if not compatible
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Full output of the entry point's validation simulation for a user operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidationOutput {
+    /// The return info from the validation function
+    pub return_info: RpcValidationReturnInfo,
+    /// The stake info for the sender
+    pub sender_info: RpcStakeInfo,
+    /// The stake info for the factory
+    pub factory_info: RpcStakeInfo,
+    /// The stake info for the paymaster
+    pub paymaster_info: RpcStakeInfo,
+    /// The aggregator and its stake info, if the operation uses one
+    pub aggregator_info: Option<RpcAggregatorInfo>,
+}
+
+impl From<ValidationOutput> for RpcValidationOutput {
+    fn from(output: ValidationOutput) -> Self {
+        Self {
+            return_info: output.return_info.into(),
+            sender_info: output.sender_info.into(),
+            factory_info: output.factory_info.into(),
+            paymaster_info: output.paymaster_info.into(),
+            aggregator_info: output.aggregator_info.map(Into::into),
+        }
+    }
+}
+
+/// Return info from the entry point's validation simulation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidationReturnInfo {
+    /// Gas used before the op is executed (pre verification gas and validation gas)
+    pub pre_op_gas: U128,
+    /// Funds the entry point requires to be deposited for the op
+    pub prefund: U256,
+    /// Whether the account signature verification failed
+    pub account_sig_failed: bool,
+    /// Whether the paymaster signature verification failed
+    pub paymaster_sig_failed: bool,
+    /// The time after which the op is valid
+    pub valid_after: U64,
+    /// The time until which the op is valid
+    pub valid_until: U64,
+    /// The paymaster context
+    pub paymaster_context: Bytes,
+}
+
+impl From<ValidationReturnInfo> for RpcValidationReturnInfo {
+    fn from(info: ValidationReturnInfo) -> Self {
+        Self {
+            pre_op_gas: U128::from(info.pre_op_gas),
+            prefund: info.prefund,
+            account_sig_failed: info.account_sig_failed,
+            paymaster_sig_failed: info.paymaster_sig_failed,
+            valid_after: U64::from(info.valid_after.seconds_since_epoch()),
+            valid_until: U64::from(info.valid_until.seconds_since_epoch()),
+            paymaster_context: info.paymaster_context,
+        }
+    }
+}
+
+/// Stake info of an entity as reported by the entry point
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStakeInfo {
+    /// The amount of stake
+    pub stake: U256,
+    /// The delay for unstaking
+    pub unstake_delay_sec: U64,
+}
+
+impl From<StakeInfo> for RpcStakeInfo {
+    fn from(info: StakeInfo) -> Self {
+        Self {
+            stake: info.stake,
+            unstake_delay_sec: U64::from(info.unstake_delay_sec),
+        }
+    }
+}
+
+/// Aggregator info as reported by the entry point
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAggregatorInfo {
+    /// The address of the aggregator
+    pub aggregator: Address,
+    /// The stake info for the aggregator
+    pub stake_info: RpcStakeInfo,
+}
+
+impl From<AggregatorInfo> for RpcAggregatorInfo {
+    fn from(info: AggregatorInfo) -> Self {
+        Self {
+            aggregator: info.address,
+            stake_info: info.stake_info.into(),
+        }
+    }
+}
@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, Bytes, B256, U128, U256};
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
 use rundler_types::{
     chain::ChainSpec,
     v0_7::{
@@ -21,7 +21,7 @@ use rundler_types::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::{FromRpc, RpcAddress};
+use super::{FromRpc, RpcAddress, RpcGasEstimateBreakdown};
 
 /// User operation definition for RPC inputs
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -154,6 +154,16 @@ pub(crate) struct RpcUserOperationOptionalGas {
     paymaster_post_op_gas_limit: Option<U128>,
     paymaster_data: Option<Bytes>,
     signature: Bytes,
+    /// Rundler extension: the signature aggregator the operation will be bundled with, whose
+    /// aggregated signature is priced into the estimated pre-verification gas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aggregator: Option<Address>,
+}
+
+impl RpcUserOperationOptionalGas {
+    pub(crate) fn aggregator(&self) -> Option<Address> {
+        self.aggregator
+    }
 }
 
 impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
@@ -185,6 +195,35 @@ pub(crate) struct RpcGasEstimate {
     call_gas_limit: U128,
     verification_gas_limit: U128,
     paymaster_verification_gas_limit: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breakdown: Option<RpcGasEstimateBreakdown>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verification_gas_rounds: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    call_gas_rounds: Option<U64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+impl RpcGasEstimate {
+    pub(crate) fn total_gas_limit(&self) -> u128 {
+        self.pre_verification_gas.to::<u128>()
+            + self.verification_gas_limit.to::<u128>()
+            + self.call_gas_limit.to::<u128>()
+            + self
+                .paymaster_verification_gas_limit
+                .map_or(0, |g| g.to::<u128>())
+    }
+
+    pub(crate) fn without_rounds(self) -> Self {
+        Self {
+            verification_gas_rounds: None,
+            call_gas_rounds: None,
+            ..self
+        }
+    }
 }
 
 impl From<GasEstimate> for RpcGasEstimate {
@@ -196,6 +235,11 @@ impl From<GasEstimate> for RpcGasEstimate {
             paymaster_verification_gas_limit: estimate
                 .paymaster_verification_gas_limit
                 .map(|x| U128::from(x)),
+            block_number: estimate.block_number.map(U64::from),
+            breakdown: estimate.breakdown.map(Into::into),
+            verification_gas_rounds: estimate.verification_gas_rounds.map(U64::from),
+            call_gas_rounds: estimate.call_gas_rounds.map(U64::from),
+            warnings: estimate.warnings.iter().map(ToString::to_string).collect(),
         }
     }
 }
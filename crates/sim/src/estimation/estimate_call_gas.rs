@@ -9,15 +9,9 @@ use rundler_contracts::{
 use rundler_provider::{EntryPoint, SimulationProvider, StateOverride};
 use rundler_types::UserOperation;
 
-use super::Settings;
+use super::{GasSearchResult, Settings};
 use crate::GasEstimationError;
 
-/// Gas estimates will be rounded up to the next multiple of this. Increasing
-/// this value reduces the number of rounds of `eth_call` needed in binary
-/// search, e.g. a value of 1024 means ten fewer `eth_call`s needed for each of
-/// verification gas and call gas.
-const GAS_ROUNDING: u64 = 4096;
-
 /// Must match the constant in `CallGasEstimationProxyTypes.sol`.
 #[allow(dead_code)]
 pub(crate) const PROXY_IMPLEMENTATION_ADDRESS_MARKER: &str =
@@ -36,7 +30,7 @@ pub trait CallGasEstimator: Send + Sync {
         op: Self::UO,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError>;
+    ) -> Result<GasSearchResult, GasEstimationError>;
 
     /// Calls simulate_handle_op, but captures the execution result. Returning an
     /// error if the operation reverts or anyhow error on any other error
@@ -100,7 +94,7 @@ where
         op: Self::UO,
         block_hash: B256,
         mut state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasSearchResult, GasEstimationError> {
         let timer = std::time::Instant::now();
         self.specialization
             .add_proxy_to_overrides(*self.entry_point.address(), &mut state_override);
@@ -116,7 +110,7 @@ where
                 callless_op.clone(),
                 min_gas,
                 max_gas,
-                GAS_ROUNDING.into(),
+                self.settings.gas_rounding.into(),
                 is_continuation,
             );
             let target_revert_data = self
@@ -146,18 +140,26 @@ where
                         "binary search for call gas took {num_rounds} rounds, {}ms",
                         timer.elapsed().as_millis()
                     );
-                    return Ok(result
-                        .gasEstimate
-                        .try_into()
-                        .ok()
-                        .context("gasEstimate return overflow")?);
+                    return Ok(GasSearchResult {
+                        gas: result
+                            .gasEstimate
+                            .try_into()
+                            .ok()
+                            .context("gasEstimate return overflow")?,
+                        num_rounds,
+                    });
                 }
                 CallGasEstimationProxyErrors::EstimateCallGasRevertAtMax(revert) => {
-                    let error = if let Ok(revert) = Revert::abi_decode(&revert.revertData, false) {
-                        GasEstimationError::RevertInCallWithMessage(revert.reason)
-                    } else {
-                        GasEstimationError::RevertInCallWithBytes(revert.revertData)
-                    };
+                    // An out of gas revert has empty revert data, report it distinctly
+                    // so that it is not mistaken for an empty revert from the call itself.
+                    let error =
+                        if revert.revertData.is_empty() && max_gas == self.settings.max_call_gas {
+                            GasEstimationError::CallGasLimitTooLow(max_gas)
+                        } else if let Ok(revert) = Revert::abi_decode(&revert.revertData, false) {
+                            GasEstimationError::RevertInCallWithMessage(revert.reason)
+                        } else {
+                            GasEstimationError::RevertInCallWithBytes(revert.revertData)
+                        };
                     return Err(error);
                 }
                 CallGasEstimationProxyErrors::EstimateCallGasContinuation(continuation) => {
@@ -1,10 +1,13 @@
-use alloy_primitives::{Address, Bytes, B256, U256};
-use anyhow::{anyhow, Context};
+use std::sync::Mutex;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use anyhow::Context;
 use async_trait::async_trait;
 use rundler_provider::{EntryPoint, EvmProvider, SimulationProvider, StateOverride};
 use rundler_types::{chain::ChainSpec, UserOperation};
+use rundler_utils::cache::LruMap;
 
-use super::Settings;
+use super::{EstimationStrategy, GasSearchResult, Settings};
 use crate::GasEstimationError;
 
 /// Gas estimation will stop when the binary search bounds are within
@@ -15,6 +18,12 @@ const GAS_ESTIMATION_ERROR_MARGIN: f64 = 0.1;
 const OUT_OF_GAS_ERROR_CODES: &[&str] = &[
     "AA13", "AA23", "AA26", "AA33", "AA36", "AA40", "AA41", "AA51",
 ];
+/// Maximum number of previous estimates kept when using the cached estimation strategy
+const ESTIMATE_CACHE_SIZE: u32 = 1024;
+
+/// Key of a cached verification gas estimate: sender, factory, paymaster, hashes of the
+/// call data and signature, and max guess
+type EstimateCacheKey = (Address, Option<Address>, Option<Address>, B256, B256, u128);
 
 /// Estimates a verification gas limit for a user operation. Can be used to
 /// estimate both verification gas and, in the v0.7 case, paymaster verification
@@ -39,7 +48,7 @@ pub trait VerificationGasEstimator: Send + Sync {
         state_override: StateOverride,
         max_guess: u128,
         get_op_with_limit: F,
-    ) -> Result<u128, GasEstimationError>;
+    ) -> Result<GasSearchResult, GasEstimationError>;
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -54,6 +63,7 @@ pub struct VerificationGasEstimatorImpl<P, E> {
     provider: P,
     entry_point: E,
     settings: Settings,
+    estimate_cache: Mutex<LruMap<EstimateCacheKey, u128>>,
 }
 
 #[async_trait]
@@ -72,7 +82,7 @@ where
         state_override: StateOverride,
         max_guess: u128,
         get_op_with_limit: F,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasSearchResult, GasEstimationError> {
         let timer = std::time::Instant::now();
         let paymaster_gas_fee = self.settings.verification_estimation_gas_fee;
 
@@ -99,9 +109,12 @@ where
         // Make one attempt at max gas, to see if success is possible.
         // Capture the gas usage of this attempt and use as the initial guess in the binary search
         let initial_op = get_op(max_guess);
-        let call = self
-            .entry_point
-            .get_simulate_handle_op_call(initial_op, state_override.clone());
+        let call = self.entry_point.get_simulate_handle_op_call(
+            initial_op,
+            Address::ZERO,
+            Bytes::new(),
+            state_override.clone(),
+        );
         let gas_used = self
             .provider
             .get_gas_used(call)
@@ -110,9 +123,9 @@ where
 
         if gas_used.success {
             if self.entry_point.simulation_should_revert() {
-                Err(anyhow!(
-                    "simulateHandleOp succeeded but should always revert. Make sure the entry point contract is deployed and the address is correct"
-                ))?;
+                return Err(GasEstimationError::UnexpectedSimulationSuccess(
+                    *self.entry_point.address(),
+                ));
             }
         } else if let Some(revert) = E::decode_simulate_handle_ops_revert(&gas_used.result)?.err() {
             return Err(GasEstimationError::RevertInValidation(revert));
@@ -156,24 +169,66 @@ where
         }
 
         let ret_gas_used: u128 = gas_used.gasUsed.try_into().unwrap();
-        let mut guess = ret_gas_used.saturating_mul(2);
+        let cache_key = (
+            op.sender(),
+            op.factory(),
+            op.paymaster(),
+            keccak256(op.call_data()),
+            keccak256(op.signature()),
+            max_guess,
+        );
+
+        let cached_gas = match self.settings.estimation_strategy {
+            EstimationStrategy::BinarySearch => None,
+            EstimationStrategy::SingleShot => Some(ret_gas_used.min(max_guess)),
+            EstimationStrategy::Cached => {
+                let cached = self.estimate_cache.lock().unwrap().get(&cache_key).copied();
+                match cached {
+                    Some(gas)
+                        if run_attempt_returning_error(gas, state_override.clone()).await? =>
+                    {
+                        Some(gas)
+                    }
+                    _ => None,
+                }
+            }
+        };
+
         let mut num_rounds = 0;
-        while (min_success_gas as f64) / (max_failure_gas as f64)
-            > (1.0 + GAS_ESTIMATION_ERROR_MARGIN)
-        {
-            num_rounds += 1;
-            if run_attempt_returning_error(guess, state_override.clone()).await? {
-                min_success_gas = guess;
-            } else {
-                max_failure_gas = guess;
+        if let Some(gas) = cached_gas {
+            min_success_gas = gas;
+        } else {
+            let mut guess = ret_gas_used.saturating_mul(2);
+            while (min_success_gas as f64) / (max_failure_gas as f64)
+                > (1.0 + GAS_ESTIMATION_ERROR_MARGIN)
+            {
+                num_rounds += 1;
+                if run_attempt_returning_error(guess, state_override.clone()).await? {
+                    min_success_gas = guess;
+                } else {
+                    max_failure_gas = guess;
+                }
+                guess = max_failure_gas.saturating_add(min_success_gas) / 2;
             }
-            guess = max_failure_gas.saturating_add(min_success_gas) / 2;
-        }
+            // the search stops within an error margin, round up like call gas estimates, but
+            // never past the bound the search ran under
+            let gas_rounding = u128::from(self.settings.gas_rounding);
+            min_success_gas = min_success_gas
+                .next_multiple_of(gas_rounding)
+                .min(max_guess);
 
-        tracing::debug!(
-            "binary search for verification gas took {num_rounds} rounds, {}ms",
-            timer.elapsed().as_millis()
-        );
+            tracing::debug!(
+                "binary search for verification gas took {num_rounds} rounds, {}ms",
+                timer.elapsed().as_millis()
+            );
+
+            if self.settings.estimation_strategy == EstimationStrategy::Cached {
+                self.estimate_cache
+                    .lock()
+                    .unwrap()
+                    .insert(cache_key, min_success_gas);
+            }
+        }
 
         // If not using a paymaster, always add the cost of a native transfer to the verification gas.
         // This may cause an over estimation when the account does have enough deposit to pay for the
@@ -182,7 +237,10 @@ where
             min_success_gas += self.chain_spec.deposit_transfer_overhead();
         }
 
-        Ok(min_success_gas)
+        Ok(GasSearchResult {
+            gas: min_success_gas,
+            num_rounds,
+        })
     }
 }
 
@@ -199,6 +257,7 @@ where
             provider,
             entry_point,
             settings,
+            estimate_cache: Mutex::new(LruMap::new(ESTIMATE_CACHE_SIZE)),
         }
     }
 }
@@ -11,11 +11,15 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::Bytes;
+use std::{future::Future, time::Duration};
+
+use alloy_primitives::{Address, Bytes, B256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{ProviderError, StateOverride};
-use rundler_types::{GasEstimate, ValidationRevert};
+use rundler_provider::{EvmProvider, ProviderError, StateOverride};
+use rundler_types::{
+    AccountType, GasEstimate, GasEstimateBreakdown, GasEstimateStages, ValidationRevert, Warning,
+};
 
 use crate::precheck::MIN_CALL_GAS_LIMIT;
 
@@ -32,8 +36,6 @@ pub use v0_6::GasEstimator as GasEstimatorV0_6;
 mod v0_7;
 pub use v0_7::GasEstimator as GasEstimatorV0_7;
 
-/// Percentage by which to increase the verification gas limit after binary search
-const VERIFICATION_GAS_BUFFER_PERCENT: u32 = 10;
 /// Absolute value by which to increase the call gas limit after binary search
 const CALL_GAS_BUFFER_VALUE: u128 = 3000;
 
@@ -49,6 +51,9 @@ pub enum GasEstimationError {
     /// Call reverted with bytes
     #[error("user operation's call reverted: {0:#x}")]
     RevertInCallWithBytes(Bytes),
+    /// Call ran out of gas at the maximum call gas limit, reverting with empty data
+    #[error("user operation's call ran out of gas at the maximum call gas limit of {0}")]
+    CallGasLimitTooLow(u128),
     /// Call used too much gas
     #[error("gas_used cannot be larger than a u64 integer")]
     GasUsedTooLarge,
@@ -58,6 +63,19 @@ pub enum GasEstimationError {
     /// The total amount of gas used by the UO is greater than allowed
     #[error("total gas used by the user operation {0} is greater than the allowed limit: {1}")]
     GasTotalTooLarge(u128, u128),
+    /// The pre-verification gas of the UO is greater than allowed
+    #[error("pre-verification gas {0} is greater than the allowed limit: {1}")]
+    PreVerificationGasTooHigh(u128, u128),
+    /// `simulateHandleOp` succeeded when it should always revert, usually because the
+    /// entry point contract is not deployed at the configured address
+    #[error("simulateHandleOp succeeded on entry point {0} but should always revert. Make sure the entry point contract is deployed and the address is correct")]
+    UnexpectedSimulationSuccess(Address),
+    /// The UO has an empty signature but is not deploying its account
+    #[error("user operation has an empty signature, provide a dummy signature of the expected length for accurate estimation")]
+    EmptySignature,
+    /// The provider no longer has the state for the block estimation was run against
+    #[error("state for block {0:?} is unavailable from the provider, it may have been pruned")]
+    PrunedState(B256),
     /// Error from provider
     #[error(transparent)]
     ProviderError(#[from] ProviderError),
@@ -66,6 +84,23 @@ pub enum GasEstimationError {
     Other(#[from] anyhow::Error),
 }
 
+impl GasEstimationError {
+    /// Returns true if the error was caused by the provider missing the state for
+    /// the requested block
+    pub fn is_pruned_state(&self) -> bool {
+        match self {
+            GasEstimationError::PrunedState(_) => true,
+            GasEstimationError::ProviderError(error) => error.is_pruned_state(),
+            GasEstimationError::Other(error) => error.chain().any(|cause| {
+                cause
+                    .downcast_ref::<ProviderError>()
+                    .is_some_and(ProviderError::is_pruned_state)
+            }),
+            _ => false,
+        }
+    }
+}
+
 /// Gas estimator trait
 #[cfg_attr(feature = "test-utils", automock(type UserOperationOptionalGas = rundler_types::v0_6::UserOperationOptionalGas;))]
 #[async_trait::async_trait]
@@ -80,6 +115,156 @@ pub trait GasEstimator: Send + Sync {
         op: Self::UserOperationOptionalGas,
         state_override: StateOverride,
     ) -> Result<GasEstimate, GasEstimationError>;
+
+    /// Returns a gas estimate against `block`, a block hash and number pair already known to
+    /// the caller, instead of looking up the latest block.
+    ///
+    /// The block is never re-pinned, and if its state is unavailable the estimate fails with
+    /// `GasEstimationError::PrunedState` regardless of the pruned state handling setting.
+    async fn estimate_op_gas_with_block(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: StateOverride,
+        block: (B256, u64),
+    ) -> Result<GasEstimate, GasEstimationError>;
+
+    /// Returns optimistic and conservative gas estimates from a single estimation, by
+    /// applying each verification gas buffer percentage to the same raw measurements.
+    async fn estimate_op_gas_scenarios(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: StateOverride,
+        optimistic_buffer_percent: u32,
+        conservative_buffer_percent: u32,
+    ) -> Result<GasEstimateScenarios, GasEstimationError>;
+
+    /// Returns a gas estimate that reuses `validation` for the verification gas limit
+    /// instead of searching for it, if the validation ran within `validation_reuse_max_blocks`
+    /// of the latest block. If the validation is stale, this is a full `estimate_op_gas`.
+    async fn estimate_op_gas_with_validation(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: StateOverride,
+        validation: RecentValidation,
+    ) -> Result<GasEstimate, GasEstimationError>;
+}
+
+/// A validation result recently computed for a user operation, e.g. by the mempool's
+/// re-validation on each new block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecentValidation {
+    /// Gas used by the operation's validation, excluding its pre-verification gas, i.e. the
+    /// entry point's `preOpGas` less `preVerificationGas`
+    pub verification_gas_used: u128,
+    /// Number of the block the validation ran against
+    pub block_number: u64,
+}
+
+/// Gas estimates for optimistic and conservative scenarios, computed from the same
+/// raw measurements with different verification gas buffers
+#[derive(Debug, Clone)]
+pub struct GasEstimateScenarios {
+    /// Estimate with the smaller buffer, more likely to be underpriced but cheaper
+    pub optimistic: GasEstimate,
+    /// Estimate with the larger buffer, more likely to succeed
+    pub conservative: GasEstimate,
+}
+
+/// Re-applies verification gas buffers to the raw measurements of an estimate.
+///
+/// `buffer` and `paymaster_buffer` map a raw measurement to its buffered and clamped
+/// values. Fields that weren't searched for, e.g. provided by the user, are left unchanged.
+fn rebuffer_estimate(
+    estimate: &GasEstimate,
+    buffer: impl Fn(u128) -> (u128, u128),
+    paymaster_buffer: impl Fn(u128) -> (u128, u128),
+) -> GasEstimate {
+    let Some(breakdown) = &estimate.breakdown else {
+        return estimate.clone();
+    };
+
+    let rebuffer = |stages: GasEstimateStages, buffer: &dyn Fn(u128) -> (u128, u128)| {
+        if stages.num_rounds.is_none() {
+            return stages;
+        }
+        let (after_buffer, after_clamp) = buffer(stages.raw);
+        GasEstimateStages {
+            after_buffer,
+            after_clamp,
+            ..stages
+        }
+    };
+
+    let verification_gas_limit = rebuffer(breakdown.verification_gas_limit, &buffer);
+    let paymaster_verification_gas_limit = breakdown
+        .paymaster_verification_gas_limit
+        .map(|stages| rebuffer(stages, &paymaster_buffer));
+
+    GasEstimate {
+        verification_gas_limit: verification_gas_limit.after_clamp,
+        paymaster_verification_gas_limit: paymaster_verification_gas_limit
+            .map(|stages| stages.after_clamp)
+            .or(estimate.paymaster_verification_gas_limit),
+        breakdown: Some(GasEstimateBreakdown {
+            verification_gas_limit,
+            paymaster_verification_gas_limit,
+            ..breakdown.clone()
+        }),
+        ..estimate.clone()
+    }
+}
+
+/// Strategy used to estimate the verification gas limits of a user operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum EstimationStrategy {
+    /// Binary search for the minimum gas limit that succeeds. Most accurate, but
+    /// requires the most provider calls.
+    #[default]
+    BinarySearch,
+    /// Use the gas used by a single simulation at the maximum gas limit. Requires
+    /// a single provider call, but may overestimate.
+    SingleShot,
+    /// Reuse the previous estimate for the same sender, factory and paymaster if a
+    /// simulation at that limit succeeds, falling back to binary search otherwise.
+    Cached,
+}
+
+/// How to handle the provider reporting that the state for the block being
+/// estimated against is unavailable, e.g. a non-archive node that has pruned it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum PrunedStateHandling {
+    /// Fail the estimation with a `PrunedState` error
+    #[default]
+    Error,
+    /// Log a warning and retry the estimation once against the latest block
+    RetryLatest,
+}
+
+/// How to handle an operation with an empty signature that isn't deploying its account.
+/// A real operation needs a signature, so estimates made without one undercount the
+/// pre-verification gas and fail at submission.
+///
+/// Non-empty signatures shorter than the configured account type's minimum are always
+/// warned about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum EmptySignatureHandling {
+    /// Log a warning, continue the estimation, and return the warning with the estimate
+    #[default]
+    Warn,
+    /// Fail the estimation with an `EmptySignature` error
+    Reject,
+}
+
+/// A gas limit found by a search, along with the number of `eth_call` rounds the search took
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasSearchResult {
+    /// The gas limit found
+    pub gas: u128,
+    /// The number of rounds the search took, zero if no search was needed
+    pub num_rounds: u32,
 }
 
 /// Settings for gas estimation
@@ -87,6 +272,9 @@ pub trait GasEstimator: Send + Sync {
 pub struct Settings {
     /// The maximum amount of gas that can be used for the verification step of a user operation
     pub max_verification_gas: u128,
+    /// The minimum verification gas limit to return from estimation, applied after the
+    /// estimation buffer. Capped at `max_verification_gas`.
+    pub min_verification_gas_limit: u128,
     /// The maximum amount of gas that can be used for the call step of a user operation
     pub max_call_gas: u128,
     /// The maximum amount of gas that can be used for the paymaster verification step of a user operation
@@ -103,6 +291,38 @@ pub struct Settings {
     /// gas price.
     /// Clients can use state overrides to set the balance of the fee-payer to at least this value.
     pub verification_estimation_gas_fee: u128,
+    /// Percentage by which to increase verification gas limits after estimation
+    pub verification_gas_buffer_percent: u32,
+    /// The strategy used to estimate verification gas limits
+    pub estimation_strategy: EstimationStrategy,
+    /// Binary searched call and verification gas estimates will be rounded up to the next
+    /// multiple of this. Increasing this value reduces the number of rounds of `eth_call`
+    /// needed in binary search, e.g. a value of 1024 means ten fewer `eth_call`s. Must be a
+    /// non-zero power of two.
+    pub gas_rounding: u64,
+    /// How to handle the provider no longer having the state for the estimation block
+    pub pruned_state_handling: PrunedStateHandling,
+    /// How to handle operations with an empty signature that aren't deploying their account
+    pub empty_signature_handling: EmptySignatureHandling,
+    /// Account type whose minimum signature length the dummy signature is checked against
+    pub dummy_signature_account_type: AccountType,
+    /// Run call gas estimation after verification gas estimation instead of concurrently.
+    /// Halves peak provider concurrency at the cost of latency.
+    pub sequential_estimation: bool,
+    /// If set, once estimation has been running for this many milliseconds the latest block
+    /// is re-fetched, and if the chain has advanced estimation is restarted against the new
+    /// block. Bounded to a single restart.
+    pub block_drift_repin_millis: Option<u64>,
+    /// If set, pre-verification gas is estimated before the verification and call gas
+    /// searches, and estimation fails early if it exceeds this cap.
+    pub max_pre_verification_gas: Option<u128>,
+    /// Number of random fills to sample when estimating the DA portion of pre-verification gas.
+    /// The minimum over all samples is used, reducing the overestimation of any single random
+    /// fill on chains that compress their DA data. Must be non-zero.
+    pub pre_verification_gas_random_fill_samples: u32,
+    /// Maximum number of blocks a validation result can be behind the latest block to be
+    /// reused by `estimate_op_gas_with_validation`. Older results trigger a full estimate.
+    pub validation_reuse_max_blocks: u64,
 }
 
 impl Settings {
@@ -111,6 +331,302 @@ impl Settings {
         if self.max_call_gas < MIN_CALL_GAS_LIMIT {
             return Some("max_call_gas field cannot be lower than MIN_CALL_GAS_LIMIT".to_string());
         }
+        if !self.gas_rounding.is_power_of_two() {
+            return Some("gas_rounding field must be a non-zero power of two".to_string());
+        }
+        if self.pre_verification_gas_random_fill_samples == 0 {
+            return Some(
+                "pre_verification_gas_random_fill_samples field must be non-zero".to_string(),
+            );
+        }
         None
     }
 }
+
+/// Returns whether `validation` ran within `max_age_blocks` of `block_number`.
+fn is_validation_recent(
+    validation: &RecentValidation,
+    block_number: u64,
+    max_age_blocks: u64,
+) -> bool {
+    block_number.saturating_sub(validation.block_number) <= max_age_blocks
+}
+
+/// Checks the result of validating the dummy fields of an operation that isn't deploying
+/// its account, returning any warnings. Applies `handling` if the signature is empty.
+fn check_dummy_fields(
+    dummy_fields: Result<(), Warning>,
+    is_empty_signature: bool,
+    is_deployment: bool,
+    handling: EmptySignatureHandling,
+) -> Result<Vec<Warning>, GasEstimationError> {
+    if is_deployment {
+        return Ok(vec![]);
+    }
+    if is_empty_signature && handling == EmptySignatureHandling::Reject {
+        return Err(GasEstimationError::EmptySignature);
+    }
+
+    let Err(warning) = dummy_fields else {
+        return Ok(vec![]);
+    };
+    tracing::warn!("estimating gas for a user operation: {warning}");
+    Ok(vec![warning])
+}
+
+/// Runs `estimate` against the latest block, applying `handling` if the provider
+/// reports that the state for that block is unavailable.
+///
+/// If `repin_after` elapses before estimation completes and the latest block has changed,
+/// estimation is restarted once against the new block.
+async fn estimate_at_latest_block<P, F, Fut, T>(
+    provider: &P,
+    handling: PrunedStateHandling,
+    repin_after: Option<Duration>,
+    estimate: F,
+) -> Result<T, GasEstimationError>
+where
+    P: EvmProvider,
+    F: Fn(B256, u64) -> Fut,
+    Fut: Future<Output = Result<T, GasEstimationError>>,
+{
+    let (mut block_hash, block_number) = provider
+        .get_latest_block_hash_and_number()
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    let estimation = estimate(block_hash, block_number);
+    let result = match repin_after {
+        None => estimation.await,
+        Some(repin_after) => {
+            tokio::pin!(estimation);
+            tokio::select! {
+                result = &mut estimation => result,
+                _ = tokio::time::sleep(repin_after) => {
+                    let (latest_hash, latest_number) = provider
+                        .get_latest_block_hash_and_number()
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                    if latest_hash == block_hash {
+                        estimation.await
+                    } else {
+                        tracing::debug!(
+                            "latest block changed from {block_hash:?} to {latest_hash:?} during gas estimation, restarting estimation"
+                        );
+                        block_hash = latest_hash;
+                        estimate(latest_hash, latest_number).await
+                    }
+                }
+            }
+        }
+    };
+
+    let error = match result {
+        Err(error) if error.is_pruned_state() => error,
+        result => return result,
+    };
+
+    match handling {
+        PrunedStateHandling::Error => Err(GasEstimationError::PrunedState(block_hash)),
+        PrunedStateHandling::RetryLatest => {
+            tracing::warn!(
+                "state for block {block_hash:?} unavailable during gas estimation, retrying against the latest block: {error}"
+            );
+            let (block_hash, block_number) = provider
+                .get_latest_block_hash_and_number()
+                .await
+                .map_err(anyhow::Error::from)?;
+            estimate(block_hash, block_number)
+                .await
+                .map_err(|error| pruned_state_at(error, block_hash))
+        }
+    }
+}
+
+/// Replaces an error caused by unavailable state with a `PrunedState` error for `block_hash`.
+fn pruned_state_at(error: GasEstimationError, block_hash: B256) -> GasEstimationError {
+    if error.is_pruned_state() {
+        GasEstimationError::PrunedState(block_hash)
+    } else {
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use rundler_provider::MockEvmProvider;
+
+    use super::*;
+
+    // Returns a provider that reports a new latest block on each call
+    fn advancing_provider() -> (MockEvmProvider, Arc<AtomicUsize>) {
+        let mut provider = MockEvmProvider::new();
+        let latest_calls = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::clone(&latest_calls);
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || {
+                let n = calls.fetch_add(1, Ordering::Relaxed) as u8 + 1;
+                Ok((B256::repeat_byte(n), n.into()))
+            });
+        (provider, latest_calls)
+    }
+
+    #[test]
+    fn test_rebuffer_estimate_skips_unsearched_fields() {
+        let searched = GasEstimateStages {
+            raw: 100_000,
+            after_buffer: 110_000,
+            after_clamp: 110_000,
+            num_rounds: Some(5),
+        };
+        let estimate = GasEstimate {
+            pre_verification_gas: 50_000,
+            call_gas_limit: 20_000,
+            verification_gas_limit: 110_000,
+            paymaster_verification_gas_limit: Some(30_000),
+            block_number: Some(1),
+            breakdown: Some(GasEstimateBreakdown {
+                pre_verification_gas: GasEstimateStages::unadjusted(50_000),
+                call_gas_limit: GasEstimateStages::unadjusted(20_000),
+                verification_gas_limit: searched,
+                paymaster_verification_gas_limit: Some(GasEstimateStages::unadjusted(30_000)),
+            }),
+            verification_gas_rounds: Some(5),
+            call_gas_rounds: None,
+            warnings: vec![],
+        };
+
+        let rebuffered = rebuffer_estimate(
+            &estimate,
+            |gas| (gas * 2, gas * 2),
+            |gas| (gas * 3, gas * 3),
+        );
+
+        assert_eq!(rebuffered.verification_gas_limit, 200_000);
+        // the provided paymaster verification gas limit is not rebuffered
+        assert_eq!(rebuffered.paymaster_verification_gas_limit, Some(30_000));
+        assert_eq!(rebuffered.call_gas_limit, 20_000);
+        assert_eq!(rebuffered.pre_verification_gas, 50_000);
+        assert_eq!(
+            rebuffered.breakdown.unwrap().verification_gas_limit,
+            GasEstimateStages {
+                after_buffer: 200_000,
+                after_clamp: 200_000,
+                ..searched
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_drift_repins_to_new_block() {
+        let (provider, latest_calls) = advancing_provider();
+
+        let block_number = estimate_at_latest_block(
+            &provider,
+            PrunedStateHandling::Error,
+            Some(Duration::from_millis(10)),
+            |_, block_number| async move {
+                // the first block's estimation is slow enough to trigger a re-pin
+                if block_number == 1 {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
+                Ok(block_number)
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(block_number, 2);
+        assert_eq!(latest_calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_drift_repin_bounded_to_one_restart() {
+        let (provider, latest_calls) = advancing_provider();
+
+        let block_number = estimate_at_latest_block(
+            &provider,
+            PrunedStateHandling::Error,
+            Some(Duration::from_millis(10)),
+            |_, block_number| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(block_number)
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(block_number, 2);
+        assert_eq!(latest_calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_drift_no_repin_when_block_unchanged() {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .times(2)
+            .returning(|| Ok((B256::repeat_byte(1), 1)));
+
+        let block_number = estimate_at_latest_block(
+            &provider,
+            PrunedStateHandling::Error,
+            Some(Duration::from_millis(10)),
+            |_, block_number| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(block_number)
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(block_number, 1);
+    }
+
+    #[test]
+    fn test_check_dummy_fields() {
+        let short = Warning::ShortDummySignature {
+            account_type: AccountType::Passkey,
+            actual: 65,
+            expected: 101,
+        };
+
+        for handling in [EmptySignatureHandling::Warn, EmptySignatureHandling::Reject] {
+            assert_eq!(
+                check_dummy_fields(Ok(()), false, false, handling).unwrap(),
+                vec![]
+            );
+            // short signatures are only warned about
+            assert_eq!(
+                check_dummy_fields(Err(short.clone()), false, false, handling).unwrap(),
+                vec![short.clone()]
+            );
+            // deployments are exempt
+            assert_eq!(
+                check_dummy_fields(Err(short.clone()), true, true, handling).unwrap(),
+                vec![]
+            );
+        }
+
+        assert_eq!(
+            check_dummy_fields(
+                Err(short.clone()),
+                true,
+                false,
+                EmptySignatureHandling::Warn
+            )
+            .unwrap(),
+            vec![short.clone()]
+        );
+        assert!(matches!(
+            check_dummy_fields(Err(short), true, false, EmptySignatureHandling::Reject),
+            Err(GasEstimationError::EmptySignature)
+        ));
+    }
+}
@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{cmp, ops::Add};
+use std::{cmp, ops::Add, time::Duration};
 
 use alloy_primitives::{Address, Bytes, B256, U256};
 use alloy_sol_types::SolInterface;
@@ -29,14 +29,14 @@ use rundler_provider::{
 use rundler_types::{
     chain::ChainSpec,
     v0_6::{UserOperation, UserOperationBuilder, UserOperationOptionalGas},
-    GasEstimate, UserOperation as _,
+    GasEstimate, GasEstimateBreakdown, GasEstimateStages, UserOperation as _, Warning,
 };
 use rundler_utils::math;
 use tokio::join;
 
 use super::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
-    Settings, VerificationGasEstimator,
+    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimateScenarios,
+    GasEstimationError, GasSearchResult, RecentValidation, Settings, VerificationGasEstimator,
 };
 use crate::{
     estimation::estimate_verification_gas::GetOpWithLimitArgs, gas, precheck::MIN_CALL_GAS_LIMIT,
@@ -70,57 +70,104 @@ where
         op: UserOperationOptionalGas,
         state_override: StateOverride,
     ) -> Result<GasEstimate, GasEstimationError> {
-        self.check_provided_limits(&op)?;
-
-        let (block_hash, _) = self
-            .provider
-            .get_latest_block_hash_and_number()
-            .await
-            .map_err(anyhow::Error::from)?;
-
-        let pre_verification_gas = self.estimate_pre_verification_gas(&op, block_hash).await?;
+        let warnings = self.check_provided_limits(&op)?;
+
+        super::estimate_at_latest_block(
+            &self.provider,
+            self.settings.pruned_state_handling,
+            self.settings
+                .block_drift_repin_millis
+                .map(Duration::from_millis),
+            |block_hash, block_number| {
+                self.estimate_op_gas_at_block(
+                    op.clone(),
+                    state_override.clone(),
+                    block_hash,
+                    block_number,
+                )
+            },
+        )
+        .await
+        .map(|estimate| GasEstimate {
+            warnings,
+            ..estimate
+        })
+    }
 
-        let full_op = op
-            .clone()
-            .into_user_operation_builder(
-                &self.chain_spec,
-                self.settings.max_call_gas,
-                self.settings.max_verification_gas,
-            )
-            .pre_verification_gas(pre_verification_gas)
-            .build();
+    async fn estimate_op_gas_with_block(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        block: (B256, u64),
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let warnings = self.check_provided_limits(&op)?;
 
-        let verification_future =
-            self.estimate_verification_gas(&op, &full_op, block_hash, state_override.clone());
-        let call_future = self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override);
+        let (block_hash, block_number) = block;
+        self.estimate_op_gas_at_block(op, state_override, block_hash, block_number)
+            .await
+            .map(|estimate| GasEstimate {
+                warnings,
+                ..estimate
+            })
+            .map_err(|error| super::pruned_state_at(error, block_hash))
+    }
 
-        // Not try_join! because then the output is nondeterministic if both
-        // verification and call estimation fail.
-        let timer = std::time::Instant::now();
-        let (verification_gas_limit, call_gas_limit) = join!(verification_future, call_future);
-        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+    async fn estimate_op_gas_scenarios(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        optimistic_buffer_percent: u32,
+        conservative_buffer_percent: u32,
+    ) -> Result<GasEstimateScenarios, GasEstimationError> {
+        let estimate = self.estimate_op_gas(op, state_override).await?;
+        Ok(self.estimate_scenarios(
+            &estimate,
+            optimistic_buffer_percent,
+            conservative_buffer_percent,
+        ))
+    }
 
-        let verification_gas_limit = verification_gas_limit?;
-        let call_gas_limit = call_gas_limit?;
+    async fn estimate_op_gas_with_validation(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        validation: RecentValidation,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let warnings = self.check_provided_limits(&op)?;
 
-        // Verify total gas limit
-        let mut op_with_gas = full_op;
-        op_with_gas.verification_gas_limit = verification_gas_limit;
-        op_with_gas.call_gas_limit = call_gas_limit;
-        // require that this can fit in a bundle of size 1
-        let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
-        if gas_limit > self.settings.max_total_execution_gas {
-            return Err(GasEstimationError::GasTotalTooLarge(
-                gas_limit,
-                self.settings.max_total_execution_gas,
-            ));
-        }
+        // The validation ran at the operation's fees, so its gas used already includes any
+        // deposit transfer. A provided verification gas limit skips the search.
+        let (_, verification_gas_limit) = self.buffer_verification_gas(
+            validation.verification_gas_used,
+            self.settings.verification_gas_buffer_percent,
+        );
 
-        Ok(GasEstimate {
-            pre_verification_gas,
-            verification_gas_limit,
-            call_gas_limit,
-            paymaster_verification_gas_limit: None,
+        super::estimate_at_latest_block(
+            &self.provider,
+            self.settings.pruned_state_handling,
+            self.settings
+                .block_drift_repin_millis
+                .map(Duration::from_millis),
+            |block_hash, block_number| {
+                let op = if super::is_validation_recent(
+                    &validation,
+                    block_number,
+                    self.settings.validation_reuse_max_blocks,
+                ) {
+                    UserOperationOptionalGas {
+                        verification_gas_limit: Some(verification_gas_limit),
+                        ..op.clone()
+                    }
+                } else {
+                    op.clone()
+                };
+                self.estimate_op_gas_at_block(op, state_override.clone(), block_hash, block_number)
+            },
+        )
+        .await
+        .map(|estimate| GasEstimate {
+            warnings,
+            ..estimate
         })
     }
 }
@@ -186,10 +233,120 @@ where
     CGE: CallGasEstimator<UO = UserOperation>,
     F: FeeEstimator,
 {
+    async fn estimate_op_gas_at_block(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        block_hash: B256,
+        block_number: u64,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        // The estimated pre-verification gas doesn't change the outcome of the verification
+        // and call gas simulations, so it is estimated alongside them.
+        let full_op = op
+            .clone()
+            .into_user_operation_builder(
+                &self.chain_spec,
+                self.settings.max_call_gas,
+                self.settings.max_verification_gas,
+            )
+            .build();
+
+        // If capped, estimate pre-verification gas first so that ops with excessive
+        // pre-verification gas skip the expensive searches.
+        let capped_pre_verification_gas = match self.settings.max_pre_verification_gas {
+            Some(max) => {
+                let pre_verification_gas =
+                    self.estimate_pre_verification_gas(&op, block_hash).await?;
+                if pre_verification_gas > max {
+                    return Err(GasEstimationError::PreVerificationGasTooHigh(
+                        pre_verification_gas,
+                        max,
+                    ));
+                }
+                Some(pre_verification_gas)
+            }
+            None => None,
+        };
+
+        let pre_verification_gas_future = async {
+            match capped_pre_verification_gas {
+                Some(pre_verification_gas) => Ok(pre_verification_gas),
+                None => self.estimate_pre_verification_gas(&op, block_hash).await,
+            }
+        };
+        let verification_future =
+            self.estimate_verification_gas(&op, &full_op, block_hash, state_override.clone());
+        let call_future = self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override);
+
+        let timer = std::time::Instant::now();
+        let (pre_verification_gas, verification_gas_limit, call_gas_limit) =
+            if self.settings.sequential_estimation {
+                // Run each phase only once the previous one has succeeded
+                let pre_verification_gas = pre_verification_gas_future.await?;
+                let verification_gas_limit = verification_future.await?;
+                (
+                    pre_verification_gas,
+                    verification_gas_limit,
+                    call_future.await?,
+                )
+            } else {
+                // Not try_join! because then the output is nondeterministic if multiple
+                // estimations fail.
+                let (pre_verification_gas, verification_gas_limit, call_gas_limit) = join!(
+                    pre_verification_gas_future,
+                    verification_future,
+                    call_future
+                );
+                (
+                    pre_verification_gas?,
+                    verification_gas_limit?,
+                    call_gas_limit?,
+                )
+            };
+        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+
+        let breakdown = GasEstimateBreakdown {
+            pre_verification_gas: GasEstimateStages::unadjusted(pre_verification_gas),
+            verification_gas_limit,
+            call_gas_limit,
+            paymaster_verification_gas_limit: None,
+        };
+        let verification_gas_limit = verification_gas_limit.after_clamp;
+        let call_gas_limit = call_gas_limit.after_clamp;
+
+        // Verify total gas limit
+        let mut op_with_gas = full_op;
+        op_with_gas.pre_verification_gas = pre_verification_gas;
+        op_with_gas.verification_gas_limit = verification_gas_limit;
+        op_with_gas.call_gas_limit = call_gas_limit;
+        // require that this can fit in a bundle of size 1
+        let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
+        if gas_limit > self.settings.max_total_execution_gas {
+            return Err(GasEstimationError::GasTotalTooLarge(
+                gas_limit,
+                self.settings.max_total_execution_gas,
+            ));
+        }
+
+        Ok(GasEstimate {
+            pre_verification_gas,
+            verification_gas_limit,
+            call_gas_limit,
+            paymaster_verification_gas_limit: None,
+            block_number: Some(block_number),
+            verification_gas_rounds: breakdown.verification_gas_limit.num_rounds,
+            call_gas_rounds: breakdown.call_gas_limit.num_rounds,
+            breakdown: Some(breakdown),
+            warnings: vec![],
+        })
+    }
+
+    /// Checks the gas limits and dummy signature provided by the user, returning any
+    /// warnings to attach to the estimate.
     fn check_provided_limits(
         &self,
         optional_op: &UserOperationOptionalGas,
-    ) -> Result<(), GasEstimationError> {
+    ) -> Result<Vec<Warning>, GasEstimationError> {
         if let Some(vl) = optional_op.verification_gas_limit {
             if vl > self.settings.max_verification_gas {
                 return Err(GasEstimationError::GasFieldTooLarge(
@@ -207,7 +364,12 @@ where
             }
         }
 
-        Ok(())
+        super::check_dummy_fields(
+            optional_op.validate_dummy_fields(self.settings.dummy_signature_account_type),
+            optional_op.signature.is_empty(),
+            !optional_op.init_code.is_empty(),
+            self.settings.empty_signature_handling,
+        )
     }
 
     async fn estimate_verification_gas(
@@ -216,13 +378,13 @@ where
         full_op: &UserOperation,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasEstimateStages, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(vl) = optional_op.verification_gas_limit {
             if vl != 0 {
                 // No need to do an extra simulation here, if the user provides a value that is
                 // insufficient it will cause a revert during call gas estimation (or simulation).
-                return Ok(vl);
+                return Ok(GasEstimateStages::unadjusted(vl));
             }
         }
 
@@ -237,7 +399,10 @@ where
                 .build()
         };
 
-        let verification_gas_limit: u128 = self
+        let GasSearchResult {
+            gas: verification_gas_limit,
+            num_rounds,
+        } = self
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
@@ -248,18 +413,58 @@ where
             )
             .await?;
 
+        let (after_buffer, after_clamp) = self.buffer_verification_gas(
+            verification_gas_limit,
+            self.settings.verification_gas_buffer_percent,
+        );
+
+        Ok(GasEstimateStages {
+            raw: verification_gas_limit,
+            after_buffer,
+            after_clamp,
+            num_rounds: Some(num_rounds),
+        })
+    }
+
+    /// Returns the verification gas limit after buffering and after clamping
+    fn buffer_verification_gas(
+        &self,
+        verification_gas_limit: u128,
+        buffer_percent: u32,
+    ) -> (u128, u128) {
         // Add a buffer to the verification gas limit. Add 10% or 2000 gas, whichever is larger
         // to ensure we get at least a 2000 gas buffer. Cap at the max verification gas.
-        let verification_gas_limit = cmp::max(
-            math::increase_by_percent(
-                verification_gas_limit,
-                super::VERIFICATION_GAS_BUFFER_PERCENT,
-            ),
+        let with_buffer = cmp::max(
+            math::increase_by_percent(verification_gas_limit, buffer_percent),
             verification_gas_limit + simulation::v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
+        );
+
+        (
+            with_buffer,
+            with_buffer
+                .max(self.settings.min_verification_gas_limit)
+                .min(self.settings.max_verification_gas),
         )
-        .min(self.settings.max_verification_gas);
+    }
+
+    fn estimate_scenarios(
+        &self,
+        estimate: &GasEstimate,
+        optimistic_buffer_percent: u32,
+        conservative_buffer_percent: u32,
+    ) -> GasEstimateScenarios {
+        let rebuffer = |buffer_percent| {
+            super::rebuffer_estimate(
+                estimate,
+                |gas| self.buffer_verification_gas(gas, buffer_percent),
+                |gas| (gas, gas),
+            )
+        };
 
-        Ok(verification_gas_limit)
+        GasEstimateScenarios {
+            optimistic: rebuffer(optimistic_buffer_percent.min(conservative_buffer_percent)),
+            conservative: rebuffer(optimistic_buffer_percent.max(conservative_buffer_percent)),
+        }
     }
 
     async fn estimate_pre_verification_gas(
@@ -289,11 +494,15 @@ where
             }
         };
 
+        let random_ops = (0..self.settings.pre_verification_gas_random_fill_samples)
+            .map(|_| optional_op.random_fill(&self.chain_spec))
+            .collect::<Vec<_>>();
+
         Ok(gas::estimate_pre_verification_gas(
             &self.chain_spec,
             &self.entry_point,
             &optional_op.max_fill(&self.chain_spec),
-            &optional_op.random_fill(&self.chain_spec),
+            &random_ops,
             block_hash.into(),
             gas_price,
         )
@@ -306,7 +515,7 @@ where
         full_op: UserOperation,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasEstimateStages, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(cl) = optional_op.call_gas_limit {
             if cl != 0 {
@@ -314,21 +523,26 @@ where
                 self.call_gas_estimator
                     .simulate_handle_op_with_result(full_op, block_hash, state_override)
                     .await?;
-                return Ok(cl);
+                return Ok(GasEstimateStages::unadjusted(cl));
             }
         }
 
-        let call_gas_limit = self
+        let GasSearchResult {
+            gas: call_gas_limit,
+            num_rounds,
+        } = self
             .call_gas_estimator
             .estimate_call_gas(full_op, block_hash, state_override)
             .await?;
 
         // Add a buffer to the call gas limit and clamp
-        let call_gas_limit = call_gas_limit
-            .add(super::CALL_GAS_BUFFER_VALUE)
-            .clamp(MIN_CALL_GAS_LIMIT, self.settings.max_call_gas);
-
-        Ok(call_gas_limit)
+        let with_buffer = call_gas_limit.add(super::CALL_GAS_BUFFER_VALUE);
+        Ok(GasEstimateStages {
+            raw: call_gas_limit,
+            after_buffer: with_buffer,
+            after_clamp: with_buffer.clamp(MIN_CALL_GAS_LIMIT, self.settings.max_call_gas),
+            num_rounds: Some(num_rounds),
+        })
     }
 }
 
@@ -356,14 +570,10 @@ impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV06 {
                 ..Default::default()
             },
         );
-        state_override.insert(
-            ep_to_override,
-            AccountOverride {
-                code: Some(estimation_proxy_bytecode_with_target(
-                    moved_entry_point_address,
-                )),
-                ..Default::default()
-            },
+        // Only replace the code at the entry point address, keeping any caller provided
+        // overrides (i.e. deposits in the entry point's storage) in place.
+        state_override.entry(ep_to_override).or_default().code = Some(
+            estimation_proxy_bytecode_with_target(moved_entry_point_address),
         );
     }
 
@@ -425,20 +635,24 @@ fn estimation_proxy_bytecode_with_target(target: Address) -> Bytes {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, Mutex};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
 
-    use alloy_primitives::{hex, uint};
+    use alloy_primitives::{bytes, hex, uint};
     use alloy_sol_types::{Revert, SolCall, SolError, SolValue};
     use anyhow::anyhow;
     use gas::MockFeeEstimator;
     use rundler_contracts::v0_6::{IEntryPoint, UserOperation as ContractUserOperation};
     use rundler_provider::{
-        EvmCall, ExecutionResult, GasUsedResult, MockEntryPointV0_6, MockEvmProvider,
+        BlockHashOrNumber, BlockId, EvmCall, ExecutionResult, GasUsedResult, MockEntryPointV0_6,
+        MockEvmProvider,
     };
     use rundler_types::{
         da::DAGasOracleType,
         v0_6::{UserOperation, UserOperationOptionalGas, UserOperationRequiredFields},
-        GasFees, UserOperation as UserOperationTrait, ValidationRevert,
+        AccountType, GasFees, UserOperation as UserOperationTrait, ValidationRevert,
     };
     use CallGasEstimationProxy::{
         EstimateCallGasContinuation, EstimateCallGasResult, EstimateCallGasRevertAtMax,
@@ -448,8 +662,8 @@ mod tests {
     use super::*;
     use crate::{
         estimation::{
-            estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, CALL_GAS_BUFFER_VALUE,
-            VERIFICATION_GAS_BUFFER_PERCENT,
+            estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, EmptySignatureHandling,
+            EstimationStrategy, PrunedStateHandling, CALL_GAS_BUFFER_VALUE,
         },
         simulation::v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
         VerificationGasEstimatorImpl,
@@ -484,13 +698,12 @@ mod tests {
 
         // Fill in concrete implementations of call data and
         // `simulation_should_revert`
-        entry
-            .expect_get_simulate_handle_op_call()
-            .returning(|op, state_override| {
+        entry.expect_get_simulate_handle_op_call().returning(
+            |op, target, target_call_data, state_override| {
                 let data = IEntryPoint::simulateHandleOpCall {
                     op: op.into(),
-                    target: Address::ZERO,
-                    targetCallData: Bytes::new(),
+                    target,
+                    targetCallData: target_call_data,
                 }
                 .abi_encode()
                 .into();
@@ -501,7 +714,8 @@ mod tests {
                     value: U256::ZERO,
                     state_override,
                 }
-            });
+            },
+        );
         entry.expect_simulation_should_revert().return_const(true);
 
         entry.expect_address().return_const(Address::ZERO);
@@ -528,20 +742,37 @@ mod tests {
 
     const TEST_MAX_GAS_LIMITS: u128 = 10000000000;
     const TEST_FEE: u128 = 1000;
+    const TEST_VERIFICATION_GAS_BUFFER_PERCENT: u32 = 10;
 
-    fn create_estimator(
-        entry: MockEntryPointV0_6,
-        provider: MockEvmProvider,
-    ) -> (GasEstimatorWithMocks, Settings) {
-        let settings = Settings {
+    fn create_settings() -> Settings {
+        Settings {
             max_verification_gas: TEST_MAX_GAS_LIMITS,
+            min_verification_gas_limit: 0,
             max_call_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
             max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
             verification_estimation_gas_fee: 1_000_000_000_000,
-        };
+            verification_gas_buffer_percent: TEST_VERIFICATION_GAS_BUFFER_PERCENT,
+            estimation_strategy: EstimationStrategy::BinarySearch,
+            gas_rounding: 4096,
+            pruned_state_handling: PrunedStateHandling::Error,
+            empty_signature_handling: EmptySignatureHandling::Warn,
+            dummy_signature_account_type: AccountType::Ecdsa,
+            sequential_estimation: false,
+            block_drift_repin_millis: None,
+            max_pre_verification_gas: None,
+            pre_verification_gas_random_fill_samples: 1,
+            validation_reuse_max_blocks: 1,
+        }
+    }
+
+    fn create_estimator(
+        entry: MockEntryPointV0_6,
+        provider: MockEvmProvider,
+    ) -> (GasEstimatorWithMocks, Settings) {
+        let settings = create_settings();
         let estimator = create_custom_estimator(
             ChainSpec::default(),
             provider,
@@ -552,6 +783,51 @@ mod tests {
         (estimator, settings)
     }
 
+    #[test]
+    fn test_estimate_scenarios_conservative_at_least_optimistic() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let verification_stages = GasEstimateStages {
+            raw: 200_000,
+            after_buffer: 200_000,
+            after_clamp: 200_000,
+            num_rounds: Some(10),
+        };
+        let estimate = GasEstimate {
+            pre_verification_gas: 50_000,
+            call_gas_limit: 100_000,
+            verification_gas_limit: 200_000,
+            paymaster_verification_gas_limit: None,
+            block_number: Some(1),
+            breakdown: Some(GasEstimateBreakdown {
+                pre_verification_gas: GasEstimateStages::unadjusted(50_000),
+                call_gas_limit: GasEstimateStages::unadjusted(100_000),
+                verification_gas_limit: verification_stages,
+                paymaster_verification_gas_limit: None,
+            }),
+            verification_gas_rounds: Some(10),
+            call_gas_rounds: None,
+            warnings: vec![],
+        };
+
+        let GasEstimateScenarios {
+            optimistic,
+            conservative,
+        } = estimator.estimate_scenarios(&estimate, 0, 50);
+
+        // at least the required verification gas buffer is always applied
+        assert_eq!(
+            optimistic.verification_gas_limit,
+            200_000 + simulation::v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER
+        );
+        assert_eq!(conservative.verification_gas_limit, 300_000);
+
+        assert!(conservative.pre_verification_gas >= optimistic.pre_verification_gas);
+        assert!(conservative.call_gas_limit >= optimistic.call_gas_limit);
+        assert!(conservative.verification_gas_limit >= optimistic.verification_gas_limit);
+    }
+
     fn demo_user_op_optional_gas(pvg: Option<u128>) -> UserOperationOptionalGas {
         UserOperationOptionalGas {
             sender: Address::ZERO,
@@ -634,13 +910,8 @@ mod tests {
             .returning(|_a, _b, _c| Ok((TEST_FEE, Default::default(), Default::default())));
 
         let settings = Settings {
-            max_verification_gas: 10000000000,
-            max_call_gas: 10000000000,
-            max_paymaster_verification_gas: 10000000000,
-            max_paymaster_post_op_gas: 10000000000,
-            max_total_execution_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
-            verification_estimation_gas_fee: 1_000_000_000_000,
+            ..create_settings()
         };
 
         // Chose arbitrum
@@ -710,13 +981,8 @@ mod tests {
             .returning(|_a, _b, _c| Ok((TEST_FEE, Default::default(), Default::default())));
 
         let settings = Settings {
-            max_verification_gas: 10000000000,
-            max_call_gas: 10000000000,
-            max_paymaster_verification_gas: 10000000000,
-            max_paymaster_post_op_gas: 10000000000,
-            max_total_execution_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
-            verification_estimation_gas_fee: 1_000_000_000_000,
+            ..create_settings()
         };
 
         // Chose OP
@@ -769,6 +1035,78 @@ mod tests {
         assert_eq!(result + da_gas, estimation as u32);
     }
 
+    #[tokio::test]
+    async fn test_calc_pre_verification_input_mantle() {
+        const L1_FEE_WEI: u128 = 1_000_000_000;
+        const TOKEN_RATIO: u128 = 4;
+        // the user op provides no fees, so the gas price is the base fee plus the bundle priority fee
+        const GAS_PRICE: u128 = TEST_FEE + TEST_FEE;
+
+        let (mut entry, provider) = create_base_config();
+
+        // the Mantle oracle converts the L1 fee to MNT with the token ratio, then to L2 gas
+        entry
+            .expect_calc_da_gas()
+            .withf(|_op, _block, gas_price| *gas_price == GAS_PRICE)
+            .returning(|_a, _b, gas_price| {
+                Ok((
+                    L1_FEE_WEI * TOKEN_RATIO / gas_price,
+                    Default::default(),
+                    Default::default(),
+                ))
+            });
+
+        // Chose Mantle
+        let cs = ChainSpec {
+            id: 5000,
+            da_pre_verification_gas: true,
+            da_gas_oracle_type: DAGasOracleType::MantleBedrock,
+            ..Default::default()
+        };
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator.expect_required_bundle_fees().returning(|_| {
+            Ok((
+                GasFees {
+                    max_fee_per_gas: TEST_FEE,
+                    max_priority_fee_per_gas: TEST_FEE,
+                },
+                TEST_FEE,
+            ))
+        });
+
+        let estimator =
+            create_custom_estimator(cs, provider, fee_estimator, entry, create_settings());
+
+        let user_op = demo_user_op_optional_gas(None);
+        let estimation = estimator
+            .estimate_pre_verification_gas(&user_op, B256::ZERO)
+            .await
+            .unwrap();
+
+        let uo = user_op.max_fill(&ChainSpec::default());
+
+        let cuo_bytes = ContractUserOperation::from(uo).abi_encode();
+        let length_in_words = (cuo_bytes.len() + 31) / 32;
+        let mut call_data_cost = 0;
+        for b in cuo_bytes.iter() {
+            if *b != 0 {
+                call_data_cost += 16;
+            } else {
+                call_data_cost += 4;
+            }
+        }
+
+        let result = FIXED / BUNDLE_SIZE
+            + call_data_cost
+            + PER_USER_OP
+            + PER_USER_OP_WORD * (length_in_words as u32);
+
+        // Mantle DA gas: 1 gwei of ETH is 4 gwei of MNT, which buys 2_000_000 gas at 2000 wei
+        let da_gas: u128 = 2_000_000;
+
+        assert_eq!(result as u128 + da_gas, estimation);
+    }
+
     #[tokio::test]
     async fn test_binary_search_verification_gas() {
         let (mut entry, mut provider) = create_base_config();
@@ -820,52 +1158,312 @@ mod tests {
         let estimation = estimator
             .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
             .await
-            .unwrap();
+            .unwrap()
+            .after_clamp;
 
-        // the estimation should be the same as the gas usage plus the buffer
-        let expected = gas_usage + ChainSpec::default().deposit_transfer_overhead();
+        // the estimation should be the rounded gas usage plus the buffer
+        let expected = gas_usage.next_multiple_of(create_settings().gas_rounding.into())
+            + ChainSpec::default().deposit_transfer_overhead();
         let expected_with_buffer =
-            math::increase_by_percent(expected, VERIFICATION_GAS_BUFFER_PERCENT);
+            math::increase_by_percent(expected, TEST_VERIFICATION_GAS_BUFFER_PERCENT);
 
         assert_eq!(expected_with_buffer, estimation);
     }
 
     #[tokio::test]
-    async fn test_binary_search_verification_gas_should_not_overflow() {
-        let (mut entry, mut provider) = create_base_config();
+    async fn test_verification_gas_min_floor() {
+        let gas_usage = 10_000;
+        let min_verification_gas_limit = 100_000;
 
         let _m = MTX.lock();
         let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
-        ctx.expect().returning(|_a| {
-            Ok(Ok(ExecutionResult {
-                pre_op_gas: 10000,
-                paid: U256::from(100000),
-                valid_after: 100000000000.into(),
-                valid_until: 100000000001.into(),
-                target_success: true,
-                target_result: Bytes::new(),
-            }))
-        });
-        entry
-            .expect_simulate_handle_op()
-            .returning(|_a, _b, _c, _d, _e| {
-                Ok(Ok(ExecutionResult {
-                    target_result: EstimateCallGasResult {
-                        gasEstimate: U256::from(10000),
-                        numRounds: U256::from(10),
-                    }
-                    .abi_encode()
-                    .into(),
-                    target_success: true,
-                    ..Default::default()
-                }))
-            });
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
 
-        // this gas used number is larger than a u128 max number so we need to
-        // check for this overflow
-        provider.expect_get_gas_used().returning(move |_a| {
-            Ok(GasUsedResult {
-                gasUsed: uint!(1000000000000000000000000000000000000000_U256),
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+
+        let (estimator, _) = create_counting_estimator(EstimationStrategy::BinarySearch, gas_usage);
+        let estimator = GasEstimator {
+            settings: Settings {
+                min_verification_gas_limit,
+                ..estimator.settings
+            },
+            ..estimator
+        };
+        let stages = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+
+        // the floor is applied after the buffer
+        assert!(stages.after_buffer < min_verification_gas_limit);
+        assert_eq!(stages.after_clamp, min_verification_gas_limit);
+
+        // the floor is capped at the max verification gas
+        let (estimator, _) = create_counting_estimator(EstimationStrategy::BinarySearch, gas_usage);
+        let max_verification_gas = estimator.settings.max_verification_gas;
+        let estimator = GasEstimator {
+            settings: Settings {
+                min_verification_gas_limit: max_verification_gas + 1,
+                ..estimator.settings
+            },
+            ..estimator
+        };
+        let stages = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+        assert_eq!(stages.after_clamp, max_verification_gas);
+    }
+
+    // Returns an estimator that counts the provider calls made during verification gas estimation
+    fn create_counting_estimator(
+        strategy: EstimationStrategy,
+        gas_usage: u128,
+    ) -> (GasEstimatorWithMocks, Arc<AtomicUsize>) {
+        create_counting_estimator_with_settings(
+            Settings {
+                estimation_strategy: strategy,
+                ..create_settings()
+            },
+            gas_usage,
+        )
+    }
+
+    fn create_counting_estimator_with_settings(
+        settings: Settings,
+        gas_usage: u128,
+    ) -> (GasEstimatorWithMocks, Arc<AtomicUsize>) {
+        let (mut entry, mut provider) = create_base_config();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let sim_calls = Arc::clone(&calls);
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |op, _b, _c, _d, _e| {
+                sim_calls.fetch_add(1, Ordering::Relaxed);
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+                Ok(Ok(ExecutionResult::default()))
+            });
+
+        let gas_used_calls = Arc::clone(&calls);
+        provider.expect_get_gas_used().returning(move |_a| {
+            gas_used_calls.fetch_add(1, Ordering::Relaxed);
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage * 2),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+        (estimator, calls)
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_rounding() {
+        let gas_usage = 10_000;
+        let overhead = ChainSpec::default().deposit_transfer_overhead();
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+
+        let (estimator, _) = create_counting_estimator_with_settings(
+            Settings {
+                gas_rounding: 4096,
+                ..create_settings()
+            },
+            gas_usage,
+        );
+        let coarse = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap()
+            .raw;
+
+        let (estimator, _) = create_counting_estimator_with_settings(
+            Settings {
+                gas_rounding: 1,
+                ..create_settings()
+            },
+            gas_usage,
+        );
+        let fine = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap()
+            .raw;
+
+        assert_eq!(coarse, 12_288 + overhead);
+        assert!(fine >= gas_usage + overhead);
+        assert!(fine < coarse);
+    }
+
+    #[tokio::test]
+    async fn test_single_shot_verification_gas_fewer_calls() {
+        let gas_usage = 10_000;
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+
+        let (estimator, binary_search_calls) =
+            create_counting_estimator(EstimationStrategy::BinarySearch, gas_usage);
+        let binary_search_stages = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+        let binary_search_estimate = binary_search_stages.after_clamp;
+
+        let (estimator, single_shot_calls) =
+            create_counting_estimator(EstimationStrategy::SingleShot, gas_usage);
+        let single_shot_stages = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+        let single_shot_estimate = single_shot_stages.after_clamp;
+
+        let binary_search_calls = binary_search_calls.load(Ordering::Relaxed);
+        let single_shot_calls = single_shot_calls.load(Ordering::Relaxed);
+        assert_eq!(single_shot_calls, 1);
+        assert!(single_shot_calls < binary_search_calls);
+
+        // every call after the initial simulation is a binary search round
+        assert_eq!(
+            binary_search_stages.num_rounds,
+            Some(binary_search_calls as u32 - 1)
+        );
+        assert_eq!(single_shot_stages.num_rounds, Some(0));
+
+        // the single shot estimate uses the gas used by the initial simulation
+        let expected = gas_usage * 2 + ChainSpec::default().deposit_transfer_overhead();
+        assert_eq!(
+            single_shot_estimate,
+            math::increase_by_percent(expected, TEST_VERIFICATION_GAS_BUFFER_PERCENT)
+        );
+        assert!(single_shot_estimate >= binary_search_estimate);
+    }
+
+    #[tokio::test]
+    async fn test_cached_verification_gas_reuses_estimate() {
+        let gas_usage = 10_000;
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+
+        let (estimator, calls) = create_counting_estimator(EstimationStrategy::Cached, gas_usage);
+        let first_estimate = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap()
+            .after_clamp;
+        let first_calls = calls.swap(0, Ordering::Relaxed);
+
+        let second_estimate = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap()
+            .after_clamp;
+        let second_calls = calls.load(Ordering::Relaxed);
+
+        assert_eq!(first_estimate, second_estimate);
+        // initial simulation plus a single simulation at the cached limit
+        assert_eq!(second_calls, 2);
+        assert!(second_calls < first_calls);
+    }
+
+    #[tokio::test]
+    async fn test_cached_verification_gas_keyed_by_call_data() {
+        let gas_usage = 10_000;
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+
+        let (estimator, calls) = create_counting_estimator(EstimationStrategy::Cached, gas_usage);
+        estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+        let first_calls = calls.swap(0, Ordering::Relaxed);
+
+        // same entities, different call data, the cached estimate isn't reused
+        let user_op = UserOperation {
+            call_data: Bytes::from_static(&[1, 2, 3]),
+            ..user_op
+        };
+        estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
+        let second_calls = calls.load(Ordering::Relaxed);
+
+        assert_eq!(second_calls, first_calls);
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_should_not_overflow() {
+        let (mut entry, mut provider) = create_base_config();
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect().returning(|_a| {
+            Ok(Ok(ExecutionResult {
+                pre_op_gas: 10000,
+                paid: U256::from(100000),
+                valid_after: 100000000000.into(),
+                valid_until: 100000000001.into(),
+                target_success: true,
+                target_result: Bytes::new(),
+            }))
+        });
+        entry
+            .expect_simulate_handle_op()
+            .returning(|_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(10000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        // this gas used number is larger than a u128 max number so we need to
+        // check for this overflow
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: uint!(1000000000000000000000000000000000000000_U256),
                 success: false,
                 result: Bytes::new(),
             })
@@ -1069,6 +1667,34 @@ mod tests {
         assert!(estimation.is_err());
     }
 
+    #[tokio::test]
+    async fn test_verification_gas_unexpected_simulation_success() {
+        let (mut entry, mut provider) = create_base_config();
+
+        entry.expect_simulate_handle_op().never();
+        provider.expect_get_gas_used().returning(|_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(10_000),
+                success: true,
+                result: Bytes::new(),
+            })
+        });
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .estimate_verification_gas(&optional_op, &user_op, B256::ZERO, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::UnexpectedSimulationSuccess(address) if address == Address::ZERO
+        ));
+    }
+
     #[tokio::test]
     async fn test_estimate_call_gas() {
         let (mut entry, mut provider) = create_base_config();
@@ -1099,7 +1725,8 @@ mod tests {
         let estimation = estimator
             .estimate_call_gas(&optional_op, user_op, B256::ZERO, StateOverride::default())
             .await
-            .unwrap();
+            .unwrap()
+            .after_clamp;
 
         // result is derived from the spoofed gas_estimate field
         let expected = gas_estimate + CALL_GAS_BUFFER_VALUE;
@@ -1107,21 +1734,22 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_estimate_call_gas_error() {
+    async fn test_estimate_call_gas_stages_buffer_and_clamp() {
         let (mut entry, mut provider) = create_base_config();
 
-        // return an invalid response for the ExecutionResult
-        // for a successful gas estimation
+        // close enough to the max that the buffer pushes the estimate over the limit
+        let gas_estimate = TEST_MAX_GAS_LIMITS - CALL_GAS_BUFFER_VALUE / 2;
         entry
             .expect_simulate_handle_op()
-            .returning(|_a, _b, _c, _d, _e| {
+            .returning(move |_a, _b, _c, _d, _e| {
                 Ok(Ok(ExecutionResult {
-                    target_result: EstimateCallGasRevertAtMax {
-                        revertData: Bytes::new(),
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(gas_estimate),
+                        numRounds: U256::from(10),
                     }
                     .abi_encode()
                     .into(),
-                    target_success: false,
+                    target_success: true,
                     ..Default::default()
                 }))
             });
@@ -1131,46 +1759,75 @@ mod tests {
             .returning(|_a, _b| Ok(Bytes::new()));
 
         let (estimator, _) = create_estimator(entry, provider);
+        let optional_op = demo_user_op_optional_gas(None);
         let user_op = demo_user_op();
-        let estimation = estimator
-            .call_gas_estimator
-            .estimate_call_gas(user_op, B256::ZERO, StateOverride::default())
+        let stages = estimator
+            .estimate_call_gas(&optional_op, user_op, B256::ZERO, StateOverride::default())
             .await
-            .err()
             .unwrap();
 
-        assert!(matches!(
-            estimation,
-            GasEstimationError::RevertInCallWithBytes(_)
-        ));
+        assert_eq!(
+            stages,
+            GasEstimateStages {
+                raw: gas_estimate,
+                after_buffer: gas_estimate + CALL_GAS_BUFFER_VALUE,
+                after_clamp: TEST_MAX_GAS_LIMITS,
+                num_rounds: Some(10),
+            }
+        );
     }
 
     #[tokio::test]
-    async fn test_estimate_call_gas_continuation() {
-        let (mut entry, mut provider) = create_base_config();
-
+    async fn test_estimate_call_gas_stages_provided() {
+        let (mut entry, provider) = create_base_config();
         entry
             .expect_simulate_handle_op()
-            .returning(|_a, _b, _c, _d, _e| {
+            .returning(move |_a, _b, _c, _d, _e| {
                 Ok(Ok(ExecutionResult {
-                    target_result: EstimateCallGasContinuation {
-                        minGas: U256::from(100),
-                        maxGas: U256::from(100000),
-                        numRounds: U256::from(10),
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
                     }
                     .abi_encode()
                     .into(),
-                    target_success: false,
+                    target_success: true,
                     ..Default::default()
                 }))
-            })
-            .times(1);
+            });
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let mut optional_op = demo_user_op_optional_gas(None);
+        optional_op.call_gas_limit = Some(50_000);
+        let stages = estimator
+            .estimate_call_gas(
+                &optional_op,
+                demo_user_op(),
+                B256::ZERO,
+                StateOverride::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(stages, GasEstimateStages::unadjusted(50_000));
+    }
+
+    async fn estimate_call_gas_with_rounding(gas_used: u128, gas_rounding: u64) -> u128 {
+        let (mut entry, mut provider) = create_base_config();
+
+        // the proxy rounds the true gas used up to the next multiple of the requested rounding
         entry
             .expect_simulate_handle_op()
-            .returning(|_a, _b, _c, _d, _e| {
+            .returning(move |_a, _b, c, _d, _e| {
+                let CallGasEstimationProxyCalls::estimateCallGas(call) =
+                    CallGasEstimationProxyCalls::abi_decode(&c, false).unwrap()
+                else {
+                    panic!("expected estimateCallGas call");
+                };
+                let rounding: u128 = call.args.rounding.try_into().unwrap();
                 Ok(Ok(ExecutionResult {
                     target_result: EstimateCallGasResult {
-                        gasEstimate: U256::from(200),
+                        gasEstimate: U256::from(gas_used.div_ceil(rounding) * rounding),
                         numRounds: U256::from(10),
                     }
                     .abi_encode()
@@ -1178,43 +1835,745 @@ mod tests {
                     target_success: true,
                     ..Default::default()
                 }))
-            })
-            .times(1);
+            });
 
         provider
             .expect_get_code()
             .returning(|_a, _b| Ok(Bytes::new()));
 
-        let (estimator, _) = create_estimator(entry, provider);
-        let user_op = demo_user_op();
-        let estimation = estimator
-            .call_gas_estimator
-            .estimate_call_gas(user_op, B256::ZERO, StateOverride::default())
-            .await
-            .unwrap();
+        let mut settings = create_settings();
+        settings.gas_rounding = gas_rounding;
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+        estimator
+            .estimate_call_gas(
+                &demo_user_op_optional_gas(None),
+                demo_user_op(),
+                B256::ZERO,
+                StateOverride::default(),
+            )
+            .await
+            .unwrap()
+            .after_clamp
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas_rounding() {
+        let gas_used = 100_001;
+
+        let coarse = estimate_call_gas_with_rounding(gas_used, 4096).await;
+        let fine = estimate_call_gas_with_rounding(gas_used, 1).await;
+
+        assert_eq!(coarse, 102_400 + CALL_GAS_BUFFER_VALUE);
+        assert_eq!(fine, gas_used + CALL_GAS_BUFFER_VALUE);
+        assert!(fine < coarse);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_estimation_invalid_gas_rounding() {
+        let (entry, provider) = create_base_config();
+        let mut settings = create_settings();
+        settings.gas_rounding = 3;
+
+        create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas_error() {
+        let (mut entry, mut provider) = create_base_config();
+
+        // return an invalid response for the ExecutionResult
+        // for a successful gas estimation
+        entry
+            .expect_simulate_handle_op()
+            .returning(|_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasRevertAtMax {
+                        revertData: bytes!("deadbeef"),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: false,
+                    ..Default::default()
+                }))
+            });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .call_gas_estimator
+            .estimate_call_gas(user_op, B256::ZERO, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::RevertInCallWithBytes(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas_out_of_gas_at_max() {
+        let (mut entry, mut provider) = create_base_config();
+
+        // an out of gas revert at the max call gas has empty revert data
+        entry
+            .expect_simulate_handle_op()
+            .returning(|_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasRevertAtMax {
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: false,
+                    ..Default::default()
+                }))
+            });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .call_gas_estimator
+            .estimate_call_gas(user_op, B256::ZERO, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::CallGasLimitTooLow(TEST_MAX_GAS_LIMITS)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas_continuation() {
+        let (mut entry, mut provider) = create_base_config();
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(|_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasContinuation {
+                        minGas: U256::from(100),
+                        maxGas: U256::from(100000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: false,
+                    ..Default::default()
+                }))
+            })
+            .times(1);
+        entry
+            .expect_simulate_handle_op()
+            .returning(|_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(200),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            })
+            .times(1);
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .call_gas_estimator
+            .estimate_call_gas(user_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap();
 
         // on the second loop of the estimate gas continuation
         // I update the spoofed value to 200
 
-        assert_eq!(estimation, 200);
+        assert_eq!(estimation.gas, 200);
+        // rounds from both the continuation and the result are counted
+        assert_eq!(estimation.num_rounds, 20);
+    }
+
+    #[tokio::test]
+    async fn test_estimation_optional_gas_used() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000;
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |op, _b, _c, _d, _e| {
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(10000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect().returning(|_a| {
+            Ok(Ok(ExecutionResult {
+                pre_op_gas: 10000,
+                paid: U256::from(100000),
+                valid_after: 100000000000.into(),
+                valid_until: 100000000001.into(),
+                target_success: true,
+                target_result: Bytes::new(),
+            }))
+        });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        provider
+            .expect_get_pending_base_fee()
+            .returning(|| Ok(TEST_FEE));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(TEST_FEE));
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .unwrap();
+
+        // this should be a pass through
+        assert_eq!(estimation.pre_verification_gas, 10000);
+
+        // rounded gas used increased by 10%
+        let expected = gas_usage.next_multiple_of(create_settings().gas_rounding.into())
+            + ChainSpec::default().deposit_transfer_overhead as u128;
+        assert_eq!(
+            estimation.verification_gas_limit,
+            cmp::max(
+                math::increase_by_percent(expected, 10),
+                expected + REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER
+            )
+        );
+
+        // input gas limit clamped with the set limit in settings and constant MIN
+        assert_eq!(estimation.call_gas_limit, 10000 + CALL_GAS_BUFFER_VALUE);
+    }
+
+    #[tokio::test]
+    async fn test_estimation_pins_da_gas_block() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000;
+        let pinned_block_hash = B256::repeat_byte(0xab);
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |op, _b, _c, _d, _e| {
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(10000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+        // the DA gas must be calculated at the block the rest of the estimation is pinned to
+        entry
+            .expect_calc_da_gas()
+            .withf(move |_op, block, _gas_price| {
+                *block == BlockHashOrNumber::Hash(pinned_block_hash)
+            })
+            .returning(|_a, _b, _c| Ok((TEST_FEE, Default::default(), Default::default())));
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect().returning(|_a| {
+            Ok(Ok(ExecutionResult {
+                pre_op_gas: 10000,
+                paid: U256::from(100000),
+                valid_after: 100000000000.into(),
+                valid_until: 100000000001.into(),
+                target_success: true,
+                target_result: Bytes::new(),
+            }))
+        });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((pinned_block_hash, 0)));
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator.expect_required_bundle_fees().returning(|_| {
+            Ok((
+                GasFees {
+                    max_fee_per_gas: TEST_FEE,
+                    max_priority_fee_per_gas: TEST_FEE,
+                },
+                TEST_FEE,
+            ))
+        });
+
+        let cs = ChainSpec {
+            id: 42161,
+            da_pre_verification_gas: true,
+            da_gas_oracle_type: DAGasOracleType::ArbitrumNitro,
+            ..Default::default()
+        };
+        let estimator =
+            create_custom_estimator(cs, provider, fee_estimator, entry, create_settings());
+
+        let estimation = estimator
+            .estimate_op_gas(demo_user_op_optional_gas(None), StateOverride::default())
+            .await
+            .unwrap();
+
+        assert!(estimation.pre_verification_gas > TEST_FEE);
+    }
+
+    #[tokio::test]
+    async fn test_estimation_with_block_skips_latest_block() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000;
+        let block_hash = B256::repeat_byte(0xcd);
+
+        entry
+            .expect_simulate_handle_op()
+            .withf(move |_op, _b, _c, block_id, _e| *block_id == BlockId::from(block_hash))
+            .returning(move |op, _b, _c, _d, _e| {
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(10000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect().returning(|_a| {
+            Ok(Ok(ExecutionResult {
+                pre_op_gas: 10000,
+                paid: U256::from(100000),
+                valid_after: 100000000000.into(),
+                valid_until: 100000000001.into(),
+                target_success: true,
+                target_result: Bytes::new(),
+            }))
+        });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider.expect_get_latest_block_hash_and_number().never();
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let estimation = estimator
+            .estimate_op_gas_with_block(
+                demo_user_op_optional_gas(Some(10000)),
+                StateOverride::default(),
+                (block_hash, 100),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(estimation.pre_verification_gas, 10000);
+        assert_eq!(estimation.call_gas_limit, 10000 + CALL_GAS_BUFFER_VALUE);
+    }
+
+    #[cfg(feature = "revm")]
+    #[tokio::test]
+    async fn test_local_evm_estimate_matches_rpc() {
+        use alloy_provider::{Provider, ProviderBuilder};
+        use rundler_contracts::v0_6::ENTRY_POINT_V0_6_DEPLOYED_BYTECODE;
+        use rundler_provider::{
+            AlloyEntryPointV0_6, AlloyEvmProvider, LocalEvmEntryPoint, LocalEvmProvider,
+        };
+
+        let alloy_provider = ProviderBuilder::new().on_anvil();
+        let chain_spec = ChainSpec {
+            id: 31337,
+            ..Default::default()
+        };
+        // the account returns a zero validation result (PUSH1 0x20 PUSH1 0 RETURN) from
+        // every call, accepting the operation
+        let sender = Address::random();
+        for (address, code) in [
+            (
+                chain_spec.entry_point_address_v0_6,
+                ENTRY_POINT_V0_6_DEPLOYED_BYTECODE.clone(),
+            ),
+            (sender, Bytes::from_static(&hex!("60206000f3"))),
+        ] {
+            alloy_provider
+                .raw_request::<_, ()>("anvil_setCode".into(), (address, code))
+                .await
+                .unwrap();
+        }
+
+        let settings = Settings {
+            max_verification_gas: 5_000_000,
+            max_call_gas: 10_000_000,
+            max_paymaster_verification_gas: 5_000_000,
+            max_paymaster_post_op_gas: 5_000_000,
+            max_total_execution_gas: 20_000_000,
+            max_simulate_handle_ops_gas: 20_000_000,
+            ..create_settings()
+        };
+        let (da_gas_oracle, _) =
+            rundler_provider::new_alloy_da_gas_oracle(&chain_spec, alloy_provider.clone());
+        let entry_point = AlloyEntryPointV0_6::new(
+            chain_spec.clone(),
+            settings.max_verification_gas.try_into().unwrap(),
+            settings.max_simulate_handle_ops_gas,
+            settings.max_simulate_handle_ops_gas,
+            alloy_provider.clone(),
+            da_gas_oracle,
+        );
+        let rpc_provider = AlloyEvmProvider::new(alloy_provider);
+        let local_provider = LocalEvmProvider::new(rpc_provider.clone(), &chain_spec);
+
+        let rpc_estimator = GasEstimator::new(
+            chain_spec.clone(),
+            rpc_provider,
+            entry_point.clone(),
+            settings,
+            MockFeeEstimator::new(),
+        );
+        let local_estimator = GasEstimator::new(
+            chain_spec.clone(),
+            local_provider.clone(),
+            LocalEvmEntryPoint::new(
+                entry_point,
+                local_provider,
+                chain_spec,
+                settings.max_simulate_handle_ops_gas,
+            ),
+            settings,
+            MockFeeEstimator::new(),
+        );
+
+        let op = UserOperationOptionalGas {
+            sender,
+            call_data: Bytes::from(vec![1; 32]),
+            ..demo_user_op_optional_gas(Some(50_000))
+        };
+        let rpc_estimate = rpc_estimator
+            .estimate_op_gas(op.clone(), StateOverride::default())
+            .await
+            .unwrap();
+        let local_estimate = local_estimator
+            .estimate_op_gas(op, StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            local_estimate.verification_gas_limit,
+            rpc_estimate.verification_gas_limit
+        );
+        assert_eq!(local_estimate.call_gas_limit, rpc_estimate.call_gas_limit);
+        assert_eq!(local_estimate.block_number, rpc_estimate.block_number);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_estimation_optional_gas_invalid_settings() {
+        let (entry, provider) = create_base_config();
+
+        //max_call_gas is less than MIN_CALL_GAS_LIMIT
+
+        let settings = Settings {
+            max_verification_gas: 10,
+            max_call_gas: 10,
+            max_paymaster_post_op_gas: 10,
+            max_paymaster_verification_gas: 10,
+            max_total_execution_gas: 10,
+            max_simulate_handle_ops_gas: 10,
+            ..create_settings()
+        };
+
+        create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vgl_over_max() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.verification_gas_limit = Some(TEST_MAX_GAS_LIMITS + 1);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::GasFieldTooLarge("verificationGasLimit", TEST_MAX_GAS_LIMITS)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_signature_rejected() {
+        // no provider expectations, the op is rejected before estimation starts
+        let (entry, provider) = create_base_config();
+        let mut settings = create_settings();
+        settings.empty_signature_handling = EmptySignatureHandling::Reject;
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+
+        let estimation = estimator
+            .estimate_op_gas(demo_user_op_optional_gas(None), StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(estimation, GasEstimationError::EmptySignature));
+    }
+
+    #[test]
+    fn test_dummy_signature_checked_against_account_type() {
+        let op = UserOperationOptionalGas {
+            signature: Bytes::from(vec![1; 65]),
+            ..demo_user_op_optional_gas(None)
+        };
+
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+        assert_eq!(estimator.check_provided_limits(&op).unwrap(), vec![]);
+
+        // an ECDSA length signature is too short for a passkey account
+        let (entry, provider) = create_base_config();
+        let mut settings = create_settings();
+        settings.dummy_signature_account_type = AccountType::Passkey;
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+        assert_eq!(
+            estimator.check_provided_limits(&op).unwrap(),
+            vec![Warning::ShortDummySignature {
+                account_type: AccountType::Passkey,
+                actual: 65,
+                expected: 101,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pvg_over_cap_skips_simulation() {
+        // no simulate_handle_op expectation, so any simulation would panic
+        let (entry, mut provider) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+
+        let mut settings = create_settings();
+        settings.max_pre_verification_gas = Some(100_000);
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+
+        let mut optional_op = demo_user_op_optional_gas(None);
+        optional_op.call_data = vec![0xff; 10_000].into();
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::PreVerificationGasTooHigh(pvg, 100_000) if pvg > 100_000
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cgl_over_max() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(TEST_MAX_GAS_LIMITS + 1);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            estimation,
+            GasEstimationError::GasFieldTooLarge("callGasLimit", TEST_MAX_GAS_LIMITS)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_return_provided_limits() {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op.clone(), StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            estimation.pre_verification_gas,
+            optional_op.pre_verification_gas.unwrap()
+        );
+        assert_eq!(
+            estimation.verification_gas_limit,
+            optional_op.verification_gas_limit.unwrap()
+        );
+        assert_eq!(
+            estimation.call_gas_limit,
+            optional_op.call_gas_limit.unwrap()
+        );
     }
 
     #[tokio::test]
-    async fn test_estimation_optional_gas_used() {
+    async fn test_estimate_returns_block_number() {
         let (mut entry, mut provider) = create_base_config();
-        let gas_usage = 10_000;
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::repeat_byte(1), 1234)));
 
         entry
             .expect_simulate_handle_op()
-            .returning(move |op, _b, _c, _d, _e| {
-                if op.total_verification_gas_limit() < gas_usage {
-                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
-                }
-
+            .returning(move |_a, _b, _c, _d, _e| {
                 Ok(Ok(ExecutionResult {
-                    target_result: EstimateCallGasResult {
-                        gasEstimate: U256::from(10000),
-                        numRounds: U256::from(10),
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
                     }
                     .abi_encode()
                     .into(),
@@ -1223,148 +2582,251 @@ mod tests {
                 }))
             });
 
-        let _m = MTX.lock();
-        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
-        ctx.expect().returning(|_a| {
-            Ok(Ok(ExecutionResult {
-                pre_op_gas: 10000,
-                paid: U256::from(100000),
-                valid_after: 100000000000.into(),
-                valid_until: 100000000001.into(),
-                target_success: true,
-                target_result: Bytes::new(),
-            }))
-        });
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(estimation.block_number, Some(1234));
+    }
+
+    // Returns an estimator at the given latest block that counts the `get_gas_used` calls,
+    // which are only made when searching for the verification gas limit
+    fn create_validation_reuse_estimator(
+        latest_block_number: u64,
+    ) -> (GasEstimatorWithMocks, Arc<AtomicUsize>) {
+        let (mut entry, mut provider) = create_base_config();
 
-        provider
-            .expect_get_code()
-            .returning(|_a, _b| Ok(Bytes::new()));
         provider
             .expect_get_latest_block_hash_and_number()
-            .returning(|| Ok((B256::ZERO, 0)));
+            .times(1)
+            .returning(move || Ok((B256::ZERO, latest_block_number)));
+
+        let searches = Arc::new(AtomicUsize::new(0));
+        let gas_used_calls = Arc::clone(&searches);
         provider.expect_get_gas_used().returning(move |_a| {
+            gas_used_calls.fetch_add(1, Ordering::Relaxed);
             Ok(GasUsedResult {
-                gasUsed: U256::from(gas_usage),
+                gasUsed: U256::from(20_000),
                 success: false,
                 result: Bytes::new(),
             })
         });
 
-        provider
-            .expect_get_pending_base_fee()
-            .returning(|| Ok(TEST_FEE));
-        provider
-            .expect_get_max_priority_fee()
-            .returning(|| Ok(TEST_FEE));
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
 
         let (estimator, _) = create_estimator(entry, provider);
+        (estimator, searches)
+    }
 
-        let optional_op = demo_user_op_optional_gas(Some(10000));
+    #[tokio::test]
+    async fn test_estimate_with_recent_validation_skips_search() {
+        let (estimator, searches) = create_validation_reuse_estimator(100);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, StateOverride::default())
+            .estimate_op_gas_with_validation(
+                optional_op,
+                StateOverride::default(),
+                RecentValidation {
+                    verification_gas_used: 30_000,
+                    block_number: 99,
+                },
+            )
             .await
             .unwrap();
 
-        // this should be a pass through
-        assert_eq!(estimation.pre_verification_gas, 10000);
-
-        // gas used increased by 10%
-        let expected = gas_usage + ChainSpec::default().deposit_transfer_overhead as u128;
+        assert_eq!(searches.load(Ordering::Relaxed), 0);
+        assert_eq!(estimation.verification_gas_rounds, None);
         assert_eq!(
             estimation.verification_gas_limit,
             cmp::max(
-                math::increase_by_percent(expected, 10),
-                expected + REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER
+                math::increase_by_percent(30_000, TEST_VERIFICATION_GAS_BUFFER_PERCENT),
+                30_000 + REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER
             )
         );
+    }
 
-        // input gas limit clamped with the set limit in settings and constant MIN
-        assert_eq!(estimation.call_gas_limit, 10000 + CALL_GAS_BUFFER_VALUE);
+    #[tokio::test]
+    async fn test_estimate_with_stale_validation_searches() {
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let (estimator, searches) = create_validation_reuse_estimator(100);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
+
+        let estimation = estimator
+            .estimate_op_gas_with_validation(
+                optional_op,
+                StateOverride::default(),
+                RecentValidation {
+                    verification_gas_used: 30_000,
+                    block_number: 98,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(searches.load(Ordering::Relaxed) > 0);
+        assert!(estimation.verification_gas_rounds.is_some());
     }
 
-    #[test]
-    #[should_panic]
-    fn test_estimation_optional_gas_invalid_settings() {
-        let (entry, provider) = create_base_config();
+    // Returns an estimator whose provider reports a new latest block on each call, and
+    // where simulations against the first block fail as if its state had been pruned.
+    fn create_pruned_state_estimator(
+        pruned_state_handling: PrunedStateHandling,
+    ) -> GasEstimatorWithMocks {
+        let (mut entry, mut provider) = create_base_config();
 
-        //max_call_gas is less than MIN_CALL_GAS_LIMIT
+        let latest_calls = AtomicUsize::new(0);
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || {
+                let n = latest_calls.fetch_add(1, Ordering::Relaxed) as u8 + 1;
+                Ok((B256::repeat_byte(n), n.into()))
+            });
 
-        let settings = Settings {
-            max_verification_gas: 10,
-            max_call_gas: 10,
-            max_paymaster_post_op_gas: 10,
-            max_paymaster_verification_gas: 10,
-            max_total_execution_gas: 10,
-            max_simulate_handle_ops_gas: 10,
-            verification_estimation_gas_fee: 1_000_000_000_000,
-        };
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, block_id, _e| {
+                if block_id == BlockId::from(B256::repeat_byte(1)) {
+                    return Err(anyhow!("missing trie node 0x01 (path )").into());
+                }
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
 
+        let mut settings = create_settings();
+        settings.pruned_state_handling = pruned_state_handling;
         create_custom_estimator(
             ChainSpec::default(),
             provider,
             MockFeeEstimator::new(),
             entry,
             settings,
-        );
+        )
     }
 
-    #[tokio::test]
-    async fn test_vgl_over_max() {
-        let (entry, provider) = create_base_config();
-        let (estimator, _) = create_estimator(entry, provider);
-
+    fn demo_user_op_optional_gas_with_limits() -> UserOperationOptionalGas {
         let mut optional_op = demo_user_op_optional_gas(Some(10000));
-        optional_op.verification_gas_limit = Some(TEST_MAX_GAS_LIMITS + 1);
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+        optional_op
+    }
 
-        let estimation = estimator
-            .estimate_op_gas(optional_op, StateOverride::default())
+    #[tokio::test]
+    async fn test_pruned_state_error() {
+        let estimator = create_pruned_state_estimator(PrunedStateHandling::Error);
+
+        let err = estimator
+            .estimate_op_gas(
+                demo_user_op_optional_gas_with_limits(),
+                StateOverride::default(),
+            )
             .await
             .err()
             .unwrap();
 
         assert!(matches!(
-            estimation,
-            GasEstimationError::GasFieldTooLarge("verificationGasLimit", TEST_MAX_GAS_LIMITS)
+            err,
+            GasEstimationError::PrunedState(hash) if hash == B256::repeat_byte(1)
         ));
     }
 
     #[tokio::test]
-    async fn test_cgl_over_max() {
-        let (entry, provider) = create_base_config();
-        let (estimator, _) = create_estimator(entry, provider);
-
-        let mut optional_op = demo_user_op_optional_gas(Some(10000));
-        optional_op.call_gas_limit = Some(TEST_MAX_GAS_LIMITS + 1);
+    async fn test_pruned_state_retry_latest() {
+        let estimator = create_pruned_state_estimator(PrunedStateHandling::RetryLatest);
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, StateOverride::default())
+            .estimate_op_gas(
+                demo_user_op_optional_gas_with_limits(),
+                StateOverride::default(),
+            )
             .await
-            .err()
             .unwrap();
 
-        assert!(matches!(
-            estimation,
-            GasEstimationError::GasFieldTooLarge("callGasLimit", TEST_MAX_GAS_LIMITS)
-        ));
+        // estimated against the block fetched on retry
+        assert_eq!(estimation.block_number, Some(2));
+        assert_eq!(estimation.call_gas_limit, 10000);
     }
 
     #[tokio::test]
-    async fn test_return_provided_limits() {
+    async fn test_sequential_estimation_phases_do_not_overlap() {
+        let gas_usage = 10_000;
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_6::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
         let (mut entry, mut provider) = create_base_config();
+        let phases = Arc::new(Mutex::new(vec![]));
 
         provider
             .expect_get_latest_block_hash_and_number()
             .returning(|| Ok((B256::ZERO, 0)));
+        let gas_used_phases = Arc::clone(&phases);
+        provider.expect_get_gas_used().returning(move |_a| {
+            gas_used_phases.lock().unwrap().push("verification");
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage * 2),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
 
+        // verification simulations have no target call data, call gas simulations do
+        let sim_phases = Arc::clone(&phases);
         entry
             .expect_simulate_handle_op()
-            .returning(move |_a, _b, _c, _d, _e| {
+            .returning(move |op, _b, call_data, _d, _e| {
+                if call_data.is_empty() {
+                    sim_phases.lock().unwrap().push("verification");
+                    if op.total_verification_gas_limit() < gas_usage {
+                        return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                    }
+                    return Ok(Ok(ExecutionResult::default()));
+                }
+                sim_phases.lock().unwrap().push("call");
                 Ok(Ok(ExecutionResult {
-                    target_result: TestCallGasResult {
-                        success: true,
-                        gasUsed: U256::ZERO,
-                        revertData: Bytes::new(),
+                    target_result: EstimateCallGasResult {
+                        gasEstimate: U256::from(gas_usage),
+                        numRounds: U256::from(10),
                     }
                     .abi_encode()
                     .into(),
@@ -1373,29 +2835,62 @@ mod tests {
                 }))
             });
 
-        let (estimator, _) = create_estimator(entry, provider);
-
-        let mut optional_op = demo_user_op_optional_gas(Some(10000));
-        optional_op.call_gas_limit = Some(10000);
-        optional_op.verification_gas_limit = Some(10000);
+        let mut settings = create_settings();
+        settings.sequential_estimation = true;
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
 
-        let estimation = estimator
-            .estimate_op_gas(optional_op.clone(), StateOverride::default())
+        estimator
+            .estimate_op_gas(
+                demo_user_op_optional_gas(Some(10000)),
+                StateOverride::default(),
+            )
             .await
             .unwrap();
 
-        assert_eq!(
-            estimation.pre_verification_gas,
-            optional_op.pre_verification_gas.unwrap()
-        );
-        assert_eq!(
-            estimation.verification_gas_limit,
-            optional_op.verification_gas_limit.unwrap()
-        );
-        assert_eq!(
-            estimation.call_gas_limit,
-            optional_op.call_gas_limit.unwrap()
+        // every verification call happens before the first call gas call
+        let phases = phases.lock().unwrap();
+        let first_call = phases.iter().position(|phase| *phase == "call").unwrap();
+        assert!(first_call > 0);
+        assert!(phases[first_call..].iter().all(|phase| *phase == "call"));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_estimation_skips_call_on_verification_error() {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider
+            .expect_get_gas_used()
+            .returning(|_a| Err(anyhow!("verification failed").into()));
+        entry.expect_simulate_handle_op().never();
+
+        let mut settings = create_settings();
+        settings.sequential_estimation = true;
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
         );
+
+        let err = estimator
+            .estimate_op_gas(
+                demo_user_op_optional_gas(Some(10000)),
+                StateOverride::default(),
+            )
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, GasEstimationError::Other(_)));
     }
 
     #[tokio::test]
@@ -1498,4 +2993,32 @@ mod tests {
         }
         assert_eq!(vec![PROXY_TARGET_OFFSET], offsets);
     }
+
+    #[test]
+    fn test_proxy_override_keeps_caller_state() {
+        let entry_point = Address::repeat_byte(1);
+        let slot = B256::repeat_byte(2);
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            entry_point,
+            AccountOverride {
+                state_diff: Some([(slot, B256::repeat_byte(3))].into_iter().collect()),
+                ..Default::default()
+            },
+        );
+
+        CallGasEstimatorSpecializationV06 {
+            chain_spec: ChainSpec::default(),
+        }
+        .add_proxy_to_overrides(entry_point, &mut state_override);
+
+        let account = &state_override[&entry_point];
+        assert!(account.code.is_some());
+        assert_eq!(
+            account.state_diff.as_ref().unwrap().get(&slot),
+            Some(&B256::repeat_byte(3))
+        );
+        // the moved entry point is added alongside the caller's override
+        assert_eq!(state_override.len(), 2);
+    }
 }
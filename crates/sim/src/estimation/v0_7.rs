@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{cmp, ops::Add};
+use std::{cmp, ops::Add, time::Duration};
 
 use alloy_primitives::{Address, Bytes, B256, U256};
 use alloy_sol_types::SolInterface;
@@ -29,12 +29,15 @@ use rundler_provider::{
 use rundler_types::{
     chain::ChainSpec,
     v0_7::{UserOperation, UserOperationBuilder, UserOperationOptionalGas},
-    GasEstimate, UserOperation as _,
+    GasEstimate, GasEstimateBreakdown, GasEstimateStages, UserOperation as _, Warning,
 };
 use rundler_utils::math;
 use tokio::join;
 
-use super::{estimate_verification_gas::GetOpWithLimitArgs, GasEstimationError, Settings};
+use super::{
+    estimate_verification_gas::GetOpWithLimitArgs, GasEstimateScenarios, GasEstimationError,
+    GasSearchResult, RecentValidation, Settings,
+};
 use crate::{
     gas, CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, FeeEstimator,
     VerificationGasEstimator, VerificationGasEstimatorImpl, MIN_CALL_GAS_LIMIT,
@@ -69,76 +72,105 @@ where
         op: UserOperationOptionalGas,
         state_override: StateOverride,
     ) -> Result<GasEstimate, GasEstimationError> {
-        self.check_provided_limits(&op)?;
+        let warnings = self.check_provided_limits(&op)?;
+
+        super::estimate_at_latest_block(
+            &self.provider,
+            self.settings.pruned_state_handling,
+            self.settings
+                .block_drift_repin_millis
+                .map(Duration::from_millis),
+            |block_hash, block_number| {
+                self.estimate_op_gas_at_block(
+                    op.clone(),
+                    state_override.clone(),
+                    block_hash,
+                    block_number,
+                )
+            },
+        )
+        .await
+        .map(|estimate| GasEstimate {
+            warnings,
+            ..estimate
+        })
+    }
 
-        let Self {
-            provider, settings, ..
-        } = self;
+    async fn estimate_op_gas_with_block(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        block: (B256, u64),
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let warnings = self.check_provided_limits(&op)?;
 
-        let (block_hash, _) = provider
-            .get_latest_block_hash_and_number()
+        let (block_hash, block_number) = block;
+        self.estimate_op_gas_at_block(op, state_override, block_hash, block_number)
             .await
-            .map_err(anyhow::Error::from)?;
-
-        let pre_verification_gas = self.estimate_pre_verification_gas(&op, block_hash).await?;
+            .map(|estimate| GasEstimate {
+                warnings,
+                ..estimate
+            })
+            .map_err(|error| super::pruned_state_at(error, block_hash))
+    }
 
-        let full_op = op
-            .clone()
-            .into_user_operation_builder(
-                &self.chain_spec,
-                settings.max_call_gas,
-                settings.max_verification_gas,
-                settings.max_paymaster_verification_gas,
-            )
-            .pre_verification_gas(pre_verification_gas)
-            .build();
+    async fn estimate_op_gas_scenarios(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        optimistic_buffer_percent: u32,
+        conservative_buffer_percent: u32,
+    ) -> Result<GasEstimateScenarios, GasEstimationError> {
+        let estimate = super::GasEstimator::estimate_op_gas(self, op, state_override).await?;
+        Ok(self.estimate_scenarios(
+            &estimate,
+            optimistic_buffer_percent,
+            conservative_buffer_percent,
+        ))
+    }
 
-        let verification_gas_future =
-            self.estimate_verification_gas(&op, &full_op, block_hash, state_override.clone());
-        let paymaster_verification_gas_future = self.estimate_paymaster_verification_gas(
-            &op,
-            &full_op,
-            block_hash,
-            state_override.clone(),
-        );
-        let call_gas_future =
-            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override);
+    async fn estimate_op_gas_with_validation(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        validation: RecentValidation,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let warnings = self.check_provided_limits(&op)?;
 
-        // Not try_join! because then the output is nondeterministic if multiple calls fail.
-        let timer = std::time::Instant::now();
-        let (verification_gas_limit, paymaster_verification_gas_limit, call_gas_limit) = join!(
-            verification_gas_future,
-            paymaster_verification_gas_future,
-            call_gas_future
+        let (_, verification_gas_limit) = self.buffer_verification_gas(
+            validation.verification_gas_used,
+            self.settings.verification_gas_buffer_percent,
         );
-        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
-
-        let verification_gas_limit = verification_gas_limit?;
-        let paymaster_verification_gas_limit = paymaster_verification_gas_limit?;
-        let call_gas_limit = call_gas_limit?;
-
-        // check the total gas limit
-        let mut op_with_gas = full_op;
-        op_with_gas.pre_verification_gas = pre_verification_gas;
-        op_with_gas.call_gas_limit = call_gas_limit;
-        op_with_gas.verification_gas_limit = verification_gas_limit;
-        op_with_gas.paymaster_verification_gas_limit = paymaster_verification_gas_limit;
-        // require that this can fit in a bundle of size 1
-        let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
-        if gas_limit > self.settings.max_total_execution_gas {
-            return Err(GasEstimationError::GasTotalTooLarge(
-                gas_limit,
-                self.settings.max_total_execution_gas,
-            ));
-        }
 
-        Ok(GasEstimate {
-            pre_verification_gas,
-            call_gas_limit,
-            verification_gas_limit,
-            paymaster_verification_gas_limit: op
-                .paymaster
-                .map(|_| paymaster_verification_gas_limit),
+        super::estimate_at_latest_block(
+            &self.provider,
+            self.settings.pruned_state_handling,
+            self.settings
+                .block_drift_repin_millis
+                .map(Duration::from_millis),
+            |block_hash, block_number| {
+                // The validation's gas used isn't split between the account and the paymaster,
+                // so operations with a paymaster always get a full estimate.
+                let op = if op.paymaster.is_none()
+                    && super::is_validation_recent(
+                        &validation,
+                        block_number,
+                        self.settings.validation_reuse_max_blocks,
+                    ) {
+                    UserOperationOptionalGas {
+                        verification_gas_limit: Some(verification_gas_limit),
+                        ..op.clone()
+                    }
+                } else {
+                    op.clone()
+                };
+                self.estimate_op_gas_at_block(op, state_override.clone(), block_hash, block_number)
+            },
+        )
+        .await
+        .map(|estimate| GasEstimate {
+            warnings,
+            ..estimate
         })
     }
 }
@@ -204,10 +236,148 @@ where
     CGE: CallGasEstimator<UO = UserOperation>,
     F: FeeEstimator,
 {
+    async fn estimate_op_gas_at_block(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: StateOverride,
+        block_hash: B256,
+        block_number: u64,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let Self { settings, .. } = self;
+
+        // The estimated pre-verification gas doesn't change the outcome of the verification
+        // and call gas simulations, so it is estimated alongside them.
+        let full_op = op
+            .clone()
+            .into_user_operation_builder(
+                &self.chain_spec,
+                settings.max_call_gas,
+                settings.max_verification_gas,
+                settings.max_paymaster_verification_gas,
+            )
+            .build();
+
+        // If capped, estimate pre-verification gas first so that ops with excessive
+        // pre-verification gas skip the expensive searches.
+        let capped_pre_verification_gas = match settings.max_pre_verification_gas {
+            Some(max) => {
+                let pre_verification_gas =
+                    self.estimate_pre_verification_gas(&op, block_hash).await?;
+                if pre_verification_gas > max {
+                    return Err(GasEstimationError::PreVerificationGasTooHigh(
+                        pre_verification_gas,
+                        max,
+                    ));
+                }
+                Some(pre_verification_gas)
+            }
+            None => None,
+        };
+
+        let pre_verification_gas_future = async {
+            match capped_pre_verification_gas {
+                Some(pre_verification_gas) => Ok(pre_verification_gas),
+                None => self.estimate_pre_verification_gas(&op, block_hash).await,
+            }
+        };
+        let verification_gas_future =
+            self.estimate_verification_gas(&op, &full_op, block_hash, state_override.clone());
+        let paymaster_verification_gas_future = self.estimate_paymaster_verification_gas(
+            &op,
+            &full_op,
+            block_hash,
+            state_override.clone(),
+        );
+        let call_gas_future =
+            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override);
+
+        let timer = std::time::Instant::now();
+        let (
+            pre_verification_gas,
+            verification_gas_limit,
+            paymaster_verification_gas_limit,
+            call_gas_limit,
+        ) = if settings.sequential_estimation {
+            // Run each phase only once the previous one has succeeded
+            let pre_verification_gas = pre_verification_gas_future.await?;
+            let verification_gas_limit = verification_gas_future.await?;
+            let paymaster_verification_gas_limit = paymaster_verification_gas_future.await?;
+            (
+                pre_verification_gas,
+                verification_gas_limit,
+                paymaster_verification_gas_limit,
+                call_gas_future.await?,
+            )
+        } else {
+            // Not try_join! because then the output is nondeterministic if multiple calls fail.
+            let (
+                pre_verification_gas,
+                verification_gas_limit,
+                paymaster_verification_gas_limit,
+                call_gas_limit,
+            ) = join!(
+                pre_verification_gas_future,
+                verification_gas_future,
+                paymaster_verification_gas_future,
+                call_gas_future
+            );
+            (
+                pre_verification_gas?,
+                verification_gas_limit?,
+                paymaster_verification_gas_limit?,
+                call_gas_limit?,
+            )
+        };
+        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+
+        let breakdown = GasEstimateBreakdown {
+            pre_verification_gas: GasEstimateStages::unadjusted(pre_verification_gas),
+            verification_gas_limit,
+            call_gas_limit,
+            paymaster_verification_gas_limit: op
+                .paymaster
+                .map(|_| paymaster_verification_gas_limit),
+        };
+        let verification_gas_limit = verification_gas_limit.after_clamp;
+        let paymaster_verification_gas_limit = paymaster_verification_gas_limit.after_clamp;
+        let call_gas_limit = call_gas_limit.after_clamp;
+
+        // check the total gas limit
+        let mut op_with_gas = full_op;
+        op_with_gas.pre_verification_gas = pre_verification_gas;
+        op_with_gas.call_gas_limit = call_gas_limit;
+        op_with_gas.verification_gas_limit = verification_gas_limit;
+        op_with_gas.paymaster_verification_gas_limit = paymaster_verification_gas_limit;
+        // require that this can fit in a bundle of size 1
+        let gas_limit = op_with_gas.execution_gas_limit(&self.chain_spec, Some(1));
+        if gas_limit > self.settings.max_total_execution_gas {
+            return Err(GasEstimationError::GasTotalTooLarge(
+                gas_limit,
+                self.settings.max_total_execution_gas,
+            ));
+        }
+
+        Ok(GasEstimate {
+            pre_verification_gas,
+            call_gas_limit,
+            verification_gas_limit,
+            paymaster_verification_gas_limit: op
+                .paymaster
+                .map(|_| paymaster_verification_gas_limit),
+            block_number: Some(block_number),
+            verification_gas_rounds: breakdown.verification_gas_limit.num_rounds,
+            call_gas_rounds: breakdown.call_gas_limit.num_rounds,
+            breakdown: Some(breakdown),
+            warnings: vec![],
+        })
+    }
+
+    /// Checks the gas limits and dummy signature provided by the user, returning any
+    /// warnings to attach to the estimate.
     fn check_provided_limits(
         &self,
         optional_op: &UserOperationOptionalGas,
-    ) -> Result<(), GasEstimationError> {
+    ) -> Result<Vec<Warning>, GasEstimationError> {
         if let Some(vl) = optional_op.verification_gas_limit {
             if vl > self.settings.max_verification_gas {
                 return Err(GasEstimationError::GasFieldTooLarge(
@@ -241,7 +411,12 @@ where
             }
         }
 
-        Ok(())
+        super::check_dummy_fields(
+            optional_op.validate_dummy_fields(self.settings.dummy_signature_account_type),
+            optional_op.signature.is_empty(),
+            optional_op.factory.is_some(),
+            self.settings.empty_signature_handling,
+        )
     }
 
     async fn estimate_verification_gas(
@@ -250,13 +425,13 @@ where
         full_op: &UserOperation,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasEstimateStages, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(vl) = optional_op.verification_gas_limit {
             if vl != 0 {
                 // No need to do an extra simulation here, if the user provides a value that is
                 // insufficient it will cause a revert during call gas estimation (or simulation).
-                return Ok(vl);
+                return Ok(GasEstimateStages::unadjusted(vl));
             }
         }
 
@@ -271,7 +446,10 @@ where
                 .build()
         };
 
-        let verification_gas_limit = self
+        let GasSearchResult {
+            gas: verification_gas_limit,
+            num_rounds,
+        } = self
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
@@ -282,13 +460,66 @@ where
             )
             .await?;
 
-        let verification_gas_limit = math::increase_by_percent(
+        let (after_buffer, after_clamp) = self.buffer_verification_gas(
             verification_gas_limit,
-            super::VERIFICATION_GAS_BUFFER_PERCENT,
+            self.settings.verification_gas_buffer_percent,
+        );
+
+        Ok(GasEstimateStages {
+            raw: verification_gas_limit,
+            after_buffer,
+            after_clamp,
+            num_rounds: Some(num_rounds),
+        })
+    }
+
+    /// Returns the verification gas limit after buffering and after clamping
+    fn buffer_verification_gas(
+        &self,
+        verification_gas_limit: u128,
+        buffer_percent: u32,
+    ) -> (u128, u128) {
+        let with_buffer = math::increase_by_percent(verification_gas_limit, buffer_percent);
+        (
+            with_buffer,
+            with_buffer
+                .max(self.settings.min_verification_gas_limit)
+                .min(self.settings.max_verification_gas),
         )
-        .min(self.settings.max_verification_gas);
+    }
+
+    /// Returns the paymaster verification gas limit after buffering and after clamping
+    fn buffer_paymaster_verification_gas(
+        &self,
+        paymaster_verification_gas_limit: u128,
+        buffer_percent: u32,
+    ) -> (u128, u128) {
+        let with_buffer =
+            math::increase_by_percent(paymaster_verification_gas_limit, buffer_percent);
+        (
+            with_buffer,
+            with_buffer.min(self.settings.max_verification_gas),
+        )
+    }
+
+    fn estimate_scenarios(
+        &self,
+        estimate: &GasEstimate,
+        optimistic_buffer_percent: u32,
+        conservative_buffer_percent: u32,
+    ) -> GasEstimateScenarios {
+        let rebuffer = |buffer_percent| {
+            super::rebuffer_estimate(
+                estimate,
+                |gas| self.buffer_verification_gas(gas, buffer_percent),
+                |gas| self.buffer_paymaster_verification_gas(gas, buffer_percent),
+            )
+        };
 
-        Ok(verification_gas_limit)
+        GasEstimateScenarios {
+            optimistic: rebuffer(optimistic_buffer_percent.min(conservative_buffer_percent)),
+            conservative: rebuffer(optimistic_buffer_percent.max(conservative_buffer_percent)),
+        }
     }
 
     async fn estimate_paymaster_verification_gas(
@@ -297,11 +528,11 @@ where
         full_op: &UserOperation,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasEstimateStages, GasEstimationError> {
         // If not using paymaster, return zero, else if set and non-zero, don't estimate and return value
         if let Some(pvl) = optional_op.verification_gas_limit {
             if pvl != 0 {
-                return Ok(pvl);
+                return Ok(GasEstimateStages::unadjusted(pvl));
             }
         }
 
@@ -316,7 +547,10 @@ where
                 .build()
         };
 
-        let paymaster_verification_gas_limit = self
+        let GasSearchResult {
+            gas: paymaster_verification_gas_limit,
+            num_rounds,
+        } = self
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
@@ -327,13 +561,17 @@ where
             )
             .await?;
 
-        let paymaster_verification_gas_limit = math::increase_by_percent(
+        let (after_buffer, after_clamp) = self.buffer_paymaster_verification_gas(
             paymaster_verification_gas_limit,
-            super::VERIFICATION_GAS_BUFFER_PERCENT,
-        )
-        .min(self.settings.max_verification_gas);
+            self.settings.verification_gas_buffer_percent,
+        );
 
-        Ok(paymaster_verification_gas_limit)
+        Ok(GasEstimateStages {
+            raw: paymaster_verification_gas_limit,
+            after_buffer,
+            after_clamp,
+            num_rounds: Some(num_rounds),
+        })
     }
 
     async fn estimate_pre_verification_gas(
@@ -363,11 +601,15 @@ where
             }
         };
 
+        let random_ops = (0..self.settings.pre_verification_gas_random_fill_samples)
+            .map(|_| optional_op.random_fill(&self.chain_spec))
+            .collect::<Vec<_>>();
+
         Ok(gas::estimate_pre_verification_gas(
             &self.chain_spec,
             &self.entry_point,
             &optional_op.max_fill(&self.chain_spec),
-            &optional_op.random_fill(&self.chain_spec),
+            &random_ops,
             block_hash.into(),
             gas_price,
         )
@@ -380,7 +622,7 @@ where
         full_op: UserOperation,
         block_hash: B256,
         state_override: StateOverride,
-    ) -> Result<u128, GasEstimationError> {
+    ) -> Result<GasEstimateStages, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(cl) = optional_op.call_gas_limit {
             if cl != 0 {
@@ -388,21 +630,26 @@ where
                 self.call_gas_estimator
                     .simulate_handle_op_with_result(full_op, block_hash, state_override)
                     .await?;
-                return Ok(cl);
+                return Ok(GasEstimateStages::unadjusted(cl));
             }
         }
 
-        let call_gas_limit = self
+        let GasSearchResult {
+            gas: call_gas_limit,
+            num_rounds,
+        } = self
             .call_gas_estimator
             .estimate_call_gas(full_op, block_hash, state_override)
             .await?;
 
         // Add a buffer to the call gas limit and clamp
-        let call_gas_limit = call_gas_limit
-            .add(super::CALL_GAS_BUFFER_VALUE)
-            .clamp(MIN_CALL_GAS_LIMIT, self.settings.max_call_gas);
-
-        Ok(call_gas_limit)
+        let with_buffer = call_gas_limit.add(super::CALL_GAS_BUFFER_VALUE);
+        Ok(GasEstimateStages {
+            raw: call_gas_limit,
+            after_buffer: with_buffer,
+            after_clamp: with_buffer.clamp(MIN_CALL_GAS_LIMIT, self.settings.max_call_gas),
+            num_rounds: Some(num_rounds),
+        })
     }
 }
 
@@ -432,14 +679,10 @@ impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV07 {
             },
         );
 
-        state_override.insert(
-            ep_to_override,
-            AccountOverride {
-                code: Some(estimation_proxy_bytecode_with_target(
-                    moved_entry_point_address,
-                )),
-                ..Default::default()
-            },
+        // Only replace the code at the entry point address, keeping any caller provided
+        // overrides (i.e. deposits in the entry point's storage) in place.
+        state_override.entry(ep_to_override).or_default().code = Some(
+            estimation_proxy_bytecode_with_target(moved_entry_point_address),
         );
     }
 
@@ -499,7 +742,7 @@ fn estimation_proxy_bytecode_with_target(target: Address) -> Bytes {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use alloy_primitives::{hex, U256};
     use alloy_sol_types::{Revert, SolCall, SolError};
@@ -507,14 +750,24 @@ mod tests {
     use rundler_contracts::v0_7::{
         CallGasEstimationProxy::TestCallGasResult, IEntryPointSimulations,
     };
-    use rundler_provider::{EvmCall, ExecutionResult, MockEntryPointV0_7, MockEvmProvider};
-    use rundler_types::v0_7::UserOperationOptionalGas;
+    use rundler_provider::{
+        BlockId, EvmCall, ExecutionResult, GasUsedResult, MockEntryPointV0_7, MockEvmProvider,
+    };
+    use rundler_types::{v0_7::UserOperationOptionalGas, AccountType};
 
     use super::*;
     use crate::{
-        estimation::estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, GasEstimator as _,
+        estimation::{
+            estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, EmptySignatureHandling,
+            EstimationStrategy, PrunedStateHandling,
+        },
+        GasEstimator as _,
     };
 
+    // Due to https://github.com/asomers/mockall/blob/master/mockall/examples/synchronization.rs
+    // sync all tests that rely on MockEntryPointV0_7::decode_simulate_handle_ops_revert_context();
+    static MTX: Mutex<()> = Mutex::new(());
+
     // Alises for complex types (which also satisfy Clippy)
     type VerificationGasEstimatorWithMocks =
         VerificationGasEstimatorImpl<Arc<MockEvmProvider>, Arc<MockEntryPointV0_7>>;
@@ -529,18 +782,23 @@ mod tests {
     >;
 
     fn create_base_config() -> (MockEntryPointV0_7, MockEvmProvider) {
+        create_base_config_with_simulation_should_revert(true)
+    }
+
+    fn create_base_config_with_simulation_should_revert(
+        simulation_should_revert: bool,
+    ) -> (MockEntryPointV0_7, MockEvmProvider) {
         let mut entry = MockEntryPointV0_7::new();
         let provider = MockEvmProvider::new();
 
         // Fill in concrete implementations of call data and
         // `simulation_should_revert`
-        entry
-            .expect_get_simulate_handle_op_call()
-            .returning(|op, state_override| {
+        entry.expect_get_simulate_handle_op_call().returning(
+            |op, target, target_call_data, state_override| {
                 let data = IEntryPointSimulations::simulateHandleOpCall {
                     op: op.pack(),
-                    target: Address::ZERO,
-                    targetCallData: Bytes::new(),
+                    target,
+                    targetCallData: target_call_data,
                 }
                 .abi_encode()
                 .into();
@@ -551,8 +809,11 @@ mod tests {
                     value: U256::ZERO,
                     state_override,
                 }
-            });
-        entry.expect_simulation_should_revert().return_const(true);
+            },
+        );
+        entry
+            .expect_simulation_should_revert()
+            .return_const(simulation_should_revert);
 
         entry.expect_address().return_const(Address::ZERO);
 
@@ -577,23 +838,96 @@ mod tests {
 
     const TEST_MAX_GAS_LIMITS: u128 = 10000000000;
 
-    fn create_estimator(
-        entry: MockEntryPointV0_7,
-        provider: MockEvmProvider,
-    ) -> (GasEstimatorWithMocks, Settings) {
-        let settings = Settings {
+    fn create_settings() -> Settings {
+        Settings {
             max_verification_gas: TEST_MAX_GAS_LIMITS,
+            min_verification_gas_limit: 0,
             max_call_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
             max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
             verification_estimation_gas_fee: 1_000_000_000_000,
-        };
+            verification_gas_buffer_percent: 10,
+            estimation_strategy: EstimationStrategy::BinarySearch,
+            gas_rounding: 4096,
+            pruned_state_handling: PrunedStateHandling::Error,
+            empty_signature_handling: EmptySignatureHandling::Warn,
+            dummy_signature_account_type: AccountType::Ecdsa,
+            sequential_estimation: false,
+            block_drift_repin_millis: None,
+            max_pre_verification_gas: None,
+            pre_verification_gas_random_fill_samples: 1,
+            validation_reuse_max_blocks: 1,
+        }
+    }
+
+    fn create_estimator(
+        entry: MockEntryPointV0_7,
+        provider: MockEvmProvider,
+    ) -> (GasEstimatorWithMocks, Settings) {
+        let settings = create_settings();
         let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
         (estimator, settings)
     }
 
+    fn searched_stages(raw: u128) -> GasEstimateStages {
+        GasEstimateStages {
+            raw,
+            after_buffer: raw,
+            after_clamp: raw,
+            num_rounds: Some(10),
+        }
+    }
+
+    #[test]
+    fn test_estimate_scenarios_conservative_at_least_optimistic() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let estimate = GasEstimate {
+            pre_verification_gas: 50_000,
+            call_gas_limit: 100_000,
+            verification_gas_limit: 200_000,
+            paymaster_verification_gas_limit: Some(300_000),
+            block_number: Some(1),
+            breakdown: Some(GasEstimateBreakdown {
+                pre_verification_gas: GasEstimateStages::unadjusted(50_000),
+                call_gas_limit: searched_stages(100_000),
+                verification_gas_limit: searched_stages(200_000),
+                paymaster_verification_gas_limit: Some(searched_stages(300_000)),
+            }),
+            verification_gas_rounds: Some(10),
+            call_gas_rounds: Some(10),
+            warnings: vec![],
+        };
+
+        let GasEstimateScenarios {
+            optimistic,
+            conservative,
+        } = estimator.estimate_scenarios(&estimate, 10, 50);
+
+        assert_eq!(optimistic.verification_gas_limit, 220_000);
+        assert_eq!(optimistic.paymaster_verification_gas_limit, Some(330_000));
+        assert_eq!(conservative.verification_gas_limit, 300_000);
+        assert_eq!(conservative.paymaster_verification_gas_limit, Some(450_000));
+
+        assert!(conservative.pre_verification_gas >= optimistic.pre_verification_gas);
+        assert!(conservative.call_gas_limit >= optimistic.call_gas_limit);
+        assert!(conservative.verification_gas_limit >= optimistic.verification_gas_limit);
+        assert!(
+            conservative.paymaster_verification_gas_limit
+                >= optimistic.paymaster_verification_gas_limit
+        );
+
+        // the buffer percentages are ordered regardless of argument order
+        let swapped = estimator.estimate_scenarios(&estimate, 50, 10);
+        assert_eq!(
+            swapped.conservative.verification_gas_limit,
+            conservative.verification_gas_limit
+        );
+    }
+
     fn demo_user_op_optional_gas(pvg: Option<u128>) -> UserOperationOptionalGas {
         UserOperationOptionalGas {
             sender: Address::ZERO,
@@ -755,6 +1089,82 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_estimate_returns_block_number() {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::repeat_byte(1), 1234)));
+
+        entry
+            .expect_simulate_handle_op()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .unwrap();
+
+        assert_eq!(estimation.block_number, Some(1234));
+    }
+
+    #[tokio::test]
+    async fn test_estimation_with_block_skips_latest_block() {
+        let (mut entry, mut provider) = create_base_config();
+        let block_hash = B256::repeat_byte(0xcd);
+
+        provider.expect_get_latest_block_hash_and_number().never();
+        entry
+            .expect_simulate_handle_op()
+            .withf(move |_op, _b, _c, block_id, _e| *block_id == BlockId::from(block_hash))
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.call_gas_limit = Some(10000);
+        optional_op.verification_gas_limit = Some(10000);
+
+        let estimation = estimator
+            .estimate_op_gas_with_block(optional_op, StateOverride::default(), (block_hash, 100))
+            .await
+            .unwrap();
+
+        assert_eq!(estimation.block_number, Some(100));
+        assert_eq!(estimation.call_gas_limit, 10000);
+    }
+
     #[tokio::test]
     async fn test_provided_reverts() {
         let (mut entry, mut provider) = create_base_config();
@@ -859,6 +1269,114 @@ mod tests {
         ));
     }
 
+    async fn estimate_verification_gas_with_simulation_success(
+        simulation_should_revert: bool,
+        gas_usage: u128,
+    ) -> Result<GasEstimateStages, GasEstimationError> {
+        let (mut entry, mut provider) =
+            create_base_config_with_simulation_should_revert(simulation_should_revert);
+        entry.expect_simulate_handle_op().never();
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: true,
+                result: Bytes::new(),
+            })
+        });
+
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            entry,
+            Settings {
+                estimation_strategy: EstimationStrategy::SingleShot,
+                ..create_settings()
+            },
+        );
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let full_op = optional_op.max_fill(&ChainSpec::default());
+        estimator
+            .estimate_verification_gas(&optional_op, &full_op, B256::ZERO, StateOverride::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_verification_gas_simulation_success_expected() {
+        let gas_usage = 100_000;
+        let stages = estimate_verification_gas_with_simulation_success(false, gas_usage)
+            .await
+            .unwrap();
+        assert_eq!(
+            stages.raw,
+            gas_usage + ChainSpec::default().deposit_transfer_overhead()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verification_gas_simulation_success_unexpected() {
+        let err = estimate_verification_gas_with_simulation_success(true, 100_000)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GasEstimationError::UnexpectedSimulationSuccess(address) if address == Address::ZERO
+        ));
+    }
+
+    // Returns the verification gas estimate stages for a single shot estimate with the given buffer
+    async fn estimate_verification_gas_with_buffer(
+        gas_usage: u128,
+        verification_gas_buffer_percent: u32,
+    ) -> GasEstimateStages {
+        let (entry, mut provider) = create_base_config();
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            entry,
+            Settings {
+                estimation_strategy: EstimationStrategy::SingleShot,
+                verification_gas_buffer_percent,
+                ..create_settings()
+            },
+        );
+
+        let optional_op = demo_user_op_optional_gas(Some(10000));
+        let full_op = optional_op.max_fill(&ChainSpec::default());
+        estimator
+            .estimate_verification_gas(&optional_op, &full_op, B256::ZERO, StateOverride::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verification_gas_buffer_percent() {
+        let gas_usage = 100_000;
+
+        let _m = MTX.lock();
+        let ctx = MockEntryPointV0_7::decode_simulate_handle_ops_revert_context();
+        ctx.expect()
+            .returning(|_a| Ok(Ok(ExecutionResult::default())));
+
+        let raw = gas_usage + ChainSpec::default().deposit_transfer_overhead();
+
+        let stages = estimate_verification_gas_with_buffer(gas_usage, 0).await;
+        assert_eq!(stages.raw, raw);
+        assert_eq!(stages.after_clamp, raw);
+
+        let stages = estimate_verification_gas_with_buffer(gas_usage, 50).await;
+        assert_eq!(stages.raw, raw);
+        assert_eq!(stages.after_clamp, raw + raw / 2);
+    }
+
     #[test]
     fn test_proxy_target_offset() {
         let proxy_target_bytes = hex::decode(PROXY_IMPLEMENTATION_ADDRESS_MARKER).unwrap();
@@ -14,14 +14,15 @@
 use std::fmt::Debug;
 
 use anyhow::Context;
+use futures_util::future::try_join_all;
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{BlockHashOrNumber, DAGasProvider, EvmProvider};
+use rundler_provider::{BlockHashOrNumber, DAGasOracleSync, DAGasProvider, EvmProvider};
 use rundler_types::{chain::ChainSpec, da::DAGasUOData, GasFees, UserOperation};
 use rundler_utils::math;
 use tokio::try_join;
 
-use super::oracle::FeeOracle;
+use super::oracle::{FeeHistoryOracle, FeeHistoryOracleConfig, FeeOracle};
 
 /// Returns the required pre_verification_gas for the given user operation
 ///
@@ -29,10 +30,14 @@ use super::oracle::FeeOracle;
 /// or the user operation that was submitted via `estimateUserOperationGas` and filled
 /// in via its `max_fill()` call. It is used to calculate the static portion of the pre_verification_gas
 ///
-/// `random_op` is either the user operation submitted via `sendUserOperation`
-/// or the user operation that was submitted via `estimateUserOperationGas` and filled
-/// in via its `random_fill()` call. It is used to calculate the DA portion of the pre_verification_gas
-/// on networks that require it.
+/// `random_ops` are either the user operation submitted via `sendUserOperation`
+/// or samples of the user operation that was submitted via `estimateUserOperationGas` and filled
+/// in via its `random_fill()` call. They are used to calculate the DA portion of the pre_verification_gas
+/// on networks that require it. The minimum DA gas over all samples is used, as any single random
+/// fill tends to overestimate the compressed size. Must not be empty.
+///
+/// `block` is the block at which the DA gas data is queried. Gas estimators pass the same block
+/// that verification and call gas are estimated at, so that all parts of an estimate are consistent.
 ///
 /// Networks that require Data Availability (DA) pre_verification_gas are those that charge extra calldata fees
 /// that can scale based on DA gas prices.
@@ -40,15 +45,21 @@ pub async fn estimate_pre_verification_gas<UO: UserOperation, E: DAGasProvider<U
     chain_spec: &ChainSpec,
     entry_point: &E,
     full_op: &UO,
-    random_op: &UO,
+    random_ops: &[UO],
     block: BlockHashOrNumber,
     gas_price: u128,
 ) -> anyhow::Result<u128> {
     let da_gas = if chain_spec.da_pre_verification_gas {
-        entry_point
-            .calc_da_gas(random_op.clone(), block, gas_price)
-            .await?
-            .0
+        try_join_all(
+            random_ops
+                .iter()
+                .map(|op| entry_point.calc_da_gas(op.clone(), block, gas_price)),
+        )
+        .await?
+        .into_iter()
+        .map(|(da_gas, _, _)| da_gas)
+        .min()
+        .context("should have at least one random op")?
     } else {
         0
     };
@@ -83,6 +94,52 @@ pub async fn calc_required_pre_verification_gas<UO: UserOperation, E: DAGasProvi
     ))
 }
 
+/// Calculate the required pre_verification_gas for a batch of user operations that share the
+/// same block and base fee.
+///
+/// The block DA gas data (i.e. the L1 gas conditions) is retrieved once and re-used for every
+/// operation, avoiding a redundant DA gas oracle query per operation. Useful for bundle pre-sizing.
+///
+/// Returns the required pre_verification_gas for each operation, in order.
+pub async fn calc_pre_verification_gas_batch<UO, E, D>(
+    chain_spec: &ChainSpec,
+    entry_point: &E,
+    da_gas_oracle: &D,
+    ops: &[UO],
+    block: BlockHashOrNumber,
+    base_fee: u128,
+) -> anyhow::Result<Vec<u128>>
+where
+    UO: UserOperation,
+    E: DAGasProvider<UO = UO>,
+    D: DAGasOracleSync,
+{
+    if !chain_spec.da_pre_verification_gas {
+        return Ok(ops
+            .iter()
+            .map(|op| op.required_pre_verification_gas(chain_spec, 1, 0))
+            .collect());
+    }
+
+    let block_data = da_gas_oracle.block_data(block).await?;
+    let uo_data = try_join_all(ops.iter().map(|op| {
+        let (data, to) = entry_point.da_gas_transaction_data(op.clone(), op.gas_price(base_fee));
+        da_gas_oracle.uo_data(data, to, block)
+    }))
+    .await?;
+
+    // Currently assume 1 op bundle
+    Ok(ops
+        .iter()
+        .zip(uo_data)
+        .map(|(op, uo_data)| {
+            let da_gas =
+                da_gas_oracle.calc_da_gas_sync(&uo_data, &block_data, op.gas_price(base_fee));
+            op.required_pre_verification_gas(chain_spec, 1, da_gas)
+        })
+        .collect())
+}
+
 /// Different modes for calculating the required priority fee
 /// for the bundler to include a user operation in a bundle.
 #[derive(Debug, Clone, Copy)]
@@ -91,6 +148,9 @@ pub enum PriorityFeeMode {
     BaseFeePercent(u32),
     /// The priority fee is required to be a percentage above the bundle priority fee.
     PriorityFeeIncreasePercent(u32),
+    /// The bundle priority fee is sampled from this percentile of the priority fees paid in
+    /// recent blocks, and the priority fee is required to be at least the bundle priority fee.
+    Percentile(f64),
 }
 
 impl PriorityFeeMode {
@@ -99,10 +159,19 @@ impl PriorityFeeMode {
         match kind {
             "base_fee_percent" => Ok(Self::BaseFeePercent(value)),
             "priority_fee_increase_percent" => Ok(Self::PriorityFeeIncreasePercent(value)),
+            "percentile" => Self::percentile(value as f64),
             _ => anyhow::bail!("Invalid priority fee mode: {}", kind),
         }
     }
 
+    /// Create a percentile priority fee mode, the percentile must be in `[0, 100]`.
+    pub fn percentile(percentile: f64) -> anyhow::Result<Self> {
+        if !(0.0..=100.0).contains(&percentile) {
+            anyhow::bail!("Priority fee percentile must be between 0 and 100, got {percentile}");
+        }
+        Ok(Self::Percentile(percentile))
+    }
+
     /// Returns the required fees for the given bundle fees based on this priority
     /// fee mode.
     pub fn required_fees(&self, bundle_fees: GasFees) -> GasFees {
@@ -113,6 +182,7 @@ impl PriorityFeeMode {
             PriorityFeeMode::PriorityFeeIncreasePercent(percent) => {
                 math::increase_by_percent(bundle_fees.max_priority_fee_per_gas, percent)
             }
+            PriorityFeeMode::Percentile(_) => bundle_fees.max_priority_fee_per_gas,
         };
 
         let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
@@ -137,6 +207,7 @@ impl PriorityFeeMode {
             PriorityFeeMode::PriorityFeeIncreasePercent(percent) => {
                 math::increase_by_percent(min_max_priority_fee_per_gas, percent)
             }
+            PriorityFeeMode::Percentile(_) => min_max_priority_fee_per_gas,
         }
     }
 }
@@ -168,6 +239,7 @@ pub struct FeeEstimatorImpl<P, O> {
     priority_fee_mode: PriorityFeeMode,
     bundle_base_fee_overhead_percent: u32,
     bundle_priority_fee_overhead_percent: u32,
+    percentile_block_window: u64,
     fee_oracle: O,
 }
 
@@ -178,12 +250,16 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimatorImpl<P, O> {
     ///
     /// `bundle_priority_fee_overhead_percent` is used to determine the overhead percentage to add
     /// to the network returned priority fee to ensure the bundle priority fee is high enough.
+    ///
+    /// `percentile_block_window` is the number of recent blocks sampled for the network
+    /// priority fee when using `PriorityFeeMode::Percentile`.
     pub fn new(
         provider: P,
         fee_oracle: O,
         priority_fee_mode: PriorityFeeMode,
         bundle_base_fee_overhead_percent: u32,
         bundle_priority_fee_overhead_percent: u32,
+        percentile_block_window: u64,
     ) -> Self {
         Self {
             provider,
@@ -191,6 +267,7 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimatorImpl<P, O> {
             priority_fee_mode,
             bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent,
+            percentile_block_window,
         }
     }
 
@@ -199,6 +276,21 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimatorImpl<P, O> {
     }
 
     async fn get_priority_fee(&self) -> anyhow::Result<u128> {
+        if let PriorityFeeMode::Percentile(percentile) = self.priority_fee_mode {
+            let oracle = FeeHistoryOracle::new(
+                &self.provider,
+                FeeHistoryOracleConfig {
+                    blocks_history: self.percentile_block_window,
+                    percentile,
+                    ..Default::default()
+                },
+            );
+            return oracle
+                .estimate_priority_fee()
+                .await
+                .context("should get percentile priority fee");
+        }
+
         self.fee_oracle
             .estimate_priority_fee()
             .await
@@ -239,3 +331,187 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimator for FeeEstimatorImpl<P, O> {
         self.priority_fee_mode.required_fees(bundle_fees)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, Bytes, U256};
+    use rundler_provider::{FeeHistory, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider};
+    use rundler_types::{
+        da::{DAGasBlockData, DAGasUOData},
+        v0_6::UserOperation as UserOperationV0_6,
+    };
+
+    use super::*;
+    use crate::gas::oracle::ConstantOracle;
+
+    #[tokio::test]
+    async fn test_calc_pre_verification_gas_batch_single_block_query() {
+        let chain_spec = ChainSpec {
+            da_pre_verification_gas: true,
+            ..Default::default()
+        };
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_da_gas_transaction_data()
+            .times(3)
+            .returning(|_, _| (Bytes::default(), Address::ZERO));
+
+        let mut da_gas_oracle = MockDAGasOracleSync::new();
+        da_gas_oracle
+            .expect_block_data()
+            .times(1)
+            .returning(|_| Ok(DAGasBlockData::Empty));
+        da_gas_oracle
+            .expect_uo_data()
+            .times(3)
+            .returning(|_, _, _| Ok(DAGasUOData::Empty));
+        da_gas_oracle
+            .expect_calc_da_gas_sync()
+            .times(3)
+            .returning(|_, _, _| 1_000);
+
+        let ops = (0..3)
+            .map(|i| UserOperationV0_6 {
+                nonce: U256::from(i),
+                max_fee_per_gas: 100,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let pvgs = calc_pre_verification_gas_batch(
+            &chain_spec,
+            &entry_point,
+            &da_gas_oracle,
+            &ops,
+            BlockHashOrNumber::Number(0),
+            10,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(pvgs.len(), 3);
+        for (op, pvg) in ops.iter().zip(pvgs) {
+            assert_eq!(pvg, op.required_pre_verification_gas(&chain_spec, 1, 1_000));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_pre_verification_gas_more_samples_lower_or_equal() {
+        let chain_spec = ChainSpec {
+            da_pre_verification_gas: true,
+            ..Default::default()
+        };
+
+        // DA gas depends on the random contents of each sample
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_calc_da_gas()
+            .returning(|op: UserOperationV0_6, _, _| {
+                Ok((
+                    op.max_fee_per_gas,
+                    DAGasUOData::Empty,
+                    DAGasBlockData::Empty,
+                ))
+            });
+
+        let full_op = UserOperationV0_6::default();
+        let random_ops = [5_000, 7_000, 3_000, 4_000, 1_000]
+            .into_iter()
+            .map(|max_fee_per_gas| UserOperationV0_6 {
+                max_fee_per_gas,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut prev = u128::MAX;
+        for samples in 1..=random_ops.len() {
+            let pvg = estimate_pre_verification_gas(
+                &chain_spec,
+                &entry_point,
+                &full_op,
+                &random_ops[..samples],
+                BlockHashOrNumber::Number(0),
+                10,
+            )
+            .await
+            .unwrap();
+            assert!(pvg <= prev);
+            prev = pvg;
+        }
+        assert_eq!(
+            prev,
+            full_op.required_pre_verification_gas(&chain_spec, 1, 1_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_pre_verification_gas_no_samples() {
+        let chain_spec = ChainSpec {
+            da_pre_verification_gas: true,
+            ..Default::default()
+        };
+        let entry_point = MockEntryPointV0_6::new();
+
+        assert!(estimate_pre_verification_gas(
+            &chain_spec,
+            &entry_point,
+            &UserOperationV0_6::default(),
+            &[],
+            BlockHashOrNumber::Number(0),
+            10,
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_percentile_priority_fee() {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_pending_base_fee()
+            .returning(|| Ok(1_000));
+        provider
+            .expect_fee_history()
+            .withf(|block_count, _, percentiles| {
+                *block_count == 5 && percentiles.to_vec() == vec![75.0]
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(FeeHistory {
+                    reward: Some(vec![vec![100], vec![200], vec![300]]),
+                    ..Default::default()
+                })
+            });
+
+        // the fee oracle is not used in percentile mode
+        let estimator = FeeEstimatorImpl::new(
+            provider,
+            ConstantOracle::new(1),
+            PriorityFeeMode::percentile(75.0).unwrap(),
+            0,
+            10,
+            5,
+        );
+
+        let (bundle_fees, base_fee) = estimator.required_bundle_fees(None).await.unwrap();
+        assert_eq!(base_fee, 1_000);
+        assert_eq!(bundle_fees.max_priority_fee_per_gas, 220);
+        assert_eq!(bundle_fees.max_fee_per_gas, 1_220);
+        assert_eq!(estimator.required_op_fees(bundle_fees), bundle_fees);
+    }
+
+    #[test]
+    fn test_percentile_priority_fee_mode_validation() {
+        assert!(PriorityFeeMode::percentile(0.0).is_ok());
+        assert!(PriorityFeeMode::percentile(100.0).is_ok());
+        assert!(PriorityFeeMode::percentile(-1.0).is_err());
+        assert!(PriorityFeeMode::percentile(100.5).is_err());
+        assert!(PriorityFeeMode::percentile(f64::NAN).is_err());
+        assert!(PriorityFeeMode::try_from("percentile", 101).is_err());
+        assert!(matches!(
+            PriorityFeeMode::try_from("percentile", 90),
+            Ok(PriorityFeeMode::Percentile(p)) if p == 90.0
+        ));
+    }
+}
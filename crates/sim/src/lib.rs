@@ -35,9 +35,10 @@ mod estimation;
 #[cfg(feature = "test-utils")]
 pub use estimation::MockGasEstimator;
 pub use estimation::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
-    GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7, Settings as EstimationSettings,
-    VerificationGasEstimator, VerificationGasEstimatorImpl,
+    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, EmptySignatureHandling,
+    EstimationStrategy, GasEstimateScenarios, GasEstimationError, GasEstimator, GasEstimatorV0_6,
+    GasEstimatorV0_7, GasSearchResult, PrunedStateHandling, RecentValidation,
+    Settings as EstimationSettings, VerificationGasEstimator, VerificationGasEstimatorImpl,
 };
 
 pub mod gas;
@@ -49,8 +50,8 @@ mod precheck;
 #[cfg(feature = "test-utils")]
 pub use precheck::MockPrechecker;
 pub use precheck::{
-    PrecheckError, PrecheckReturn, Prechecker, PrecheckerImpl, Settings as PrecheckSettings,
-    MIN_CALL_GAS_LIMIT,
+    AbsurdFeeHandling, PrecheckError, PrecheckReturn, Prechecker, PrecheckerImpl,
+    Settings as PrecheckSettings, MIN_CALL_GAS_LIMIT,
 };
 
 /// Simulation and violation checking
@@ -63,4 +64,4 @@ pub use simulation::{
 };
 
 mod types;
-pub use types::{ExpectedStorage, ViolationError};
+pub use types::{ExpectedStorage, StorageWrites, ViolationError};
@@ -22,7 +22,7 @@ use rundler_provider::{BlockHashOrNumber, DAGasProvider, EntryPoint, EvmProvider
 use rundler_types::{
     chain::ChainSpec,
     da::DAGasUOData,
-    pool::{MempoolError, PrecheckViolation},
+    pool::{MempoolError, PrecheckSettingsUpdate, PrecheckViolation},
     GasFees, UserOperation,
 };
 use rundler_utils::math;
@@ -62,6 +62,14 @@ pub trait Prechecker: Send + Sync {
     ///
     /// This MUST be called at block boundaries before checking any operations.
     async fn update_fees(&self) -> anyhow::Result<(GasFees, u128)>;
+
+    /// Updates the settings used by subsequent prechecks, returning the effective settings.
+    ///
+    /// Fails without changing any settings if the update is invalid.
+    fn update_settings(
+        &self,
+        update: PrecheckSettingsUpdate,
+    ) -> anyhow::Result<PrecheckSettingsUpdate>;
 }
 
 /// Precheck error
@@ -90,7 +98,7 @@ pub struct PrecheckerImpl<UO, P, E, F> {
     chain_spec: ChainSpec,
     provider: P,
     entry_point: E,
-    settings: Settings,
+    settings: RwLock<Settings>,
     fee_estimator: F,
     cache: RwLock<AsyncDataCache>,
     _uo_type: PhantomData<UO>,
@@ -101,6 +109,10 @@ pub struct PrecheckerImpl<UO, P, E, F> {
 pub struct Settings {
     /// Maximum verification gas allowed for a user operation
     pub max_verification_gas: u128,
+    /// Maximum paymaster verification gas allowed for a user operation, if any.
+    /// For v0.7 this caps `paymasterVerificationGasLimit`, for v0.6 it caps the paymaster's
+    /// share of the total verification gas limit.
+    pub max_paymaster_verification_gas: Option<u128>,
     /// Maximum total execution gas allowed for a user operation
     pub max_total_execution_gas: u128,
     /// The percentage to add to the network pending base fee as a safety margin for fast inclusion.
@@ -110,11 +122,39 @@ pub struct Settings {
     pub bundle_priority_fee_overhead_percent: u32,
     /// The priority fee mode to use for calculating required user operation priority fee.
     pub priority_fee_mode: gas::PriorityFeeMode,
+    /// Number of recent blocks sampled for the network priority fee when using the
+    /// percentile priority fee mode.
+    pub priority_fee_percentile_blocks: u64,
     /// Percentage of the current network base fee that a user operation must have to be accepted into the mempool.
     pub base_fee_accept_percent: u32,
     /// Percentage of the preVerificationGas that a user operation must have to be accepted into the mempool.
     /// Only applied if the chain has dynamic preVerificationGas, else enforced to 100%
     pub pre_verification_gas_accept_percent: u32,
+    /// Maximum length in bytes of a user operation's call data, if any
+    pub max_call_data_len: Option<usize>,
+    /// Maximum total gas allowed for a user operation, if any. This is the sum of
+    /// `preVerificationGas`, the total verification gas limit, and `callGasLimit`.
+    pub max_total_op_gas: Option<u128>,
+    /// If set, a user operation whose `maxFeePerGas` and `maxPriorityFeePerGas` are both more
+    /// than this multiple of the current network fees is handled per `absurd_fee_handling`.
+    pub absurd_fee_multiple: Option<u128>,
+    /// How to handle a user operation with fees above `absurd_fee_multiple` of network fees
+    pub absurd_fee_handling: AbsurdFeeHandling,
+    /// Whether to warn when a user operation's sender has pending transactions. Costs two
+    /// extra provider calls per precheck.
+    pub warn_sender_pending_txs: bool,
+}
+
+/// How to handle a user operation whose fees are far above current network fees. Such fees
+/// are almost always a mistake, e.g. a value entered in the wrong unit, and overpay for inclusion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum AbsurdFeeHandling {
+    /// Log a warning and accept the operation
+    #[default]
+    Warn,
+    /// Reject the operation with a `MaxFeePerGasTooHigh` violation
+    Reject,
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -122,12 +162,19 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             max_verification_gas: 5_000_000,
+            max_paymaster_verification_gas: None,
             bundle_base_fee_overhead_percent: 27, // 2 12.5% EIP-1559 increases
             bundle_priority_fee_overhead_percent: 0,
             priority_fee_mode: gas::PriorityFeeMode::BaseFeePercent(0),
+            priority_fee_percentile_blocks: 20,
             max_total_execution_gas: 10_000_000,
             base_fee_accept_percent: 50,
             pre_verification_gas_accept_percent: 100,
+            max_call_data_len: None,
+            max_total_op_gas: None,
+            absurd_fee_multiple: None,
+            absurd_fee_handling: AbsurdFeeHandling::Warn,
+            warn_sender_pending_txs: false,
         }
     }
 }
@@ -138,7 +185,9 @@ struct AsyncData {
     sender_exists: bool,
     paymaster_exists: bool,
     payer_funds: U256,
+    sender_has_pending_txs: bool,
     base_fee: u128,
+    network_priority_fee: u128,
     min_pre_verification_gas: u128,
     da_gas_data: DAGasUOData,
 }
@@ -172,8 +221,12 @@ where
         let async_data = self.load_async_data(op, block).await?;
         let mut violations: Vec<PrecheckViolation> = vec![];
         violations.extend(self.check_init_code(op, &async_data));
+        violations.extend(self.check_call_data(op));
+        violations.extend(self.check_total_op_gas(op));
         violations.extend(self.check_gas(op, &async_data));
+        violations.extend(self.check_absurd_fees(op, &async_data));
         violations.extend(self.check_payer(op, &async_data));
+        self.check_sender_pending_txs(op, &async_data);
         if !violations.is_empty() {
             Err(violations)?
         }
@@ -194,6 +247,38 @@ where
 
         Ok((bundle_fees, base_fee))
     }
+
+    fn update_settings(
+        &self,
+        update: PrecheckSettingsUpdate,
+    ) -> anyhow::Result<PrecheckSettingsUpdate> {
+        if let Some(err) = update.validate() {
+            anyhow::bail!("invalid precheck settings: {err}");
+        }
+
+        let mut settings = self.settings.write().unwrap();
+        if let Some(max_verification_gas) = update.max_verification_gas {
+            settings.max_verification_gas = max_verification_gas;
+        }
+        if let Some(max_total_execution_gas) = update.max_total_execution_gas {
+            settings.max_total_execution_gas = max_total_execution_gas;
+        }
+        if let Some(base_fee_accept_percent) = update.base_fee_accept_percent {
+            settings.base_fee_accept_percent = base_fee_accept_percent;
+        }
+        if let Some(pre_verification_gas_accept_percent) =
+            update.pre_verification_gas_accept_percent
+        {
+            settings.pre_verification_gas_accept_percent = pre_verification_gas_accept_percent;
+        }
+
+        Ok(PrecheckSettingsUpdate {
+            max_verification_gas: Some(settings.max_verification_gas),
+            max_total_execution_gas: Some(settings.max_total_execution_gas),
+            base_fee_accept_percent: Some(settings.base_fee_accept_percent),
+            pre_verification_gas_accept_percent: Some(settings.pre_verification_gas_accept_percent),
+        })
+    }
 }
 
 impl<UO, P, E, F> PrecheckerImpl<UO, P, E, F>
@@ -215,13 +300,17 @@ where
             chain_spec,
             provider,
             entry_point,
-            settings,
+            settings: RwLock::new(settings),
             fee_estimator,
             cache: RwLock::new(AsyncDataCache { fees: None }),
             _uo_type: PhantomData,
         }
     }
 
+    fn settings(&self) -> Settings {
+        *self.settings.read().unwrap()
+    }
+
     fn check_init_code(&self, op: &UO, async_data: &AsyncData) -> ArrayVec<PrecheckViolation, 2> {
         let AsyncData {
             factory_exists,
@@ -248,12 +337,45 @@ where
         violations
     }
 
-    fn check_gas(&self, op: &UO, async_data: &AsyncData) -> ArrayVec<PrecheckViolation, 6> {
+    fn check_call_data(&self, op: &UO) -> Option<PrecheckViolation> {
+        let max_call_data_len = self.settings().max_call_data_len?;
+        let call_data_len = op.call_data().len();
+        if call_data_len > max_call_data_len {
+            Some(PrecheckViolation::CallDataTooLarge(
+                call_data_len,
+                max_call_data_len,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn check_total_op_gas(&self, op: &UO) -> Option<PrecheckViolation> {
+        let max_total_op_gas = self.settings().max_total_op_gas?;
+        let total_op_gas = op
+            .pre_verification_gas()
+            .saturating_add(op.total_verification_gas_limit())
+            .saturating_add(op.call_gas_limit());
+        if total_op_gas > max_total_op_gas {
+            Some(PrecheckViolation::TotalOpGasTooHigh(
+                total_op_gas,
+                max_total_op_gas,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn check_gas(&self, op: &UO, async_data: &AsyncData) -> ArrayVec<PrecheckViolation, 7> {
         let Settings {
             max_verification_gas,
+            max_paymaster_verification_gas,
             max_total_execution_gas,
+            base_fee_accept_percent,
+            pre_verification_gas_accept_percent,
+            priority_fee_mode,
             ..
-        } = self.settings;
+        } = self.settings();
         let AsyncData {
             base_fee,
             mut min_pre_verification_gas,
@@ -268,6 +390,21 @@ where
             ));
         }
 
+        // The paymaster's share of the total verification gas limit. For v0.6 the paymaster is
+        // given the same limit as the account, for v0.7 this is the paymaster verification gas limit.
+        if let Some(max_paymaster_verification_gas) = max_paymaster_verification_gas {
+            let paymaster_verification_gas_limit =
+                op.total_verification_gas_limit() - op.verification_gas_limit();
+            if op.paymaster().is_some()
+                && paymaster_verification_gas_limit > max_paymaster_verification_gas
+            {
+                violations.push(PrecheckViolation::PaymasterVerificationGasLimitTooHigh(
+                    paymaster_verification_gas_limit,
+                    max_paymaster_verification_gas,
+                ));
+            }
+        }
+
         // Compute the worst case total gas limit by assuming the UO is in its own bundle.
         // This is conservative and potentially may invalidate some very large UOs that would otherwise be valid.
         let gas_limit = op.execution_gas_limit(&self.chain_spec, Some(1));
@@ -283,7 +420,7 @@ where
         if self.chain_spec.da_pre_verification_gas {
             min_pre_verification_gas = math::percent(
                 min_pre_verification_gas,
-                self.settings.pre_verification_gas_accept_percent,
+                pre_verification_gas_accept_percent,
             );
         }
         if op.pre_verification_gas() < min_pre_verification_gas {
@@ -294,10 +431,10 @@ where
         }
 
         // check that the max fee per gas and max priority fee per gas are at least the required fees
-        let min_base_fee = math::percent(base_fee, self.settings.base_fee_accept_percent);
-        let min_priority_fee = self.settings.priority_fee_mode.minimum_priority_fee(
+        let min_base_fee = math::percent(base_fee, base_fee_accept_percent);
+        let min_priority_fee = priority_fee_mode.minimum_priority_fee(
             base_fee,
-            self.settings.base_fee_accept_percent,
+            base_fee_accept_percent,
             self.chain_spec.min_max_priority_fee_per_gas(),
         );
         let min_max_fee = min_base_fee + min_priority_fee;
@@ -325,6 +462,40 @@ where
         violations
     }
 
+    fn check_absurd_fees(&self, op: &UO, async_data: &AsyncData) -> Option<PrecheckViolation> {
+        let multiple = self.settings().absurd_fee_multiple?;
+        let max_fee_limit = async_data
+            .base_fee
+            .saturating_add(async_data.network_priority_fee)
+            .saturating_mul(multiple);
+        let max_priority_fee_limit = async_data
+            .network_priority_fee
+            .max(self.chain_spec.min_max_priority_fee_per_gas())
+            .saturating_mul(multiple);
+        if op.max_fee_per_gas() <= max_fee_limit
+            || op.max_priority_fee_per_gas() <= max_priority_fee_limit
+        {
+            return None;
+        }
+
+        match self.settings().absurd_fee_handling {
+            AbsurdFeeHandling::Warn => {
+                tracing::warn!(
+                    "user operation from {:?} has maxFeePerGas {} and maxPriorityFeePerGas {}, more than {}x current network fees",
+                    op.sender(),
+                    op.max_fee_per_gas(),
+                    op.max_priority_fee_per_gas(),
+                    multiple
+                );
+                None
+            }
+            AbsurdFeeHandling::Reject => Some(PrecheckViolation::MaxFeePerGasTooHigh(
+                op.max_fee_per_gas(),
+                max_fee_limit,
+            )),
+        }
+    }
+
     fn check_payer(&self, op: &UO, async_data: &AsyncData) -> Option<PrecheckViolation> {
         let AsyncData {
             paymaster_exists,
@@ -336,7 +507,8 @@ where
                 return Some(PrecheckViolation::PaymasterIsNotContract(paymaster));
             }
         }
-        let max_gas_cost = op.max_gas_cost();
+        // An overflowing max gas cost can't be covered by any payer
+        let max_gas_cost = op.checked_max_gas_cost().unwrap_or(U256::MAX);
         if payer_funds < max_gas_cost {
             if op.paymaster().is_none() {
                 return Some(PrecheckViolation::SenderFundsTooLow(
@@ -353,32 +525,53 @@ where
         None
     }
 
+    fn check_sender_pending_txs(&self, op: &UO, async_data: &AsyncData) {
+        // Not a violation, the sender's pending transactions may land before the operation is
+        // bundled, but they can conflict with the operation (i.e. EIP-7702 delegated EOAs).
+        if async_data.sender_has_pending_txs {
+            tracing::warn!(
+                "sender {:?} has pending transactions that may conflict with user operation",
+                op.sender()
+            );
+        }
+    }
+
     async fn load_async_data(
         &self,
         op: &UO,
         block: BlockHashOrNumber,
     ) -> anyhow::Result<AsyncData> {
-        let (_, base_fee) = self.get_fees().await?;
-
+        let (bundle_fees, base_fee) = self.get_fees().await?;
+        let warn_sender_pending_txs = self.settings().warn_sender_pending_txs;
+
+        // the pending transaction check only produces a warning, so it must not fail the precheck
+        let (required_data, sender_has_pending_txs) = tokio::join!(
+            async {
+                tokio::try_join!(
+                    self.is_contract(op.factory()),
+                    self.is_contract(Some(op.sender())),
+                    self.is_contract(op.paymaster()),
+                    self.get_payer_funds(op),
+                    self.get_required_pre_verification_gas(op.clone(), block, base_fee)
+                )
+            },
+            async { warn_sender_pending_txs && self.sender_has_pending_txs(op.sender()).await },
+        );
         let (
             factory_exists,
             sender_exists,
             paymaster_exists,
             payer_funds,
             (min_pre_verification_gas, da_gas_data),
-        ) = tokio::try_join!(
-            self.is_contract(op.factory()),
-            self.is_contract(Some(op.sender())),
-            self.is_contract(op.paymaster()),
-            self.get_payer_funds(op),
-            self.get_required_pre_verification_gas(op.clone(), block, base_fee)
-        )?;
+        ) = required_data?;
         Ok(AsyncData {
             factory_exists,
             sender_exists,
             paymaster_exists,
             payer_funds,
+            sender_has_pending_txs,
             base_fee,
+            network_priority_fee: bundle_fees.max_priority_fee_per_gas,
             min_pre_verification_gas,
             da_gas_data,
         })
@@ -396,6 +589,20 @@ where
         Ok(!bytecode.is_empty())
     }
 
+    /// Best-effort: returns false if the transaction counts can't be loaded.
+    async fn sender_has_pending_txs(&self, sender: Address) -> bool {
+        match tokio::try_join!(
+            self.provider.get_transaction_count(sender),
+            self.provider.get_pending_transaction_count(sender),
+        ) {
+            Ok((latest, pending)) => pending > latest,
+            Err(e) => {
+                tracing::warn!("Failed to get transaction counts for sender {sender:?}: {e:?}");
+                false
+            }
+        }
+    }
+
     async fn get_payer_funds(&self, op: &UO) -> anyhow::Result<U256> {
         let (deposit, balance) =
             tokio::try_join!(self.get_payer_deposit(op), self.get_payer_balance(op),)?;
@@ -454,10 +661,12 @@ mod tests {
 
     use alloy_primitives::{address, bytes, Bytes};
     use gas::MockFeeEstimator;
-    use rundler_provider::{MockEntryPointV0_6, MockEvmProvider};
+    use rundler_provider::{
+        MockEntryPointV0_6, MockEntryPointV0_7, MockEvmProvider, ProviderError,
+    };
     use rundler_types::{
         v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
-        UserOperation as _,
+        v0_7, UserOperation as _,
     };
 
     use super::*;
@@ -482,7 +691,9 @@ mod tests {
             sender_exists: true,
             paymaster_exists: true,
             payer_funds: U256::from(5_000_000),
+            sender_has_pending_txs: false,
             base_fee: 4_000,
+            network_priority_fee: 1_000,
             min_pre_verification_gas: 1_000,
             da_gas_data: DAGasUOData::Empty,
         }
@@ -525,16 +736,226 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[tokio::test]
+    async fn test_check_init_code_undeployed_sender() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let provider = Arc::new(provider);
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            provider,
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+        let op = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                init_code: bytes!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                call_data: Bytes::default(),
+                call_gas_limit: 9_000,
+                verification_gas_limit: 10_000_000,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 5_000,
+                max_priority_fee_per_gas: 2_000,
+                paymaster_and_data: Bytes::default(),
+                signature: Bytes::default(),
+            },
+        )
+        .build();
+
+        let async_data = AsyncData {
+            sender_exists: false,
+            ..get_test_async_data()
+        };
+        let res = prechecker.check_init_code(&op, &async_data);
+        assert!(res.is_empty());
+    }
+
+    fn op_with_call_data_len(cs: &ChainSpec, len: usize) -> UserOperation {
+        UserOperationBuilder::new(
+            cs,
+            UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                init_code: Bytes::default(),
+                call_data: Bytes::from(vec![1; len]),
+                call_gas_limit: 9_000,
+                verification_gas_limit: 10_000,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 5_000,
+                max_priority_fee_per_gas: 2_000,
+                paymaster_and_data: Bytes::default(),
+                signature: Bytes::default(),
+            },
+        )
+        .build()
+    }
+
+    #[tokio::test]
+    async fn test_check_call_data() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                max_call_data_len: Some(1_024),
+                ..Default::default()
+            },
+        );
+
+        let op = op_with_call_data_len(&cs, 1_024);
+        assert_eq!(prechecker.check_call_data(&op), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_call_data_too_large() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                max_call_data_len: Some(1_024),
+                ..Default::default()
+            },
+        );
+
+        let op = op_with_call_data_len(&cs, 1_025);
+        assert_eq!(
+            prechecker.check_call_data(&op),
+            Some(PrecheckViolation::CallDataTooLarge(1_025, 1_024))
+        );
+    }
+
+    fn prechecker_with_max_total_op_gas(
+        max_total_op_gas: u128,
+    ) -> (
+        ChainSpec,
+        PrecheckerImpl<UserOperation, Arc<MockEvmProvider>, MockEntryPointV0_6, MockFeeEstimator>,
+    ) {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                max_total_op_gas: Some(max_total_op_gas),
+                ..Default::default()
+            },
+        );
+        (cs, prechecker)
+    }
+
+    #[tokio::test]
+    async fn test_check_total_op_gas() {
+        // pre_verification_gas + verification_gas_limit + call_gas_limit = 0 + 10_000 + 9_000
+        let (cs, prechecker) = prechecker_with_max_total_op_gas(20_000);
+        let op = op_with_call_data_len(&cs, 0);
+        assert_eq!(prechecker.check_total_op_gas(&op), None);
+
+        let (cs, prechecker) = prechecker_with_max_total_op_gas(19_000);
+        let op = op_with_call_data_len(&cs, 0);
+        assert_eq!(prechecker.check_total_op_gas(&op), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_total_op_gas_too_high() {
+        let (cs, prechecker) = prechecker_with_max_total_op_gas(18_999);
+        let op = UserOperationBuilder::from_uo(op_with_call_data_len(&cs, 0), &cs)
+            .pre_verification_gas(1)
+            .build();
+        assert_eq!(
+            prechecker.check_total_op_gas(&op),
+            Some(PrecheckViolation::TotalOpGasTooHigh(19_001, 18_999))
+        );
+    }
+
+    fn prechecker_with_absurd_fees(
+        handling: AbsurdFeeHandling,
+    ) -> (
+        ChainSpec,
+        PrecheckerImpl<UserOperation, Arc<MockEvmProvider>, MockEntryPointV0_6, MockFeeEstimator>,
+    ) {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                absurd_fee_multiple: Some(100),
+                absurd_fee_handling: handling,
+                ..Default::default()
+            },
+        );
+        (cs, prechecker)
+    }
+
+    #[tokio::test]
+    async fn test_check_absurd_fees() {
+        // network fees are a 4_000 base fee and a 1_000 priority fee, so limits at 100x are
+        // 500_000 for maxFeePerGas and 100_000 for maxPriorityFeePerGas
+        let (cs, prechecker) = prechecker_with_absurd_fees(AbsurdFeeHandling::Reject);
+        let async_data = get_test_async_data();
+
+        let normal = op_with_call_data_len(&cs, 0);
+        assert_eq!(prechecker.check_absurd_fees(&normal, &async_data), None);
+
+        let absurd = UserOperationBuilder::from_uo(normal.clone(), &cs)
+            .max_fee_per_gas(600_000)
+            .max_priority_fee_per_gas(200_000)
+            .build();
+        assert_eq!(
+            prechecker.check_absurd_fees(&absurd, &async_data),
+            Some(PrecheckViolation::MaxFeePerGasTooHigh(600_000, 500_000))
+        );
+
+        // a high max fee alone is a common way to tolerate base fee spikes
+        let high_max_fee = UserOperationBuilder::from_uo(normal, &cs)
+            .max_fee_per_gas(600_000)
+            .build();
+        assert_eq!(
+            prechecker.check_absurd_fees(&high_max_fee, &async_data),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_absurd_fees_warn() {
+        let (cs, prechecker) = prechecker_with_absurd_fees(AbsurdFeeHandling::Warn);
+        let absurd = UserOperationBuilder::from_uo(op_with_call_data_len(&cs, 0), &cs)
+            .max_fee_per_gas(600_000)
+            .max_priority_fee_per_gas(200_000)
+            .build();
+        assert_eq!(
+            prechecker.check_absurd_fees(&absurd, &get_test_async_data()),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_check_gas() {
         let test_settings = Settings {
             max_verification_gas: 5_000_000,
+            max_paymaster_verification_gas: None,
             max_total_execution_gas: 10_000_000,
             bundle_base_fee_overhead_percent: 27,
             bundle_priority_fee_overhead_percent: 0,
             priority_fee_mode: gas::PriorityFeeMode::BaseFeePercent(100),
+            priority_fee_percentile_blocks: 20,
             base_fee_accept_percent: 100,
             pre_verification_gas_accept_percent: 100,
+            max_call_data_len: None,
+            max_total_op_gas: None,
+            absurd_fee_multiple: None,
+            absurd_fee_handling: AbsurdFeeHandling::Warn,
+            warn_sender_pending_txs: false,
         };
 
         let (cs, provider, entry_point, fee_estimator) = create_base_config();
@@ -570,15 +991,189 @@ mod tests {
         let total_gas_limit = op.gas_limit(&cs, Some(1));
 
         assert_eq!(
-            res,
-            ArrayVec::<PrecheckViolation, 6>::from([
+            res.as_slice(),
+            [
                 PrecheckViolation::VerificationGasLimitTooHigh(10_000_000, 5_000_000,),
                 PrecheckViolation::TotalGasLimitTooHigh(total_gas_limit, 10_000_000,),
                 PrecheckViolation::PreVerificationGasTooLow(0, 1_000,),
                 PrecheckViolation::MaxPriorityFeePerGasTooLow(2_000, 4_000,),
                 PrecheckViolation::MaxFeePerGasTooLow(5_000, 8_000,),
                 PrecheckViolation::CallGasLimitTooLow(9_000, 9_100,),
-            ])
+            ]
+        );
+    }
+
+    fn paymaster_op(cs: &ChainSpec, verification_gas_limit: u128) -> UserOperation {
+        UserOperationBuilder::new(
+            cs,
+            UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: 500_000,
+                verification_gas_limit,
+                pre_verification_gas: 1_000,
+                max_fee_per_gas: 8_000,
+                max_priority_fee_per_gas: 4_000,
+                paymaster_and_data: bytes!(
+                    "a4b2c8f0351d60729e4f0a12345678d9b1c3e5f27890abcdef123456780abcdef1"
+                ),
+                signature: Bytes::default(),
+            },
+        )
+        .build()
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_paymaster_verification_within_cap() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                max_paymaster_verification_gas: Some(500_000),
+                ..Default::default()
+            },
+        );
+
+        let op = paymaster_op(&cs, 500_000);
+        let res = prechecker.check_gas(&op, &get_test_async_data());
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_paymaster_verification_over_cap() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings {
+                max_paymaster_verification_gas: Some(400_000),
+                ..Default::default()
+            },
+        );
+
+        let op = paymaster_op(&cs, 500_000);
+        let res = prechecker.check_gas(&op, &get_test_async_data());
+        assert_eq!(
+            res.as_slice(),
+            [PrecheckViolation::PaymasterVerificationGasLimitTooHigh(
+                500_000, 400_000
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_applies_to_next_check() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        let op = paymaster_op(&cs, 500_000);
+        assert!(prechecker.check_gas(&op, &get_test_async_data()).is_empty());
+
+        let effective = prechecker
+            .update_settings(PrecheckSettingsUpdate {
+                max_verification_gas: Some(400_000),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            effective,
+            PrecheckSettingsUpdate {
+                max_verification_gas: Some(400_000),
+                max_total_execution_gas: Some(10_000_000),
+                base_fee_accept_percent: Some(50),
+                pre_verification_gas_accept_percent: Some(100),
+            }
+        );
+
+        let res = prechecker.check_gas(&op, &get_test_async_data());
+        assert_eq!(
+            res.as_slice(),
+            [PrecheckViolation::VerificationGasLimitTooHigh(
+                500_000, 400_000
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_rejects_zero_values() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        assert!(prechecker
+            .update_settings(PrecheckSettingsUpdate {
+                max_verification_gas: Some(400_000),
+                base_fee_accept_percent: Some(0),
+                ..Default::default()
+            })
+            .is_err());
+
+        // the valid field of the rejected update is not applied
+        let op = paymaster_op(&cs, 500_000);
+        assert!(prechecker.check_gas(&op, &get_test_async_data()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_paymaster_verification_over_cap_v0_7() {
+        let cs = ChainSpec::default();
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(MockEvmProvider::new()),
+            MockEntryPointV0_7::new(),
+            MockFeeEstimator::new(),
+            Settings {
+                max_paymaster_verification_gas: Some(400_000),
+                ..Default::default()
+            },
+        );
+
+        // the account verification gas limit is not counted against the paymaster cap
+        let op = v0_7::UserOperationBuilder::new(
+            &cs,
+            v0_7::UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                call_data: Bytes::default(),
+                call_gas_limit: 500_000,
+                verification_gas_limit: 1_000_000,
+                pre_verification_gas: 1_000,
+                max_priority_fee_per_gas: 4_000,
+                max_fee_per_gas: 8_000,
+                signature: Bytes::default(),
+            },
+        )
+        .paymaster(
+            address!("a4b2c8f0351d60729e4f0a12345678d9b1c3e5f2"),
+            500_000,
+            0,
+            Bytes::default(),
+        )
+        .build();
+
+        let res = prechecker.check_gas(&op, &get_test_async_data());
+        assert_eq!(
+            res.as_slice(),
+            [PrecheckViolation::PaymasterVerificationGasLimitTooHigh(
+                500_000, 400_000
+            )]
         );
     }
 
@@ -624,6 +1219,85 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_payer_paymaster_deposit_sufficient() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let provider = Arc::new(provider);
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            provider,
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        let op = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: 500_000,
+                verification_gas_limit: 500_000,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 0,
+                paymaster_and_data: bytes!(
+                    "a4b2c8f0351d60729e4f0a12345678d9b1c3e5f27890abcdef123456780abcdef1"
+                ),
+                signature: Bytes::default(),
+            },
+        )
+        .build();
+
+        // max gas cost of 2_000_000 is covered by the 5_000_000 deposit
+        let res = prechecker.check_payer(&op, &get_test_async_data());
+        assert_eq!(res, None);
+    }
+
+    #[tokio::test]
+    async fn test_check_payer_max_gas_cost_overflow() {
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let provider = Arc::new(provider);
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            provider,
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        let op = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender: address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d"),
+                nonce: U256::from(100),
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: u128::MAX,
+                verification_gas_limit: u128::MAX,
+                pre_verification_gas: u128::MAX,
+                max_fee_per_gas: u128::MAX,
+                max_priority_fee_per_gas: 0,
+                paymaster_and_data: bytes!(
+                    "a4b2c8f0351d60729e4f0a12345678d9b1c3e5f27890abcdef123456780abcdef1"
+                ),
+                signature: Bytes::default(),
+            },
+        )
+        .build();
+
+        let res = prechecker.check_payer(&op, &get_test_async_data());
+        assert_eq!(
+            res,
+            Some(PrecheckViolation::PaymasterDepositTooLow(
+                U256::from(5_000_000),
+                U256::MAX,
+            ))
+        );
+    }
+
     #[tokio::test]
     async fn test_check_fees() {
         let settings = Settings {
@@ -768,4 +1442,61 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    #[tokio::test]
+    async fn test_sender_has_pending_txs() {
+        let sender = address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d");
+        let (cs, mut provider, entry_point, fee_estimator) = create_base_config();
+        provider.expect_get_transaction_count().returning(|_| Ok(3));
+        provider
+            .expect_get_pending_transaction_count()
+            .returning(|_| Ok(5));
+        let prechecker = PrecheckerImpl::<UserOperation, _, _, _>::new(
+            cs,
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        assert!(prechecker.sender_has_pending_txs(sender).await);
+    }
+
+    #[tokio::test]
+    async fn test_sender_no_pending_txs() {
+        let sender = address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d");
+        let (cs, mut provider, entry_point, fee_estimator) = create_base_config();
+        provider.expect_get_transaction_count().returning(|_| Ok(3));
+        provider
+            .expect_get_pending_transaction_count()
+            .returning(|_| Ok(3));
+        let prechecker = PrecheckerImpl::<UserOperation, _, _, _>::new(
+            cs,
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        assert!(!prechecker.sender_has_pending_txs(sender).await);
+    }
+
+    #[tokio::test]
+    async fn test_sender_pending_txs_best_effort() {
+        let sender = address!("3f8a2b6c4d5e1079286fa1b3c0d4e5f6902b7c8d");
+        let (cs, mut provider, entry_point, fee_estimator) = create_base_config();
+        provider.expect_get_transaction_count().returning(|_| Ok(3));
+        provider
+            .expect_get_pending_transaction_count()
+            .returning(|_| Err(ProviderError::Other(anyhow::anyhow!("unsupported"))));
+        let prechecker = PrecheckerImpl::<UserOperation, _, _, _>::new(
+            cs,
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        assert!(!prechecker.sender_has_pending_txs(sender).await);
+    }
 }
@@ -59,6 +59,9 @@ pub(crate) struct Phase {
     pub(crate) ran_out_of_gas: bool,
     pub(crate) undeployed_contract_accesses: Vec<Address>,
     pub(crate) ext_code_access_info: HashMap<Address, Opcode>,
+    /// Counts of the opcodes executed immediately after each `GAS` opcode, by contract
+    #[serde(default)]
+    pub(crate) gas_followed_by: HashMap<Address, HashMap<Opcode, u64>>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -169,6 +172,19 @@ pub(crate) fn is_staked(info: StakeInfo, sim_settings: &Settings) -> bool {
         && info.unstake_delay_sec >= sim_settings.min_unstake_delay
 }
 
+/// Returns true if the `GAS` opcode was used anywhere other than immediately before a
+/// `*CALL`, given the counts of the opcodes executed after it.
+///
+/// [OP-012] - `GAS` is only allowed when used to forward gas to a call.
+pub(crate) fn uses_gas_outside_call_context(gas_followed_by: &HashMap<Opcode, u64>) -> bool {
+    gas_followed_by.keys().any(|next| {
+        !matches!(
+            next,
+            Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL
+        )
+    })
+}
+
 pub(crate) fn parse_combined_context_str<A, B>(combined: &str) -> anyhow::Result<(A, B)>
 where
     A: std::str::FromStr,
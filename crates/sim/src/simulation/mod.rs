@@ -41,7 +41,7 @@ pub mod v0_6;
 /// Entry Point v0.7 Tracing
 pub mod v0_7;
 
-use crate::{ExpectedStorage, ViolationError};
+use crate::{ExpectedStorage, StorageWrites, ViolationError};
 
 /// The result of a successful simulation
 #[derive(Clone, Debug, Default)]
@@ -67,6 +67,8 @@ pub struct SimulationResult {
     pub associated_addresses: HashSet<Address>,
     /// Expected storage values for all accessed slots during validation
     pub expected_storage: ExpectedStorage,
+    /// Storage slots written during validation
+    pub storage_writes: StorageWrites,
     /// Whether the operation requires a post-op
     pub requires_post_op: bool,
     /// All the entities used in this operation and their staking state
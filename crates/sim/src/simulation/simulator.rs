@@ -42,7 +42,7 @@ use crate::{
         v0_7::ValidationContextProvider as ValidationContextProviderV0_7,
         Settings, Simulator,
     },
-    types::ViolationError,
+    types::{StorageWrites, ViolationError},
     SimulationError, SimulationResult,
 };
 
@@ -523,6 +523,15 @@ where
             ..
         } = return_info;
 
+        let mut storage_writes = StorageWrites::default();
+        for phase in &tracer_out.phases {
+            for (address, access_info) in &phase.storage_accesses {
+                for slot in access_info.writes.keys() {
+                    storage_writes.insert(*address, *slot);
+                }
+            }
+        }
+
         // Conduct any stake overrides before assigning entity_infos
         override_infos_staked(&mut context.entity_infos, &self.allow_unstaked_addresses);
 
@@ -536,6 +545,7 @@ where
             accessed_addresses,
             associated_addresses,
             expected_storage: tracer_out.expected_storage,
+            storage_writes,
             requires_post_op: !paymaster_context.is_empty(),
             entity_infos: context.entity_infos,
         })
@@ -768,6 +778,7 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -778,6 +789,7 @@ mod tests {
                     storage_accesses:  HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -788,6 +800,7 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 }
             ],
             revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
@@ -1081,6 +1094,39 @@ mod tests {
         assert!(res.unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_sender_storage_access_during_deploy() {
+        let (provider, mut ep, mut context_provider) = create_base_config();
+        ep.expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let sender_address = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let sender_bytes = U256::from_be_bytes(sender_address.into_word().into());
+        // an associated slot and an arbitrary slot in the to-be-deployed sender
+        let access_info = AccessInfo {
+            reads: HashMap::from([(U256::from(1), U256::ZERO)]),
+            writes: HashMap::from([(sender_bytes, 1), (U256::from(2), 1)]),
+        };
+
+        let mut context = get_test_context();
+        assert!(context.has_factory);
+        assert!(!context.entity_infos.factory.as_ref().unwrap().is_staked);
+        // [STO-010] - the factory initializing the sender and the sender itself are both
+        // allowed to access the sender's own storage without stake
+        for phase in &mut context.tracer_out.phases[..2] {
+            phase
+                .storage_accesses
+                .insert(sender_address, access_info.clone());
+        }
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+        assert!(res.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_paymaster_access_during_deploy() {
         let (provider, mut ep, mut context_provider) = create_base_config();
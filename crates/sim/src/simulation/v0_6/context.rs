@@ -19,7 +19,7 @@ use anyhow::Context;
 use rundler_contracts::v0_6::IEntryPoint::FailedOp;
 use rundler_provider::{BlockId, EvmProvider, SimulationProvider};
 use rundler_types::{
-    pool::SimulationViolation, v0_6::UserOperation, EntityType,
+    pool::SimulationViolation, v0_6::UserOperation, EntityType, Opcode,
     UserOperation as UserOperationTrait, ValidationOutput,
 };
 
@@ -56,10 +56,21 @@ where
         let factory_address = op.factory();
         let sender_address = op.sender;
         let paymaster_address = op.paymaster();
-        let tracer_out = self
+        let mut tracer_out = self
             .simulate_validation_tracer
             .trace_simulate_validation(op.clone(), block_id)
             .await?;
+        // [OP-012] - the tracer records the opcodes following `GAS`, whether they are allowed
+        // is decided here
+        for phase in &mut tracer_out.phases {
+            for (address, gas_followed_by) in &phase.gas_followed_by {
+                if sim_context::uses_gas_outside_call_context(gas_followed_by) {
+                    phase
+                        .forbidden_opcodes_used
+                        .push(format!("{address}:{}", Opcode::GAS));
+                }
+            }
+        }
         let num_phases = tracer_out.phases.len() as u32;
         // Check if there are too many phases here, then check too few at the
         // end. We are detecting cases where the entry point is broken. Too many
@@ -203,7 +214,6 @@ mod tests {
     use rundler_types::{
         chain::ChainSpec,
         v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
-        Opcode,
     };
     use sim_context::ContractInfo;
 
@@ -270,6 +280,7 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -280,6 +291,7 @@ mod tests {
                     storage_accesses:  HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -290,6 +302,7 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_followed_by: HashMap::new(),
                 }
             ],
             revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
@@ -360,4 +373,60 @@ mod tests {
             )
         ));
     }
+
+    #[tokio::test]
+    async fn test_gas_opcode_applied_contextually() {
+        let mut tracer = MockTracer::new();
+
+        tracer.expect_trace_simulate_validation().returning(|_, _| {
+            let mut tracer_output = get_test_tracer_output();
+            tracer_output.phases[1].gas_followed_by = HashMap::from([
+                (
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    HashMap::from([(Opcode::CALL, 2), (Opcode::STATICCALL, 1)]),
+                ),
+                (
+                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    HashMap::from([(Opcode::CALL, 1), (Opcode::SSTORE, 1)]),
+                ),
+            ]);
+            Ok(tracer_output)
+        });
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: Default::default(),
+        };
+
+        let user_operation = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                nonce: U256::ZERO,
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: 0,
+                verification_gas_limit: 0,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 0,
+                max_priority_fee_per_gas: 0,
+                paymaster_and_data: Bytes::default(),
+                signature: Bytes::default(),
+            },
+        )
+        .build();
+
+        let context = context
+            .get_context(user_operation, BlockId::Number(0.into()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            context.tracer_out.phases[1].forbidden_opcodes_used,
+            vec![format!(
+                "{}:GAS",
+                address!("8abb13360b87be5eeb1b98647a016add927a136c")
+            )]
+        );
+    }
 }
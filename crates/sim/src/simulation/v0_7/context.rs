@@ -29,7 +29,7 @@ use rundler_types::{
 
 use super::tracer::{
     CallInfo, ExitType, MethodInfo, SimulateValidationTracer, SimulateValidationTracerImpl,
-    TopLevelCallInfo, TracerOutput,
+    TopLevelCallInfo, TracerOutput, ValidationPhase,
 };
 use crate::{
     simulation::context::{
@@ -42,9 +42,8 @@ use crate::{
 
 // Banned opcodes
 //
-// Some banned opcodes (i.e. CREATE2) have special handling and aren't on this list.
+// Some banned opcodes (i.e. CREATE2, GAS) have special handling and aren't on this list.
 const BANNED_OPCODES: &[Opcode] = &[
-    Opcode::GAS,
     Opcode::GASPRICE,
     Opcode::GASLIMIT,
     Opcode::DIFFICULTY,
@@ -127,6 +126,11 @@ where
             &self.sim_settings,
         );
 
+        let top_level_phases = tracer_out
+            .calls_from_entry_point
+            .iter()
+            .map(Self::top_level_call_phase)
+            .collect::<Vec<_>>();
         let mut tracer_out = self.parse_tracer_out(&op, tracer_out)?;
 
         // Check the call stack for calls with value or to the entry point
@@ -146,13 +150,13 @@ where
                 }
 
                 // [OP-054] all other calls to entry point are banned
-                let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
+                let phase = Self::get_call_phase(&call_stack, i, &top_level_phases, &entity_infos);
                 tracer_out.phases[phase].called_banned_entry_point_method = true;
             }
 
             // [OP-061] calls with value are banned, except for the calls above
             if call.value.is_some_and(|v| v != U256::ZERO) {
-                let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
+                let phase = Self::get_call_phase(&call_stack, i, &top_level_phases, &entity_infos);
                 tracer_out.phases[phase].called_non_entry_point_with_value = true;
             }
         }
@@ -203,6 +207,7 @@ struct CallWithResult {
     gas_used: u64,
     exit_type: ExitType,
     exit_data: String,
+    top_level_call_index: Option<usize>,
 }
 
 impl<T> ValidationContextProvider<T> {
@@ -226,6 +231,7 @@ impl<T> ValidationContextProvider<T> {
                         gas_used: exit_info.gas_used,
                         exit_type: exit_info.exit_type,
                         exit_data: exit_info.data,
+                        top_level_call_index: method_info.top_level_call_index,
                     });
                 }
                 CallInfo::Method(info) => {
@@ -247,6 +253,7 @@ impl<T> ValidationContextProvider<T> {
                     gas_used: exit_info.gas_used,
                     exit_type: exit_info.exit_type,
                     exit_data: exit_info.data,
+                    top_level_call_index: None,
                 });
             }
             CallInfo::Method(info) => {
@@ -289,10 +296,8 @@ impl<T> ValidationContextProvider<T> {
         let mut factory_called_create2_twice = false;
 
         // Check factory
-        if let Some(call_from_entry_point) = tracer_out
-            .calls_from_entry_point
-            .iter()
-            .find(|c| c.top_level_method_sig == CREATE_SENDER_METHOD)
+        if let Some(call_from_entry_point) =
+            Self::find_phase_call(&tracer_out.calls_from_entry_point, 0)
         {
             phases[0] = Self::parse_call_to_phase(call_from_entry_point, EntityType::Factory);
             // [OP-031] - create call can only be called once
@@ -304,19 +309,15 @@ impl<T> ValidationContextProvider<T> {
         }
 
         // Check account
-        if let Some(call_from_entry_point) = tracer_out
-            .calls_from_entry_point
-            .iter()
-            .find(|c| c.top_level_method_sig == VALIDATE_USER_OP_METHOD)
+        if let Some(call_from_entry_point) =
+            Self::find_phase_call(&tracer_out.calls_from_entry_point, 1)
         {
             phases[1] = Self::parse_call_to_phase(call_from_entry_point, EntityType::Account);
         }
 
         // Check paymaster
-        if let Some(call_from_entry_point) = tracer_out
-            .calls_from_entry_point
-            .iter()
-            .find(|c| c.top_level_method_sig == VALIDATE_PAYMASTER_USER_OP_METHOD)
+        if let Some(call_from_entry_point) =
+            Self::find_phase_call(&tracer_out.calls_from_entry_point, 2)
         {
             phases[2] = Self::parse_call_to_phase(call_from_entry_point, EntityType::Paymaster);
         }
@@ -361,9 +362,36 @@ impl<T> ValidationContextProvider<T> {
         })
     }
 
+    /// Returns the index of the validation phase that a top-level call from the entry point
+    /// belongs to, or `None` if the call is not part of an entity's validation.
+    ///
+    /// Uses the phase reported by the tracer, falling back to the called method for tracer
+    /// output that doesn't include it.
+    fn top_level_call_phase(call: &TopLevelCallInfo) -> Option<usize> {
+        let phase = call
+            .phase
+            .unwrap_or_else(|| match call.top_level_method_sig.as_str() {
+                CREATE_SENDER_METHOD => ValidationPhase::Factory,
+                VALIDATE_USER_OP_METHOD => ValidationPhase::Account,
+                VALIDATE_PAYMASTER_USER_OP_METHOD => ValidationPhase::Paymaster,
+                _ => ValidationPhase::Unknown,
+            });
+        match phase {
+            ValidationPhase::Factory => Some(0),
+            ValidationPhase::Account => Some(1),
+            ValidationPhase::Paymaster => Some(2),
+            ValidationPhase::Unknown => None,
+        }
+    }
+
+    fn find_phase_call(calls: &[TopLevelCallInfo], phase: usize) -> Option<&TopLevelCallInfo> {
+        calls
+            .iter()
+            .find(|c| Self::top_level_call_phase(c) == Some(phase))
+    }
+
     fn parse_call_to_phase(call: &TopLevelCallInfo, entity_type: EntityType) -> Phase {
         // [OP-011] - banned opcodes
-        // [OP-012] - tracer will not add GAS to list if followed by *CALL
         let mut forbidden_opcodes_used = vec![];
         for opcode in call.opcodes.keys() {
             if BANNED_OPCODES.contains(opcode)
@@ -374,6 +402,13 @@ impl<T> ValidationContextProvider<T> {
                     .push(format!("{}:{}", call.top_level_target_address, opcode));
             }
         }
+        if sim_context::uses_gas_outside_call_context(&call.gas_followed_by) {
+            forbidden_opcodes_used.push(format!(
+                "{}:{}",
+                call.top_level_target_address,
+                Opcode::GAS
+            ));
+        }
 
         let storage_accesses = call
             .access
@@ -417,6 +452,7 @@ impl<T> ValidationContextProvider<T> {
             ran_out_of_gas: call.oog.unwrap_or(false),
             undeployed_contract_accesses,
             ext_code_access_info: call.ext_code_access_info.clone(),
+            gas_followed_by: HashMap::new(), // applied to forbidden_opcodes_used above
         }
     }
 
@@ -434,6 +470,20 @@ impl<T> ValidationContextProvider<T> {
         Ok(())
     }
 
+    /// Attributes the call at index `i` of the call stack to a validation phase, using the
+    /// top-level call it was made under when the tracer reports it.
+    fn get_call_phase(
+        calls: &[CallWithResult],
+        i: usize,
+        top_level_phases: &[Option<usize>],
+        entities: &EntityInfos,
+    ) -> usize {
+        calls[i]
+            .top_level_call_index
+            .and_then(|index| top_level_phases.get(index).copied().flatten())
+            .unwrap_or_else(|| Self::get_nearest_entity_phase(&calls[i..], entities))
+    }
+
     fn get_nearest_entity_phase(calls: &[CallWithResult], entities: &EntityInfos) -> usize {
         // Call stack is ordered in order in which calls complete.
         // To attribute a particular call to an entity, scan from that call forward until
@@ -474,3 +524,201 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::{
+        chain::ChainSpec,
+        v0_7::{UserOperationBuilder, UserOperationRequiredFields},
+    };
+
+    use super::*;
+
+    const SENDER: Address = address!("1111111111111111111111111111111111111111");
+    const PAYMASTER: Address = address!("2222222222222222222222222222222222222222");
+
+    fn call_info(gas_followed_by: serde_json::Value) -> TopLevelCallInfo {
+        serde_json::from_value(serde_json::json!({
+            "topLevelMethodSig": VALIDATE_USER_OP_METHOD,
+            "topLevelTargetAddress": "0x1111111111111111111111111111111111111111",
+            "opcodes": { "SLOAD": 1 },
+            "gasFollowedBy": gas_followed_by,
+            "access": {},
+            "contractInfo": {},
+            "extCodeAccessInfo": {},
+        }))
+        .unwrap()
+    }
+
+    fn forbidden_opcodes(call: &TopLevelCallInfo) -> Vec<String> {
+        ValidationContextProvider::<()>::parse_call_to_phase(call, EntityType::Account)
+            .forbidden_opcodes_used
+    }
+
+    #[test]
+    fn test_gas_before_call_allowed() {
+        let call = call_info(serde_json::json!({
+            "CALL": 2,
+            "DELEGATECALL": 1,
+            "STATICCALL": 1,
+        }));
+        assert!(forbidden_opcodes(&call).is_empty());
+    }
+
+    #[test]
+    fn test_gas_outside_call_forbidden() {
+        let call = call_info(serde_json::json!({ "CALL": 1, "SSTORE": 1 }));
+        assert_eq!(
+            forbidden_opcodes(&call),
+            vec!["0x1111111111111111111111111111111111111111:GAS".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_gas_followed_by_defaults_to_empty() {
+        let value = serde_json::json!({
+            "topLevelMethodSig": VALIDATE_USER_OP_METHOD,
+            "topLevelTargetAddress": "0x1111111111111111111111111111111111111111",
+            "opcodes": {},
+            "access": {},
+            "contractInfo": {},
+            "extCodeAccessInfo": {},
+        });
+        let call: TopLevelCallInfo = serde_json::from_value(value).unwrap();
+        assert!(call.gas_followed_by.is_empty());
+        assert!(forbidden_opcodes(&call).is_empty());
+    }
+
+    fn context_provider() -> ValidationContextProvider<()> {
+        ValidationContextProvider {
+            simulate_validation_tracer: (),
+            sim_settings: SimulationSettings::default(),
+            entry_point_address: Address::ZERO,
+        }
+    }
+
+    fn op_with_paymaster() -> UserOperation {
+        UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender: SENDER,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 50_000,
+                max_priority_fee_per_gas: 1,
+                max_fee_per_gas: 1,
+                signature: Bytes::new(),
+            },
+        )
+        .paymaster(PAYMASTER, 100_000, 0, Bytes::new())
+        .build()
+    }
+
+    fn top_level_call(method_sig: &str, phase: Option<&str>, target: Address) -> serde_json::Value {
+        serde_json::json!({
+            "topLevelMethodSig": method_sig,
+            "topLevelTargetAddress": target,
+            "phase": phase,
+            "opcodes": {},
+            "access": {
+                (target.to_string()): { "reads": { "0x1": "0x2" }, "writes": {} },
+            },
+            "contractInfo": {},
+            "extCodeAccessInfo": {},
+        })
+    }
+
+    fn tracer_output(calls_from_entry_point: Vec<serde_json::Value>) -> TracerOutput {
+        serde_json::from_value(serde_json::json!({
+            "callsFromEntryPoint": calls_from_entry_point,
+            "keccak": [],
+            "calls": [],
+            "expectedStorage": {},
+            "logs": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_paymaster_access_attributed_to_paymaster_phase() {
+        let tracer_out = tracer_output(vec![
+            top_level_call(VALIDATE_USER_OP_METHOD, Some("account"), SENDER),
+            top_level_call(
+                VALIDATE_PAYMASTER_USER_OP_METHOD,
+                Some("paymaster"),
+                PAYMASTER,
+            ),
+        ]);
+
+        let out = context_provider()
+            .parse_tracer_out(&op_with_paymaster(), tracer_out)
+            .unwrap();
+        assert!(out.phases[0].storage_accesses.is_empty());
+        assert_eq!(
+            out.phases[1].storage_accesses.keys().collect::<Vec<_>>(),
+            vec![&SENDER]
+        );
+        assert_eq!(
+            out.phases[2].storage_accesses.keys().collect::<Vec<_>>(),
+            vec![&PAYMASTER]
+        );
+    }
+
+    #[test]
+    fn test_phase_falls_back_to_method_sig() {
+        let tracer_out = tracer_output(vec![top_level_call(
+            VALIDATE_PAYMASTER_USER_OP_METHOD,
+            None,
+            PAYMASTER,
+        )]);
+
+        let out = context_provider()
+            .parse_tracer_out(&op_with_paymaster(), tracer_out)
+            .unwrap();
+        assert!(out.phases[1].storage_accesses.is_empty());
+        assert!(out.phases[2].storage_accesses.contains_key(&PAYMASTER));
+    }
+
+    #[test]
+    fn test_call_in_paymaster_phase_attributed_by_top_level_call() {
+        let call = |top_level_call_index| CallWithResult {
+            call_type: Opcode::CALL,
+            method: "0x".to_string(),
+            // not an entity, so the nearest entity heuristic would pick the account
+            to: address!("3333333333333333333333333333333333333333"),
+            from: PAYMASTER,
+            value: Some(U256::from(1)),
+            gas: 0,
+            gas_used: 0,
+            exit_type: ExitType::Return,
+            exit_data: "0x".to_string(),
+            top_level_call_index,
+        };
+        let top_level_phases = vec![Some(1), Some(2)];
+        let entities = EntityInfos::default();
+
+        let calls = vec![call(Some(1))];
+        assert_eq!(
+            ValidationContextProvider::<()>::get_call_phase(
+                &calls,
+                0,
+                &top_level_phases,
+                &entities
+            ),
+            2
+        );
+
+        let calls = vec![call(None)];
+        assert_eq!(
+            ValidationContextProvider::<()>::get_call_phase(
+                &calls,
+                0,
+                &top_level_phases,
+                &entities
+            ),
+            1
+        );
+    }
+}
@@ -41,13 +41,29 @@ pub(super) struct TracerOutput {
 pub(super) struct TopLevelCallInfo {
     pub(super) top_level_method_sig: String,
     pub(super) top_level_target_address: String,
+    /// Validation phase this call belongs to, absent in output from older tracers
+    #[serde(default)]
+    pub(super) phase: Option<ValidationPhase>,
     pub(super) opcodes: HashMap<Opcode, u64>,
+    /// Counts of the opcodes executed immediately after each `GAS` opcode
+    #[serde(default)]
+    pub(super) gas_followed_by: HashMap<Opcode, u64>,
     pub(super) access: HashMap<Address, AccessInfo>,
     pub(super) contract_info: HashMap<Address, ContractInfo>,
     pub(super) ext_code_access_info: HashMap<Address, Opcode>,
     pub(super) oog: Option<bool>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(super) enum ValidationPhase {
+    Factory,
+    Account,
+    Paymaster,
+    #[serde(other)]
+    Unknown,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct AccessInfo {
@@ -87,6 +103,9 @@ pub(super) struct MethodInfo {
     pub(super) method: String,
     pub(super) value: Option<U256>,
     pub(super) gas: u64,
+    /// Index into `calls_from_entry_point` of the top-level call this call was made under
+    #[serde(rename = "topLevelCallIndex", default)]
+    pub(super) top_level_call_index: Option<usize>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
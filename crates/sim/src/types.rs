@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::collections::{btree_map, BTreeMap};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
 
 use alloy_primitives::{Address, B256, U256};
 use anyhow::bail;
@@ -56,6 +56,24 @@ impl ExpectedStorage {
     }
 }
 
+/// The storage slots written by a user operation during validation.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StorageWrites(pub BTreeMap<Address, BTreeSet<B256>>);
+
+impl StorageWrites {
+    /// Insert a written storage slot for a given address.
+    pub fn insert(&mut self, address: Address, slot: U256) {
+        self.0.entry(address).or_default().insert(B256::from(slot));
+    }
+
+    /// Returns true if the given storage slot was written.
+    pub fn contains(&self, address: Address, slot: B256) -> bool {
+        self.0
+            .get(&address)
+            .map_or(false, |slots| slots.contains(&slot))
+    }
+}
+
 use std::fmt::{Display, Formatter};
 
 /// An error that occurs when a user operation violates a spec rule.
@@ -37,6 +37,7 @@ interface Phase {
   ranOutOfGas: boolean;
   undeployedContractAccesses: string[];
   extCodeAccessInfo: Record<string, string>;
+  gasFollowedBy: Record<string, Record<string, number>>;
 }
 
 interface AccessInfo {
@@ -150,6 +151,7 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas: false,
       undeployedContractAccesses: {},
       extCodeAccessInfo: {},
+      gasFollowedBy: {},
     };
   }
 
@@ -159,6 +161,7 @@ type StringSet = Record<string, boolean | undefined>;
       calledNonEntryPointWithValue,
       ranOutOfGas,
       extCodeAccessInfo,
+      gasFollowedBy,
     } = currentPhase;
     const forbiddenOpcodesUsed = Object.keys(currentPhase.forbiddenOpcodesUsed);
     const forbiddenPrecompilesUsed = Object.keys(
@@ -181,6 +184,7 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas,
       undeployedContractAccesses,
       extCodeAccessInfo,
+      gasFollowedBy,
     };
     phases.push(phase);
     currentPhase = newInternalPhase();
@@ -284,12 +288,17 @@ type StringSet = Record<string, boolean | undefined>;
           revertData = toHex(log.memory.slice(offset, offset + length));
         }
       } else {
-        // The entry point is allowed to freely call `GAS`, but otherwise we
-        // require that a call opcode comes next.
-        if (last?.opcode === "GAS" && !CALL_OPCODES[opcode]) {
-          currentPhase.forbiddenOpcodesUsed[
-            getContractCombinedKey(log, "GAS")
-          ] = true;
+        // The entry point is allowed to freely call `GAS`, otherwise record the
+        // opcode following it, whether it is allowed is decided when parsing the trace.
+        if (last?.opcode === "GAS") {
+          countSlot(
+            computeIfAbsent(
+              currentPhase.gasFollowedBy,
+              toHex(log.contract.getAddress()),
+              (): Record<string, number> => ({})
+            ),
+            opcode
+          );
         }
 
         if (FORBIDDEN_OPCODES[opcode]) {
@@ -72,6 +72,11 @@ export interface MethodInfo {
   method: string
   value: any
   gas: number
+  /**
+   * index into callsFromEntryPoint of the top-level call this call was made under,
+   * undefined if made before the first top-level call
+   */
+  topLevelCallIndex?: number
 }
 
 export interface ExitInfo {
@@ -80,10 +85,17 @@ export interface ExitInfo {
   data: string
 }
 
+/**
+ * validation phase of a top-level call, derived from the entry point's call structure
+ */
+export type ValidationPhase = 'factory' | 'account' | 'paymaster' | 'unknown'
+
 export interface TopLevelCallInfo {
   topLevelMethodSig: string
   topLevelTargetAddress: string
+  phase: ValidationPhase
   opcodes: { [opcode: string]: number }
+  gasFollowedBy: { [opcode: string]: number }
   access: { [address: string]: AccessInfo }
   contractInfo: { [addr: string]: ContractInfo }
   extCodeAccessInfo: { [addr: string]: string }
@@ -133,6 +145,7 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
   currentLevel: TopLevelCallInfo
   topLevelCallCounter: number
   allStorageAccesses: Record<string, Record<string, string | null>>
+  phaseFromMethodSig: (methodSig: string) => ValidationPhase
   countSlot: (list: { [key: string]: number | undefined }, key: any) => void
   computeIfAbsent<K extends keyof any, V>(
     map: Record<K, V>,
@@ -215,7 +228,8 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
         to: toHex(frame.getTo()),
         method: toHex(frame.getInput()).slice(0, 10),
         gas: frame.getGas(),
-        value: frame.getValue()
+        value: frame.getValue(),
+        topLevelCallIndex: this.topLevelCallCounter > 0 ? this.topLevelCallCounter - 1 : undefined
       })
     },
     exit (frame: LogFrameResult): void {
@@ -229,6 +243,20 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       })
     },
 
+    // map the method called by the entry point to the validation phase it starts
+    phaseFromMethodSig (methodSig: string): ValidationPhase {
+      switch (methodSig) {
+        case '0x570e1a36': // createSender
+          return 'factory'
+        case '0x19822f7c': // validateUserOp
+          return 'account'
+        case '0x52b7512c': // validatePaymasterUserOp
+          return 'paymaster'
+        default:
+          return 'unknown'
+      }
+    },
+
     // increment the "key" in the list. if the key is not defined yet, then set it to "1"
     countSlot (list: { [key: string]: number | undefined }, key: any) {
       list[key] = (list[key] ?? 0) + 1
@@ -303,8 +331,10 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
           this.currentLevel = this.callsFromEntryPoint[this.topLevelCallCounter] = {
             topLevelMethodSig,
             topLevelTargetAddress,
+            phase: this.phaseFromMethodSig(topLevelMethodSig),
             access: {},
             opcodes: {},
+            gasFollowedBy: {},
             extCodeAccessInfo: {},
             contractInfo: {}
           }
@@ -362,9 +392,9 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       }
 
       // [OP-012]
-      if (this.lastOp === 'GAS' && !opcode.includes('CALL')) {
-        // count "GAS" opcode only if not followed by "CALL"
-        this.countSlot(this.currentLevel.opcodes, 'GAS')
+      if (this.lastOp === 'GAS') {
+        // record the opcode following "GAS", whether it is allowed is decided when parsing the trace
+        this.countSlot(this.currentLevel.gasFollowedBy, opcode)
       }
       if (opcode !== 'GAS') {
         // ignore "unimportant" opcodes:
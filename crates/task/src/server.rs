@@ -97,4 +97,9 @@ pub trait HealthCheck: Send + Sync + 'static {
 
     /// Check the status of the server.
     async fn status(&self) -> ServerStatus;
+
+    /// Check the status of the server, along with details to include in health responses.
+    async fn status_with_details(&self) -> (ServerStatus, Option<String>) {
+        (self.status().await, None)
+    }
 }
@@ -15,7 +15,10 @@ use alloy_primitives::{Address, B256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 
-use super::{error::BuilderError, types::BundlingMode};
+use super::{
+    error::BuilderError,
+    types::{BundleBuildReport, BundlingMode},
+};
 
 /// Builder result
 pub type BuilderResult<T> = std::result::Result<T, BuilderError>;
@@ -34,4 +37,10 @@ pub trait Builder: Send + Sync {
 
     /// Set the bundling mode
     async fn debug_set_bundling_mode(&self, mode: BundlingMode) -> BuilderResult<()>;
+
+    /// Get the report of the most recent bundle build attempt of each builder and entry
+    /// point, used for debugging.
+    ///
+    /// Builders that have not attempted a bundle yet have no report.
+    async fn debug_get_last_bundle_build_reports(&self) -> BuilderResult<Vec<BundleBuildReport>>;
 }
@@ -11,6 +11,9 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::collections::BTreeMap;
+
+use alloy_primitives::Address;
 use parse_display::Display;
 use serde::{Deserialize, Serialize};
 
@@ -28,3 +31,20 @@ pub enum BundlingMode {
     /// Bundles will be sent automatically.
     Auto,
 }
+
+/// Summary of a bundle build attempt, used for debugging why bundles are empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleBuildReport {
+    /// Index of the bundle builder that made the attempt
+    pub builder_index: u64,
+    /// Entry point the bundle was built for
+    pub entry_point: Address,
+    /// Number of operations from the mempool considered for the bundle
+    pub ops_considered: u64,
+    /// Number of operations skipped or rejected from the bundle, by reason
+    pub ops_skipped: BTreeMap<String, u64>,
+    /// Number of operations included in the bundle
+    pub ops_included: u64,
+    /// Error that ended the attempt early, if any
+    pub error: Option<String>,
+}
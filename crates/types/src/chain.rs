@@ -54,12 +54,20 @@ pub struct ChainSpec {
     /// Per user operation deploy gas cost overhead, to capture
     /// deploy costs that are not metered by the entry point
     pub per_user_op_deploy_overhead_gas: u64,
+    /// If set, the deploy overhead is only applied to user operations whose
+    /// ABI encoded size is above this many bytes
+    pub deploy_overhead_applies_above_bytes: Option<u64>,
     /// Gas cost for a user operation word in a bundle transaction
     pub per_user_op_word_gas: u64,
     /// Gas cost for a zero byte in calldata
     pub calldata_zero_byte_gas: u64,
     /// Gas cost for a non-zero byte in calldata
     pub calldata_non_zero_byte_gas: u64,
+    /// True if the EIP-7623 calldata floor cost is active (post-Pectra)
+    pub eip7623_calldata_floor_enabled: bool,
+    /// Latest hardfork active on the chain, selecting the rules used when executing
+    /// calls in a local EVM
+    pub evm_hardfork: EvmHardfork,
 
     /*
      * Gas estimation
@@ -131,6 +139,19 @@ pub enum PriorityFeeOracleType {
     UsageBased,
 }
 
+/// EVM hardfork rules
+#[derive(Clone, Copy, Debug, Deserialize, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EvmHardfork {
+    /// Shanghai rules
+    Shanghai,
+    /// Cancun rules
+    #[default]
+    Cancun,
+    /// Prague rules
+    Prague,
+}
+
 impl Default for ChainSpec {
     fn default() -> Self {
         Self {
@@ -143,9 +164,12 @@ impl Default for ChainSpec {
             per_user_op_v0_6_gas: 18_300,
             per_user_op_v0_7_gas: 19_500,
             per_user_op_deploy_overhead_gas: 0,
+            deploy_overhead_applies_above_bytes: None,
             per_user_op_word_gas: 4,
             calldata_zero_byte_gas: 4,
             calldata_non_zero_byte_gas: 16,
+            eip7623_calldata_floor_enabled: false,
+            evm_hardfork: EvmHardfork::default(),
             eip1559_enabled: true,
             da_pre_verification_gas: false,
             da_gas_oracle_type: DAGasOracleType::default(),
@@ -216,4 +240,13 @@ impl ChainSpec {
     pub fn per_user_op_deploy_overhead_gas(&self) -> u128 {
         self.per_user_op_deploy_overhead_gas as u128
     }
+
+    /// Get the deploy overhead gas for a user operation with a factory, given the
+    /// operation's ABI encoded size in bytes
+    pub fn deploy_overhead_gas(&self, op_size_bytes: usize) -> u128 {
+        match self.deploy_overhead_applies_above_bytes {
+            Some(threshold) if op_size_bytes as u64 <= threshold => 0,
+            _ => self.per_user_op_deploy_overhead_gas(),
+        }
+    }
 }
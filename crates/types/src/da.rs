@@ -26,10 +26,16 @@ pub enum DAGasOracleType {
     ArbitrumNitro,
     /// Optimism Bedrock type gas oracle
     OptimismBedrock,
+    /// Mantle Bedrock type gas oracle, converts the L1 fee to MNT using the oracle's
+    /// token ratio
+    MantleBedrock,
     /// Local Bedrock type gas oracle
     LocalBedrock,
     /// Cached Nitro type gas oracle
     CachedNitro,
+    /// Local blob type gas oracle, prices calldata against L1 blob gas using the blob base
+    /// fee reported by the gas oracle contract
+    LocalBlob,
 }
 
 /// Data associated with a user operation for Nitro DA gas calculations
@@ -49,6 +55,13 @@ pub struct BedrockDAGasUOData {
     pub uo_units: u64,
 }
 
+/// Data associated with a user operation for blob DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobDAGasUOData {
+    /// The L1 blob gas consumed by the user operation's data.
+    pub blob_gas: u64,
+}
+
 /// Data associated with a user operation for DA gas calculations
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum DAGasUOData {
@@ -59,6 +72,8 @@ pub enum DAGasUOData {
     Nitro(NitroDAGasUOData),
     /// Bedrock DA
     Bedrock(BedrockDAGasUOData),
+    /// Blob DA
+    Blob(BlobDAGasUOData),
 }
 
 /// Data associated with a block for DA gas calculations
@@ -71,6 +86,8 @@ pub enum DAGasBlockData {
     Nitro(NitroDAGasBlockData),
     /// Bedrock DA
     Bedrock(BedrockDAGasBlockData),
+    /// Blob DA
+    Blob(BlobDAGasBlockData),
 }
 
 /// Data associated with a block for Nitro DA gas calculations
@@ -94,3 +111,10 @@ pub struct BedrockDAGasBlockData {
     /// Blob base fee retrieved from the bedrock gas oracle.
     pub blob_base_fee: u64,
 }
+
+/// Data associated with a block for blob DA gas calculations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobDAGasBlockData {
+    /// L1 blob base fee retrieved from the gas oracle contract.
+    pub blob_base_fee: u128,
+}
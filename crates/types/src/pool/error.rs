@@ -100,12 +100,20 @@ pub enum MempoolError {
     /// Call gas limit efficiency too low
     #[error("Call gas limit efficiency too low. Required: {0}, Actual: {1}")]
     CallGasLimitEfficiencyTooLow(f32, f32),
+    /// Operation does not round-trip through its packed encoding
+    #[error("Operation does not round-trip through its packed encoding: {0}")]
+    PackedEncodingMismatch(String),
+    /// Operation calls a contract that is not on the call target allowlist
+    #[error("Operation call target {0} is not allowlisted")]
+    CallTargetNotAllowed(Address),
 }
 
 /// Precheck violation enumeration
 ///
 /// All possible errors that can be returned from a precheck.
-#[derive(Clone, Debug, parse_display::Display, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Clone, Debug, parse_display::Display, Eq, PartialEq, Ord, PartialOrd, strum::IntoStaticStr,
+)]
 pub enum PrecheckViolation {
     /// The sender is not deployed, and no init code is provided.
     #[display("sender {0:?} is not a contract and initCode is empty")]
@@ -120,9 +128,16 @@ pub enum PrecheckViolation {
     /// See `gas::user_operation_execution_gas_limit` for calculation.
     #[display("total gas limit is {0} but must be at most {1}")]
     TotalGasLimitTooHigh(u128, u128),
+    /// The sum of the pre-verification, verification, and call gas of the user operation
+    /// exceeds the configured ceiling.
+    #[display("total operation gas is {0} but must be at most {1}")]
+    TotalOpGasTooHigh(u128, u128),
     /// The verification gas limit of the user operation is too high.
     #[display("verificationGasLimit is {0} but must be at most {1}")]
     VerificationGasLimitTooHigh(u128, u128),
+    /// The paymaster portion of the verification gas limit of the user operation is too high.
+    #[display("paymaster verification gas limit is {0} but must be at most {1}")]
+    PaymasterVerificationGasLimitTooHigh(u128, u128),
     /// The pre-verification gas of the user operation is too low.
     #[display("preVerificationGas is {0} but must be at least {1}")]
     PreVerificationGasTooLow(u128, u128),
@@ -142,9 +157,16 @@ pub enum PrecheckViolation {
     /// The provided max fee per gas is too low based on the current network rate.
     #[display("maxFeePerGas is {0} but must be at least {1}")]
     MaxFeePerGasTooLow(u128, u128),
+    /// The provided max fee per gas and max priority fee per gas are both far above the
+    /// current network rate.
+    #[display("maxFeePerGas is {0} but must be at most {1}, as maxPriorityFeePerGas is also far above network fees")]
+    MaxFeePerGasTooHigh(u128, u128),
     /// The call gas limit is too low to account for any possible call.
     #[display("callGasLimit is {0} but must be at least {1}")]
     CallGasLimitTooLow(u128, u128),
+    /// The call data of the user operation is too large.
+    #[display("callData is {0} bytes but must be at most {1} bytes")]
+    CallDataTooLarge(usize, usize),
 }
 
 /// All possible simulation violations
@@ -20,7 +20,10 @@ use mockall::automock;
 
 use super::{
     error::PoolError,
-    types::{NewHead, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus},
+    types::{
+        NewHead, PaymasterMetadata, PoolOperation, PrecheckSettingsUpdate, Reputation,
+        ReputationStatus, StakeStatus,
+    },
 };
 use crate::{EntityUpdate, UserOperationId, UserOperationVariant};
 
@@ -37,6 +40,9 @@ pub trait Pool: Send + Sync {
     /// Add an operation to the pool
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256>;
 
+    /// Check if an operation would be accepted by the pool, without adding it
+    async fn check_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256>;
+
     /// Get operations from the pool
     async fn get_ops(
         &self,
@@ -50,6 +56,20 @@ pub trait Pool: Send + Sync {
     /// Returns None if the operation is not found
     async fn get_op_by_hash(&self, hash: B256) -> PoolResult<Option<PoolOperation>>;
 
+    /// Get an operation from the pool by id
+    /// Returns None if the operation is not found
+    async fn get_op_by_id(
+        &self,
+        entry_point: Address,
+        id: UserOperationId,
+    ) -> PoolResult<Option<PoolOperation>>;
+
+    /// Get the position of an operation in the bundling queue by hash, 0 being the next
+    /// operation to be bundled.
+    /// Checks each entry point in order until the operation is found
+    /// Returns None if the operation is not pending in the pool
+    async fn get_op_queue_position(&self, hash: B256) -> PoolResult<Option<u64>>;
+
     /// Remove operations from the pool by hash
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()>;
 
@@ -121,4 +141,12 @@ pub trait Pool: Send + Sync {
         paymaster: bool,
         reputation: bool,
     ) -> PoolResult<()>;
+
+    /// Updates the precheck settings used by the mempool for an entry point, returning the
+    /// effective settings after the update
+    async fn admin_set_precheck_settings(
+        &self,
+        entry_point: Address,
+        settings: PrecheckSettingsUpdate,
+    ) -> PoolResult<PrecheckSettingsUpdate>;
 }
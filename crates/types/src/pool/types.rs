@@ -26,6 +26,21 @@ pub struct NewHead {
     pub block_hash: B256,
     /// The number of the new head
     pub block_number: u64,
+    /// The user operations mined in the new head
+    pub mined_ops: Vec<MinedUserOperation>,
+}
+
+/// A user operation mined in a new head
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinedUserOperation {
+    /// The hash of the user operation
+    pub hash: B256,
+    /// The entry point the user operation was mined through
+    pub entry_point: Address,
+    /// The sender of the user operation
+    pub sender: Address,
+    /// The nonce of the user operation
+    pub nonce: U256,
 }
 
 /// The reputation of an entity
@@ -99,6 +114,41 @@ pub struct PaymasterMetadata {
     pub pending_balance: U256,
 }
 
+/// Precheck settings that can be changed on a running mempool.
+///
+/// When updating, unset fields are left unchanged. When returned from an update, all
+/// fields are set to the effective values.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct PrecheckSettingsUpdate {
+    /// Maximum verification gas allowed for a user operation
+    pub max_verification_gas: Option<u128>,
+    /// Maximum total execution gas allowed for a user operation
+    pub max_total_execution_gas: Option<u128>,
+    /// Percentage of the current network base fee that a user operation must have
+    pub base_fee_accept_percent: Option<u32>,
+    /// Percentage of the required preVerificationGas that a user operation must have
+    pub pre_verification_gas_accept_percent: Option<u32>,
+}
+
+impl PrecheckSettingsUpdate {
+    /// Check if the update is valid, returning the reason if it sets any field to zero
+    pub fn validate(&self) -> Option<String> {
+        if self.max_verification_gas == Some(0) {
+            return Some("max_verification_gas field must be non-zero".to_string());
+        }
+        if self.max_total_execution_gas == Some(0) {
+            return Some("max_total_execution_gas field must be non-zero".to_string());
+        }
+        if self.base_fee_accept_percent == Some(0) {
+            return Some("base_fee_accept_percent field must be non-zero".to_string());
+        }
+        if self.pre_verification_gas_accept_percent == Some(0) {
+            return Some("pre_verification_gas_accept_percent field must be non-zero".to_string());
+        }
+        None
+    }
+}
+
 /// A user operation with additional metadata from validation.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PoolOperation {
@@ -116,6 +166,11 @@ pub struct PoolOperation {
     pub sim_block_hash: B256,
     /// The block number simulation was completed at
     pub sim_block_number: u64,
+    /// Gas used by the operation's most recent validation, excluding its pre-verification gas
+    pub validation_gas_used: u128,
+    /// The block number of the operation's most recent validation, either on entering the
+    /// pool or on re-validation at a new block
+    pub validation_block_number: u64,
     /// Whether the account is staked.
     pub account_is_staked: bool,
     /// Staking information about all the entities.
@@ -89,6 +89,9 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Get the user operation calldata
     fn call_data(&self) -> &Bytes;
 
+    /// Get the user operation signature
+    fn signature(&self) -> &Bytes;
+
     /// Returns the call gas limit
     fn call_gas_limit(&self) -> u128;
 
@@ -104,6 +107,27 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Returns the maximum cost, in wei, of this user operation
     fn max_gas_cost(&self) -> U256;
 
+    /// Returns the maximum cost, in wei, of this user operation, or None if it overflows.
+    ///
+    /// Unlike `max_gas_cost`, the math is performed in `U256` so adversarially large gas
+    /// fields can't wrap.
+    fn checked_max_gas_cost(&self) -> Option<U256>;
+
+    /// Returns the prefund, in wei, that the entry point requires the account or paymaster
+    /// to have deposited for this user operation. Saturates at `U256::MAX`.
+    ///
+    /// This is the entry point's `_getRequiredPrefund`:
+    ///
+    /// v0.6: `maxFeePerGas * (callGasLimit + verificationGasLimit * mul + preVerificationGas)`
+    /// where `mul` is 3 if the op has a paymaster and 1 otherwise.
+    ///
+    /// v0.7: `maxFeePerGas * (verificationGasLimit + callGasLimit + paymasterVerificationGasLimit
+    /// + paymasterPostOpGasLimit + preVerificationGas)`
+    ///
+    /// If `include_paymaster_overhead` is false the paymaster's share is excluded, i.e. `mul`
+    /// is 1 for v0.6 and the paymaster gas limits are excluded for v0.7.
+    fn required_prefund(&self, include_paymaster_overhead: bool) -> U256;
+
     /// Returns the gas price for this UO given the base fee
     fn gas_price(&self, base_fee: u128) -> u128 {
         self.max_fee_per_gas()
@@ -120,6 +144,13 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// It does not include the signature field.
     fn hash(&self, entry_point: Address, chain_id: u64) -> B256;
 
+    /// Hash the fields of a user operation, without the entry point and chain ID.
+    ///
+    /// This is NOT the canonical on-chain user operation hash, see `hash` for that.
+    /// It is intended for off-chain indexing, e.g. correlating the same operation
+    /// across chains. It does not include the signature field.
+    fn content_hash(&self) -> B256;
+
     /// Get the user operation id
     fn id(&self) -> UserOperationId;
 
@@ -344,6 +375,13 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn content_hash(&self) -> B256 {
+        match self {
+            UserOperationVariant::V0_6(op) => op.content_hash(),
+            UserOperationVariant::V0_7(op) => op.content_hash(),
+        }
+    }
+
     fn id(&self) -> UserOperationId {
         match self {
             UserOperationVariant::V0_6(op) => op.id(),
@@ -386,6 +424,13 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn signature(&self) -> &Bytes {
+        match self {
+            UserOperationVariant::V0_6(op) => op.signature(),
+            UserOperationVariant::V0_7(op) => op.signature(),
+        }
+    }
+
     fn max_gas_cost(&self) -> U256 {
         match self {
             UserOperationVariant::V0_6(op) => op.max_gas_cost(),
@@ -393,6 +438,20 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn checked_max_gas_cost(&self) -> Option<U256> {
+        match self {
+            UserOperationVariant::V0_6(op) => op.checked_max_gas_cost(),
+            UserOperationVariant::V0_7(op) => op.checked_max_gas_cost(),
+        }
+    }
+
+    fn required_prefund(&self, include_paymaster_overhead: bool) -> U256 {
+        match self {
+            UserOperationVariant::V0_6(op) => op.required_prefund(include_paymaster_overhead),
+            UserOperationVariant::V0_7(op) => op.required_prefund(include_paymaster_overhead),
+        }
+    }
+
     fn entities(&'_ self) -> Vec<Entity> {
         match self {
             UserOperationVariant::V0_6(op) => op.entities(),
@@ -502,6 +561,58 @@ impl UserOperationVariant {
     }
 }
 
+/// Signature scheme of an account, used to sanity check the dummy signatures sent
+/// for gas estimation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum AccountType {
+    /// ECDSA over secp256k1, e.g. accounts owned by an EOA
+    #[default]
+    Ecdsa,
+    /// WebAuthn passkey, a P-256 signature along with WebAuthn authenticator data
+    Passkey,
+}
+
+impl AccountType {
+    /// Minimum plausible signature length in bytes for this account type
+    pub fn min_signature_len(&self) -> usize {
+        match self {
+            // r, s and v
+            AccountType::Ecdsa => 65,
+            // r, s and the minimum 37 bytes of authenticator data, before any client data
+            AccountType::Passkey => 101,
+        }
+    }
+}
+
+/// Non-fatal diagnostic about a user operation sent for gas estimation
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Warning {
+    /// The dummy signature is shorter than a real signature would be, so the
+    /// calldata cost of the signature will be underestimated
+    #[error("dummy signature is {actual} bytes, shorter than the {expected} bytes expected for a {account_type:?} account, pre-verification gas may be underestimated")]
+    ShortDummySignature {
+        /// Account type the signature was checked against
+        account_type: AccountType,
+        /// Length of the dummy signature
+        actual: usize,
+        /// Minimum expected length for the account type
+        expected: usize,
+    },
+}
+
+fn validate_dummy_signature(signature: &Bytes, account_type: AccountType) -> Result<(), Warning> {
+    let expected = account_type.min_signature_len();
+    if signature.len() < expected {
+        return Err(Warning::ShortDummySignature {
+            account_type,
+            actual: signature.len(),
+            expected,
+        });
+    }
+    Ok(())
+}
+
 /// User operation optional gas enum
 #[derive(Debug, Clone)]
 pub enum UserOperationOptionalGas {
@@ -520,6 +631,71 @@ impl UserOperationOptionalGas {
         };
         abi_size + BUNDLE_BYTE_OVERHEAD + USER_OP_OFFSET_WORD_SIZE
     }
+
+    /// Returns the hash of the user operation as requested for gas estimation, with any
+    /// unset gas and fee fields set to zero
+    pub fn hash(&self, entry_point: Address, chain_spec: &ChainSpec) -> B256 {
+        match self {
+            UserOperationOptionalGas::V0_6(op) => op
+                .clone()
+                .into_user_operation_builder(chain_spec, 0, 0)
+                .build()
+                .hash(entry_point, chain_spec.id),
+            UserOperationOptionalGas::V0_7(op) => op
+                .clone()
+                .into_user_operation_builder(chain_spec, 0, 0, 0)
+                .build()
+                .hash(entry_point, chain_spec.id),
+        }
+    }
+
+    /// Returns the id of the user operation
+    pub fn id(&self) -> UserOperationId {
+        match self {
+            UserOperationOptionalGas::V0_6(op) => UserOperationId {
+                sender: op.sender,
+                nonce: op.nonce,
+            },
+            UserOperationOptionalGas::V0_7(op) => UserOperationId {
+                sender: op.sender,
+                nonce: op.nonce,
+            },
+        }
+    }
+
+    /// Check that the dummy fields are plausibly sized for `account_type`
+    pub fn validate_dummy_fields(&self, account_type: AccountType) -> Result<(), Warning> {
+        match self {
+            UserOperationOptionalGas::V0_6(op) => op.validate_dummy_fields(account_type),
+            UserOperationOptionalGas::V0_7(op) => op.validate_dummy_fields(account_type),
+        }
+    }
+
+    /// Returns true if `op` is the same version and has the same fields as this operation,
+    /// ignoring the gas and fee fields
+    pub fn matches_ignoring_gas(&self, op: &UserOperationVariant) -> bool {
+        match (self, op) {
+            (UserOperationOptionalGas::V0_6(a), UserOperationVariant::V0_6(b)) => {
+                a.matches_ignoring_gas(b)
+            }
+            (UserOperationOptionalGas::V0_7(a), UserOperationVariant::V0_7(b)) => {
+                a.matches_ignoring_gas(b)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the user operation with its call data replaced by `call_data`
+    pub fn with_call_data(self, call_data: Bytes) -> Self {
+        match self {
+            UserOperationOptionalGas::V0_6(op) => {
+                UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas { call_data, ..op })
+            }
+            UserOperationOptionalGas::V0_7(op) => {
+                UserOperationOptionalGas::V0_7(v0_7::UserOperationOptionalGas { call_data, ..op })
+            }
+        }
+    }
 }
 
 /// Gas estimate
@@ -537,6 +713,54 @@ pub struct GasEstimate {
     ///
     /// v0.7: populated only if the user operation has a paymaster
     pub paymaster_verification_gas_limit: Option<u128>,
+    /// Number of the block the estimate was made against, for debugging
+    pub block_number: Option<u64>,
+    /// Breakdown of the buffers and clamps applied to each gas field, for debugging
+    pub breakdown: Option<GasEstimateBreakdown>,
+    /// Number of `eth_call` rounds taken by the verification gas binary search, for debugging
+    pub verification_gas_rounds: Option<u32>,
+    /// Number of `eth_call` rounds taken by the call gas binary search, for debugging
+    pub call_gas_rounds: Option<u32>,
+    /// Non-fatal diagnostics about the user operation sent for estimation
+    pub warnings: Vec<Warning>,
+}
+
+/// The stages of adjustment applied to an estimated gas value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasEstimateStages {
+    /// The value as measured by simulation
+    pub raw: u128,
+    /// The value after buffers and floors are applied
+    pub after_buffer: u128,
+    /// The value after clamping to the configured limits. This is the returned estimate.
+    pub after_clamp: u128,
+    /// The number of rounds taken to search for the raw value, if a search was performed
+    pub num_rounds: Option<u32>,
+}
+
+impl GasEstimateStages {
+    /// Stages for a value that is not adjusted, e.g. a value provided by the user
+    pub fn unadjusted(value: u128) -> Self {
+        Self {
+            raw: value,
+            after_buffer: value,
+            after_clamp: value,
+            num_rounds: None,
+        }
+    }
+}
+
+/// Breakdown of the adjustments applied to each field of a gas estimate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasEstimateBreakdown {
+    /// Pre verification gas stages
+    pub pre_verification_gas: GasEstimateStages,
+    /// Call gas limit stages
+    pub call_gas_limit: GasEstimateStages,
+    /// Verification gas limit stages
+    pub verification_gas_limit: GasEstimateStages,
+    /// Paymaster verification gas limit stages, if estimated
+    pub paymaster_verification_gas_limit: Option<GasEstimateStages>,
 }
 
 /// User operations per aggregator
@@ -550,11 +774,17 @@ pub struct UserOpsPerAggregator<UO: UserOperation> {
     pub signature: Bytes,
 }
 
+/// EIP-7623 floor cost per calldata token
+const EIP7623_FLOOR_COST_PER_TOKEN: u128 = 10;
+/// EIP-7623 number of tokens per non-zero calldata byte, a zero byte is a single token
+const EIP7623_TOKENS_PER_NON_ZERO_BYTE: u128 = 4;
+
 pub(crate) fn op_calldata_gas_cost<UO: SolValue>(
     uo: UO,
     zero_byte_cost: u128,
     non_zero_byte_cost: u128,
     per_word_cost: u128,
+    eip7623_floor_enabled: bool,
 ) -> u128 {
     let encoded_op = uo.abi_encode();
     let length_in_words: u128 = (encoded_op.len() as u128 + 31) >> 5; // ceil(encoded_op.len() / 32)
@@ -569,8 +799,33 @@ pub(crate) fn op_calldata_gas_cost<UO: SolValue>(
         })
         .reduce(|a, b| a + b)
         .unwrap_or_default();
+    let standard_cost = call_data_cost + per_word_cost * length_in_words;
+
+    if !eip7623_floor_enabled {
+        return standard_cost;
+    }
+
+    let tokens: u128 = encoded_op
+        .iter()
+        .map(|&x| {
+            if x == 0 {
+                1
+            } else {
+                EIP7623_TOKENS_PER_NON_ZERO_BYTE
+            }
+        })
+        .sum();
+    standard_cost.max(EIP7623_FLOOR_COST_PER_TOKEN * tokens)
+}
 
-    call_data_cost + per_word_cost * length_in_words
+/// Calldata gas of an aggregated signature of `length` bytes in a `handleAggregatedOps` bundle,
+/// including its aggregator address, offset and length words
+///
+/// Each operation of an aggregator is charged the full cost, as it may be the only operation of
+/// its aggregator in a bundle.
+pub fn aggregated_signature_gas(chain_spec: &ChainSpec, length: usize) -> u128 {
+    let encoded_len = 3 * 32 + length.next_multiple_of(32);
+    encoded_len as u128 * chain_spec.calldata_non_zero_byte_gas()
 }
 
 /// Calculates the size a byte array padded to the next largest multiple of 32
@@ -606,6 +861,142 @@ fn random_bytes(fill_size: usize) -> Bytes {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_optional_gas_hash_unset_fields() {
+        let chain_spec = ChainSpec::default();
+        let op = v0_6::UserOperationOptionalGas {
+            sender: Address::repeat_byte(1),
+            nonce: U256::ZERO,
+            init_code: Bytes::new(),
+            call_data: Bytes::from(vec![1; 4]),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        };
+        let zeroed = v0_6::UserOperationOptionalGas {
+            call_gas_limit: Some(0),
+            max_fee_per_gas: Some(0),
+            ..op.clone()
+        };
+        let with_gas = v0_6::UserOperationOptionalGas {
+            call_gas_limit: Some(100_000),
+            ..op.clone()
+        };
+
+        let hash = UserOperationOptionalGas::V0_6(op).hash(Address::ZERO, &chain_spec);
+        assert_eq!(
+            hash,
+            UserOperationOptionalGas::V0_6(zeroed).hash(Address::ZERO, &chain_spec)
+        );
+        assert_ne!(
+            hash,
+            UserOperationOptionalGas::V0_6(with_gas).hash(Address::ZERO, &chain_spec)
+        );
+    }
+
+    #[test]
+    fn test_aggregated_signature_gas() {
+        let chain_spec = ChainSpec::default();
+        assert_eq!(aggregated_signature_gas(&chain_spec, 0), 96 * 16);
+        assert_eq!(aggregated_signature_gas(&chain_spec, 48), 160 * 16);
+        assert_eq!(aggregated_signature_gas(&chain_spec, 96), 192 * 16);
+    }
+
+    fn optional_gas_ops_with_signature(signature_len: usize) -> [UserOperationOptionalGas; 2] {
+        let signature = Bytes::from(vec![1; signature_len]);
+        [
+            UserOperationOptionalGas::V0_6(v0_6::UserOperationOptionalGas {
+                sender: Address::repeat_byte(1),
+                nonce: U256::ZERO,
+                init_code: Bytes::new(),
+                call_data: Bytes::new(),
+                call_gas_limit: None,
+                verification_gas_limit: None,
+                pre_verification_gas: None,
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                paymaster_and_data: Bytes::new(),
+                signature: signature.clone(),
+            }),
+            UserOperationOptionalGas::V0_7(v0_7::UserOperationOptionalGas {
+                sender: Address::repeat_byte(1),
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                signature,
+                call_gas_limit: None,
+                verification_gas_limit: None,
+                pre_verification_gas: None,
+                max_priority_fee_per_gas: None,
+                max_fee_per_gas: None,
+                factory: None,
+                factory_data: Bytes::new(),
+                paymaster: None,
+                paymaster_verification_gas_limit: None,
+                paymaster_post_op_gas_limit: None,
+                paymaster_data: Bytes::new(),
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_validate_dummy_fields_ecdsa() {
+        for op in optional_gas_ops_with_signature(65) {
+            assert_eq!(op.validate_dummy_fields(AccountType::Ecdsa), Ok(()));
+        }
+        for len in [0, 64] {
+            for op in optional_gas_ops_with_signature(len) {
+                assert_eq!(
+                    op.validate_dummy_fields(AccountType::Ecdsa),
+                    Err(Warning::ShortDummySignature {
+                        account_type: AccountType::Ecdsa,
+                        actual: len,
+                        expected: 65,
+                    })
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_dummy_fields_passkey() {
+        for op in optional_gas_ops_with_signature(101) {
+            assert_eq!(op.validate_dummy_fields(AccountType::Passkey), Ok(()));
+        }
+        // long enough for ECDSA, but not for a passkey
+        for op in optional_gas_ops_with_signature(65) {
+            assert_eq!(
+                op.validate_dummy_fields(AccountType::Passkey),
+                Err(Warning::ShortDummySignature {
+                    account_type: AccountType::Passkey,
+                    actual: 65,
+                    expected: 101,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_ignoring_gas() {
+        let [optional_v0_6, optional_v0_7] = optional_gas_ops_with_signature(65);
+        let op = UserOperationVariant::V0_6(v0_6::UserOperation {
+            sender: Address::repeat_byte(1),
+            signature: Bytes::from(vec![1; 65]),
+            call_gas_limit: 100_000,
+            max_fee_per_gas: 1_000,
+            ..Default::default()
+        });
+
+        assert!(optional_v0_6.matches_ignoring_gas(&op));
+        assert!(!optional_v0_7.matches_ignoring_gas(&op));
+
+        let [optional_v0_6, _] = optional_gas_ops_with_signature(101);
+        assert!(!optional_v0_6.matches_ignoring_gas(&op));
+    }
+
     #[test]
     fn test_byte_array_abi_len() {
         let b = Bytes::from(vec![0u8; 32]);
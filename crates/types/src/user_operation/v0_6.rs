@@ -18,8 +18,8 @@ use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
 use super::{
-    random_bytes, random_bytes_array, UserOperation as UserOperationTrait, UserOperationId,
-    UserOperationVariant,
+    random_bytes, random_bytes_array, AccountType, UserOperation as UserOperationTrait,
+    UserOperationId, UserOperationVariant, Warning,
 };
 use crate::{
     chain::ChainSpec,
@@ -155,9 +155,8 @@ impl UserOperationTrait for UserOperation {
     }
 
     fn hash(&self, entry_point: Address, chain_id: u64) -> B256 {
-        let packed = UserOperationPackedForHash::from(self.clone());
         let encoded = UserOperationHashEncoded {
-            encodedHash: alloy_primitives::keccak256(packed.abi_encode()),
+            encodedHash: self.content_hash(),
             entryPoint: entry_point,
             chainId: U256::from(chain_id),
         };
@@ -165,6 +164,11 @@ impl UserOperationTrait for UserOperation {
         alloy_primitives::keccak256(encoded.abi_encode())
     }
 
+    fn content_hash(&self) -> B256 {
+        let packed = UserOperationPackedForHash::from(self.clone());
+        alloy_primitives::keccak256(packed.abi_encode())
+    }
+
     fn id(&self) -> UserOperationId {
         UserOperationId {
             sender: self.sender,
@@ -192,6 +196,10 @@ impl UserOperationTrait for UserOperation {
         &self.call_data
     }
 
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         let mul: u128 = if self.paymaster().is_some() { 3 } else { 1 };
         U256::from(
@@ -202,6 +210,27 @@ impl UserOperationTrait for UserOperation {
         )
     }
 
+    fn checked_max_gas_cost(&self) -> Option<U256> {
+        let mul = if self.paymaster().is_some() { 3 } else { 1 };
+        let gas = U256::from(self.pre_verification_gas)
+            + U256::from(self.call_gas_limit)
+            + U256::from(self.verification_gas_limit) * U256::from(mul);
+        U256::from(self.max_fee_per_gas).checked_mul(gas)
+    }
+
+    fn required_prefund(&self, include_paymaster_overhead: bool) -> U256 {
+        // EntryPoint v0.6 `_getRequiredPrefund`
+        let mul = if include_paymaster_overhead && self.paymaster().is_some() {
+            3
+        } else {
+            1
+        };
+        let required_gas = U256::from(self.call_gas_limit)
+            + U256::from(self.verification_gas_limit) * U256::from(mul)
+            + U256::from(self.pre_verification_gas);
+        required_gas.saturating_mul(U256::from(self.max_fee_per_gas))
+    }
+
     fn heap_size(&self) -> usize {
         self.init_code.len()
             + self.call_data.len()
@@ -253,9 +282,10 @@ impl UserOperationTrait for UserOperation {
             chain_spec.calldata_zero_byte_gas(),
             chain_spec.calldata_non_zero_byte_gas(),
             chain_spec.per_user_op_word_gas(),
+            chain_spec.eip7623_calldata_floor_enabled,
         ) + chain_spec.per_user_op_v0_6_gas()
             + (if self.factory().is_some() {
-                chain_spec.per_user_op_deploy_overhead_gas()
+                chain_spec.deploy_overhead_gas(self.abi_encoded_size())
             } else {
                 0
             })
@@ -292,7 +322,42 @@ impl From<UserOperation> for ContractUserOperation {
     }
 }
 
+/// Number of bits in the sequence portion of the nonce, the remaining high bits are the key
+const NONCE_SEQUENCE_BITS: usize = 64;
+
+/// Error returned when a nonce key does not fit in the 192 key bits of the nonce
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("nonce key {0:#x} exceeds 192 bits")]
+pub struct NonceKeyTooLarge(pub U256);
+
 impl UserOperation {
+    /// Returns the 192-bit key portion of the nonce
+    pub fn nonce_key(&self) -> U256 {
+        self.nonce >> NONCE_SEQUENCE_BITS
+    }
+
+    /// Returns the 64-bit sequence portion of the nonce
+    pub fn nonce_sequence(&self) -> u64 {
+        self.nonce.as_limbs()[0]
+    }
+
+    /// Returns the user operation with the 192-bit key portion of its nonce replaced by `key`,
+    /// keeping the 64-bit sequence.
+    ///
+    /// The cached calldata gas cost is recomputed for the new nonce using `chain_spec`.
+    /// Returns an error if `key` exceeds 192 bits.
+    pub fn with_nonce_key(
+        self,
+        key: U256,
+        chain_spec: &ChainSpec,
+    ) -> Result<Self, NonceKeyTooLarge> {
+        if key.bit_len() > 256 - NONCE_SEQUENCE_BITS {
+            return Err(NonceKeyTooLarge(key));
+        }
+        let nonce = (key << NONCE_SEQUENCE_BITS) | U256::from(self.nonce_sequence());
+        Ok(UserOperationBuilder::from_uo(UserOperation { nonce, ..self }, chain_spec).build())
+    }
+
     fn get_address_from_field(data: &Bytes) -> Option<Address> {
         if data.len() < 20 {
             None
@@ -384,6 +449,25 @@ pub struct UserOperationOptionalGas {
 }
 
 impl UserOperationOptionalGas {
+    /// Check that the dummy signature is plausibly long enough for `account_type`.
+    ///
+    /// `max_fill` and `random_fill` size the signature from the dummy, so a short dummy
+    /// signature causes pre-verification gas to be underestimated.
+    pub fn validate_dummy_fields(&self, account_type: AccountType) -> Result<(), Warning> {
+        super::validate_dummy_signature(&self.signature, account_type)
+    }
+
+    /// Returns true if `op` has the same fields as this operation, ignoring the gas and
+    /// fee fields
+    pub fn matches_ignoring_gas(&self, op: &UserOperation) -> bool {
+        self.sender == op.sender
+            && self.nonce == op.nonce
+            && self.init_code == op.init_code
+            && self.call_data == op.call_data
+            && self.paymaster_and_data == op.paymaster_and_data
+            && self.signature == op.signature
+    }
+
     /// Fill in the optional and dummy fields of the user operation with values
     /// that will cause the maximum possible calldata gas cost.
     pub fn max_fill(&self, chain_spec: &ChainSpec) -> UserOperation {
@@ -654,6 +738,7 @@ impl<'a> UserOperationBuilder<'a> {
             self.chain_spec.calldata_zero_byte_gas(),
             self.chain_spec.calldata_non_zero_byte_gas(),
             self.chain_spec.per_user_op_word_gas(),
+            self.chain_spec.eip7623_calldata_floor_enabled,
         );
 
         uo
@@ -771,6 +856,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_content_hash() {
+        let build = |chain_id| {
+            UserOperationBuilder::new(
+                &ChainSpec {
+                    id: chain_id,
+                    ..Default::default()
+                },
+                UserOperationRequiredFields {
+                    sender: address!("1306b01bc3e4ad202612d3843387e94737673f53"),
+                    nonce: U256::from(8942),
+                    init_code: Bytes::default(),
+                    call_data: bytes!("0000000000000000000000000000000000000000080085"),
+                    call_gas_limit: 10_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 100,
+                    max_fee_per_gas: 99_999,
+                    max_priority_fee_per_gas: 9_999_999,
+                    paymaster_and_data: Bytes::default(),
+                    signature: Bytes::default(),
+                },
+            )
+            .build()
+        };
+        let op_a = build(1);
+        let op_b = build(1337);
+        let entry_point = address!("66a15edcc3b50a663e72f1457ffd49b9ae284ddc");
+
+        assert_eq!(op_a.content_hash(), op_b.content_hash());
+        assert_ne!(op_a.hash(entry_point, 1), op_b.hash(entry_point, 1337));
+        assert_ne!(op_a.content_hash(), op_a.hash(entry_point, 1));
+
+        let mut op_c = op_a.clone();
+        op_c.nonce = U256::from(8943);
+        assert_ne!(op_a.content_hash(), op_c.content_hash());
+    }
+
     #[test]
     fn test_get_address_from_field() {
         let paymaster_and_data: Bytes =
@@ -784,6 +906,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_calldata_gas_cost_eip7623_floor() {
+        let build = |chain_spec: &ChainSpec| {
+            UserOperationBuilder::new(
+                chain_spec,
+                UserOperationRequiredFields {
+                    sender: Address::ZERO,
+                    nonce: U256::ZERO,
+                    init_code: Bytes::new(),
+                    call_data: Bytes::from(vec![0_u8; 4096]),
+                    call_gas_limit: 0,
+                    verification_gas_limit: 0,
+                    pre_verification_gas: 0,
+                    max_fee_per_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    paymaster_and_data: Bytes::new(),
+                    signature: Bytes::new(),
+                },
+            )
+            .build()
+        };
+
+        let chain_spec = ChainSpec::default();
+        let op = build(&chain_spec);
+        let encoded = ContractUserOperation::from(op.clone()).abi_encode();
+        let zero_bytes = encoded.iter().filter(|&&b| b == 0).count() as u128;
+        let non_zero_bytes = encoded.len() as u128 - zero_bytes;
+        let words = (encoded.len() as u128).div_ceil(32);
+
+        let standard_cost = zero_bytes * 4 + non_zero_bytes * 16 + words * 4;
+        assert_eq!(op.calldata_gas_cost, standard_cost);
+
+        let op = build(&ChainSpec {
+            eip7623_calldata_floor_enabled: true,
+            ..chain_spec
+        });
+        let floor_cost = 10 * (zero_bytes + non_zero_bytes * 4);
+        assert!(floor_cost > standard_cost);
+        assert_eq!(op.calldata_gas_cost, floor_cost);
+    }
+
     #[test]
     fn test_abi_encoded_size() {
         let operation = UserOperationBuilder::new(
@@ -870,4 +1033,174 @@ mod tests {
         let cuo = ContractUserOperation::from(max_op).abi_encode();
         assert_eq!(size, cuo.len());
     }
+
+    fn nonce_op(nonce: U256) -> UserOperation {
+        UserOperationBuilder::from_uo(
+            UserOperation {
+                nonce,
+                ..Default::default()
+            },
+            &ChainSpec::default(),
+        )
+        .build()
+    }
+
+    #[test]
+    fn test_nonce_key_and_sequence() {
+        let max_key = U256::MAX >> 64;
+
+        let uo = nonce_op(U256::ZERO);
+        assert_eq!(uo.nonce_key(), U256::ZERO);
+        assert_eq!(uo.nonce_sequence(), 0);
+
+        let uo = nonce_op(U256::from(u64::MAX));
+        assert_eq!(uo.nonce_key(), U256::ZERO);
+        assert_eq!(uo.nonce_sequence(), u64::MAX);
+
+        let uo = nonce_op(U256::from(1) << 64);
+        assert_eq!(uo.nonce_key(), U256::from(1));
+        assert_eq!(uo.nonce_sequence(), 0);
+
+        let uo = nonce_op(U256::MAX);
+        assert_eq!(uo.nonce_key(), max_key);
+        assert_eq!(uo.nonce_sequence(), u64::MAX);
+    }
+
+    #[test]
+    fn test_with_nonce_key() {
+        let cs = ChainSpec::default();
+        let max_key = U256::MAX >> 64;
+        let uo = nonce_op(U256::from(u64::MAX));
+
+        for key in [U256::ZERO, U256::from(1), max_key] {
+            let keyed = uo.clone().with_nonce_key(key, &cs).unwrap();
+            assert_eq!(keyed.nonce_key(), key);
+            assert_eq!(keyed.nonce_sequence(), u64::MAX);
+            // the cached calldata gas cost reflects the new nonce
+            assert_eq!(keyed, nonce_op(keyed.nonce));
+        }
+
+        // replacing the key clears the previous one
+        let keyed = nonce_op(U256::MAX)
+            .with_nonce_key(U256::from(2), &cs)
+            .unwrap();
+        assert_eq!(keyed.nonce, (U256::from(2) << 64) | U256::from(u64::MAX));
+    }
+
+    #[test]
+    fn test_with_nonce_key_too_large() {
+        let key = U256::from(1) << 192;
+        assert_eq!(
+            nonce_op(U256::ZERO).with_nonce_key(key, &ChainSpec::default()),
+            Err(NonceKeyTooLarge(key))
+        );
+    }
+
+    #[test]
+    fn test_checked_max_gas_cost() {
+        let uo = UserOperation {
+            call_gas_limit: 100,
+            verification_gas_limit: 200,
+            pre_verification_gas: 300,
+            max_fee_per_gas: 10,
+            ..Default::default()
+        };
+        assert_eq!(uo.checked_max_gas_cost(), Some(U256::from(6_000)));
+        assert_eq!(uo.checked_max_gas_cost(), Some(uo.max_gas_cost()));
+
+        // verification gas is counted three times with a paymaster
+        let uo = UserOperation {
+            paymaster_and_data: Address::random().to_vec().into(),
+            ..uo
+        };
+        assert_eq!(uo.checked_max_gas_cost(), Some(U256::from(10_000)));
+    }
+
+    #[test]
+    fn test_checked_max_gas_cost_u128_max() {
+        // u128::MAX * u128::MAX fits in a U256, but wraps in u128 math
+        let uo = UserOperation {
+            call_gas_limit: u128::MAX,
+            max_fee_per_gas: u128::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            uo.checked_max_gas_cost(),
+            Some(U256::from(u128::MAX) * U256::from(u128::MAX))
+        );
+
+        let uo = UserOperation {
+            call_gas_limit: u128::MAX,
+            verification_gas_limit: u128::MAX,
+            pre_verification_gas: u128::MAX,
+            max_fee_per_gas: u128::MAX,
+            paymaster_and_data: Address::random().to_vec().into(),
+            ..Default::default()
+        };
+        assert_eq!(uo.checked_max_gas_cost(), None);
+    }
+
+    #[test]
+    fn test_required_prefund() {
+        // prefund returned by the v0.6 entry point's `simulateValidation` for this op
+        let uo = UserOperation {
+            sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+            nonce: U256::from(264),
+            call_gas_limit: 9100,
+            verification_gas_limit: 64805,
+            pre_verification_gas: 46128,
+            max_fee_per_gas: 105000100,
+            max_priority_fee_per_gas: 105000000,
+            ..Default::default()
+        };
+        assert_eq!(uo.required_prefund(true), U256::from(0xb7679c50c24_u64));
+        assert_eq!(uo.required_prefund(false), uo.required_prefund(true));
+
+        let uo = UserOperation {
+            call_gas_limit: 100_000,
+            verification_gas_limit: 150_000,
+            pre_verification_gas: 50_000,
+            max_fee_per_gas: 10_000_000_000,
+            paymaster_and_data: Address::random().to_vec().into(),
+            ..uo
+        };
+        // (100_000 + 150_000 * 3 + 50_000) * 10 gwei
+        assert_eq!(
+            uo.required_prefund(true),
+            U256::from(6_000_000_000_000_000_u128)
+        );
+        // (100_000 + 150_000 + 50_000) * 10 gwei
+        assert_eq!(
+            uo.required_prefund(false),
+            U256::from(3_000_000_000_000_000_u128)
+        );
+    }
+
+    #[test]
+    fn test_deploy_overhead_size_threshold() {
+        let uo = UserOperation {
+            init_code: [Address::random().to_vec(), vec![1; 100]].concat().into(),
+            ..Default::default()
+        };
+        let size = uo.abi_encoded_size() as u64;
+        let base_cs = ChainSpec {
+            per_user_op_deploy_overhead_gas: 20_000,
+            ..Default::default()
+        };
+        let base_gas = uo.static_pre_verification_gas(&base_cs);
+
+        // op is at the threshold, just below applying the overhead
+        let cs = ChainSpec {
+            deploy_overhead_applies_above_bytes: Some(size),
+            ..base_cs.clone()
+        };
+        assert_eq!(uo.static_pre_verification_gas(&cs), base_gas - 20_000);
+
+        // op is just above the threshold
+        let cs = ChainSpec {
+            deploy_overhead_applies_above_bytes: Some(size - 1),
+            ..base_cs
+        };
+        assert_eq!(uo.static_pre_verification_gas(&cs), base_gas);
+    }
 }
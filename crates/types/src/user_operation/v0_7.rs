@@ -16,8 +16,8 @@ use alloy_sol_types::{sol, SolValue};
 use rundler_contracts::v0_7::PackedUserOperation;
 
 use super::{
-    random_bytes, random_bytes_array, UserOperation as UserOperationTrait, UserOperationId,
-    UserOperationVariant,
+    random_bytes, random_bytes_array, AccountType, UserOperation as UserOperationTrait,
+    UserOperationId, UserOperationVariant, Warning,
 };
 use crate::{chain::ChainSpec, Entity, EntryPointVersion};
 
@@ -108,6 +108,10 @@ impl UserOperationTrait for UserOperation {
         self.hash
     }
 
+    fn content_hash(&self) -> B256 {
+        content_hash_packed_user_operation(&self.packed)
+    }
+
     fn id(&self) -> UserOperationId {
         UserOperationId {
             sender: self.sender,
@@ -135,6 +139,10 @@ impl UserOperationTrait for UserOperation {
         &self.call_data
     }
 
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         U256::from(
             self.max_fee_per_gas
@@ -146,6 +154,27 @@ impl UserOperationTrait for UserOperation {
         )
     }
 
+    fn checked_max_gas_cost(&self) -> Option<U256> {
+        let gas = U256::from(self.pre_verification_gas)
+            + U256::from(self.call_gas_limit)
+            + U256::from(self.verification_gas_limit)
+            + U256::from(self.paymaster_verification_gas_limit)
+            + U256::from(self.paymaster_post_op_gas_limit);
+        U256::from(self.max_fee_per_gas).checked_mul(gas)
+    }
+
+    fn required_prefund(&self, include_paymaster_overhead: bool) -> U256 {
+        // EntryPoint v0.7 `_getRequiredPrefund`
+        let mut required_gas = U256::from(self.verification_gas_limit)
+            + U256::from(self.call_gas_limit)
+            + U256::from(self.pre_verification_gas);
+        if include_paymaster_overhead {
+            required_gas += U256::from(self.paymaster_verification_gas_limit)
+                + U256::from(self.paymaster_post_op_gas_limit);
+        }
+        required_gas.saturating_mul(U256::from(self.max_fee_per_gas))
+    }
+
     fn entities(&self) -> Vec<Entity> {
         let mut ret = vec![Entity::account(self.sender)];
         if let Some(factory) = self.factory {
@@ -195,7 +224,7 @@ impl UserOperationTrait for UserOperation {
         self.calldata_gas_cost
             + chain_spec.per_user_op_v0_7_gas()
             + (if self.factory.is_some() {
-                chain_spec.per_user_op_deploy_overhead_gas()
+                chain_spec.deploy_overhead_gas(self.abi_encoded_size())
             } else {
                 0
             })
@@ -233,6 +262,17 @@ impl UserOperationTrait for UserOperation {
     }
 }
 
+/// Error returned when a user operation does not round-trip through its packed encoding
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum EncodingError {
+    /// A packed field is too short to be decoded
+    #[error("packed {0} is too short to decode")]
+    FieldTooShort(&'static str),
+    /// A field does not match its packed encoding
+    #[error("{0} does not match its packed encoding")]
+    FieldMismatch(&'static str),
+}
+
 impl UserOperation {
     /// Packs the user operation to its offchain representation
     pub fn pack(self) -> PackedUserOperation {
@@ -243,6 +283,94 @@ impl UserOperation {
     pub fn packed(&self) -> &PackedUserOperation {
         &self.packed
     }
+
+    /// Validates that the user operation converts to its packed contract form and back
+    /// without loss, and that re-packing its fields is byte-identical to its packed form.
+    pub fn validate_packed_roundtrip(&self) -> Result<(), EncodingError> {
+        let packed = &self.packed;
+
+        let (factory, factory_data) = if packed.initCode.is_empty() {
+            (None, Bytes::new())
+        } else if packed.initCode.len() < 20 {
+            return Err(EncodingError::FieldTooShort("initCode"));
+        } else {
+            (
+                Some(Address::from_slice(&packed.initCode[..20])),
+                Bytes::from_iter(&packed.initCode[20..]),
+            )
+        };
+
+        let (
+            paymaster,
+            paymaster_verification_gas_limit,
+            paymaster_post_op_gas_limit,
+            paymaster_data,
+        ) = if packed.paymasterAndData.is_empty() {
+            (None, 0, 0, Bytes::new())
+        } else if packed.paymasterAndData.len() < 52 {
+            return Err(EncodingError::FieldTooShort("paymasterAndData"));
+        } else {
+            (
+                Some(Address::from_slice(&packed.paymasterAndData[..20])),
+                u128_from_be_slice(&packed.paymasterAndData[20..36]),
+                u128_from_be_slice(&packed.paymasterAndData[36..52]),
+                Bytes::from_iter(&packed.paymasterAndData[52..]),
+            )
+        };
+
+        let pre_verification_gas: u128 = packed
+            .preVerificationGas
+            .try_into()
+            .map_err(|_| EncodingError::FieldMismatch("preVerificationGas"))?;
+
+        let fields = [
+            ("sender", packed.sender == self.sender),
+            ("nonce", packed.nonce == self.nonce),
+            ("factory", factory == self.factory),
+            ("factoryData", factory_data == self.factory_data),
+            ("callData", packed.callData == self.call_data),
+            (
+                "verificationGasLimit",
+                u128_from_be_slice(&packed.accountGasLimits[..16]) == self.verification_gas_limit,
+            ),
+            (
+                "callGasLimit",
+                u128_from_be_slice(&packed.accountGasLimits[16..]) == self.call_gas_limit,
+            ),
+            (
+                "preVerificationGas",
+                pre_verification_gas == self.pre_verification_gas,
+            ),
+            (
+                "maxPriorityFeePerGas",
+                u128_from_be_slice(&packed.gasFees[..16]) == self.max_priority_fee_per_gas,
+            ),
+            (
+                "maxFeePerGas",
+                u128_from_be_slice(&packed.gasFees[16..]) == self.max_fee_per_gas,
+            ),
+            ("paymaster", paymaster == self.paymaster),
+            (
+                "paymasterVerificationGasLimit",
+                paymaster_verification_gas_limit == self.paymaster_verification_gas_limit,
+            ),
+            (
+                "paymasterPostOpGasLimit",
+                paymaster_post_op_gas_limit == self.paymaster_post_op_gas_limit,
+            ),
+            ("paymasterData", paymaster_data == self.paymaster_data),
+            ("signature", packed.signature == self.signature),
+        ];
+        if let Some((field, _)) = fields.into_iter().find(|(_, matches)| !matches) {
+            return Err(EncodingError::FieldMismatch(field));
+        }
+
+        if pack_user_operation(self.clone()).abi_encode() != packed.abi_encode() {
+            return Err(EncodingError::FieldMismatch("packedUserOperation"));
+        }
+
+        Ok(())
+    }
 }
 
 impl From<UserOperationVariant> for UserOperation {
@@ -331,6 +459,27 @@ pub struct UserOperationOptionalGas {
 }
 
 impl UserOperationOptionalGas {
+    /// Check that the dummy signature is plausibly long enough for `account_type`.
+    ///
+    /// `max_fill` and `random_fill` size the signature from the dummy, so a short dummy
+    /// signature causes pre-verification gas to be underestimated.
+    pub fn validate_dummy_fields(&self, account_type: AccountType) -> Result<(), Warning> {
+        super::validate_dummy_signature(&self.signature, account_type)
+    }
+
+    /// Returns true if `op` has the same fields as this operation, ignoring the gas and
+    /// fee fields
+    pub fn matches_ignoring_gas(&self, op: &UserOperation) -> bool {
+        self.sender == op.sender
+            && self.nonce == op.nonce
+            && self.call_data == op.call_data
+            && self.signature == op.signature
+            && self.factory == op.factory
+            && self.factory_data == op.factory_data
+            && self.paymaster == op.paymaster
+            && self.paymaster_data == op.paymaster_data
+    }
+
     /// Fill in the optional and dummy fields of the user operation with values
     /// that will cause the maximum possible calldata gas cost.
     pub fn max_fill(&self, chain_spec: &ChainSpec) -> UserOperation {
@@ -733,6 +882,7 @@ impl<'a> UserOperationBuilder<'a> {
             self.chain_spec.calldata_zero_byte_gas(),
             self.chain_spec.calldata_non_zero_byte_gas(),
             self.chain_spec.per_user_op_word_gas(),
+            self.chain_spec.eip7623_calldata_floor_enabled,
         );
 
         UserOperation {
@@ -814,6 +964,16 @@ fn hash_packed_user_operation(
     entry_point: Address,
     chain_id: u64,
 ) -> B256 {
+    let encoded = UserOperationHashEncoded {
+        encodedHash: content_hash_packed_user_operation(puo),
+        entryPoint: entry_point,
+        chainId: U256::from(chain_id),
+    };
+
+    alloy_primitives::keccak256(encoded.abi_encode())
+}
+
+fn content_hash_packed_user_operation(puo: &PackedUserOperation) -> B256 {
     let hash_init_code = alloy_primitives::keccak256(&puo.initCode);
     let hash_call_data = alloy_primitives::keccak256(&puo.callData);
     let hash_paymaster_and_data = alloy_primitives::keccak256(&puo.paymasterAndData);
@@ -829,15 +989,7 @@ fn hash_packed_user_operation(
         hashPaymasterAndData: hash_paymaster_and_data,
     };
 
-    let hashed = alloy_primitives::keccak256(packed_for_hash.abi_encode());
-
-    let encoded = UserOperationHashEncoded {
-        encodedHash: hashed,
-        entryPoint: entry_point,
-        chainId: U256::from(chain_id),
-    };
-
-    alloy_primitives::keccak256(encoded.abi_encode())
+    alloy_primitives::keccak256(packed_for_hash.abi_encode())
 }
 
 fn concat_u128_be(a: u128, b: u128) -> [u8; 32] {
@@ -940,6 +1092,39 @@ mod tests {
         assert_eq!(uo.hash(cs.entry_point_address_v0_7, cs.id), hash);
     }
 
+    #[test]
+    fn test_content_hash() {
+        let build = |chain_id| {
+            UserOperationBuilder::new(
+                &ChainSpec {
+                    id: chain_id,
+                    ..Default::default()
+                },
+                UserOperationRequiredFields {
+                    sender: address!("b292Cf4a8E1fF21Ac27C4f94071Cd02C022C414b"),
+                    nonce: U256::from(1),
+                    call_data: bytes!("e9ae5c53"),
+                    call_gas_limit: 10_000,
+                    verification_gas_limit: 100_000,
+                    pre_verification_gas: 48_916,
+                    max_priority_fee_per_gas: 1_000,
+                    max_fee_per_gas: 10_000,
+                    signature: Bytes::new(),
+                },
+            )
+            .build()
+        };
+        let uo_a = build(1);
+        let uo_b = build(11155111);
+
+        assert_eq!(uo_a.content_hash(), uo_b.content_hash());
+        assert_ne!(
+            uo_a.hash(Address::ZERO, 1),
+            uo_b.hash(Address::ZERO, 11155111)
+        );
+        assert_ne!(uo_a.content_hash(), uo_a.hash(Address::ZERO, 1));
+    }
+
     #[test]
     fn test_builder() {
         let factory_address = Address::random();
@@ -969,4 +1154,162 @@ mod tests {
         assert_eq!(uo.paymaster_verification_gas_limit, 10);
         assert_eq!(uo.paymaster_post_op_gas_limit, 20);
     }
+
+    fn random_gas_limit_op(cs: &ChainSpec) -> UserOperation {
+        UserOperationBuilder::new(
+            cs,
+            UserOperationRequiredFields {
+                sender: Address::random(),
+                nonce: U256::from(rand::random::<u64>()),
+                call_data: bytes!("deadbeef"),
+                call_gas_limit: rand::random(),
+                verification_gas_limit: rand::random(),
+                pre_verification_gas: rand::random(),
+                max_priority_fee_per_gas: rand::random(),
+                max_fee_per_gas: rand::random(),
+                signature: bytes!("0123"),
+            },
+        )
+        .factory(Address::random(), bytes!("abcd"))
+        .paymaster(
+            Address::random(),
+            rand::random(),
+            rand::random(),
+            bytes!("4567"),
+        )
+        .build()
+    }
+
+    #[test]
+    fn test_validate_packed_roundtrip_random_gas_limits() {
+        let cs = ChainSpec::default();
+        for _ in 0..1000 {
+            let uo = random_gas_limit_op(&cs);
+            assert_eq!(uo.validate_packed_roundtrip(), Ok(()), "{uo:?}");
+
+            let unpacked = UserOperationBuilder::from_packed(uo.clone().pack(), &cs)
+                .unwrap()
+                .build();
+            assert_eq!(unpacked.validate_packed_roundtrip(), Ok(()));
+            assert_eq!(uo, unpacked);
+        }
+    }
+
+    #[test]
+    fn test_validate_packed_roundtrip_max_gas_limits() {
+        let cs = ChainSpec::default();
+        let uo = UserOperationBuilder::from_uo(random_gas_limit_op(&cs), &cs)
+            .call_gas_limit(u128::MAX)
+            .verification_gas_limit(u128::MAX)
+            .max_fee_per_gas(u128::MAX)
+            .max_priority_fee_per_gas(u128::MAX)
+            .paymaster_verification_gas_limit(u128::MAX)
+            .paymaster_post_op_gas_limit(u128::MAX)
+            .build();
+
+        assert_eq!(uo.validate_packed_roundtrip(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_packed_roundtrip_truncated_account_gas_limits() {
+        let cs = ChainSpec::default();
+        let mut uo = UserOperationBuilder::from_uo(random_gas_limit_op(&cs), &cs)
+            .verification_gas_limit(u128::MAX)
+            .build();
+
+        // truncate the high bits of the packed verification gas limit
+        uo.packed.accountGasLimits[0] = 0;
+
+        assert_eq!(
+            uo.validate_packed_roundtrip(),
+            Err(EncodingError::FieldMismatch("verificationGasLimit"))
+        );
+    }
+
+    #[test]
+    fn test_validate_packed_roundtrip_short_paymaster_and_data() {
+        let cs = ChainSpec::default();
+        let mut uo = random_gas_limit_op(&cs);
+        uo.packed.paymasterAndData = Bytes::from_iter(&uo.packed.paymasterAndData[..40]);
+
+        assert_eq!(
+            uo.validate_packed_roundtrip(),
+            Err(EncodingError::FieldTooShort("paymasterAndData"))
+        );
+    }
+
+    #[test]
+    fn test_required_prefund() {
+        let cs = ChainSpec::default();
+        let required = UserOperationRequiredFields {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            call_data: Bytes::new(),
+            call_gas_limit: 100_000,
+            verification_gas_limit: 150_000,
+            pre_verification_gas: 50_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            max_fee_per_gas: 10_000_000_000,
+            signature: Bytes::new(),
+        };
+
+        let uo = UserOperationBuilder::new(&cs, required).build();
+        // (150_000 + 100_000 + 50_000) * 10 gwei
+        assert_eq!(
+            uo.required_prefund(true),
+            U256::from(3_000_000_000_000_000_u128)
+        );
+        assert_eq!(uo.required_prefund(true), uo.required_prefund(false));
+
+        let uo = UserOperationBuilder::from_uo(uo, &cs)
+            .paymaster(Address::random(), 60_000, 40_000, Bytes::new())
+            .build();
+        // (150_000 + 100_000 + 60_000 + 40_000 + 50_000) * 10 gwei
+        assert_eq!(
+            uo.required_prefund(true),
+            U256::from(4_000_000_000_000_000_u128)
+        );
+        assert_eq!(
+            uo.required_prefund(false),
+            U256::from(3_000_000_000_000_000_u128)
+        );
+    }
+
+    #[test]
+    fn test_deploy_overhead_size_threshold() {
+        let base_cs = ChainSpec {
+            per_user_op_deploy_overhead_gas: 20_000,
+            ..Default::default()
+        };
+        let required = UserOperationRequiredFields {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            call_data: Bytes::new(),
+            call_gas_limit: 100_000,
+            verification_gas_limit: 150_000,
+            pre_verification_gas: 50_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            max_fee_per_gas: 10_000_000_000,
+            signature: Bytes::new(),
+        };
+        let uo = UserOperationBuilder::new(&base_cs, required)
+            .factory(Address::random(), vec![1; 100].into())
+            .build();
+        let size = uo.abi_encoded_size() as u64;
+        let base_gas = uo.static_pre_verification_gas(&base_cs);
+
+        // op is at the threshold, just below applying the overhead
+        let cs = ChainSpec {
+            deploy_overhead_applies_above_bytes: Some(size),
+            ..base_cs.clone()
+        };
+        assert_eq!(uo.static_pre_verification_gas(&cs), base_gas - 20_000);
+
+        // op is just above the threshold
+        let cs = ChainSpec {
+            deploy_overhead_applies_above_bytes: Some(size - 1),
+            ..base_cs
+        };
+        assert_eq!(uo.static_pre_verification_gas(&cs), base_gas);
+    }
 }
@@ -245,6 +245,8 @@ impl TryFrom<ValidationResultV0_7> for ValidationOutput {
 pub struct ValidationReturnInfo {
     /// The amount of gas used before the op was executed (pre verification gas and validation gas)
     pub pre_op_gas: u128,
+    /// The amount of funds the entry point requires to be deposited for the op
+    pub prefund: U256,
     /// Whether the account signature verification failed
     pub account_sig_failed: bool,
     /// Whether the paymaster signature verification failed
@@ -272,7 +274,7 @@ impl TryFrom<ReturnInfoV0_6> for ValidationReturnInfo {
     fn try_from(value: ReturnInfoV0_6) -> Result<Self, Self::Error> {
         let ReturnInfoV0_6 {
             preOpGas,
-            prefund: _,
+            prefund,
             sigFailed,
             validAfter,
             validUntil,
@@ -282,6 +284,7 @@ impl TryFrom<ReturnInfoV0_6> for ValidationReturnInfo {
             pre_op_gas: preOpGas
                 .try_into()
                 .map_err(|_| "preOpGas is larger than u128")?,
+            prefund,
             // In v0.6 if one signature fails both do
             account_sig_failed: sigFailed,
             paymaster_sig_failed: sigFailed,
@@ -298,7 +301,7 @@ impl TryFrom<ReturnInfoV0_7> for ValidationReturnInfo {
     fn try_from(value: ReturnInfoV0_7) -> Result<Self, Self::Error> {
         let ReturnInfoV0_7 {
             preOpGas,
-            prefund: _,
+            prefund,
             accountValidationData,
             paymasterValidationData,
             paymasterContext,
@@ -314,6 +317,7 @@ impl TryFrom<ReturnInfoV0_7> for ValidationReturnInfo {
             pre_op_gas: preOpGas
                 .try_into()
                 .map_err(|_| "preOpGas is larger than u128")?,
+            prefund,
             account_sig_failed: !account.signature_valid(),
             paymaster_sig_failed: !paymaster.signature_valid(),
             valid_after: intersect_range.valid_after,
@@ -49,12 +49,29 @@ pub async fn with_retries<Func, Fut, Out, Err>(
 where
     Func: Fn() -> Fut,
     Fut: Future<Output = Result<Out, Err>>,
+{
+    with_retries_if(description, func, |_| true, opts).await
+}
+
+/// Retry a function using exponential backoff with jitter, only while `should_retry`
+/// returns true for the error. Other errors are returned immediately.
+pub async fn with_retries_if<Func, Fut, Out, Err, Pred>(
+    description: &str,
+    func: Func,
+    should_retry: Pred,
+    opts: RetryOpts,
+) -> Result<Out, Err>
+where
+    Func: Fn() -> Fut,
+    Fut: Future<Output = Result<Out, Err>>,
+    Pred: Fn(&Err) -> bool,
 {
     let mut next_wait = Duration::ZERO;
     let mut last_error: Option<Err> = None;
     for attempt_number in 1..=opts.max_attempts {
         match func().await {
             Ok(out) => return Ok(out),
+            Err(error) if !should_retry(&error) => return Err(error),
             Err(error) => {
                 last_error = Some(error);
                 warn!("Failed to {description} (attempt {attempt_number})");